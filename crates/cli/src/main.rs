@@ -5,8 +5,8 @@ use clap::Parser;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
 
-use stormdb_common::{DEFAULT_HOST, DEFAULT_PORT};
-use stormdb_protocol::Frame;
+use stormdb_common::{DEFAULT_HOST, DEFAULT_PORT, ProtocolError};
+use stormdb_protocol::{Frame, tokenize};
 
 #[derive(Parser, Debug)]
 #[command(name = "stormdb-cli", about = "StormDB CLI client")]
@@ -89,75 +89,23 @@ async fn execute_request(stream: &mut TcpStream, frame: Frame) -> anyhow::Result
         }
 
         let mut cursor = std::io::Cursor::new(&response_buf[..]);
-        if Frame::check(&mut cursor).is_ok() {
-            cursor.set_position(0);
-            let response =
-                Frame::parse(&mut cursor).map_err(|e| anyhow::anyhow!("parse error: {e}"))?;
-            println!("{}", format_frame(&response, 0));
-            break;
-        }
-    }
-    Ok(())
-}
-
-/// Tokeniza a linha de input com suporte a strings quoted.
-fn tokenize(input: &str) -> Vec<String> {
-    let mut tokens = Vec::new();
-    let mut current = String::new();
-    let mut in_quote = false;
-    let mut quote_char = '"';
-    let mut chars = input.chars().peekable();
-
-    while let Some(c) = chars.next() {
-        if in_quote {
-            if c == quote_char {
-                in_quote = false;
-            } else if c == '\\' {
-                if let Some(&next) = chars.peek() {
-                    match next {
-                        'n' => {
-                            current.push('\n');
-                            chars.next();
-                        }
-                        't' => {
-                            current.push('\t');
-                            chars.next();
-                        }
-                        '\\' => {
-                            current.push('\\');
-                            chars.next();
-                        }
-                        '"' => {
-                            current.push('"');
-                            chars.next();
-                        }
-                        '\'' => {
-                            current.push('\'');
-                            chars.next();
-                        }
-                        _ => current.push(c),
-                    }
-                }
-            } else {
-                current.push(c);
-            }
-        } else if c == '"' || c == '\'' {
-            in_quote = true;
-            quote_char = c;
-        } else if c.is_whitespace() {
-            if !current.is_empty() {
-                tokens.push(std::mem::take(&mut current));
+        // Respostas do servidor são confiáveis: o limite de elementos vale
+        // para requisições, e um LRANGE grande passaria dele
+        match Frame::check_with_limit(&mut cursor, usize::MAX) {
+            Ok(()) => {
+                cursor.set_position(0);
+                let response =
+                    Frame::parse(&mut cursor).map_err(|e| anyhow::anyhow!("parse error: {e}"))?;
+                println!("{}", format_frame(&response, 0));
+                break;
             }
-        } else {
-            current.push(c);
+            // Só um frame incompleto justifica esperar mais bytes; resposta
+            // malformada nunca vai se completar
+            Err(ProtocolError::Incomplete) => continue,
+            Err(e) => return Err(anyhow::anyhow!("parse error: {e}")),
         }
     }
-
-    if !current.is_empty() {
-        tokens.push(current);
-    }
-
-    tokens
+    Ok(())
 }
 
 /// Formata um frame para exibição humana.
@@ -171,7 +119,7 @@ fn format_frame(frame: &Frame, indent: usize) -> String {
             Ok(s) => format!("{pad}\"{s}\""),
             Err(_) => format!("{pad}(binary) {} bytes", data.len()),
         },
-        Frame::Null => format!("{pad}(nil)"),
+        Frame::Null | Frame::NullArray => format!("{pad}(nil)"),
         Frame::Array(frames) => {
             if frames.is_empty() {
                 return format!("{pad}(empty array)");
@@ -189,40 +137,6 @@ fn format_frame(frame: &Frame, indent: usize) -> String {
 mod tests {
     use super::*;
 
-    #[test]
-    fn tokenize_simple() {
-        assert_eq!(tokenize("SET key value"), vec!["SET", "key", "value"]);
-    }
-
-    #[test]
-    fn tokenize_quoted() {
-        assert_eq!(
-            tokenize(r#"SET key "hello world""#),
-            vec!["SET", "key", "hello world"]
-        );
-    }
-
-    #[test]
-    fn tokenize_single_quotes() {
-        assert_eq!(
-            tokenize("SET key 'hello world'"),
-            vec!["SET", "key", "hello world"]
-        );
-    }
-
-    #[test]
-    fn tokenize_escaped() {
-        assert_eq!(
-            tokenize(r#"SET key "hello\"world""#),
-            vec!["SET", "key", r#"hello"world"#]
-        );
-    }
-
-    #[test]
-    fn tokenize_empty() {
-        assert_eq!(tokenize(""), Vec::<String>::new());
-    }
-
     #[test]
     fn format_integer() {
         let frame = Frame::Integer(42);
@@ -239,4 +153,30 @@ mod tests {
         let frame = Frame::Error("ERR unknown command".into());
         assert_eq!(format_frame(&frame, 0), "(error) ERR unknown command");
     }
+
+    #[tokio::test]
+    async fn malformed_reply_is_an_error() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 64];
+            let _ = socket.read(&mut buf).await.unwrap();
+            // Byte de tipo inválido: o cliente não pode ficar esperando
+            socket.write_all(b"?oops\r\n").await.unwrap();
+            // Mantém a conexão aberta, como um servidor com bug faria
+            tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+        });
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let frame = Frame::array_from_strs(&["PING"]);
+        let result = tokio::time::timeout(
+            std::time::Duration::from_secs(1),
+            execute_request(&mut stream, frame),
+        )
+        .await
+        .expect("cli ficou esperando uma resposta malformada");
+        assert!(result.unwrap_err().to_string().contains("parse error"));
+        server.abort();
+    }
 }
@@ -11,6 +11,8 @@ pub enum ProtocolError {
     InvalidBulkLength(i64),
     #[error("frame excede tamanho máximo ({0} bytes)")]
     FrameTooLarge(usize),
+    #[error("array excede o número máximo de elementos ({0})")]
+    ArrayTooLarge(i64),
     #[error("encoding inválido: {0}")]
     InvalidEncoding(String),
 }
@@ -24,6 +26,12 @@ pub enum StorageError {
     NotAnInteger,
     #[error("chave não encontrada")]
     KeyNotFound,
+    #[error("string excede o tamanho máximo permitido")]
+    ValueTooLarge,
+    #[error("intervalo excede o máximo de {0} elementos por resposta")]
+    RangeTooLarge(usize),
+    #[error("tempo de expiração fora do intervalo")]
+    InvalidExpireTime,
 }
 
 /// Erros de conexão TCP.
@@ -35,6 +43,10 @@ pub enum ConnectionError {
     Io(#[from] std::io::Error),
     #[error("servidor em shutdown")]
     Shutdown,
+    /// Frame malformado descartado em modo de ressincronização; a conexão
+    /// continua utilizável.
+    #[error("erro de protocolo: {0}")]
+    Protocol(#[from] ProtocolError),
 }
 
 /// Erros de parsing/validação de comandos.
@@ -44,10 +56,122 @@ pub enum CommandError {
     Unknown(String),
     #[error("número errado de argumentos para '{0}'")]
     WrongArity(String),
+    #[error("'{command}' excede o máximo de {max} argumentos")]
+    TooManyArguments { command: String, max: usize },
     #[error("opção inválida para SET: {0}")]
     InvalidSetOption(String),
     #[error("argumento inválido: {0}")]
     InvalidArgument(String),
+    #[error("'{0}' não é um inteiro")]
+    NotAnInteger(String),
+    #[error("subcomando {command} desconhecido: {sub}")]
+    UnknownSubcommand { command: String, sub: String },
+    #[error("tempo de expiração inválido em '{0}'")]
+    InvalidExpireTime(String),
+    #[error("timeout inválido: '{0}'")]
+    InvalidTimeout(String),
+    #[error("count deve ser positivo")]
+    NegativeCount,
+    #[error("string UTF-8 inválida: byte {byte:#04x} na posição {pos} (\"{preview}\")")]
+    InvalidUtf8 {
+        byte: u8,
+        pos: usize,
+        preview: String,
+    },
+}
+
+/// Conversão de um erro para a resposta de erro RESP enviada ao cliente:
+/// `{código} {mensagem}`. Os clientes Redis decidem a classe do erro pelo
+/// código em maiúsculas, e esperam a mensagem em inglês — o `Display` em
+/// português fica para os logs.
+pub trait ErrorReply {
+    /// Código da resposta (`ERR`, `WRONGTYPE`, ...).
+    fn code(&self) -> &'static str {
+        "ERR"
+    }
+
+    /// Mensagem para o cliente, em inglês.
+    fn client_message(&self) -> String;
+
+    /// Texto completo do `Frame::Error`.
+    fn reply(&self) -> String {
+        format!("{} {}", self.code(), self.client_message())
+    }
+}
+
+impl ErrorReply for ProtocolError {
+    fn client_message(&self) -> String {
+        let detail = match self {
+            ProtocolError::Incomplete => "incomplete frame".to_string(),
+            ProtocolError::InvalidFrameType(b) => format!("invalid type byte {b:#x}"),
+            ProtocolError::InvalidInteger(_) => "invalid integer".to_string(),
+            ProtocolError::InvalidBulkLength(n) => format!("invalid bulk length {n}"),
+            ProtocolError::FrameTooLarge(n) => format!("frame exceeds maximum size ({n} bytes)"),
+            ProtocolError::ArrayTooLarge(n) => format!("invalid multibulk length {n}"),
+            ProtocolError::InvalidEncoding(_) => "invalid encoding".to_string(),
+        };
+        format!("Protocol error: {detail}")
+    }
+}
+
+impl ErrorReply for StorageError {
+    fn code(&self) -> &'static str {
+        match self {
+            StorageError::WrongType => "WRONGTYPE",
+            _ => "ERR",
+        }
+    }
+
+    fn client_message(&self) -> String {
+        match self {
+            StorageError::WrongType => {
+                "Operation against a key holding the wrong kind of value".to_string()
+            }
+            StorageError::NotAnInteger => "value is not an integer or out of range".to_string(),
+            StorageError::KeyNotFound => "no such key".to_string(),
+            StorageError::ValueTooLarge => "string exceeds maximum allowed size".to_string(),
+            StorageError::RangeTooLarge(max) => format!(
+                "range would return more than {max} elements, use a smaller range to page through the list"
+            ),
+            StorageError::InvalidExpireTime => "invalid expire time".to_string(),
+        }
+    }
+}
+
+impl ErrorReply for CommandError {
+    fn client_message(&self) -> String {
+        match self {
+            CommandError::Unknown(name) => format!("unknown command '{name}'"),
+            CommandError::WrongArity(name) => format!(
+                "wrong number of arguments for '{}' command",
+                name.to_lowercase()
+            ),
+            CommandError::TooManyArguments { command, max } => format!(
+                "too many arguments for '{}' command (max {max})",
+                command.to_lowercase()
+            ),
+            CommandError::InvalidSetOption(_) | CommandError::InvalidArgument(_) => {
+                "syntax error".to_string()
+            }
+            CommandError::NotAnInteger(_) => "value is not an integer or out of range".to_string(),
+            CommandError::UnknownSubcommand { command, sub } => {
+                format!(
+                    "unknown subcommand '{sub}' for '{}'",
+                    command.to_lowercase()
+                )
+            }
+            CommandError::InvalidExpireTime(name) => {
+                format!("invalid expire time in '{}' command", name.to_lowercase())
+            }
+            CommandError::InvalidTimeout(_) => "timeout is not a float or out of range".to_string(),
+            CommandError::NegativeCount => "value is out of range, must be positive".to_string(),
+            CommandError::InvalidUtf8 { byte, pos, preview } => {
+                format!(
+                    "invalid UTF-8 in argument: byte {byte:#04x} at position {pos} (\"{preview}\")"
+                )
+            }
+        }
+    }
 }
 
 /// Erro top-level do StormDB.
@@ -108,6 +232,26 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn error_replies_have_uppercase_code() {
+        assert_eq!(
+            StorageError::WrongType.reply(),
+            "WRONGTYPE Operation against a key holding the wrong kind of value"
+        );
+        assert_eq!(
+            StorageError::NotAnInteger.reply(),
+            "ERR value is not an integer or out of range"
+        );
+        assert_eq!(
+            CommandError::WrongArity("GET".into()).reply(),
+            "ERR wrong number of arguments for 'get' command"
+        );
+        assert_eq!(
+            ProtocolError::InvalidFrameType(b'!').reply(),
+            "ERR Protocol error: invalid type byte 0x21"
+        );
+    }
+
     #[test]
     fn command_error_display() {
         let err = CommandError::WrongArity("GET".into());
@@ -9,3 +9,5 @@ pub const DEFAULT_HOST: &str = "127.0.0.1";
 pub const MAX_CONNECTIONS: usize = 1024;
 pub const INITIAL_BUFFER_CAPACITY: usize = 4 * 1024; // 4 KB
 pub const MAX_FRAME_SIZE: usize = 64 * 1024 * 1024; // 64 MB
+pub const MAX_ARRAY_LEN: usize = 1024 * 1024; // elementos por array
+pub const MAX_COMMAND_ARGS: usize = MAX_ARRAY_LEN; // argumentos por comando (padrão: tudo que o parser aceita)
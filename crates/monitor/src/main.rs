@@ -1,10 +1,10 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::{io, time::Duration};
 
 use anyhow::Result;
 use clap::Parser;
 use crossterm::{
-    event::{self, Event, KeyCode},
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
@@ -12,7 +12,7 @@ use ratatui::{
     prelude::*,
     widgets::{Axis, Block, Borders, Chart, Dataset, GraphType, Paragraph},
 };
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
 use tokio::net::TcpStream;
 use tokio::time::interval;
 
@@ -25,32 +25,139 @@ struct Args {
     port: u16,
 }
 
-struct App {
+/// Métricas exibidas: título, campo do INFO e cor da série.
+const METRICS: &[(&str, &str, Color)] = &[
+    ("Keys", "keys", Color::Yellow),
+    ("Clients", "connected_clients", Color::Green),
+    ("Ops/sec", "instantaneous_ops_per_sec", Color::Magenta),
+];
+
+/// Histórico de uma métrica, limitado à janela do `App`.
+struct Series {
+    name: &'static str,
+    /// Campo do INFO de onde vem o valor.
+    field: &'static str,
+    color: Color,
     data: VecDeque<(f64, f64)>,
+}
+
+impl Series {
+    fn push(&mut self, point: (f64, f64), window_size: usize) {
+        if self.data.len() >= window_size {
+            self.data.pop_front();
+        }
+        self.data.push_back(point);
+    }
+
+    fn to_dataset(&self) -> Vec<(f64, f64)> {
+        self.data.iter().cloned().collect()
+    }
+}
+
+struct App {
+    series: Vec<Series>,
+    /// Série em destaque, alternada com Tab.
+    focused: usize,
     window_size: usize,
     x_offset: f64,
+    /// Pausado com `p`: o INFO não é enviado e as séries ficam congeladas.
+    paused: bool,
 }
 
 impl App {
     fn new() -> Self {
+        Self::with_window(100)
+    }
+
+    fn with_window(window_size: usize) -> Self {
+        let series = METRICS
+            .iter()
+            .map(|&(name, field, color)| Series {
+                name,
+                field,
+                color,
+                data: VecDeque::with_capacity(window_size),
+            })
+            .collect();
         Self {
-            data: VecDeque::with_capacity(100),
-            window_size: 100,
+            series,
+            focused: 0,
+            window_size,
             x_offset: 0.0,
+            paused: false,
         }
     }
 
-    fn add_point(&mut self, y: f64) {
+    /// Registra uma amostra do INFO: um ponto por série cujo campo veio na
+    /// resposta, todas no mesmo X.
+    fn add_sample(&mut self, info: &HashMap<String, f64>) {
         self.x_offset += 1.0;
-        if self.data.len() >= self.window_size {
-            self.data.pop_front();
+        for series in &mut self.series {
+            if let Some(&y) = info.get(series.field) {
+                series.push((self.x_offset, y), self.window_size);
+            }
         }
-        self.data.push_back((self.x_offset, y));
     }
 
-    fn to_dataset(&self) -> Vec<(f64, f64)> {
-        self.data.iter().cloned().collect()
+    fn focus_next(&mut self) {
+        self.focused = (self.focused + 1) % self.series.len();
+    }
+
+    /// Limpa as séries (tecla `c`); o eixo X continua de onde estava.
+    fn clear(&mut self) {
+        for series in &mut self.series {
+            series.data.clear();
+        }
+    }
+}
+
+/// Extrai os campos numéricos (`campo:valor`) de uma resposta do INFO. O
+/// total de chaves vem de `db0:keys=N,...`, que some com o keyspace vazio.
+fn parse_info(text: &str) -> HashMap<String, f64> {
+    let mut fields = HashMap::new();
+    if text.contains("# Keyspace") {
+        fields.insert("keys".to_string(), 0.0);
+    }
+    for line in text.lines() {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        if name == "db0" {
+            let keys = value
+                .split(',')
+                .find_map(|kv| kv.strip_prefix("keys="))
+                .and_then(|n| n.parse().ok());
+            if let Some(keys) = keys {
+                fields.insert("keys".to_string(), keys);
+            }
+        } else if let Ok(value) = value.trim().parse() {
+            fields.insert(name.to_string(), value);
+        }
+    }
+    fields
+}
+
+/// Envia `INFO` e lê a resposta inteira (um bulk string).
+async fn fetch_info(stream: &mut BufReader<TcpStream>) -> Result<String> {
+    stream
+        .get_mut()
+        .write_all(b"*1\r\n$4\r\nINFO\r\n")
+        .await?;
+
+    let mut header = String::new();
+    if stream.read_line(&mut header).await? == 0 {
+        anyhow::bail!("conexão fechada pelo servidor");
     }
+    let len: usize = header
+        .trim_end()
+        .strip_prefix('$')
+        .and_then(|n| n.parse().ok())
+        .ok_or_else(|| anyhow::anyhow!("resposta inesperada ao INFO: {}", header.trim_end()))?;
+
+    let mut body = vec![0u8; len + 2];
+    stream.read_exact(&mut body).await?;
+    body.truncate(len);
+    Ok(String::from_utf8_lossy(&body).into_owned())
 }
 
 #[tokio::main]
@@ -70,41 +177,45 @@ async fn main() -> Result<()> {
     let mut ticker = interval(Duration::from_secs(1));
 
     // Connection loop
-    let mut stream = TcpStream::connect(&addr).await?;
+    let mut stream = BufReader::new(TcpStream::connect(&addr).await?);
 
     // UI Loop
     loop {
         // Draw
         terminal.draw(|f| ui(f, &app, &addr))?;
 
-        // Handle Input (Non-blocking check)
-        if event::poll(Duration::from_millis(0))?
-            && let Event::Key(key) = event::read()?
-                && key.code == KeyCode::Char('q') {
-                    break;
+        // Handle Input (Non-blocking check): drena todos os eventos
+        // pendentes, senão um resize em rajada (tmux) fica na fila
+        let mut quit = false;
+        let mut redraw = false;
+        while event::poll(Duration::from_millis(0))? {
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => {
+                    quit |= handle_key(&mut app, key);
+                    redraw = true;
                 }
+                // O draw ajusta o layout ao novo tamanho; só redesenha já
+                Event::Resize(_, _) => redraw = true,
+                _ => {}
+            }
+        }
+        if quit {
+            break;
+        }
+        if redraw {
+            continue;
+        }
 
         // Update Data (Tick)
         tokio::select! {
             _ = ticker.tick() => {
-                // Send DBSIZE (*1\r\n$6\r\nDBSIZE\r\n)
-                let cmd = "*1\r\n$6\r\nDBSIZE\r\n";
-                if stream.write_all(cmd.as_bytes()).await.is_err() {
-                     // Try reconnect logic would go here
-                     break;
+                if app.paused {
+                    continue;
                 }
-
-                // Read Response (Simple parser assuming integer response :123\r\n)
-                let mut buf = [0u8; 128];
-                match stream.read(&mut buf).await {
-                    Ok(n) if n > 0 => {
-                        let s = String::from_utf8_lossy(&buf[..n]);
-                        if s.starts_with(':')
-                             && let Ok(val) = s.trim()[1..].parse::<f64>() {
-                                 app.add_point(val);
-                             }
-                    }
-                    _ => break,
+                match fetch_info(&mut stream).await {
+                    Ok(text) => app.add_sample(&parse_info(&text)),
+                    // Try reconnect logic would go here
+                    Err(_) => break,
                 }
             }
         }
@@ -118,6 +229,19 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+/// Aplica uma tecla ao estado. Retorna `true` para sair (`q` ou Ctrl-C).
+fn handle_key(app: &mut App, key: KeyEvent) -> bool {
+    match key.code {
+        KeyCode::Char('q') => return true,
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return true,
+        KeyCode::Char('p') => app.paused = !app.paused,
+        KeyCode::Char('c') => app.clear(),
+        KeyCode::Tab => app.focus_next(),
+        _ => {}
+    }
+    false
+}
+
 fn ui(f: &mut Frame, app: &App, addr: &str) {
     let size = f.size();
     let chunks = Layout::default()
@@ -126,18 +250,37 @@ fn ui(f: &mut Frame, app: &App, addr: &str) {
         .split(size);
 
     // Header
-    let title = Paragraph::new(format!("StormDB Monitor - Connected to {}", addr))
+    let state = if app.paused { " [PAUSED]" } else { "" };
+    let title = Paragraph::new(format!(
+        "StormDB Monitor - Connected to {}{}  (q: quit, p: pause, c: clear, Tab: focus)",
+        addr, state
+    ))
         .block(Block::default().borders(Borders::ALL).title("Status"))
         .style(Style::default().fg(Color::Cyan));
     f.render_widget(title, chunks[0]);
 
-    // Chart
-    let data_points = app.to_dataset();
+    // Um gráfico por série, empilhados; o focado ganha o dobro da altura
+    let weights: Vec<u32> = (0..app.series.len())
+        .map(|i| if i == app.focused { 2 } else { 1 })
+        .collect();
+    let total: u32 = weights.iter().sum();
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(weights.iter().map(|&w| Constraint::Ratio(w, total)))
+        .split(chunks[1]);
+
+    for (i, series) in app.series.iter().enumerate() {
+        render_series(f, app, series, i == app.focused, rows[i]);
+    }
+}
+
+fn render_series(f: &mut Frame, app: &App, series: &Series, focused: bool, area: Rect) {
+    let data_points = series.to_dataset();
     let dataset = vec![
         Dataset::default()
-            .name("Keys")
+            .name(series.name)
             .marker(symbols::Marker::Braille)
-            .style(Style::default().fg(Color::Yellow))
+            .style(Style::default().fg(series.color))
             .graph_type(GraphType::Line)
             .data(&data_points),
     ];
@@ -153,13 +296,19 @@ fn ui(f: &mut Frame, app: &App, addr: &str) {
         ),
     ];
 
-    let max_y = app.data.iter().map(|(_, y)| *y).fold(0.0, f64::max) + 10.0;
+    let max_y = series.data.iter().map(|(_, y)| *y).fold(0.0, f64::max) + 10.0;
 
+    let border = if focused {
+        Style::default().fg(series.color)
+    } else {
+        Style::default()
+    };
     let chart = Chart::new(dataset)
         .block(
             Block::default()
-                .title("Keys over Time")
-                .borders(Borders::ALL),
+                .title(format!("{} over Time", series.name))
+                .borders(Borders::ALL)
+                .border_style(border),
         )
         .x_axis(
             Axis::default()
@@ -182,5 +331,54 @@ fn ui(f: &mut Frame, app: &App, addr: &str) {
                 ]),
         );
 
-    f.render_widget(chart, chunks[1]);
+    f.render_widget(chart, area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(keys: f64) -> HashMap<String, f64> {
+        HashMap::from([("keys".to_string(), keys)])
+    }
+
+    #[test]
+    fn series_keep_only_the_window() {
+        let mut app = App::with_window(3);
+        for keys in 1..=5 {
+            app.add_sample(&sample(keys as f64));
+        }
+
+        let keys = &app.series[0];
+        assert_eq!(keys.to_dataset(), vec![(3.0, 3.0), (4.0, 4.0), (5.0, 5.0)]);
+        // Métricas ausentes na resposta não ganham pontos
+        assert!(app.series[1].data.is_empty());
+
+        app.clear();
+        app.add_sample(&sample(9.0));
+        assert_eq!(app.series[0].to_dataset(), vec![(6.0, 9.0)]);
+    }
+
+    #[test]
+    fn tab_cycles_focus() {
+        let mut app = App::new();
+        for expected in [1, 2, 0] {
+            app.focus_next();
+            assert_eq!(app.focused, expected);
+        }
+    }
+
+    #[test]
+    fn parse_info_fields() {
+        let text = "# Clients\r\nconnected_clients:2\r\n\r\n# Stats\r\n\
+                    instantaneous_ops_per_sec:150\r\n\r\n# Keyspace\r\n\
+                    db0:keys=7,strings=5,lists=2\r\n";
+        let fields = parse_info(text);
+        assert_eq!(fields["keys"], 7.0);
+        assert_eq!(fields["connected_clients"], 2.0);
+        assert_eq!(fields["instantaneous_ops_per_sec"], 150.0);
+
+        // Keyspace vazio: a seção vem sem a linha db0
+        assert_eq!(parse_info("# Keyspace\r\n")["keys"], 0.0);
+    }
 }
@@ -73,6 +73,21 @@ fn bench_parse_set_command(c: &mut Criterion) {
     });
 }
 
+fn bench_parse_three_element_command(c: &mut Criterion) {
+    let frame = Frame::array_from_strs(&["SET", "mykey", "myvalue"]);
+    let mut buf = BytesMut::new();
+    frame.encode(&mut buf);
+    let encoded = buf.freeze();
+
+    c.bench_function("parse_three_element_command", |b| {
+        b.iter(|| {
+            let mut cursor = Cursor::new(black_box(encoded.as_ref()));
+            let frame = Frame::parse(&mut cursor).unwrap();
+            Command::from_frame(frame).unwrap()
+        })
+    });
+}
+
 fn bench_roundtrip_array(c: &mut Criterion) {
     let frame = Frame::Array(vec![
         Frame::Simple("OK".into()),
@@ -100,6 +115,7 @@ criterion_group!(
     bench_parse_bulk_1kb,
     bench_encode_bulk_1kb,
     bench_parse_set_command,
+    bench_parse_three_element_command,
     bench_roundtrip_array,
 );
 criterion_main!(benches);
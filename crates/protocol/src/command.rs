@@ -1,7 +1,7 @@
 use bytes::Bytes;
 use stormdb_common::CommandError;
 
-use crate::{Frame, Parse};
+use crate::{CommandSpec, Frame, Parse, lookup_command};
 
 /// Condição para SET (NX ou XX).
 #[derive(Debug, Clone, PartialEq)]
@@ -12,11 +12,178 @@ pub enum SetCondition {
     Xx,
 }
 
+/// Condição para EXPIRE/PEXPIRE.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExpireCondition {
+    /// Só aplica se a chave não tiver TTL.
+    Nx,
+    /// Só aplica se a chave já tiver TTL.
+    Xx,
+    /// Só aplica se o novo TTL for maior que o atual (sem TTL = infinito).
+    Gt,
+    /// Só aplica se o novo TTL for menor que o atual (sem TTL = infinito).
+    Lt,
+}
+
+impl ExpireCondition {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExpireCondition::Nx => "NX",
+            ExpireCondition::Xx => "XX",
+            ExpireCondition::Gt => "GT",
+            ExpireCondition::Lt => "LT",
+        }
+    }
+}
+
+/// TTL pedido no SET/INCR, sempre em ms.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Expiry {
+    /// `EX`/`PX`: relativo ao momento em que o comando roda.
+    Px(u64),
+    /// `EXAT`/`PXAT`: instante absoluto, em ms desde a época Unix. É a forma
+    /// gravada no AOF e enviada às réplicas, para que um replay horas depois
+    /// expire a chave no mesmo horário.
+    PxAt(u64),
+}
+
+impl Expiry {
+    /// ms que faltam a partir de `now_unix_ms` (0 se o instante já passou).
+    pub fn remaining_ms(self, now_unix_ms: u64) -> u64 {
+        match self {
+            Expiry::Px(ms) => ms,
+            Expiry::PxAt(at) => at.saturating_sub(now_unix_ms),
+        }
+    }
+
+    /// Converte para `PxAt` tomando `now_unix_ms` como o momento atual.
+    /// `None` se o instante passa de i64::MAX ms, que o parser não lê de
+    /// volta: o comando deve falhar com `InvalidExpireTime`, como no Redis.
+    pub fn absolute(self, now_unix_ms: u64) -> Option<Self> {
+        match self {
+            Expiry::Px(ms) => now_unix_ms
+                .checked_add(ms)
+                .filter(|&at| at <= i64::MAX as u64)
+                .map(Expiry::PxAt),
+            at => Some(at),
+        }
+    }
+
+    fn push_to(self, parts: &mut Vec<Frame>) {
+        let (name, ms) = match self {
+            Expiry::Px(ms) => ("PX", ms),
+            Expiry::PxAt(at) => ("PXAT", at),
+        };
+        parts.push(Frame::bulk(name));
+        parts.push(Frame::bulk(&ms.to_string()));
+    }
+}
+
 /// Opções do comando SET.
 #[derive(Debug, Clone, PartialEq)]
 pub struct SetOptions {
-    pub expire_ms: Option<u64>,
+    pub expire: Option<Expiry>,
     pub condition: Option<SetCondition>,
+    /// `GET`: responde com o valor anterior em vez de OK.
+    pub get: bool,
+    /// `PERSIST`: grava sem TTL, ignorando o `--default-ttl` do servidor.
+    pub persist: bool,
+}
+
+/// Subcomandos do CLIENT.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientCommand {
+    /// Id da conexão atual.
+    Id,
+    /// Segura o processamento de comandos dos clientes por `ms`.
+    Pause { ms: u64, mode: PauseMode },
+    /// Encerra uma pausa antes do prazo.
+    Unpause,
+}
+
+/// Quais comandos o `CLIENT PAUSE` segura.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseMode {
+    /// Todos os comandos.
+    All,
+    /// Só os de escrita; leituras e pub/sub seguem normalmente.
+    Write,
+}
+
+/// Subcomandos do MEMORY.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MemoryCommand {
+    /// Estatísticas agregadas de memória do keyspace.
+    Stats,
+}
+
+/// Subcomandos do OBJECT.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ObjectCommand {
+    /// Encoding interno reportado para a chave (`int`, `embstr`, `raw`...).
+    Encoding(Bytes),
+}
+
+/// Subcomandos do CONFIG.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigCommand {
+    /// Zera as estatísticas de runtime do INFO.
+    ResetStat,
+}
+
+/// Subcomandos do CLUSTER. O StormDB não tem cluster: eles só existem
+/// para drivers cluster-aware conseguirem se conectar a um standalone.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClusterCommand {
+    /// Bulk com `cluster_enabled:0`.
+    Info,
+    /// Array vazio: nenhum slot atribuído.
+    Slots,
+    /// Array vazio: nenhum nó de cluster.
+    Nodes,
+}
+
+impl ClusterCommand {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ClusterCommand::Info => "INFO",
+            ClusterCommand::Slots => "SLOTS",
+            ClusterCommand::Nodes => "NODES",
+        }
+    }
+}
+
+/// Operação do BITOP.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BitOp {
+    And,
+    Or,
+    Xor,
+    Not,
+}
+
+impl BitOp {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BitOp::And => "AND",
+            BitOp::Or => "OR",
+            BitOp::Xor => "XOR",
+            BitOp::Not => "NOT",
+        }
+    }
+}
+
+/// Subcomandos do DEBUG (só aceitos com `--enable-debug`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum DebugCommand {
+    /// Persiste o dataset, limpa a memória e recarrega do disco.
+    Reload,
+    /// Gera um novo replid, forçando full resync nas réplicas.
+    ChangeReplId,
+    /// Informações internas da chave (encoding, tamanho serializado).
+    Object(Bytes),
+    /// Liga/desliga a remoção ativa de chaves vencidas.
+    SetActiveExpire(bool),
 }
 
 /// Enum com todos os comandos suportados.
@@ -24,187 +191,263 @@ pub struct SetOptions {
 pub enum Command {
     Ping(Option<Bytes>),
     Echo(Bytes),
-    Get(String),
+    Get(Bytes),
     Set {
-        key: String,
+        key: Bytes,
         value: Bytes,
         options: SetOptions,
     },
-    Del(Vec<String>),
-    Exists(Vec<String>),
-    Incr(String),
-    Decr(String),
+    Del(Vec<Bytes>),
+    Exists(Vec<Bytes>),
+    /// `EXISTSATLEAST key [key ...] n`: 1 se pelo menos `n` das chaves existem.
+    ExistsAtLeast {
+        keys: Vec<Bytes>,
+        n: usize,
+    },
+    /// EXPIRE/PEXPIRE; o TTL é sempre guardado em ms.
+    Expire {
+        key: Bytes,
+        ms: i64,
+        condition: Option<ExpireCondition>,
+    },
+    /// EXPIREAT/PEXPIREAT: TTL até um instante absoluto, sempre guardado em
+    /// ms desde a época Unix.
+    ExpireAt {
+        key: Bytes,
+        unix_ms: i64,
+        condition: Option<ExpireCondition>,
+    },
+    /// TTL restante em segundos (-2 sem a chave, -1 sem TTL).
+    Ttl(Bytes),
+    /// Como TTL, em ms.
+    Pttl(Bytes),
+    /// INCR, com `EX`/`PX`/`EXAT`/`PXAT` opcional: o TTL só é aplicado
+    /// quando o INCR cria a chave (contador de janela fixa para rate limiting).
+    Incr {
+        key: Bytes,
+        expire: Option<Expiry>,
+    },
+    Decr(Bytes),
+    /// GETRANGE: bytes de `start` a `end` (inclusivo; negativos contam do fim).
+    GetRange {
+        key: Bytes,
+        start: i64,
+        end: i64,
+    },
+    /// SETRANGE: sobrescreve a partir de `offset`, completando com zeros.
+    SetRange {
+        key: Bytes,
+        offset: usize,
+        value: Bytes,
+    },
+    Append {
+        key: Bytes,
+        value: Bytes,
+    },
+    /// STRLEN: tamanho em bytes da string (0 se a chave não existe).
+    Strlen(Bytes),
+    /// MGET: o valor de cada chave, na ordem pedida (Null se ausente).
+    MGet(Vec<Bytes>),
+    /// MSET: pares aplicados em ordem, então uma chave repetida fica com o
+    /// último valor.
+    MSet(Vec<(Bytes, Bytes)>),
+    /// MSETNX: grava todos os pares só se nenhuma das chaves existe.
+    MSetNx(Vec<(Bytes, Bytes)>),
     LPush {
-        key: String,
+        key: Bytes,
         values: Vec<Bytes>,
     },
     RPush {
-        key: String,
+        key: Bytes,
+        values: Vec<Bytes>,
+    },
+    /// RPUSHCAP: RPUSH seguido de um trim para os últimos `maxlen`
+    /// elementos, atomicamente.
+    RPushCap {
+        key: Bytes,
+        maxlen: usize,
         values: Vec<Bytes>,
     },
     LPop {
-        key: String,
+        key: Bytes,
         count: Option<usize>,
     },
     RPop {
-        key: String,
+        key: Bytes,
         count: Option<usize>,
     },
     LRange {
-        key: String,
+        key: Bytes,
         start: i64,
         stop: i64,
     },
+    RPopLPush {
+        source: Bytes,
+        destination: Bytes,
+    },
+    /// BRPOPLPUSH; `timeout_ms` 0 bloqueia indefinidamente.
+    BRPopLPush {
+        source: Bytes,
+        destination: Bytes,
+        timeout_ms: u64,
+    },
     Subscribe(Vec<String>),
     Unsubscribe(Vec<String>),
+    /// PSUBSCRIBE: inscrição em padrões glob de canal.
+    PSubscribe(Vec<String>),
+    PUnsubscribe(Vec<String>),
     Publish {
         channel: String,
         message: Bytes,
     },
+    BitOp {
+        op: BitOp,
+        dest: Bytes,
+        keys: Vec<Bytes>,
+    },
+    /// RENAME: move o valor (e o TTL) de `source` para `destination`,
+    /// sobrescrevendo o que houver lá.
+    Rename {
+        source: Bytes,
+        destination: Bytes,
+    },
+    /// RENAMENX: como RENAME, só se `destination` não existe.
+    RenameNx {
+        source: Bytes,
+        destination: Bytes,
+    },
+    /// TYPE: `string`, `list` ou `none`.
+    Type(Bytes),
+    /// KEYS pattern: todas as chaves que casam com o glob.
+    Keys(Bytes),
     DbSize,
+    Time,
+    Info(Option<String>),
+    /// `REPLICAOF host port`, ou `REPLICAOF NO ONE` (`None`).
+    ReplicaOf(Option<(String, u16)>),
+    /// `REPLCONF opção valor [opção valor ...]`, enviado pela réplica antes
+    /// do PSYNC.
+    ReplConf(Vec<(String, String)>),
+    /// Handshake de replicação enviado por uma réplica.
+    Psync {
+        replid: String,
+        offset: i64,
+    },
+    Debug(DebugCommand),
+    Client(ClientCommand),
+    Memory(MemoryCommand),
+    Object(ObjectCommand),
+    Config(ConfigCommand),
+    Cluster(ClusterCommand),
+    /// `COMMAND INFO name...`. Sem nomes (ou `COMMAND` puro) lista todos.
+    CommandInfo(Vec<String>),
+    /// Comando não reconhecido. Guarda só o nome: os argumentos são
+    /// descartados no parse.
     Unknown(String),
 }
 
 impl Command {
     /// Faz o parse de um Frame em um Command.
     pub fn from_frame(frame: Frame) -> Result<Command, CommandError> {
+        Self::from_frame_with_max_args(frame, usize::MAX)
+    }
+
+    /// Como `from_frame`, mas rejeita comandos com mais de `max_args`
+    /// argumentos (sem contar o nome) antes de montar os Vecs de chaves e
+    /// valores.
+    pub fn from_frame_with_max_args(
+        frame: Frame,
+        max_args: usize,
+    ) -> Result<Command, CommandError> {
         let mut parse = Parse::new(frame)?;
-        let cmd_name = parse.next_string()?.to_uppercase();
-
-        let cmd = match cmd_name.as_str() {
-            "PING" => {
-                let msg = if parse.has_remaining() {
-                    Some(parse.next_bytes()?)
-                } else {
-                    None
-                };
-                parse.finish()?;
-                Command::Ping(msg)
-            }
-            "ECHO" => {
-                let msg = parse.next_bytes()?;
-                parse.finish()?;
-                Command::Echo(msg)
-            }
-            "GET" => {
-                let key = parse.next_string()?;
-                parse.finish()?;
-                Command::Get(key)
-            }
-            "SET" => parse_set(&mut parse)?,
-            "DEL" => {
-                if !parse.has_remaining() {
-                    return Err(CommandError::WrongArity("DEL".into()));
-                }
-                let mut keys = Vec::new();
-                while parse.has_remaining() {
-                    keys.push(parse.next_string()?);
-                }
-                Command::Del(keys)
-            }
-            "EXISTS" => {
-                if !parse.has_remaining() {
-                    return Err(CommandError::WrongArity("EXISTS".into()));
-                }
-                let mut keys = Vec::new();
-                while parse.has_remaining() {
-                    keys.push(parse.next_string()?);
-                }
-                Command::Exists(keys)
-            }
-            "INCR" => {
-                let key = parse.next_string()?;
-                parse.finish()?;
-                Command::Incr(key)
-            }
-            "DECR" => {
-                let key = parse.next_string()?;
-                parse.finish()?;
-                Command::Decr(key)
-            }
-            "LPUSH" => {
-                let key = parse.next_string()?;
-                if !parse.has_remaining() {
-                    return Err(CommandError::WrongArity("LPUSH".into()));
-                }
-                let mut values = Vec::new();
-                while parse.has_remaining() {
-                    values.push(parse.next_bytes()?);
-                }
-                Command::LPush { key, values }
-            }
-            "RPUSH" => {
-                let key = parse.next_string()?;
-                if !parse.has_remaining() {
-                    return Err(CommandError::WrongArity("RPUSH".into()));
-                }
-                let mut values = Vec::new();
-                while parse.has_remaining() {
-                    values.push(parse.next_bytes()?);
-                }
-                Command::RPush { key, values }
-            }
-            "LPOP" => {
-                let key = parse.next_string()?;
-                let count = if parse.has_remaining() {
-                    Some(parse.next_int()? as usize)
-                } else {
-                    None
-                };
-                parse.finish()?;
-                Command::LPop { key, count }
-            }
-            "RPOP" => {
-                let key = parse.next_string()?;
-                let count = if parse.has_remaining() {
-                    Some(parse.next_int()? as usize)
-                } else {
-                    None
-                };
-                parse.finish()?;
-                Command::RPop { key, count }
-            }
-            "LRANGE" => {
-                let key = parse.next_string()?;
-                let start = parse.next_int()?;
-                let stop = parse.next_int()?;
-                parse.finish()?;
-                Command::LRange { key, start, stop }
-            }
-            "SUBSCRIBE" => {
-                if !parse.has_remaining() {
-                    return Err(CommandError::WrongArity("SUBSCRIBE".into()));
-                }
-                let mut channels = Vec::new();
-                while parse.has_remaining() {
-                    channels.push(parse.next_string()?);
-                }
-                Command::Subscribe(channels)
-            }
-            "UNSUBSCRIBE" => {
-                let mut channels = Vec::new();
-                while parse.has_remaining() {
-                    channels.push(parse.next_string()?);
-                }
-                Command::Unsubscribe(channels)
-            }
-            "PUBLISH" => {
-                let channel = parse.next_string()?;
-                let message = parse.next_bytes()?;
-                parse.finish()?;
-                Command::Publish { channel, message }
-            }
-            "DBSIZE" => {
-                parse.finish()?;
-                Command::DbSize
-            }
-            _ => Command::Unknown(cmd_name),
+        let cmd_name = parse.next_command()?;
+        if parse.remaining() > max_args {
+            return Err(CommandError::TooManyArguments {
+                command: cmd_name,
+                max: max_args,
+            });
+        }
+        // A aridade é checada pela COMMAND_TABLE antes de despachar para o
+        // parser registrado nela
+        let Some(spec) = lookup_command(&cmd_name) else {
+            return Ok(Command::Unknown(cmd_name));
         };
+        if !spec.accepts(parse.remaining() + 1) {
+            return Err(CommandError::WrongArity(cmd_name));
+        }
+        (spec.parse)(&mut parse)
+    }
+
+    /// Nome do comando em minúsculas, como na `COMMAND_TABLE`. Variantes
+    /// que cobrem mais de um comando usam o nome da forma que `to_frame`
+    /// gera (EXPIRE vira `pexpire`).
+    pub fn name(&self) -> &str {
+        match self {
+            Command::Ping(_) => "ping",
+            Command::Echo(_) => "echo",
+            Command::Get(_) => "get",
+            Command::Set { .. } => "set",
+            Command::Del(_) => "del",
+            Command::Exists(_) => "exists",
+            Command::ExistsAtLeast { .. } => "existsatleast",
+            Command::Expire { .. } => "pexpire",
+            Command::ExpireAt { .. } => "pexpireat",
+            Command::Ttl(_) => "ttl",
+            Command::Pttl(_) => "pttl",
+            Command::Incr { .. } => "incr",
+            Command::Decr(_) => "decr",
+            Command::GetRange { .. } => "getrange",
+            Command::SetRange { .. } => "setrange",
+            Command::Append { .. } => "append",
+            Command::Strlen(_) => "strlen",
+            Command::MSet(_) => "mset",
+            Command::MSetNx(_) => "msetnx",
+            Command::MGet(_) => "mget",
+            Command::LPush { .. } => "lpush",
+            Command::RPush { .. } => "rpush",
+            Command::RPushCap { .. } => "rpushcap",
+            Command::LPop { .. } => "lpop",
+            Command::RPop { .. } => "rpop",
+            Command::LRange { .. } => "lrange",
+            Command::RPopLPush { .. } => "rpoplpush",
+            Command::BRPopLPush { .. } => "brpoplpush",
+            Command::Subscribe(_) => "subscribe",
+            Command::Unsubscribe(_) => "unsubscribe",
+            Command::PSubscribe(_) => "psubscribe",
+            Command::PUnsubscribe(_) => "punsubscribe",
+            Command::Publish { .. } => "publish",
+            Command::BitOp { .. } => "bitop",
+            Command::Rename { .. } => "rename",
+            Command::RenameNx { .. } => "renamenx",
+            Command::Type(_) => "type",
+            Command::Keys(_) => "keys",
+            Command::DbSize => "dbsize",
+            Command::Time => "time",
+            Command::Info(_) => "info",
+            Command::ReplicaOf(_) => "replicaof",
+            Command::ReplConf(_) => "replconf",
+            Command::Psync { .. } => "psync",
+            Command::Debug(_) => "debug",
+            Command::Client(_) => "client",
+            Command::Memory(_) => "memory",
+            Command::Object(_) => "object",
+            Command::Config(_) => "config",
+            Command::Cluster(_) => "cluster",
+            Command::CommandInfo(_) => "command",
+            Command::Unknown(name) => name,
+        }
+    }
 
-        Ok(cmd)
+    /// Entrada do comando na `COMMAND_TABLE`; `None` só para `Unknown`.
+    pub fn spec(&self) -> Option<&'static CommandSpec> {
+        lookup_command(self.name())
     }
 
     /// Encoda o comando como Frame para envio via RESP.
+    ///
+    /// Para todo comando produzido por `from_frame`, exceto `Unknown`,
+    /// `from_frame(cmd.to_frame()) == cmd` (é o que o AOF e a replicação
+    /// usam). `Unknown` vira só o nome, sem os argumentos originais.
     pub fn to_frame(&self) -> Frame {
         match self {
             Command::Ping(None) => Frame::Array(vec![Frame::bulk("PING")]),
@@ -212,7 +455,9 @@ impl Command {
                 Frame::Array(vec![Frame::bulk("PING"), Frame::Bulk(msg.clone())])
             }
             Command::Echo(msg) => Frame::Array(vec![Frame::bulk("ECHO"), Frame::Bulk(msg.clone())]),
-            Command::Get(key) => Frame::Array(vec![Frame::bulk("GET"), Frame::bulk(key)]),
+            Command::Get(key) => Frame::Array(vec![Frame::bulk("GET"), Frame::Bulk(key.clone())]),
+            Command::Ttl(key) => Frame::Array(vec![Frame::bulk("TTL"), Frame::Bulk(key.clone())]),
+            Command::Pttl(key) => Frame::Array(vec![Frame::bulk("PTTL"), Frame::Bulk(key.clone())]),
             Command::Set {
                 key,
                 value,
@@ -220,12 +465,11 @@ impl Command {
             } => {
                 let mut parts = vec![
                     Frame::bulk("SET"),
-                    Frame::bulk(key),
+                    Frame::Bulk(key.clone()),
                     Frame::Bulk(value.clone()),
                 ];
-                if let Some(ms) = options.expire_ms {
-                    parts.push(Frame::bulk("PX"));
-                    parts.push(Frame::bulk(&ms.to_string()));
+                if let Some(expiry) = options.expire {
+                    expiry.push_to(&mut parts);
                 }
                 if let Some(ref cond) = options.condition {
                     match cond {
@@ -233,39 +477,123 @@ impl Command {
                         SetCondition::Xx => parts.push(Frame::bulk("XX")),
                     }
                 }
+                if options.get {
+                    parts.push(Frame::bulk("GET"));
+                }
+                if options.persist {
+                    parts.push(Frame::bulk("PERSIST"));
+                }
                 Frame::Array(parts)
             }
             Command::Del(keys) => {
                 let mut parts = vec![Frame::bulk("DEL")];
-                parts.extend(keys.iter().map(|k| Frame::bulk(k)));
+                parts.extend(keys.iter().cloned().map(Frame::Bulk));
                 Frame::Array(parts)
             }
             Command::Exists(keys) => {
                 let mut parts = vec![Frame::bulk("EXISTS")];
-                parts.extend(keys.iter().map(|k| Frame::bulk(k)));
+                parts.extend(keys.iter().cloned().map(Frame::Bulk));
+                Frame::Array(parts)
+            }
+            Command::ExistsAtLeast { keys, n } => {
+                let mut parts = vec![Frame::bulk("EXISTSATLEAST")];
+                parts.extend(keys.iter().cloned().map(Frame::Bulk));
+                parts.push(Frame::bulk(&n.to_string()));
+                Frame::Array(parts)
+            }
+            Command::Expire { key, ms, condition } => {
+                let mut parts = vec![
+                    Frame::bulk("PEXPIRE"),
+                    Frame::Bulk(key.clone()),
+                    Frame::bulk(&ms.to_string()),
+                ];
+                if let Some(cond) = condition {
+                    parts.push(Frame::bulk(cond.as_str()));
+                }
+                Frame::Array(parts)
+            }
+            Command::ExpireAt {
+                key,
+                unix_ms,
+                condition,
+            } => {
+                let mut parts = vec![
+                    Frame::bulk("PEXPIREAT"),
+                    Frame::Bulk(key.clone()),
+                    Frame::bulk(&unix_ms.to_string()),
+                ];
+                if let Some(cond) = condition {
+                    parts.push(Frame::bulk(cond.as_str()));
+                }
+                Frame::Array(parts)
+            }
+            Command::Incr { key, expire } => {
+                let mut parts = vec![Frame::bulk("INCR"), Frame::Bulk(key.clone())];
+                if let Some(expiry) = expire {
+                    expiry.push_to(&mut parts);
+                }
+                Frame::Array(parts)
+            }
+            Command::Decr(key) => Frame::Array(vec![Frame::bulk("DECR"), Frame::Bulk(key.clone())]),
+            Command::GetRange { key, start, end } => Frame::Array(vec![
+                Frame::bulk("GETRANGE"),
+                Frame::Bulk(key.clone()),
+                Frame::bulk(&start.to_string()),
+                Frame::bulk(&end.to_string()),
+            ]),
+            Command::SetRange { key, offset, value } => Frame::Array(vec![
+                Frame::bulk("SETRANGE"),
+                Frame::Bulk(key.clone()),
+                Frame::bulk(&offset.to_string()),
+                Frame::Bulk(value.clone()),
+            ]),
+            Command::Append { key, value } => Frame::Array(vec![
+                Frame::bulk("APPEND"),
+                Frame::Bulk(key.clone()),
+                Frame::Bulk(value.clone()),
+            ]),
+            Command::Strlen(key) => {
+                Frame::Array(vec![Frame::bulk("STRLEN"), Frame::Bulk(key.clone())])
+            }
+            Command::MSet(pairs) => pairs_to_frame("MSET", pairs),
+            Command::MSetNx(pairs) => pairs_to_frame("MSETNX", pairs),
+            Command::MGet(keys) => {
+                let mut parts = vec![Frame::bulk("MGET")];
+                parts.extend(keys.iter().cloned().map(Frame::Bulk));
                 Frame::Array(parts)
             }
-            Command::Incr(key) => Frame::Array(vec![Frame::bulk("INCR"), Frame::bulk(key)]),
-            Command::Decr(key) => Frame::Array(vec![Frame::bulk("DECR"), Frame::bulk(key)]),
             Command::LPush { key, values } => {
-                let mut parts = vec![Frame::bulk("LPUSH"), Frame::bulk(key)];
+                let mut parts = vec![Frame::bulk("LPUSH"), Frame::Bulk(key.clone())];
                 parts.extend(values.iter().map(|v| Frame::Bulk(v.clone())));
                 Frame::Array(parts)
             }
             Command::RPush { key, values } => {
-                let mut parts = vec![Frame::bulk("RPUSH"), Frame::bulk(key)];
+                let mut parts = vec![Frame::bulk("RPUSH"), Frame::Bulk(key.clone())];
+                parts.extend(values.iter().map(|v| Frame::Bulk(v.clone())));
+                Frame::Array(parts)
+            }
+            Command::RPushCap {
+                key,
+                maxlen,
+                values,
+            } => {
+                let mut parts = vec![
+                    Frame::bulk("RPUSHCAP"),
+                    Frame::Bulk(key.clone()),
+                    Frame::bulk(&maxlen.to_string()),
+                ];
                 parts.extend(values.iter().map(|v| Frame::Bulk(v.clone())));
                 Frame::Array(parts)
             }
             Command::LPop { key, count } => {
-                let mut parts = vec![Frame::bulk("LPOP"), Frame::bulk(key)];
+                let mut parts = vec![Frame::bulk("LPOP"), Frame::Bulk(key.clone())];
                 if let Some(c) = count {
                     parts.push(Frame::bulk(&c.to_string()));
                 }
                 Frame::Array(parts)
             }
             Command::RPop { key, count } => {
-                let mut parts = vec![Frame::bulk("RPOP"), Frame::bulk(key)];
+                let mut parts = vec![Frame::bulk("RPOP"), Frame::Bulk(key.clone())];
                 if let Some(c) = count {
                     parts.push(Frame::bulk(&c.to_string()));
                 }
@@ -273,10 +601,29 @@ impl Command {
             }
             Command::LRange { key, start, stop } => Frame::Array(vec![
                 Frame::bulk("LRANGE"),
-                Frame::bulk(key),
+                Frame::Bulk(key.clone()),
                 Frame::bulk(&start.to_string()),
                 Frame::bulk(&stop.to_string()),
             ]),
+            Command::RPopLPush {
+                source,
+                destination,
+            } => Frame::Array(vec![
+                Frame::bulk("RPOPLPUSH"),
+                Frame::Bulk(source.clone()),
+                Frame::Bulk(destination.clone()),
+            ]),
+            Command::BRPopLPush {
+                source,
+                destination,
+                timeout_ms,
+            } => Frame::Array(vec![
+                Frame::bulk("BRPOPLPUSH"),
+                Frame::Bulk(source.clone()),
+                Frame::Bulk(destination.clone()),
+                // Decimal exato, sem passar por f64
+                Frame::bulk(&format!("{}.{:03}", timeout_ms / 1000, timeout_ms % 1000)),
+            ]),
             Command::Subscribe(channels) => {
                 let mut parts = vec![Frame::bulk("SUBSCRIBE")];
                 parts.extend(channels.iter().map(|c| Frame::bulk(c)));
@@ -287,69 +634,128 @@ impl Command {
                 parts.extend(channels.iter().map(|c| Frame::bulk(c)));
                 Frame::Array(parts)
             }
+            Command::PSubscribe(patterns) => {
+                let mut parts = vec![Frame::bulk("PSUBSCRIBE")];
+                parts.extend(patterns.iter().map(|p| Frame::bulk(p)));
+                Frame::Array(parts)
+            }
+            Command::PUnsubscribe(patterns) => {
+                let mut parts = vec![Frame::bulk("PUNSUBSCRIBE")];
+                parts.extend(patterns.iter().map(|p| Frame::bulk(p)));
+                Frame::Array(parts)
+            }
             Command::Publish { channel, message } => Frame::Array(vec![
                 Frame::bulk("PUBLISH"),
                 Frame::bulk(channel),
                 Frame::Bulk(message.clone()),
             ]),
+            Command::BitOp { op, dest, keys } => {
+                let mut parts = vec![
+                    Frame::bulk("BITOP"),
+                    Frame::bulk(op.as_str()),
+                    Frame::Bulk(dest.clone()),
+                ];
+                parts.extend(keys.iter().cloned().map(Frame::Bulk));
+                Frame::Array(parts)
+            }
+            Command::Rename {
+                source,
+                destination,
+            } => Frame::Array(vec![
+                Frame::bulk("RENAME"),
+                Frame::Bulk(source.clone()),
+                Frame::Bulk(destination.clone()),
+            ]),
+            Command::RenameNx {
+                source,
+                destination,
+            } => Frame::Array(vec![
+                Frame::bulk("RENAMENX"),
+                Frame::Bulk(source.clone()),
+                Frame::Bulk(destination.clone()),
+            ]),
+            Command::Type(key) => Frame::Array(vec![Frame::bulk("TYPE"), Frame::Bulk(key.clone())]),
+            Command::Keys(pattern) => {
+                Frame::Array(vec![Frame::bulk("KEYS"), Frame::Bulk(pattern.clone())])
+            }
             Command::DbSize => Frame::Array(vec![Frame::bulk("DBSIZE")]),
-            Command::Unknown(name) => Frame::Array(vec![Frame::bulk(name)]),
-        }
-    }
-}
-
-fn parse_set(parse: &mut Parse) -> Result<Command, CommandError> {
-    let key = parse.next_string()?;
-    let value = parse.next_bytes()?;
-
-    let mut options = SetOptions {
-        expire_ms: None,
-        condition: None,
-    };
-
-    while parse.has_remaining() {
-        let opt = parse.next_string()?.to_uppercase();
-        match opt.as_str() {
-            "EX" => {
-                let secs = parse.next_int()?;
-                if secs <= 0 {
-                    return Err(CommandError::InvalidSetOption(
-                        "EX deve ser positivo".into(),
-                    ));
-                }
-                options.expire_ms = Some(secs as u64 * 1000);
-            }
-            "PX" => {
-                let ms = parse.next_int()?;
-                if ms <= 0 {
-                    return Err(CommandError::InvalidSetOption(
-                        "PX deve ser positivo".into(),
-                    ));
+            Command::Time => Frame::Array(vec![Frame::bulk("TIME")]),
+            Command::Info(None) => Frame::Array(vec![Frame::bulk("INFO")]),
+            Command::Info(Some(section)) => {
+                Frame::Array(vec![Frame::bulk("INFO"), Frame::bulk(section)])
+            }
+            Command::ReplicaOf(None) => Frame::array_from_strs(&["REPLICAOF", "NO", "ONE"]),
+            Command::ReplicaOf(Some((host, port))) => {
+                Frame::array_from_strs(&["REPLICAOF", host, &port.to_string()])
+            }
+            Command::ReplConf(options) => {
+                let mut parts = vec![Frame::bulk("REPLCONF")];
+                for (option, value) in options {
+                    parts.push(Frame::bulk(option));
+                    parts.push(Frame::bulk(value));
                 }
-                options.expire_ms = Some(ms as u64);
+                Frame::Array(parts)
+            }
+            Command::Psync { replid, offset } => {
+                Frame::array_from_strs(&["PSYNC", replid, &offset.to_string()])
+            }
+            Command::Debug(DebugCommand::Reload) => Frame::array_from_strs(&["DEBUG", "RELOAD"]),
+            Command::Debug(DebugCommand::ChangeReplId) => {
+                Frame::array_from_strs(&["DEBUG", "CHANGE-REPL-ID"])
+            }
+            Command::Debug(DebugCommand::SetActiveExpire(enabled)) => {
+                let enabled = if *enabled { "1" } else { "0" };
+                Frame::array_from_strs(&["DEBUG", "SET-ACTIVE-EXPIRE", enabled])
             }
-            "NX" => {
-                options.condition = Some(SetCondition::Nx);
+            Command::Debug(DebugCommand::Object(key)) => Frame::Array(vec![
+                Frame::bulk("DEBUG"),
+                Frame::bulk("OBJECT"),
+                Frame::Bulk(key.clone()),
+            ]),
+            Command::Client(ClientCommand::Id) => Frame::array_from_strs(&["CLIENT", "ID"]),
+            Command::Client(ClientCommand::Pause { ms, mode }) => {
+                let mode = match mode {
+                    PauseMode::All => "ALL",
+                    PauseMode::Write => "WRITE",
+                };
+                Frame::array_from_strs(&["CLIENT", "PAUSE", &ms.to_string(), mode])
             }
-            "XX" => {
-                options.condition = Some(SetCondition::Xx);
+            Command::Client(ClientCommand::Unpause) => {
+                Frame::array_from_strs(&["CLIENT", "UNPAUSE"])
+            }
+            Command::Memory(MemoryCommand::Stats) => Frame::array_from_strs(&["MEMORY", "STATS"]),
+            Command::Object(ObjectCommand::Encoding(key)) => Frame::Array(vec![
+                Frame::bulk("OBJECT"),
+                Frame::bulk("ENCODING"),
+                Frame::Bulk(key.clone()),
+            ]),
+            Command::Config(ConfigCommand::ResetStat) => {
+                Frame::array_from_strs(&["CONFIG", "RESETSTAT"])
             }
-            other => {
-                return Err(CommandError::InvalidSetOption(other.to_string()));
+            Command::Cluster(sub) => Frame::array_from_strs(&["CLUSTER", sub.as_str()]),
+            Command::CommandInfo(names) => {
+                let mut parts = vec![Frame::bulk("COMMAND"), Frame::bulk("INFO")];
+                parts.extend(names.iter().map(|n| Frame::bulk(n)));
+                Frame::Array(parts)
             }
+            Command::Unknown(name) => Frame::Array(vec![Frame::bulk(name)]),
         }
     }
+}
 
-    Ok(Command::Set {
-        key,
-        value,
-        options,
-    })
+fn pairs_to_frame(name: &str, pairs: &[(Bytes, Bytes)]) -> Frame {
+    let mut parts = vec![Frame::bulk(name)];
+    for (key, value) in pairs {
+        parts.push(Frame::Bulk(key.clone()));
+        parts.push(Frame::Bulk(value.clone()));
+    }
+    Frame::Array(parts)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use stormdb_common::ErrorReply;
 
     #[test]
     fn parse_ping() {
@@ -363,6 +769,14 @@ mod tests {
         let frame = Frame::array_from_strs(&["PING", "hello"]);
         let cmd = Command::from_frame(frame).unwrap();
         assert_eq!(cmd, Command::Ping(Some(Bytes::from("hello"))));
+
+        // Mensagens binárias são mantidas byte a byte
+        let frame = Frame::Array(vec![
+            Frame::bulk("PING"),
+            Frame::Bulk(Bytes::from_static(b"\x00\xff")),
+        ]);
+        let cmd = Command::from_frame(frame).unwrap();
+        assert_eq!(cmd, Command::Ping(Some(Bytes::from_static(b"\x00\xff"))));
     }
 
     #[test]
@@ -389,8 +803,10 @@ mod tests {
                 key: "key".into(),
                 value: Bytes::from("value"),
                 options: SetOptions {
-                    expire_ms: None,
+                    expire: None,
                     condition: None,
+                    get: false,
+                    persist: false,
                 },
             }
         );
@@ -402,7 +818,7 @@ mod tests {
         let cmd = Command::from_frame(frame).unwrap();
         match cmd {
             Command::Set { options, .. } => {
-                assert_eq!(options.expire_ms, Some(10_000));
+                assert_eq!(options.expire, Some(Expiry::Px(10_000)));
                 assert_eq!(options.condition, None);
             }
             _ => panic!("expected Set"),
@@ -415,13 +831,114 @@ mod tests {
         let cmd = Command::from_frame(frame).unwrap();
         match cmd {
             Command::Set { options, .. } => {
-                assert_eq!(options.expire_ms, Some(5000));
+                assert_eq!(options.expire, Some(Expiry::Px(5000)));
                 assert_eq!(options.condition, Some(SetCondition::Nx));
             }
             _ => panic!("expected Set"),
         }
     }
 
+    #[test]
+    fn parse_set_with_exat_pxat() {
+        let frame = Frame::array_from_strs(&["SET", "k", "v", "exat", "1700000000"]);
+        let cmd = Command::from_frame(frame).unwrap();
+        let Command::Set { ref options, .. } = cmd else {
+            panic!("esperava SET, veio {cmd:?}");
+        };
+        assert_eq!(options.expire, Some(Expiry::PxAt(1_700_000_000_000)));
+        // Vai para o AOF como PXAT
+        assert_eq!(Command::from_frame(cmd.to_frame()).unwrap(), cmd);
+
+        let frame = Frame::array_from_strs(&["SET", "k", "v", "PXAT", "0"]);
+        assert!(matches!(
+            Command::from_frame(frame),
+            Err(CommandError::InvalidExpireTime(_))
+        ));
+        let frame = Frame::array_from_strs(&["SET", "k", "v", "PXAT", "1", "PERSIST"]);
+        assert!(Command::from_frame(frame).is_err());
+    }
+
+    #[test]
+    fn absolute_expiry_roundtrips_at_the_extremes() {
+        let now = 1_700_000_000_000;
+        let max = i64::MAX as u64;
+        for (opt, value) in [
+            ("PX", max - now),
+            ("PX", 1),
+            ("EX", (max - now) / 1000),
+            ("PXAT", max),
+            ("EXAT", max / 1000),
+        ] {
+            let frame = Frame::array_from_strs(&["SET", "k", "v", opt, &value.to_string()]);
+            let Command::Set {
+                key,
+                value,
+                options,
+            } = Command::from_frame(frame).unwrap()
+            else {
+                panic!("esperava SET");
+            };
+            // A forma propagada (PXAT) é lida de volta sem mudar
+            let propagated = Command::Set {
+                key,
+                value,
+                options: SetOptions {
+                    expire: Some(options.expire.unwrap().absolute(now).unwrap()),
+                    ..options
+                },
+            };
+            assert_eq!(
+                Command::from_frame(propagated.to_frame()).unwrap(),
+                propagated
+            );
+        }
+
+        // Relativos que passam de i64::MAX ms depois de somados a `now`
+        for expiry in [
+            Expiry::Px(max - now + 1),
+            Expiry::Px(max),
+            Expiry::Px((max / 1000) * 1000),
+        ] {
+            assert_eq!(expiry.absolute(now), None);
+        }
+    }
+
+    #[test]
+    fn parse_set_bad_expire_options() {
+        let reply = |args: &[&str]| {
+            Command::from_frame(Frame::array_from_strs(args))
+                .unwrap_err()
+                .reply()
+        };
+        assert_eq!(
+            reply(&["SET", "k", "v", "EX", "abc"]),
+            "ERR value is not an integer or out of range"
+        );
+        assert_eq!(reply(&["SET", "k", "v", "EX"]), "ERR syntax error");
+        assert_eq!(reply(&["SET", "k", "v", "NX", "PX"]), "ERR syntax error");
+        assert_eq!(
+            reply(&["SET", "k", "v", "EX", "10", "EX", "20"]),
+            "ERR syntax error"
+        );
+        assert_eq!(
+            reply(&["SET", "k", "v", "EX", "10", "PX", "20"]),
+            "ERR syntax error"
+        );
+        assert_eq!(
+            reply(&["SET", "k", "v", "EX", "-1"]),
+            "ERR invalid expire time in 'set' command"
+        );
+        // EX em ms não cabe em i64: erro, não um TTL saturado
+        assert_eq!(
+            reply(&["SET", "k", "v", "EX", "9223372036854775807"]),
+            "ERR invalid expire time in 'set' command"
+        );
+        assert_eq!(
+            reply(&["INCR", "k", "EXAT", "9223372036854776"]),
+            "ERR invalid expire time in 'incr' command"
+        );
+    }
+
     #[test]
     fn parse_set_xx() {
         let frame = Frame::array_from_strs(&["SET", "key", "value", "XX"]);
@@ -441,6 +958,20 @@ mod tests {
         assert_eq!(cmd, Command::Del(vec!["a".into(), "b".into(), "c".into()]));
     }
 
+    #[test]
+    fn parse_rejects_too_many_arguments() {
+        let frame = Frame::array_from_strs(&["DEL", "a", "b", "c"]);
+        let err = Command::from_frame_with_max_args(frame, 2).unwrap_err();
+        assert!(matches!(
+            err,
+            CommandError::TooManyArguments { ref command, max: 2 } if command == "DEL"
+        ));
+
+        // No limite exato ainda é aceito
+        let frame = Frame::array_from_strs(&["DEL", "a", "b"]);
+        assert!(Command::from_frame_with_max_args(frame, 2).is_ok());
+    }
+
     #[test]
     fn parse_exists() {
         let frame = Frame::array_from_strs(&["EXISTS", "key1"]);
@@ -449,66 +980,201 @@ mod tests {
     }
 
     #[test]
-    fn parse_incr_decr() {
-        let frame = Frame::array_from_strs(&["INCR", "counter"]);
+    fn parse_exists_at_least() {
+        let frame = Frame::array_from_strs(&["EXISTSATLEAST", "a", "b", "c", "2"]);
+        let cmd = Command::from_frame(frame).unwrap();
         assert_eq!(
-            Command::from_frame(frame).unwrap(),
-            Command::Incr("counter".into())
+            cmd,
+            Command::ExistsAtLeast {
+                keys: vec!["a".into(), "b".into(), "c".into()],
+                n: 2,
+            }
         );
+        assert_eq!(Command::from_frame(cmd.to_frame()).unwrap(), cmd);
 
-        let frame = Frame::array_from_strs(&["DECR", "counter"]);
+        let frame = Frame::array_from_strs(&["EXISTSATLEAST", "2"]);
+        assert!(Command::from_frame(frame).is_err());
+        let frame = Frame::array_from_strs(&["EXISTSATLEAST", "a", "-1"]);
+        assert!(Command::from_frame(frame).is_err());
+    }
+
+    #[test]
+    fn parse_expire_pexpire() {
+        let frame = Frame::array_from_strs(&["EXPIRE", "k", "10", "gt"]);
         assert_eq!(
             Command::from_frame(frame).unwrap(),
-            Command::Decr("counter".into())
+            Command::Expire {
+                key: "k".into(),
+                ms: 10_000,
+                condition: Some(ExpireCondition::Gt),
+            }
         );
-    }
 
-    #[test]
-    fn parse_lpush_rpush() {
-        let frame = Frame::array_from_strs(&["LPUSH", "list", "a", "b"]);
+        let frame = Frame::array_from_strs(&["PEXPIRE", "k", "1500"]);
         let cmd = Command::from_frame(frame).unwrap();
         assert_eq!(
             cmd,
-            Command::LPush {
-                key: "list".into(),
-                values: vec![Bytes::from("a"), Bytes::from("b")],
+            Command::Expire {
+                key: "k".into(),
+                ms: 1500,
+                condition: None,
             }
         );
+        assert_eq!(Command::from_frame(cmd.to_frame()).unwrap(), cmd);
     }
 
     #[test]
-    fn parse_lpop_rpop() {
-        let frame = Frame::array_from_strs(&["LPOP", "list"]);
+    fn parse_expire_flags_are_exclusive() {
+        for flags in [["NX", "XX"], ["GT", "LT"], ["NX", "GT"]] {
+            let frame = Frame::array_from_strs(&["EXPIRE", "k", "10", flags[0], flags[1]]);
+            assert!(Command::from_frame(frame).is_err());
+        }
+        let frame = Frame::array_from_strs(&["EXPIRE", "k", "10", "FOO"]);
+        assert!(Command::from_frame(frame).is_err());
+
+        let frame = Frame::array_from_strs(&["PEXPIREAT", "k", "1700000000000", "NX"]);
         assert_eq!(
             Command::from_frame(frame).unwrap(),
-            Command::LPop {
-                key: "list".into(),
-                count: None,
+            Command::ExpireAt {
+                key: "k".into(),
+                unix_ms: 1_700_000_000_000,
+                condition: Some(ExpireCondition::Nx),
             }
         );
 
-        let frame = Frame::array_from_strs(&["RPOP", "list", "3"]);
+        // EXPIREAT em segundos vira PEXPIREAT em ms
+        let frame = Frame::array_from_strs(&["EXPIREAT", "k", "1700000000"]);
+        let cmd = Command::from_frame(frame).unwrap();
         assert_eq!(
-            Command::from_frame(frame).unwrap(),
-            Command::RPop {
-                key: "list".into(),
-                count: Some(3),
+            cmd,
+            Command::ExpireAt {
+                key: "k".into(),
+                unix_ms: 1_700_000_000_000,
+                condition: None,
             }
         );
+        assert_eq!(Command::from_frame(cmd.to_frame()).unwrap(), cmd);
     }
 
     #[test]
-    fn parse_lrange() {
-        let frame = Frame::array_from_strs(&["LRANGE", "list", "0", "-1"]);
+    fn parse_incr_decr() {
+        let frame = Frame::array_from_strs(&["INCR", "counter"]);
         assert_eq!(
             Command::from_frame(frame).unwrap(),
-            Command::LRange {
-                key: "list".into(),
-                start: 0,
-                stop: -1,
+            Command::Incr {
+                key: "counter".into(),
+                expire: None
             }
         );
-    }
+
+        let frame = Frame::array_from_strs(&["incr", "counter", "ex", "60"]);
+        assert_eq!(
+            Command::from_frame(frame).unwrap(),
+            Command::Incr {
+                key: "counter".into(),
+                expire: Some(Expiry::Px(60_000))
+            }
+        );
+
+        let frame = Frame::array_from_strs(&["INCR", "counter", "PXAT", "1700000000000"]);
+        assert_eq!(
+            Command::from_frame(frame).unwrap(),
+            Command::Incr {
+                key: "counter".into(),
+                expire: Some(Expiry::PxAt(1_700_000_000_000))
+            }
+        );
+        for args in [
+            &["INCR", "counter", "EX", "0"][..],
+            &["INCR", "counter", "KEEPTTL"],
+            &["INCR", "counter", "PX"],
+        ] {
+            assert!(Command::from_frame(Frame::array_from_strs(args)).is_err());
+        }
+
+        let frame = Frame::array_from_strs(&["DECR", "counter"]);
+        assert_eq!(
+            Command::from_frame(frame).unwrap(),
+            Command::Decr("counter".into())
+        );
+    }
+
+    #[test]
+    fn parse_lpush_rpush() {
+        let frame = Frame::array_from_strs(&["LPUSH", "list", "a", "b"]);
+        let cmd = Command::from_frame(frame).unwrap();
+        assert_eq!(
+            cmd,
+            Command::LPush {
+                key: "list".into(),
+                values: vec![Bytes::from("a"), Bytes::from("b")],
+            }
+        );
+    }
+
+    #[test]
+    fn parse_lpop_rpop() {
+        let frame = Frame::array_from_strs(&["LPOP", "list"]);
+        assert_eq!(
+            Command::from_frame(frame).unwrap(),
+            Command::LPop {
+                key: "list".into(),
+                count: None,
+            }
+        );
+
+        let frame = Frame::array_from_strs(&["RPOP", "list", "3"]);
+        assert_eq!(
+            Command::from_frame(frame).unwrap(),
+            Command::RPop {
+                key: "list".into(),
+                count: Some(3),
+            }
+        );
+
+        let frame = Frame::array_from_strs(&["LPOP", "list", "-1"]);
+        assert!(Command::from_frame(frame).is_err());
+    }
+
+    #[test]
+    fn parse_lrange() {
+        let frame = Frame::array_from_strs(&["LRANGE", "list", "0", "-1"]);
+        assert_eq!(
+            Command::from_frame(frame).unwrap(),
+            Command::LRange {
+                key: "list".into(),
+                start: 0,
+                stop: -1,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_rpoplpush_brpoplpush() {
+        let frame = Frame::array_from_strs(&["RPOPLPUSH", "jobs", "processing"]);
+        assert_eq!(
+            Command::from_frame(frame).unwrap(),
+            Command::RPopLPush {
+                source: "jobs".into(),
+                destination: "processing".into(),
+            }
+        );
+
+        let frame = Frame::array_from_strs(&["BRPOPLPUSH", "jobs", "processing", "0.5"]);
+        let cmd = Command::from_frame(frame).unwrap();
+        assert_eq!(
+            cmd,
+            Command::BRPopLPush {
+                source: "jobs".into(),
+                destination: "processing".into(),
+                timeout_ms: 500,
+            }
+        );
+        assert_eq!(Command::from_frame(cmd.to_frame()).unwrap(), cmd);
+
+        let frame = Frame::array_from_strs(&["BRPOPLPUSH", "a", "b", "-1"]);
+        assert!(Command::from_frame(frame).is_err());
+    }
 
     #[test]
     fn parse_subscribe() {
@@ -531,6 +1197,378 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_time() {
+        let frame = Frame::array_from_strs(&["TIME"]);
+        assert_eq!(Command::from_frame(frame).unwrap(), Command::Time);
+
+        let frame = Frame::array_from_strs(&["TIME", "extra"]);
+        assert!(Command::from_frame(frame).is_err());
+    }
+
+    #[test]
+    fn parse_info() {
+        let frame = Frame::array_from_strs(&["INFO"]);
+        assert_eq!(Command::from_frame(frame).unwrap(), Command::Info(None));
+
+        let frame = Frame::array_from_strs(&["INFO", "persistence"]);
+        assert_eq!(
+            Command::from_frame(frame).unwrap(),
+            Command::Info(Some("persistence".into()))
+        );
+    }
+
+    #[test]
+    fn parse_replicaof() {
+        let frame = Frame::array_from_strs(&["REPLICAOF", "10.0.0.1", "6399"]);
+        assert_eq!(
+            Command::from_frame(frame).unwrap(),
+            Command::ReplicaOf(Some(("10.0.0.1".into(), 6399)))
+        );
+        let frame = Frame::array_from_strs(&["replicaof", "no", "one"]);
+        assert_eq!(
+            Command::from_frame(frame).unwrap(),
+            Command::ReplicaOf(None)
+        );
+        let frame = Frame::array_from_strs(&["REPLICAOF", "host", "notaport"]);
+        assert!(Command::from_frame(frame).is_err());
+    }
+
+    #[test]
+    fn parse_debug_reload() {
+        let frame = Frame::array_from_strs(&["DEBUG", "reload"]);
+        assert_eq!(
+            Command::from_frame(frame).unwrap(),
+            Command::Debug(DebugCommand::Reload)
+        );
+
+        let frame = Frame::array_from_strs(&["DEBUG", "change-repl-id"]);
+        assert_eq!(
+            Command::from_frame(frame).unwrap(),
+            Command::Debug(DebugCommand::ChangeReplId)
+        );
+
+        let frame = Frame::array_from_strs(&["debug", "set-active-expire", "0"]);
+        assert_eq!(
+            Command::from_frame(frame).unwrap(),
+            Command::Debug(DebugCommand::SetActiveExpire(false))
+        );
+
+        let frame = Frame::array_from_strs(&["DEBUG", "SEGFAULT"]);
+        assert!(Command::from_frame(frame).is_err());
+    }
+
+    #[test]
+    fn parse_psync() {
+        let frame = Frame::array_from_strs(&["PSYNC", "?", "-1"]);
+        let cmd = Command::from_frame(frame).unwrap();
+        assert_eq!(
+            cmd,
+            Command::Psync {
+                replid: "?".into(),
+                offset: -1,
+            }
+        );
+        assert_eq!(Command::from_frame(cmd.to_frame()).unwrap(), cmd);
+    }
+
+    #[test]
+    fn parse_replconf() {
+        let frame =
+            Frame::array_from_strs(&["REPLCONF", "CAPA", "psync2", "listening-port", "6380"]);
+        let cmd = Command::from_frame(frame).unwrap();
+        assert_eq!(
+            cmd,
+            Command::ReplConf(vec![
+                ("capa".into(), "psync2".into()),
+                ("listening-port".into(), "6380".into()),
+            ])
+        );
+        assert_eq!(Command::from_frame(cmd.to_frame()).unwrap(), cmd);
+
+        // Opção sem valor
+        let frame = Frame::array_from_strs(&["REPLCONF", "capa"]);
+        assert!(Command::from_frame(frame).is_err());
+        let frame = Frame::array_from_strs(&["REPLCONF"]);
+        assert!(Command::from_frame(frame).is_err());
+    }
+
+    #[test]
+    fn parse_set_with_get() {
+        let frame = Frame::array_from_strs(&["SET", "k", "v", "NX", "get"]);
+        let cmd = Command::from_frame(frame).unwrap();
+        assert_eq!(
+            cmd,
+            Command::Set {
+                key: "k".into(),
+                value: Bytes::from("v"),
+                options: SetOptions {
+                    expire: None,
+                    condition: Some(SetCondition::Nx),
+                    get: true,
+                    persist: false,
+                },
+            }
+        );
+        assert_eq!(Command::from_frame(cmd.to_frame()).unwrap(), cmd);
+    }
+
+    #[test]
+    fn parse_set_persist() {
+        let frame = Frame::array_from_strs(&["SET", "k", "v", "persist"]);
+        let cmd = Command::from_frame(frame).unwrap();
+        let Command::Set { ref options, .. } = cmd else {
+            panic!("esperava SET, veio {cmd:?}");
+        };
+        assert!(options.persist && options.expire.is_none());
+        assert_eq!(Command::from_frame(cmd.to_frame()).unwrap(), cmd);
+
+        // PERSIST com TTL explícito é contraditório
+        let frame = Frame::array_from_strs(&["SET", "k", "v", "EX", "10", "PERSIST"]);
+        assert!(matches!(
+            Command::from_frame(frame),
+            Err(CommandError::InvalidSetOption(_))
+        ));
+    }
+
+    #[test]
+    fn parse_string_range_commands_keep_binary() {
+        let raw = Bytes::from_static(b"\x00\xff\xfe");
+        let frame = Frame::Array(vec![
+            Frame::bulk("SETRANGE"),
+            Frame::bulk("k"),
+            Frame::bulk("2"),
+            Frame::Bulk(raw.clone()),
+        ]);
+        let cmd = Command::from_frame(frame).unwrap();
+        assert_eq!(
+            cmd,
+            Command::SetRange {
+                key: "k".into(),
+                offset: 2,
+                value: raw.clone(),
+            }
+        );
+        assert_eq!(Command::from_frame(cmd.to_frame()).unwrap(), cmd);
+
+        let frame = Frame::Array(vec![
+            Frame::bulk("APPEND"),
+            Frame::Bulk(raw.clone()),
+            Frame::Bulk(raw.clone()),
+        ]);
+        assert_eq!(
+            Command::from_frame(frame).unwrap(),
+            Command::Append {
+                key: raw.clone(),
+                value: raw,
+            }
+        );
+
+        let frame = Frame::array_from_strs(&["SETRANGE", "k", "-1", "x"]);
+        assert!(Command::from_frame(frame).is_err());
+        let frame = Frame::array_from_strs(&["GETRANGE", "k", "0"]);
+        assert!(Command::from_frame(frame).is_err());
+    }
+
+    #[test]
+    fn parse_mset_pairs() {
+        let frame = Frame::array_from_strs(&["MSET", "k", "v1", "k", "v2"]);
+        assert_eq!(
+            Command::from_frame(frame).unwrap(),
+            Command::MSet(vec![("k".into(), "v1".into()), ("k".into(), "v2".into())])
+        );
+
+        // Chave sem valor
+        let frame = Frame::array_from_strs(&["MSETNX", "a", "1", "b"]);
+        assert!(matches!(
+            Command::from_frame(frame),
+            Err(CommandError::WrongArity(_))
+        ));
+        let frame = Frame::array_from_strs(&["MSET"]);
+        assert!(Command::from_frame(frame).is_err());
+    }
+
+    #[test]
+    fn parse_rpushcap() {
+        let frame = Frame::array_from_strs(&["RPUSHCAP", "log", "100", "a", "b"]);
+        assert_eq!(
+            Command::from_frame(frame).unwrap(),
+            Command::RPushCap {
+                key: "log".into(),
+                maxlen: 100,
+                values: vec!["a".into(), "b".into()],
+            }
+        );
+
+        let frame = Frame::array_from_strs(&["RPUSHCAP", "log", "0", "a"]);
+        assert!(Command::from_frame(frame).is_err());
+        let frame = Frame::array_from_strs(&["RPUSHCAP", "log", "10"]);
+        assert!(Command::from_frame(frame).is_err());
+    }
+
+    #[test]
+    fn parse_bitop() {
+        let frame = Frame::array_from_strs(&["BITOP", "and", "dest", "a", "b"]);
+        let cmd = Command::from_frame(frame).unwrap();
+        assert_eq!(
+            cmd,
+            Command::BitOp {
+                op: BitOp::And,
+                dest: "dest".into(),
+                keys: vec!["a".into(), "b".into()],
+            }
+        );
+        assert_eq!(Command::from_frame(cmd.to_frame()).unwrap(), cmd);
+
+        let frame = Frame::array_from_strs(&["BITOP", "NOT", "dest", "a", "b"]);
+        assert!(Command::from_frame(frame).is_err());
+
+        let frame = Frame::array_from_strs(&["BITOP", "NAND", "dest", "a"]);
+        assert!(Command::from_frame(frame).is_err());
+    }
+
+    #[test]
+    fn parse_client_id() {
+        let frame = Frame::array_from_strs(&["client", "id"]);
+        assert_eq!(
+            Command::from_frame(frame).unwrap(),
+            Command::Client(ClientCommand::Id)
+        );
+
+        let frame = Frame::array_from_strs(&["CLIENT", "FOO"]);
+        assert!(Command::from_frame(frame).is_err());
+    }
+
+    #[test]
+    fn parse_client_pause() {
+        let frame = Frame::array_from_strs(&["client", "pause", "500"]);
+        assert_eq!(
+            Command::from_frame(frame).unwrap(),
+            Command::Client(ClientCommand::Pause {
+                ms: 500,
+                mode: PauseMode::All
+            })
+        );
+        let frame = Frame::array_from_strs(&["CLIENT", "PAUSE", "500", "write"]);
+        assert_eq!(
+            Command::from_frame(frame).unwrap(),
+            Command::Client(ClientCommand::Pause {
+                ms: 500,
+                mode: PauseMode::Write
+            })
+        );
+
+        for args in [
+            &["CLIENT", "PAUSE", "-1"][..],
+            &["CLIENT", "PAUSE", "500", "READ"],
+            &["CLIENT", "PAUSE"],
+            &["CLIENT", "UNPAUSE", "now"],
+        ] {
+            assert!(Command::from_frame(Frame::array_from_strs(args)).is_err());
+        }
+    }
+
+    #[test]
+    fn parse_memory_stats() {
+        let frame = Frame::array_from_strs(&["memory", "stats"]);
+        let cmd = Command::from_frame(frame).unwrap();
+        assert_eq!(cmd, Command::Memory(MemoryCommand::Stats));
+        assert_eq!(cmd.to_frame(), Frame::array_from_strs(&["MEMORY", "STATS"]));
+
+        let frame = Frame::array_from_strs(&["MEMORY", "DOCTOR"]);
+        assert!(Command::from_frame(frame).is_err());
+    }
+
+    #[test]
+    fn parse_object_encoding() {
+        let frame = Frame::array_from_strs(&["object", "encoding", "k"]);
+        let cmd = Command::from_frame(frame).unwrap();
+        assert_eq!(cmd, Command::Object(ObjectCommand::Encoding("k".into())));
+
+        let frame = Frame::array_from_strs(&["OBJECT", "FREQ", "k"]);
+        assert!(Command::from_frame(frame).is_err());
+        let frame = Frame::array_from_strs(&["OBJECT", "ENCODING"]);
+        assert!(Command::from_frame(frame).is_err());
+    }
+
+    #[test]
+    fn parse_config_resetstat() {
+        let frame = Frame::array_from_strs(&["config", "resetstat"]);
+        assert_eq!(
+            Command::from_frame(frame).unwrap(),
+            Command::Config(ConfigCommand::ResetStat)
+        );
+
+        let frame = Frame::array_from_strs(&["CONFIG", "GET", "maxmemory"]);
+        assert!(matches!(
+            Command::from_frame(frame),
+            Err(CommandError::UnknownSubcommand { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_cluster() {
+        let frame = Frame::array_from_strs(&["cluster", "slots"]);
+        assert_eq!(
+            Command::from_frame(frame).unwrap(),
+            Command::Cluster(ClusterCommand::Slots)
+        );
+
+        let frame = Frame::array_from_strs(&["CLUSTER", "MEET", "127.0.0.1", "7000"]);
+        assert!(matches!(
+            Command::from_frame(frame),
+            Err(CommandError::UnknownSubcommand { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_command_info() {
+        let frame = Frame::array_from_strs(&["COMMAND", "info", "get", "set"]);
+        assert_eq!(
+            Command::from_frame(frame).unwrap(),
+            Command::CommandInfo(vec!["get".into(), "set".into()])
+        );
+
+        let frame = Frame::array_from_strs(&["COMMAND"]);
+        assert_eq!(
+            Command::from_frame(frame).unwrap(),
+            Command::CommandInfo(vec![])
+        );
+
+        let frame = Frame::array_from_strs(&["COMMAND", "DOCS"]);
+        assert!(Command::from_frame(frame).is_err());
+    }
+
+    #[test]
+    fn parse_non_utf8_arguments() {
+        // Chaves são binary-safe
+        let frame = Frame::Array(vec![
+            Frame::bulk("GET"),
+            Frame::Bulk(Bytes::from_static(b"caf\xe9")),
+        ]);
+        assert_eq!(
+            Command::from_frame(frame).unwrap(),
+            Command::Get(Bytes::from_static(b"caf\xe9"))
+        );
+
+        // Argumentos textuais (ex.: canais) continuam exigindo UTF-8
+        let frame = Frame::Array(vec![
+            Frame::bulk("SUBSCRIBE"),
+            Frame::Bulk(Bytes::from_static(b"caf\xe9")),
+        ]);
+        let err = Command::from_frame(frame).unwrap_err().to_string();
+        assert!(err.contains("byte 0xe9 na posição 3"), "{err}");
+        assert!(err.contains(r"caf\xe9"), "{err}");
+
+        // Valores binários continuam aceitos
+        let frame = Frame::Array(vec![
+            Frame::bulk("SET"),
+            Frame::bulk("k"),
+            Frame::Bulk(Bytes::from_static(b"\xff\xfe")),
+        ]);
+        assert!(Command::from_frame(frame).is_ok());
+    }
+
     #[test]
     fn parse_unknown_command() {
         let frame = Frame::array_from_strs(&["FOOBAR"]);
@@ -548,7 +1586,7 @@ mod tests {
         let frame = Frame::array_from_strs(&["set", "k", "v", "ex", "5"]);
         let cmd = Command::from_frame(frame).unwrap();
         match cmd {
-            Command::Set { options, .. } => assert_eq!(options.expire_ms, Some(5000)),
+            Command::Set { options, .. } => assert_eq!(options.expire, Some(Expiry::Px(5000))),
             _ => panic!("expected Set"),
         }
     }
@@ -564,4 +1602,411 @@ mod tests {
         let frame = Frame::array_from_strs(&["SET", "k", "v", "INVALID"]);
         assert!(Command::from_frame(frame).is_err());
     }
+
+    /// Uma amostra de cada variante (exceto `Unknown`), com os casos de
+    /// borda de cada uma.
+    fn command_samples() -> Vec<Command> {
+        let binary = Bytes::from_static(b"\x00\xff\r\n");
+        vec![
+            Command::Ping(None),
+            Command::Ping(Some(binary.clone())),
+            Command::Echo(Bytes::new()),
+            Command::Get(binary.clone()),
+            Command::Ttl(binary.clone()),
+            Command::Pttl("k".into()),
+            Command::Set {
+                key: "k".into(),
+                value: binary.clone(),
+                options: SetOptions {
+                    expire: None,
+                    condition: None,
+                    get: false,
+                    persist: false,
+                },
+            },
+            Command::Set {
+                key: binary.clone(),
+                value: Bytes::new(),
+                options: SetOptions {
+                    expire: Some(Expiry::Px(1)),
+                    condition: Some(SetCondition::Xx),
+                    get: true,
+                    persist: false,
+                },
+            },
+            Command::Del(vec!["a".into(), binary.clone()]),
+            Command::Exists(vec!["a".into(), "a".into()]),
+            Command::ExistsAtLeast {
+                keys: vec!["a".into()],
+                n: 0,
+            },
+            Command::Expire {
+                key: "k".into(),
+                ms: -5,
+                condition: None,
+            },
+            Command::Expire {
+                key: "k".into(),
+                ms: i64::MAX,
+                condition: Some(ExpireCondition::Lt),
+            },
+            Command::Incr {
+                key: "n".into(),
+                expire: None,
+            },
+            Command::Incr {
+                key: "n".into(),
+                expire: Some(Expiry::Px(1500)),
+            },
+            Command::Incr {
+                key: "n".into(),
+                expire: Some(Expiry::PxAt(1_700_000_000_000)),
+            },
+            Command::ExpireAt {
+                key: "k".into(),
+                unix_ms: -1,
+                condition: Some(ExpireCondition::Gt),
+            },
+            Command::Decr("n".into()),
+            Command::GetRange {
+                key: binary.clone(),
+                start: -3,
+                end: i64::MAX,
+            },
+            Command::SetRange {
+                key: "s".into(),
+                offset: 7,
+                value: binary.clone(),
+            },
+            Command::Append {
+                key: "s".into(),
+                value: binary.clone(),
+            },
+            Command::Strlen(binary.clone()),
+            Command::MSet(vec![
+                ("a".into(), binary.clone()),
+                ("a".into(), Bytes::new()),
+            ]),
+            Command::MSetNx(vec![("b".into(), "1".into())]),
+            Command::MGet(vec!["a".into(), binary.clone(), "a".into()]),
+            Command::LPush {
+                key: "l".into(),
+                values: vec![binary.clone(), Bytes::new()],
+            },
+            Command::RPush {
+                key: "l".into(),
+                values: vec!["x".into()],
+            },
+            Command::RPushCap {
+                key: "l".into(),
+                maxlen: 2,
+                values: vec!["x".into(), "y".into(), "z".into()],
+            },
+            Command::LPop {
+                key: "l".into(),
+                count: Some(0),
+            },
+            Command::LPop {
+                key: "l".into(),
+                count: None,
+            },
+            Command::RPop {
+                key: "l".into(),
+                count: Some(usize::MAX >> 1),
+            },
+            Command::LRange {
+                key: "l".into(),
+                start: i64::MIN,
+                stop: -1,
+            },
+            Command::RPopLPush {
+                source: "a".into(),
+                destination: "a".into(),
+            },
+            Command::BRPopLPush {
+                source: "a".into(),
+                destination: "b".into(),
+                timeout_ms: 0,
+            },
+            Command::BRPopLPush {
+                source: "a".into(),
+                destination: "b".into(),
+                timeout_ms: 1,
+            },
+            Command::Subscribe(vec!["ch".into(), "news.*".into()]),
+            Command::Unsubscribe(vec![]),
+            Command::PSubscribe(vec!["news.*".into(), "h[ae]llo".into()]),
+            Command::PUnsubscribe(vec!["news.*".into()]),
+            Command::Publish {
+                channel: "ch".into(),
+                message: binary.clone(),
+            },
+            Command::BitOp {
+                op: BitOp::Not,
+                dest: "d".into(),
+                keys: vec!["s".into()],
+            },
+            Command::Keys(Bytes::from_static(b"user:[a-c]\\*")),
+            Command::Type(binary.clone()),
+            Command::Rename {
+                source: binary.clone(),
+                destination: "k".into(),
+            },
+            Command::RenameNx {
+                source: "k".into(),
+                destination: binary.clone(),
+            },
+            Command::DbSize,
+            Command::Time,
+            Command::Info(None),
+            Command::Info(Some("stats".into())),
+            Command::ReplicaOf(None),
+            Command::ReplicaOf(Some(("localhost".into(), 6380))),
+            Command::ReplConf(vec![("capa".into(), "psync2".into())]),
+            Command::Psync {
+                replid: "?".into(),
+                offset: -1,
+            },
+            Command::Debug(DebugCommand::Reload),
+            Command::Debug(DebugCommand::ChangeReplId),
+            Command::Debug(DebugCommand::Object(Bytes::from("k"))),
+            Command::Debug(DebugCommand::SetActiveExpire(true)),
+            Command::Debug(DebugCommand::SetActiveExpire(false)),
+            Command::Client(ClientCommand::Id),
+            Command::Client(ClientCommand::Pause {
+                ms: 100,
+                mode: PauseMode::Write,
+            }),
+            Command::Client(ClientCommand::Unpause),
+            Command::Memory(MemoryCommand::Stats),
+            Command::Object(ObjectCommand::Encoding(Bytes::from("k"))),
+            Command::Config(ConfigCommand::ResetStat),
+            Command::Cluster(ClusterCommand::Info),
+            Command::Cluster(ClusterCommand::Slots),
+            Command::Cluster(ClusterCommand::Nodes),
+            Command::CommandInfo(vec![]),
+            Command::CommandInfo(vec!["get".into()]),
+        ]
+    }
+
+    /// `to_frame` -> bytes RESP -> `Frame::parse` -> `from_frame`, como no
+    /// AOF e na replicação.
+    fn roundtrip_wire(cmd: &Command) -> Command {
+        let mut buf = bytes::BytesMut::new();
+        cmd.to_frame().encode(&mut buf);
+        let frame = Frame::parse(&mut std::io::Cursor::new(&buf[..])).unwrap();
+        Command::from_frame(frame).unwrap()
+    }
+
+    #[test]
+    fn every_command_roundtrips_through_frame() {
+        // Match exaustivo: uma variante nova não compila até ganhar um
+        // índice aqui (e uma amostra em `command_samples`).
+        fn variant(cmd: &Command) -> usize {
+            match cmd {
+                Command::Ping(_) => 0,
+                Command::Echo(_) => 1,
+                Command::Get(_) => 2,
+                Command::Set { .. } => 3,
+                Command::Del(_) => 4,
+                Command::Exists(_) => 5,
+                Command::ExistsAtLeast { .. } => 6,
+                Command::Expire { .. } => 7,
+                Command::Incr { .. } => 8,
+                Command::Decr(_) => 9,
+                Command::LPush { .. } => 10,
+                Command::RPush { .. } => 11,
+                Command::LPop { .. } => 12,
+                Command::RPop { .. } => 13,
+                Command::LRange { .. } => 14,
+                Command::RPopLPush { .. } => 15,
+                Command::BRPopLPush { .. } => 16,
+                Command::Subscribe(_) => 17,
+                Command::Unsubscribe(_) => 18,
+                Command::Publish { .. } => 19,
+                Command::BitOp { .. } => 20,
+                Command::DbSize => 21,
+                Command::Time => 22,
+                Command::Info(_) => 23,
+                Command::ReplicaOf(_) => 24,
+                Command::ReplConf(_) => 25,
+                Command::Psync { .. } => 26,
+                Command::Debug(_) => 27,
+                Command::Client(_) => 28,
+                Command::Memory(_) => 29,
+                Command::CommandInfo(_) => 30,
+                Command::Cluster(_) => 31,
+                Command::RPushCap { .. } => 32,
+                Command::Config(_) => 33,
+                Command::GetRange { .. } => 34,
+                Command::SetRange { .. } => 35,
+                Command::Append { .. } => 36,
+                Command::Object(_) => 37,
+                Command::MSet(_) => 38,
+                Command::MSetNx(_) => 39,
+                Command::ExpireAt { .. } => 40,
+                Command::PSubscribe(_) => 41,
+                Command::PUnsubscribe(_) => 42,
+                Command::Ttl(_) => 43,
+                Command::Pttl(_) => 44,
+                Command::Keys(_) => 45,
+                Command::Type(_) => 46,
+                Command::Rename { .. } => 47,
+                Command::RenameNx { .. } => 48,
+                Command::MGet(_) => 49,
+                Command::Strlen(_) => 50,
+                Command::Unknown(_) => unreachable!("Unknown não faz round-trip"),
+            }
+        }
+        const VARIANTS: usize = 51;
+
+        let samples = command_samples();
+        let covered: std::collections::HashSet<usize> = samples.iter().map(variant).collect();
+        assert_eq!(covered.len(), VARIANTS, "falta amostra de alguma variante");
+
+        for cmd in samples {
+            assert_eq!(Command::from_frame(cmd.to_frame()).unwrap(), cmd);
+            assert_eq!(roundtrip_wire(&cmd), cmd);
+        }
+    }
+
+    #[test]
+    fn every_command_name_is_in_the_table() {
+        for cmd in command_samples() {
+            let spec = crate::lookup_command(cmd.name())
+                .unwrap_or_else(|| panic!("{} fora da COMMAND_TABLE", cmd.name()));
+            // O nome bate com o que vai para o AOF/réplicas
+            let Frame::Array(parts) = cmd.to_frame() else {
+                unreachable!()
+            };
+            assert_eq!(parts[0], Frame::bulk(&spec.name.to_uppercase()));
+        }
+    }
+
+    #[test]
+    fn every_table_entry_has_a_parser() {
+        // O parser registrado para o nome que `to_frame` gera remonta o
+        // comando. EXPIRE e EXPIREAT saem como PEXPIRE/PEXPIREAT e são
+        // cobertos pelos testes de parse.
+        let mut covered = vec!["expire", "expireat"];
+        for cmd in command_samples() {
+            let mut parse = Parse::new(cmd.to_frame()).unwrap();
+            let spec = crate::lookup_command(&parse.next_command().unwrap()).unwrap();
+            assert_eq!((spec.parse)(&mut parse).unwrap(), cmd);
+            covered.push(spec.name);
+        }
+        for spec in crate::COMMAND_TABLE {
+            assert!(covered.contains(&spec.name), "{} sem amostra", spec.name);
+        }
+        assert!(Command::Unknown("FOO".into()).spec().is_none());
+    }
+
+    #[test]
+    fn unknown_to_frame_drops_arguments() {
+        let frame = Frame::array_from_strs(&["FOO", "a", "b"]);
+        let cmd = Command::from_frame(frame).unwrap();
+        assert_eq!(cmd, Command::Unknown("FOO".into()));
+        assert_eq!(cmd.to_frame(), Frame::array_from_strs(&["FOO"]));
+    }
+
+    /// Comando aleatório dentre os que `from_frame` pode produzir.
+    fn random_command(rng: &mut fastrand::Rng) -> Command {
+        fn bytes(rng: &mut fastrand::Rng) -> Bytes {
+            let len = rng.usize(0..8);
+            Bytes::from((0..len).map(|_| rng.u8(..)).collect::<Vec<u8>>())
+        }
+        fn text(rng: &mut fastrand::Rng) -> String {
+            let len = rng.usize(1..8);
+            (0..len).map(|_| rng.alphanumeric()).collect()
+        }
+        fn list(rng: &mut fastrand::Rng) -> Vec<Bytes> {
+            (0..rng.usize(1..4)).map(|_| bytes(rng)).collect()
+        }
+
+        match rng.u8(0..12) {
+            0 => {
+                let expire = rng.bool().then(|| {
+                    let ms = rng.u64(1..=i64::MAX as u64);
+                    if rng.bool() {
+                        Expiry::Px(ms)
+                    } else {
+                        Expiry::PxAt(ms)
+                    }
+                });
+                Command::Set {
+                    key: bytes(rng),
+                    value: bytes(rng),
+                    options: SetOptions {
+                        expire,
+                        condition: [None, Some(SetCondition::Nx), Some(SetCondition::Xx)]
+                            [rng.usize(0..3)]
+                        .clone(),
+                        get: rng.bool(),
+                        // PERSIST só é válido sem EX/PX
+                        persist: expire.is_none() && rng.bool(),
+                    },
+                }
+            }
+            1 => Command::Del(list(rng)),
+            2 => Command::ExistsAtLeast {
+                keys: list(rng),
+                n: rng.usize(0..=i64::MAX as usize),
+            },
+            3 => Command::Expire {
+                key: bytes(rng),
+                ms: rng.i64(..),
+                condition: [
+                    None,
+                    Some(ExpireCondition::Nx),
+                    Some(ExpireCondition::Xx),
+                    Some(ExpireCondition::Gt),
+                    Some(ExpireCondition::Lt),
+                ][rng.usize(0..5)],
+            },
+            4 => Command::LPush {
+                key: bytes(rng),
+                values: list(rng),
+            },
+            5 => Command::RPush {
+                key: bytes(rng),
+                values: list(rng),
+            },
+            6 => Command::LPop {
+                key: bytes(rng),
+                count: rng.bool().then(|| rng.usize(0..=i64::MAX as usize)),
+            },
+            7 => Command::RPop {
+                key: bytes(rng),
+                count: rng.bool().then(|| rng.usize(0..=i64::MAX as usize)),
+            },
+            8 => Command::LRange {
+                key: bytes(rng),
+                start: rng.i64(..),
+                stop: rng.i64(..),
+            },
+            9 => Command::BRPopLPush {
+                source: bytes(rng),
+                destination: bytes(rng),
+                timeout_ms: rng.u64(0..1 << 40),
+            },
+            10 => Command::BitOp {
+                op: [BitOp::And, BitOp::Or, BitOp::Xor][rng.usize(0..3)],
+                dest: bytes(rng),
+                keys: list(rng),
+            },
+            _ => Command::Publish {
+                channel: text(rng),
+                message: bytes(rng),
+            },
+        }
+    }
+
+    #[test]
+    fn random_commands_roundtrip() {
+        let mut rng = fastrand::Rng::with_seed(0x5707_2db0);
+        for _ in 0..2000 {
+            let cmd = random_command(&mut rng);
+            assert_eq!(roundtrip_wire(&cmd), cmd, "{cmd:?}");
+        }
+    }
 }
@@ -1,6 +1,14 @@
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 use std::io::Cursor;
-use stormdb_common::{MAX_FRAME_SIZE, ProtocolError};
+use stormdb_common::{MAX_ARRAY_LEN, MAX_FRAME_SIZE, ProtocolError};
+
+/// Versão do protocolo usada para encodar as respostas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RespVersion {
+    #[default]
+    Resp2,
+    Resp3,
+}
 
 /// Representação de um frame RESP2.
 #[derive(Debug, Clone, PartialEq)]
@@ -9,7 +17,10 @@ pub enum Frame {
     Error(String),
     Integer(i64),
     Bulk(Bytes),
+    /// Null bulk string (`$-1` no RESP2).
     Null,
+    /// Null array (`*-1` no RESP2). No RESP3 os dois nulls viram `_`.
+    NullArray,
     Array(Vec<Frame>),
 }
 
@@ -17,6 +28,15 @@ impl Frame {
     /// Verifica se um frame completo está disponível no buffer sem alocar.
     /// Retorna Ok(()) se completo, Err(Incomplete) se precisa mais dados.
     pub fn check(src: &mut Cursor<&[u8]>) -> Result<(), ProtocolError> {
+        Frame::check_with_limit(src, MAX_ARRAY_LEN)
+    }
+
+    /// Como `check`, mas rejeitando arrays com mais de `max_array_len`
+    /// elementos declarados (em qualquer nível de aninhamento).
+    pub fn check_with_limit(
+        src: &mut Cursor<&[u8]>,
+        max_array_len: usize,
+    ) -> Result<(), ProtocolError> {
         match get_u8(src)? {
             b'+' | b'-' => {
                 get_line(src)?;
@@ -49,8 +69,11 @@ impl Frame {
                 if count < 0 {
                     return Err(ProtocolError::InvalidBulkLength(count));
                 }
+                if count as u64 > max_array_len as u64 {
+                    return Err(ProtocolError::ArrayTooLarge(count));
+                }
                 for _ in 0..count {
-                    Frame::check(src)?;
+                    Frame::check_with_limit(src, max_array_len)?;
                 }
                 Ok(())
             }
@@ -94,10 +117,14 @@ impl Frame {
             b'*' => {
                 let count = get_decimal(src)?;
                 if count == -1 {
-                    return Ok(Frame::Null);
+                    return Ok(Frame::NullArray);
                 }
-                let count = count as usize;
-                let mut frames = Vec::with_capacity(count);
+                if count < 0 {
+                    return Err(ProtocolError::InvalidBulkLength(count));
+                }
+                // Sem pré-alocar pelo count declarado: o Vec só cresce com
+                // elementos que realmente estão no buffer.
+                let mut frames = Vec::new();
                 for _ in 0..count {
                     frames.push(Frame::parse(src)?);
                 }
@@ -109,6 +136,12 @@ impl Frame {
 
     /// Encoda o frame no buffer de saída em formato RESP2.
     pub fn encode(&self, dst: &mut BytesMut) {
+        self.encode_with_version(dst, RespVersion::Resp2);
+    }
+
+    /// Encoda o frame na versão de protocolo `version`. Só os nulls mudam:
+    /// no RESP3 tanto o null bulk quanto o null array são `_`.
+    pub fn encode_with_version(&self, dst: &mut BytesMut, version: RespVersion) {
         match self {
             Frame::Simple(s) => {
                 dst.put_u8(b'+');
@@ -132,20 +165,55 @@ impl Frame {
                 dst.put(data.as_ref());
                 dst.put(&b"\r\n"[..]);
             }
+            Frame::Null | Frame::NullArray if version == RespVersion::Resp3 => {
+                dst.put(&b"_\r\n"[..]);
+            }
             Frame::Null => {
                 dst.put(&b"$-1\r\n"[..]);
             }
+            Frame::NullArray => {
+                dst.put(&b"*-1\r\n"[..]);
+            }
             Frame::Array(frames) => {
                 dst.put_u8(b'*');
                 dst.put(frames.len().to_string().as_bytes());
                 dst.put(&b"\r\n"[..]);
                 for frame in frames {
-                    frame.encode(dst);
+                    frame.encode_with_version(dst, version);
                 }
             }
         }
     }
 
+    /// Indica se um buffer começando em `first` é um comando inline (texto
+    /// puro, ex.: `SET k v`) em vez de um frame RESP.
+    pub fn is_inline(first: u8) -> bool {
+        !matches!(first, b'+' | b'-' | b':' | b'$' | b'*')
+    }
+
+    /// Lê um comando inline terminado em `\r\n` ou só `\n` (editores e
+    /// ferramentas manuais) e o converte em um Array de Bulk strings, com a
+    /// mesma regra de aspas do stormdb-cli. Linhas em branco viram um Array
+    /// vazio. Bytes de controle ou UTF-8 inválido são tratados como dados
+    /// corrompidos.
+    pub fn parse_inline(src: &mut Cursor<&[u8]>) -> Result<Frame, ProtocolError> {
+        let line = get_inline_line(src)?;
+        let line =
+            std::str::from_utf8(line).map_err(|e| ProtocolError::InvalidEncoding(e.to_string()))?;
+        if line.chars().any(|c| c.is_control() && c != '\t') {
+            return Err(ProtocolError::InvalidEncoding(
+                "caractere de controle em comando inline".into(),
+            ));
+        }
+
+        Ok(Frame::Array(
+            crate::tokenize(line)
+                .into_iter()
+                .map(|arg| Frame::Bulk(Bytes::from(arg)))
+                .collect(),
+        ))
+    }
+
     /// Helper: cria um Frame::Bulk a partir de &str.
     pub fn bulk(s: &str) -> Frame {
         Frame::Bulk(Bytes::from(s.to_string()))
@@ -178,6 +246,19 @@ fn get_line<'a>(src: &mut Cursor<&'a [u8]>) -> Result<&'a [u8], ProtocolError> {
     Err(ProtocolError::Incomplete)
 }
 
+/// Como `get_line`, mas aceita um `\n` sem `\r` como fim de linha. Só
+/// para comandos inline: frames RESP continuam exigindo `\r\n`.
+fn get_inline_line<'a>(src: &mut Cursor<&'a [u8]>) -> Result<&'a [u8], ProtocolError> {
+    let start = src.position() as usize;
+    let rest = &src.get_ref()[start..];
+
+    let Some(i) = rest.iter().position(|&b| b == b'\n') else {
+        return Err(ProtocolError::Incomplete);
+    };
+    src.set_position((start + i + 1) as u64);
+    Ok(rest[..i].strip_suffix(b"\r").unwrap_or(&rest[..i]))
+}
+
 fn get_decimal(src: &mut Cursor<&[u8]>) -> Result<i64, ProtocolError> {
     let line = get_line(src)?;
     let s = std::str::from_utf8(line).map_err(|e| ProtocolError::InvalidInteger(e.to_string()))?;
@@ -236,6 +317,25 @@ mod tests {
         roundtrip(&Frame::Null);
     }
 
+    #[test]
+    fn roundtrip_null_array() {
+        // `*-1` não pode voltar como null bulk
+        roundtrip(&Frame::NullArray);
+        roundtrip(&Frame::Array(vec![Frame::Null, Frame::NullArray]));
+
+        let mut buf = BytesMut::new();
+        Frame::NullArray.encode(&mut buf);
+        assert_eq!(&buf[..], b"*-1\r\n");
+    }
+
+    #[test]
+    fn resp3_encodes_both_nulls_as_underscore() {
+        let frame = Frame::Array(vec![Frame::Null, Frame::NullArray, Frame::Integer(1)]);
+        let mut buf = BytesMut::new();
+        frame.encode_with_version(&mut buf, RespVersion::Resp3);
+        assert_eq!(&buf[..], b"*3\r\n_\r\n_\r\n:1\r\n");
+    }
+
     #[test]
     fn roundtrip_array() {
         let frame = Frame::Array(vec![
@@ -286,6 +386,88 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn huge_array_header_rejected() {
+        let data = b"*2000000000\r\n";
+        let mut cursor = Cursor::new(&data[..]);
+        assert!(matches!(
+            Frame::check(&mut cursor),
+            Err(ProtocolError::ArrayTooLarge(2_000_000_000))
+        ));
+
+        // Mesmo chamando parse direto, nada é pré-alocado pelo count
+        let mut cursor = Cursor::new(&data[..]);
+        assert!(matches!(
+            Frame::parse(&mut cursor),
+            Err(ProtocolError::Incomplete)
+        ));
+    }
+
+    #[test]
+    fn array_limit_applies_to_nested_arrays() {
+        let data = b"*1\r\n*3\r\n:1\r\n:2\r\n:3\r\n";
+        let mut cursor = Cursor::new(&data[..]);
+        assert!(matches!(
+            Frame::check_with_limit(&mut cursor, 2),
+            Err(ProtocolError::ArrayTooLarge(3))
+        ));
+        let mut cursor = Cursor::new(&data[..]);
+        assert!(Frame::check_with_limit(&mut cursor, 3).is_ok());
+    }
+
+    #[test]
+    fn parse_inline_command() {
+        let data = b"SET greeting \"hello world\"\r\n\r\nGET";
+        let mut cursor = Cursor::new(&data[..]);
+        assert!(Frame::is_inline(data[0]));
+        assert_eq!(
+            Frame::parse_inline(&mut cursor).unwrap(),
+            Frame::array_from_strs(&["SET", "greeting", "hello world"])
+        );
+        assert_eq!(
+            Frame::parse_inline(&mut cursor).unwrap(),
+            Frame::Array(vec![])
+        );
+        // Sem \r\n: ainda incompleto
+        assert!(matches!(
+            Frame::parse_inline(&mut cursor),
+            Err(ProtocolError::Incomplete)
+        ));
+
+        let mut cursor = Cursor::new(&b"\x00\x01\r\n"[..]);
+        assert!(matches!(
+            Frame::parse_inline(&mut cursor),
+            Err(ProtocolError::InvalidEncoding(_))
+        ));
+        assert!(!Frame::is_inline(b'*'));
+    }
+
+    #[test]
+    fn inline_accepts_bare_lf_but_resp_does_not() {
+        let data = b"PING\nSET k v\r\n";
+        let mut cursor = Cursor::new(&data[..]);
+        assert_eq!(
+            Frame::parse_inline(&mut cursor).unwrap(),
+            Frame::array_from_strs(&["PING"])
+        );
+        assert_eq!(
+            Frame::parse_inline(&mut cursor).unwrap(),
+            Frame::array_from_strs(&["SET", "k", "v"])
+        );
+
+        // Frames RESP seguem exigindo \r\n
+        let mut cursor = Cursor::new(&b"+OK\n"[..]);
+        assert!(matches!(
+            Frame::check(&mut cursor),
+            Err(ProtocolError::Incomplete)
+        ));
+        let mut cursor = Cursor::new(&b"$2\r\nhi\n"[..]);
+        assert!(matches!(
+            Frame::check(&mut cursor),
+            Err(ProtocolError::Incomplete)
+        ));
+    }
+
     #[test]
     fn roundtrip_set_command() {
         let frame = Frame::array_from_strs(&["SET", "key", "value", "EX", "10"]);
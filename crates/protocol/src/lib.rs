@@ -3,7 +3,16 @@
 mod command;
 mod frame;
 mod parse;
+mod parsers;
+mod table;
+mod tokenize;
 
-pub use command::{Command, SetCondition, SetOptions};
-pub use frame::Frame;
+pub use command::{
+    BitOp, ClientCommand, ClusterCommand, Command, ConfigCommand, DebugCommand, ExpireCondition,
+    Expiry, MemoryCommand, ObjectCommand, PauseMode, SetCondition, SetOptions,
+};
+pub use frame::{Frame, RespVersion};
 pub use parse::Parse;
+pub use parsers::CommandParser;
+pub use table::{COMMAND_TABLE, CommandSpec, lookup_command};
+pub use tokenize::tokenize;
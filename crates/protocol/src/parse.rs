@@ -1,29 +1,54 @@
+use std::vec;
+
 use bytes::Bytes;
 use stormdb_common::CommandError;
 
 use crate::Frame;
 
+/// Quantos bytes de um argumento inválido aparecem na mensagem de erro.
+const MAX_ERROR_PREVIEW: usize = 32;
+
 /// Cursor sobre um Frame::Array para extrair argumentos sequencialmente.
+///
+/// Consome o Vec do array, entregando cada frame por valor (sem clonar).
 pub struct Parse {
-    parts: Vec<Frame>,
-    pos: usize,
+    parts: vec::IntoIter<Frame>,
+    /// Nome do comando (em maiúsculas), para os erros de aridade.
+    command: String,
 }
 
 impl Parse {
     /// Cria um Parse a partir de um Frame. O frame deve ser Array.
     pub fn new(frame: Frame) -> Result<Parse, CommandError> {
         match frame {
-            Frame::Array(parts) => Ok(Parse { parts, pos: 0 }),
+            Frame::Array(parts) => Ok(Parse {
+                parts: parts.into_iter(),
+                command: String::new(),
+            }),
             _ => Err(CommandError::InvalidArgument("esperado array".into())),
         }
     }
 
+    /// Lê o nome do comando (primeiro elemento), em maiúsculas.
+    pub fn next_command(&mut self) -> Result<String, CommandError> {
+        self.command = self.next_string()?.to_uppercase();
+        Ok(self.command.clone())
+    }
+
     /// Retorna o próximo elemento como String (de Bulk ou Simple).
     pub fn next_string(&mut self) -> Result<String, CommandError> {
         match self.next()? {
             Frame::Simple(s) => Ok(s),
-            Frame::Bulk(data) => String::from_utf8(data.to_vec())
-                .map_err(|_| CommandError::InvalidArgument("string UTF-8 inválida".into())),
+            Frame::Bulk(data) => String::from_utf8(Vec::from(data)).map_err(|e| {
+                let pos = e.utf8_error().valid_up_to();
+                let bytes = e.as_bytes();
+                let preview = &bytes[..bytes.len().min(MAX_ERROR_PREVIEW)];
+                CommandError::InvalidUtf8 {
+                    byte: bytes[pos],
+                    pos,
+                    preview: preview.escape_ascii().to_string(),
+                }
+            }),
             _ => Err(CommandError::InvalidArgument(
                 "esperado string ou bulk".into(),
             )),
@@ -44,24 +69,19 @@ impl Parse {
         match self.next()? {
             Frame::Integer(n) => Ok(n),
             Frame::Bulk(data) => {
-                let s = std::str::from_utf8(&data)
-                    .map_err(|_| CommandError::InvalidArgument("inteiro inválido".into()))?;
+                let s = String::from_utf8_lossy(&data);
                 s.parse::<i64>()
-                    .map_err(|_| CommandError::InvalidArgument(format!("'{s}' não é um inteiro")))
+                    .map_err(|_| CommandError::NotAnInteger(s.into_owned()))
             }
-            Frame::Simple(s) => s
-                .parse::<i64>()
-                .map_err(|_| CommandError::InvalidArgument(format!("'{s}' não é um inteiro"))),
+            Frame::Simple(s) => s.parse::<i64>().map_err(|_| CommandError::NotAnInteger(s)),
             _ => Err(CommandError::InvalidArgument("esperado inteiro".into())),
         }
     }
 
     /// Verifica se todos os argumentos foram consumidos.
     pub fn finish(&self) -> Result<(), CommandError> {
-        if self.pos < self.parts.len() {
-            Err(CommandError::InvalidArgument(
-                "argumentos extras não esperados".into(),
-            ))
+        if self.has_remaining() {
+            Err(CommandError::WrongArity(self.command.clone()))
         } else {
             Ok(())
         }
@@ -69,23 +89,18 @@ impl Parse {
 
     /// Verifica se ainda há argumentos restantes.
     pub fn has_remaining(&self) -> bool {
-        self.pos < self.parts.len()
+        self.parts.len() > 0
     }
 
     /// Retorna o número de argumentos restantes.
     pub fn remaining(&self) -> usize {
-        self.parts.len() - self.pos
+        self.parts.len()
     }
 
     fn next(&mut self) -> Result<Frame, CommandError> {
-        if self.pos >= self.parts.len() {
-            return Err(CommandError::InvalidArgument(
-                "argumentos insuficientes".into(),
-            ));
-        }
-        let frame = self.parts[self.pos].clone();
-        self.pos += 1;
-        Ok(frame)
+        self.parts
+            .next()
+            .ok_or_else(|| CommandError::WrongArity(self.command.clone()))
     }
 }
 
@@ -128,9 +143,12 @@ mod tests {
 
     #[test]
     fn parse_insufficient_args() {
-        let frame = Frame::array_from_strs(&["GET"]);
+        let frame = Frame::array_from_strs(&["get"]);
         let mut parse = Parse::new(frame).unwrap();
-        parse.next_string().unwrap();
-        assert!(parse.next_string().is_err());
+        assert_eq!(parse.next_command().unwrap(), "GET");
+        assert!(matches!(
+            parse.next_string(),
+            Err(CommandError::WrongArity(name)) if name == "GET"
+        ));
     }
 }
@@ -0,0 +1,623 @@
+//! Parsers de cada comando, registrados na `COMMAND_TABLE`.
+//!
+//! Cada um recebe o `Parse` já sem o nome do comando e com a aridade
+//! checada pela tabela: só tratam das formas válidas dentro dela.
+
+use bytes::Bytes;
+use stormdb_common::CommandError;
+
+use crate::Parse;
+use crate::command::{
+    BitOp, ClientCommand, ClusterCommand, Command, ConfigCommand, DebugCommand, ExpireCondition,
+    Expiry, MemoryCommand, ObjectCommand, PauseMode, SetCondition, SetOptions,
+};
+
+/// Assinatura dos parsers da `COMMAND_TABLE`.
+pub type CommandParser = fn(&mut Parse) -> Result<Command, CommandError>;
+
+pub(crate) fn ping(parse: &mut Parse) -> Result<Command, CommandError> {
+    let msg = if parse.has_remaining() {
+        Some(parse.next_bytes()?)
+    } else {
+        None
+    };
+    parse.finish()?;
+    Ok(Command::Ping(msg))
+}
+
+pub(crate) fn echo(parse: &mut Parse) -> Result<Command, CommandError> {
+    let msg = parse.next_bytes()?;
+    parse.finish()?;
+    Ok(Command::Echo(msg))
+}
+
+pub(crate) fn get(parse: &mut Parse) -> Result<Command, CommandError> {
+    let key = parse.next_bytes()?;
+    parse.finish()?;
+    Ok(Command::Get(key))
+}
+
+pub(crate) fn set(parse: &mut Parse) -> Result<Command, CommandError> {
+    let key = parse.next_bytes()?;
+    let value = parse.next_bytes()?;
+
+    let mut options = SetOptions {
+        expire: None,
+        condition: None,
+        get: false,
+        persist: false,
+    };
+
+    while parse.has_remaining() {
+        let opt = parse.next_string()?.to_uppercase();
+        if let Some(expiry) = parse_expiry(&opt, parse, "SET")? {
+            // Só um entre EX, PX, EXAT e PXAT, uma vez
+            if options.expire.replace(expiry).is_some() {
+                return Err(CommandError::InvalidSetOption(opt));
+            }
+            continue;
+        }
+        match opt.as_str() {
+            "NX" => {
+                options.condition = Some(SetCondition::Nx);
+            }
+            "XX" => {
+                options.condition = Some(SetCondition::Xx);
+            }
+            "GET" => {
+                options.get = true;
+            }
+            "PERSIST" => {
+                options.persist = true;
+            }
+            other => {
+                return Err(CommandError::InvalidSetOption(other.to_string()));
+            }
+        }
+    }
+    // PERSIST contradiz um TTL explícito
+    if options.persist && options.expire.is_some() {
+        return Err(CommandError::InvalidSetOption("PERSIST".into()));
+    }
+
+    Ok(Command::Set {
+        key,
+        value,
+        options,
+    })
+}
+
+pub(crate) fn del(parse: &mut Parse) -> Result<Command, CommandError> {
+    Ok(Command::Del(parse_keys(parse)?))
+}
+
+pub(crate) fn exists(parse: &mut Parse) -> Result<Command, CommandError> {
+    Ok(Command::Exists(parse_keys(parse)?))
+}
+
+pub(crate) fn exists_at_least(parse: &mut Parse) -> Result<Command, CommandError> {
+    // O último argumento é o limiar; a tabela garante ao menos uma chave
+    let mut keys = Vec::with_capacity(parse.remaining() - 1);
+    while parse.remaining() > 1 {
+        keys.push(parse.next_bytes()?);
+    }
+    let n = usize::try_from(parse.next_int()?).map_err(|_| CommandError::NegativeCount)?;
+    Ok(Command::ExistsAtLeast { keys, n })
+}
+
+pub(crate) fn expire(parse: &mut Parse) -> Result<Command, CommandError> {
+    let (key, ms, condition) = parse_expire(parse, "EXPIRE", 1000)?;
+    Ok(Command::Expire { key, ms, condition })
+}
+
+pub(crate) fn pexpire(parse: &mut Parse) -> Result<Command, CommandError> {
+    let (key, ms, condition) = parse_expire(parse, "PEXPIRE", 1)?;
+    Ok(Command::Expire { key, ms, condition })
+}
+
+pub(crate) fn expireat(parse: &mut Parse) -> Result<Command, CommandError> {
+    let (key, unix_ms, condition) = parse_expire(parse, "EXPIREAT", 1000)?;
+    Ok(Command::ExpireAt {
+        key,
+        unix_ms,
+        condition,
+    })
+}
+
+pub(crate) fn pexpireat(parse: &mut Parse) -> Result<Command, CommandError> {
+    let (key, unix_ms, condition) = parse_expire(parse, "PEXPIREAT", 1)?;
+    Ok(Command::ExpireAt {
+        key,
+        unix_ms,
+        condition,
+    })
+}
+
+pub(crate) fn ttl(parse: &mut Parse) -> Result<Command, CommandError> {
+    let key = parse.next_bytes()?;
+    parse.finish()?;
+    Ok(Command::Ttl(key))
+}
+
+pub(crate) fn pttl(parse: &mut Parse) -> Result<Command, CommandError> {
+    let key = parse.next_bytes()?;
+    parse.finish()?;
+    Ok(Command::Pttl(key))
+}
+
+pub(crate) fn incr(parse: &mut Parse) -> Result<Command, CommandError> {
+    let key = parse.next_bytes()?;
+    let expire = if parse.has_remaining() {
+        let opt = parse.next_string()?.to_uppercase();
+        match parse_expiry(&opt, parse, "INCR")? {
+            Some(expiry) => Some(expiry),
+            None => return Err(CommandError::InvalidArgument(opt)),
+        }
+    } else {
+        None
+    };
+    parse.finish()?;
+    Ok(Command::Incr { key, expire })
+}
+
+pub(crate) fn decr(parse: &mut Parse) -> Result<Command, CommandError> {
+    let key = parse.next_bytes()?;
+    parse.finish()?;
+    Ok(Command::Decr(key))
+}
+
+pub(crate) fn getrange(parse: &mut Parse) -> Result<Command, CommandError> {
+    let key = parse.next_bytes()?;
+    let start = parse.next_int()?;
+    let end = parse.next_int()?;
+    parse.finish()?;
+    Ok(Command::GetRange { key, start, end })
+}
+
+pub(crate) fn setrange(parse: &mut Parse) -> Result<Command, CommandError> {
+    let key = parse.next_bytes()?;
+    let offset = usize::try_from(parse.next_int()?).map_err(|_| CommandError::NegativeCount)?;
+    let value = parse.next_bytes()?;
+    parse.finish()?;
+    Ok(Command::SetRange { key, offset, value })
+}
+
+pub(crate) fn append(parse: &mut Parse) -> Result<Command, CommandError> {
+    let key = parse.next_bytes()?;
+    let value = parse.next_bytes()?;
+    parse.finish()?;
+    Ok(Command::Append { key, value })
+}
+
+pub(crate) fn strlen(parse: &mut Parse) -> Result<Command, CommandError> {
+    let key = parse.next_bytes()?;
+    parse.finish()?;
+    Ok(Command::Strlen(key))
+}
+
+pub(crate) fn mget(parse: &mut Parse) -> Result<Command, CommandError> {
+    Ok(Command::MGet(parse_keys(parse)?))
+}
+
+pub(crate) fn mset(parse: &mut Parse) -> Result<Command, CommandError> {
+    Ok(Command::MSet(parse_pairs(parse, "MSET")?))
+}
+
+pub(crate) fn msetnx(parse: &mut Parse) -> Result<Command, CommandError> {
+    Ok(Command::MSetNx(parse_pairs(parse, "MSETNX")?))
+}
+
+pub(crate) fn lpush(parse: &mut Parse) -> Result<Command, CommandError> {
+    let key = parse.next_bytes()?;
+    let values = parse_keys(parse)?;
+    Ok(Command::LPush { key, values })
+}
+
+pub(crate) fn rpush(parse: &mut Parse) -> Result<Command, CommandError> {
+    let key = parse.next_bytes()?;
+    let values = parse_keys(parse)?;
+    Ok(Command::RPush { key, values })
+}
+
+pub(crate) fn rpushcap(parse: &mut Parse) -> Result<Command, CommandError> {
+    let key = parse.next_bytes()?;
+    let maxlen = usize::try_from(parse.next_int()?)
+        .ok()
+        .filter(|&n| n > 0)
+        .ok_or(CommandError::NegativeCount)?;
+    let values = parse_keys(parse)?;
+    Ok(Command::RPushCap {
+        key,
+        maxlen,
+        values,
+    })
+}
+
+pub(crate) fn lpop(parse: &mut Parse) -> Result<Command, CommandError> {
+    let key = parse.next_bytes()?;
+    let count = parse_pop_count(parse)?;
+    parse.finish()?;
+    Ok(Command::LPop { key, count })
+}
+
+pub(crate) fn rpop(parse: &mut Parse) -> Result<Command, CommandError> {
+    let key = parse.next_bytes()?;
+    let count = parse_pop_count(parse)?;
+    parse.finish()?;
+    Ok(Command::RPop { key, count })
+}
+
+pub(crate) fn lrange(parse: &mut Parse) -> Result<Command, CommandError> {
+    let key = parse.next_bytes()?;
+    let start = parse.next_int()?;
+    let stop = parse.next_int()?;
+    parse.finish()?;
+    Ok(Command::LRange { key, start, stop })
+}
+
+pub(crate) fn rpoplpush(parse: &mut Parse) -> Result<Command, CommandError> {
+    let source = parse.next_bytes()?;
+    let destination = parse.next_bytes()?;
+    parse.finish()?;
+    Ok(Command::RPopLPush {
+        source,
+        destination,
+    })
+}
+
+pub(crate) fn brpoplpush(parse: &mut Parse) -> Result<Command, CommandError> {
+    let source = parse.next_bytes()?;
+    let destination = parse.next_bytes()?;
+    let timeout_ms = parse_timeout(parse)?;
+    parse.finish()?;
+    Ok(Command::BRPopLPush {
+        source,
+        destination,
+        timeout_ms,
+    })
+}
+
+pub(crate) fn subscribe(parse: &mut Parse) -> Result<Command, CommandError> {
+    Ok(Command::Subscribe(parse_names(parse)?))
+}
+
+pub(crate) fn unsubscribe(parse: &mut Parse) -> Result<Command, CommandError> {
+    Ok(Command::Unsubscribe(parse_names(parse)?))
+}
+
+pub(crate) fn psubscribe(parse: &mut Parse) -> Result<Command, CommandError> {
+    Ok(Command::PSubscribe(parse_names(parse)?))
+}
+
+pub(crate) fn punsubscribe(parse: &mut Parse) -> Result<Command, CommandError> {
+    Ok(Command::PUnsubscribe(parse_names(parse)?))
+}
+
+pub(crate) fn publish(parse: &mut Parse) -> Result<Command, CommandError> {
+    let channel = parse.next_string()?;
+    let message = parse.next_bytes()?;
+    parse.finish()?;
+    Ok(Command::Publish { channel, message })
+}
+
+pub(crate) fn bitop(parse: &mut Parse) -> Result<Command, CommandError> {
+    let op = match parse.next_string()?.to_uppercase().as_str() {
+        "AND" => BitOp::And,
+        "OR" => BitOp::Or,
+        "XOR" => BitOp::Xor,
+        "NOT" => BitOp::Not,
+        other => {
+            return Err(CommandError::InvalidArgument(format!(
+                "operação BITOP desconhecida: {other}"
+            )));
+        }
+    };
+    let dest = parse.next_bytes()?;
+    let keys = parse_keys(parse)?;
+    if op == BitOp::Not && keys.len() != 1 {
+        return Err(CommandError::InvalidArgument(
+            "BITOP NOT aceita exatamente uma chave de origem".into(),
+        ));
+    }
+    Ok(Command::BitOp { op, dest, keys })
+}
+
+pub(crate) fn rename(parse: &mut Parse) -> Result<Command, CommandError> {
+    let source = parse.next_bytes()?;
+    let destination = parse.next_bytes()?;
+    parse.finish()?;
+    Ok(Command::Rename {
+        source,
+        destination,
+    })
+}
+
+pub(crate) fn renamenx(parse: &mut Parse) -> Result<Command, CommandError> {
+    let source = parse.next_bytes()?;
+    let destination = parse.next_bytes()?;
+    parse.finish()?;
+    Ok(Command::RenameNx {
+        source,
+        destination,
+    })
+}
+
+pub(crate) fn key_type(parse: &mut Parse) -> Result<Command, CommandError> {
+    let key = parse.next_bytes()?;
+    parse.finish()?;
+    Ok(Command::Type(key))
+}
+
+pub(crate) fn keys(parse: &mut Parse) -> Result<Command, CommandError> {
+    let pattern = parse.next_bytes()?;
+    parse.finish()?;
+    Ok(Command::Keys(pattern))
+}
+
+pub(crate) fn dbsize(parse: &mut Parse) -> Result<Command, CommandError> {
+    parse.finish()?;
+    Ok(Command::DbSize)
+}
+
+pub(crate) fn time(parse: &mut Parse) -> Result<Command, CommandError> {
+    parse.finish()?;
+    Ok(Command::Time)
+}
+
+pub(crate) fn info(parse: &mut Parse) -> Result<Command, CommandError> {
+    let section = if parse.has_remaining() {
+        Some(parse.next_string()?)
+    } else {
+        None
+    };
+    parse.finish()?;
+    Ok(Command::Info(section))
+}
+
+pub(crate) fn replicaof(parse: &mut Parse) -> Result<Command, CommandError> {
+    let host = parse.next_string()?;
+    let port = parse.next_string()?;
+    parse.finish()?;
+    if host.eq_ignore_ascii_case("NO") && port.eq_ignore_ascii_case("ONE") {
+        return Ok(Command::ReplicaOf(None));
+    }
+    let port = port
+        .parse::<u16>()
+        .map_err(|_| CommandError::NotAnInteger(port))?;
+    Ok(Command::ReplicaOf(Some((host, port))))
+}
+
+pub(crate) fn replconf(parse: &mut Parse) -> Result<Command, CommandError> {
+    if !parse.has_remaining() {
+        return Err(CommandError::WrongArity("REPLCONF".into()));
+    }
+    let mut options = Vec::new();
+    while parse.has_remaining() {
+        let option = parse.next_string()?.to_lowercase();
+        let value = parse.next_string()?;
+        options.push((option, value));
+    }
+    Ok(Command::ReplConf(options))
+}
+
+pub(crate) fn psync(parse: &mut Parse) -> Result<Command, CommandError> {
+    let replid = parse.next_string()?;
+    let offset = parse.next_int()?;
+    parse.finish()?;
+    Ok(Command::Psync { replid, offset })
+}
+
+pub(crate) fn debug(parse: &mut Parse) -> Result<Command, CommandError> {
+    let sub = parse.next_string()?.to_uppercase();
+    let debug = match sub.as_str() {
+        "RELOAD" => DebugCommand::Reload,
+        "CHANGE-REPL-ID" => DebugCommand::ChangeReplId,
+        "OBJECT" => DebugCommand::Object(parse.next_bytes()?),
+        "SET-ACTIVE-EXPIRE" => DebugCommand::SetActiveExpire(parse.next_int()? != 0),
+        _ => return Err(unknown_subcommand("DEBUG", sub)),
+    };
+    parse.finish()?;
+    Ok(Command::Debug(debug))
+}
+
+pub(crate) fn client(parse: &mut Parse) -> Result<Command, CommandError> {
+    let sub = parse.next_string()?.to_uppercase();
+    let client = match sub.as_str() {
+        "ID" => ClientCommand::Id,
+        "PAUSE" => {
+            let arg = parse.next_string()?;
+            let ms = arg
+                .parse::<u64>()
+                .map_err(|_| CommandError::InvalidTimeout(arg))?;
+            let mode = if parse.has_remaining() {
+                match parse.next_string()?.to_uppercase().as_str() {
+                    "ALL" => PauseMode::All,
+                    "WRITE" => PauseMode::Write,
+                    opt => return Err(CommandError::InvalidArgument(opt.to_string())),
+                }
+            } else {
+                PauseMode::All
+            };
+            ClientCommand::Pause { ms, mode }
+        }
+        "UNPAUSE" => ClientCommand::Unpause,
+        _ => return Err(unknown_subcommand("CLIENT", sub)),
+    };
+    parse.finish()?;
+    Ok(Command::Client(client))
+}
+
+pub(crate) fn memory(parse: &mut Parse) -> Result<Command, CommandError> {
+    let sub = parse.next_string()?.to_uppercase();
+    let memory = match sub.as_str() {
+        "STATS" => MemoryCommand::Stats,
+        _ => return Err(unknown_subcommand("MEMORY", sub)),
+    };
+    parse.finish()?;
+    Ok(Command::Memory(memory))
+}
+
+pub(crate) fn object(parse: &mut Parse) -> Result<Command, CommandError> {
+    let sub = parse.next_string()?.to_uppercase();
+    let object = match sub.as_str() {
+        "ENCODING" => ObjectCommand::Encoding(parse.next_bytes()?),
+        _ => return Err(unknown_subcommand("OBJECT", sub)),
+    };
+    parse.finish()?;
+    Ok(Command::Object(object))
+}
+
+pub(crate) fn config(parse: &mut Parse) -> Result<Command, CommandError> {
+    let sub = parse.next_string()?.to_uppercase();
+    let config = match sub.as_str() {
+        "RESETSTAT" => ConfigCommand::ResetStat,
+        _ => return Err(unknown_subcommand("CONFIG", sub)),
+    };
+    parse.finish()?;
+    Ok(Command::Config(config))
+}
+
+pub(crate) fn cluster(parse: &mut Parse) -> Result<Command, CommandError> {
+    let sub = parse.next_string()?.to_uppercase();
+    let cluster = match sub.as_str() {
+        "INFO" => ClusterCommand::Info,
+        "SLOTS" => ClusterCommand::Slots,
+        "NODES" => ClusterCommand::Nodes,
+        _ => return Err(unknown_subcommand("CLUSTER", sub)),
+    };
+    parse.finish()?;
+    Ok(Command::Cluster(cluster))
+}
+
+pub(crate) fn command(parse: &mut Parse) -> Result<Command, CommandError> {
+    if parse.has_remaining() {
+        let sub = parse.next_string()?.to_uppercase();
+        if sub != "INFO" {
+            return Err(unknown_subcommand("COMMAND", sub));
+        }
+    }
+    Ok(Command::CommandInfo(parse_names(parse)?))
+}
+
+fn unknown_subcommand(command: &str, sub: String) -> CommandError {
+    CommandError::UnknownSubcommand {
+        command: command.into(),
+        sub,
+    }
+}
+
+/// Todos os argumentos restantes, como Bytes (chaves ou valores).
+fn parse_keys(parse: &mut Parse) -> Result<Vec<Bytes>, CommandError> {
+    let mut keys = Vec::with_capacity(parse.remaining());
+    while parse.has_remaining() {
+        keys.push(parse.next_bytes()?);
+    }
+    Ok(keys)
+}
+
+/// Todos os argumentos restantes, como String (canais, padrões, nomes).
+fn parse_names(parse: &mut Parse) -> Result<Vec<String>, CommandError> {
+    let mut names = Vec::with_capacity(parse.remaining());
+    while parse.has_remaining() {
+        names.push(parse.next_string()?);
+    }
+    Ok(names)
+}
+
+/// Pares `chave valor` do MSET/MSETNX: pelo menos um, e nunca uma chave
+/// sem valor.
+fn parse_pairs(parse: &mut Parse, cmd: &str) -> Result<Vec<(Bytes, Bytes)>, CommandError> {
+    if !parse.has_remaining() || !parse.remaining().is_multiple_of(2) {
+        return Err(CommandError::WrongArity(cmd.into()));
+    }
+    let mut pairs = Vec::with_capacity(parse.remaining() / 2);
+    while parse.has_remaining() {
+        pairs.push((parse.next_bytes()?, parse.next_bytes()?));
+    }
+    Ok(pairs)
+}
+
+/// Count opcional do LPOP/RPOP; precisa ser >= 0.
+fn parse_pop_count(parse: &mut Parse) -> Result<Option<usize>, CommandError> {
+    if !parse.has_remaining() {
+        return Ok(None);
+    }
+    let count = parse.next_int()?;
+    usize::try_from(count)
+        .map(Some)
+        .map_err(|_| CommandError::NegativeCount)
+}
+
+/// Timeout de comandos bloqueantes, em segundos (aceita fração), para ms.
+fn parse_timeout(parse: &mut Parse) -> Result<u64, CommandError> {
+    let raw = parse.next_string()?;
+    match raw.parse::<f64>() {
+        Ok(secs) if secs.is_finite() && secs >= 0.0 => Ok((secs * 1000.0).round() as u64),
+        _ => Err(CommandError::InvalidTimeout(raw)),
+    }
+}
+
+/// EXPIRE/PEXPIRE/EXPIREAT/PEXPIREAT: chave, tempo em ms (`unit_ms` converte o
+/// argumento) e condição.
+fn parse_expire(
+    parse: &mut Parse,
+    name: &str,
+    unit_ms: i64,
+) -> Result<(Bytes, i64, Option<ExpireCondition>), CommandError> {
+    let key = parse.next_bytes()?;
+    let ms = parse
+        .next_int()?
+        .checked_mul(unit_ms)
+        .ok_or_else(|| CommandError::InvalidExpireTime(name.into()))?;
+
+    let mut condition = None;
+    while parse.has_remaining() {
+        let flag = match parse.next_string()?.to_uppercase().as_str() {
+            "NX" => ExpireCondition::Nx,
+            "XX" => ExpireCondition::Xx,
+            "GT" => ExpireCondition::Gt,
+            "LT" => ExpireCondition::Lt,
+            other => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "opção de EXPIRE desconhecida: {other}"
+                )));
+            }
+        };
+        // NX, XX, GT e LT são mutuamente exclusivos
+        if condition.is_some_and(|c| c != flag) {
+            return Err(CommandError::InvalidArgument(
+                "NX, XX, GT e LT não podem ser combinados".into(),
+            ));
+        }
+        condition = Some(flag);
+    }
+
+    Ok((key, ms, condition))
+}
+
+/// Opção de TTL do SET/INCR (`EX`, `PX`, `EXAT`, `PXAT`) seguida do valor,
+/// que precisa ser positivo e caber em i64 depois de convertido para ms.
+/// `None` se `opt` não é uma dessas opções.
+fn parse_expiry(opt: &str, parse: &mut Parse, cmd: &str) -> Result<Option<Expiry>, CommandError> {
+    let (unit, absolute) = match opt {
+        "EX" => (1000, false),
+        "PX" => (1, false),
+        "EXAT" => (1000, true),
+        "PXAT" => (1, true),
+        _ => return Ok(None),
+    };
+    // Como no Redis, opção sem valor é erro de sintaxe, não de aridade
+    if !parse.has_remaining() {
+        return Err(CommandError::InvalidArgument(format!("{opt} sem valor")));
+    }
+    let value = parse.next_int()?;
+    let ms = value
+        .checked_mul(unit)
+        .filter(|_| value > 0)
+        .ok_or_else(|| CommandError::InvalidExpireTime(cmd.into()))? as u64;
+    Ok(Some(if absolute {
+        Expiry::PxAt(ms)
+    } else {
+        Expiry::Px(ms)
+    }))
+}
@@ -0,0 +1,420 @@
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use crate::Frame;
+use crate::parsers::{self, CommandParser};
+
+/// Um comando da `COMMAND_TABLE`: os metadados de `COMMAND INFO` e o parser
+/// que monta o `Command` a partir dos argumentos.
+#[derive(Debug, Clone, Copy)]
+pub struct CommandSpec {
+    /// Nome em minúsculas.
+    pub name: &'static str,
+    /// Número de argumentos (incluindo o nome). Negativo = "pelo menos |arity|".
+    pub arity: i64,
+    pub flags: &'static [&'static str],
+    /// Posição da primeira chave (0 = sem chaves).
+    pub first_key: i64,
+    /// Posição da última chave (-1 = até o fim).
+    pub last_key: i64,
+    pub key_step: i64,
+    /// Monta o `Command` a partir dos argumentos (sem o nome), com a
+    /// aridade já checada.
+    pub parse: CommandParser,
+}
+
+impl CommandSpec {
+    const fn new(
+        name: &'static str,
+        arity: i64,
+        flags: &'static [&'static str],
+        keys: (i64, i64, i64),
+        parse: CommandParser,
+    ) -> Self {
+        Self {
+            name,
+            arity,
+            flags,
+            first_key: keys.0,
+            last_key: keys.1,
+            key_step: keys.2,
+            parse,
+        }
+    }
+
+    /// Se o comando aceita `argc` argumentos, contando o nome.
+    pub fn accepts(&self, argc: usize) -> bool {
+        let argc = argc as i64;
+        if self.arity >= 0 {
+            argc == self.arity
+        } else {
+            argc >= -self.arity
+        }
+    }
+
+    /// Se o comando altera o keyspace e vai para o AOF e as réplicas como
+    /// veio. Os bloqueantes (`blocking`) ficam de fora: propagam o efeito
+    /// concreto que devolvem, nunca a si mesmos.
+    pub fn is_write(&self) -> bool {
+        self.flags.contains(&"write") && !self.flags.contains(&"blocking")
+    }
+
+    /// Entrada de `COMMAND INFO`: `[name, arity, [flags], first, last, step]`.
+    pub fn to_frame(&self) -> Frame {
+        Frame::Array(vec![
+            Frame::bulk(self.name),
+            Frame::Integer(self.arity),
+            Frame::Array(
+                self.flags
+                    .iter()
+                    .map(|f| Frame::Simple((*f).into()))
+                    .collect(),
+            ),
+            Frame::Integer(self.first_key),
+            Frame::Integer(self.last_key),
+            Frame::Integer(self.key_step),
+        ])
+    }
+}
+
+const NO_KEYS: (i64, i64, i64) = (0, 0, 0);
+const ONE_KEY: (i64, i64, i64) = (1, 1, 1);
+const ALL_KEYS: (i64, i64, i64) = (1, -1, 1);
+
+/// Tabela de todos os comandos suportados: a fonte única de aridade, flags,
+/// posições de chave e parser. `Command::from_frame` checa a aridade e
+/// despacha para o parser por ela, `is_write_command` e o LOADING leem as
+/// flags, e o COMMAND a devolve como está. O servidor registra a execução
+/// de cada comando pelo mesmo nome.
+pub const COMMAND_TABLE: &[CommandSpec] = &[
+    CommandSpec::new(
+        "ping",
+        -1,
+        &["fast", "loading", "stale"],
+        NO_KEYS,
+        parsers::ping,
+    ),
+    CommandSpec::new("echo", 2, &["fast"], NO_KEYS, parsers::echo),
+    CommandSpec::new("get", 2, &["readonly", "fast"], ONE_KEY, parsers::get),
+    CommandSpec::new("set", -3, &["write", "denyoom"], ONE_KEY, parsers::set),
+    CommandSpec::new("del", -2, &["write"], ALL_KEYS, parsers::del),
+    CommandSpec::new(
+        "exists",
+        -2,
+        &["readonly", "fast"],
+        ALL_KEYS,
+        parsers::exists,
+    ),
+    CommandSpec::new(
+        "existsatleast",
+        -3,
+        &["readonly", "fast"],
+        (1, -2, 1),
+        parsers::exists_at_least,
+    ),
+    CommandSpec::new("expire", -3, &["write", "fast"], ONE_KEY, parsers::expire),
+    CommandSpec::new("pexpire", -3, &["write", "fast"], ONE_KEY, parsers::pexpire),
+    CommandSpec::new(
+        "expireat",
+        -3,
+        &["write", "fast"],
+        ONE_KEY,
+        parsers::expireat,
+    ),
+    CommandSpec::new(
+        "pexpireat",
+        -3,
+        &["write", "fast"],
+        ONE_KEY,
+        parsers::pexpireat,
+    ),
+    CommandSpec::new(
+        "ttl",
+        2,
+        &["readonly", "random", "fast"],
+        ONE_KEY,
+        parsers::ttl,
+    ),
+    CommandSpec::new(
+        "pttl",
+        2,
+        &["readonly", "random", "fast"],
+        ONE_KEY,
+        parsers::pttl,
+    ),
+    CommandSpec::new(
+        "incr",
+        -2,
+        &["write", "denyoom", "fast"],
+        ONE_KEY,
+        parsers::incr,
+    ),
+    CommandSpec::new(
+        "decr",
+        2,
+        &["write", "denyoom", "fast"],
+        ONE_KEY,
+        parsers::decr,
+    ),
+    CommandSpec::new("getrange", 4, &["readonly"], ONE_KEY, parsers::getrange),
+    CommandSpec::new(
+        "setrange",
+        4,
+        &["write", "denyoom"],
+        ONE_KEY,
+        parsers::setrange,
+    ),
+    CommandSpec::new(
+        "append",
+        3,
+        &["write", "denyoom", "fast"],
+        ONE_KEY,
+        parsers::append,
+    ),
+    CommandSpec::new("strlen", 2, &["readonly", "fast"], ONE_KEY, parsers::strlen),
+    CommandSpec::new("mget", -2, &["readonly", "fast"], ALL_KEYS, parsers::mget),
+    CommandSpec::new("mset", -3, &["write", "denyoom"], (1, -1, 2), parsers::mset),
+    CommandSpec::new(
+        "msetnx",
+        -3,
+        &["write", "denyoom"],
+        (1, -1, 2),
+        parsers::msetnx,
+    ),
+    CommandSpec::new(
+        "lpush",
+        -3,
+        &["write", "denyoom", "fast"],
+        ONE_KEY,
+        parsers::lpush,
+    ),
+    CommandSpec::new(
+        "rpush",
+        -3,
+        &["write", "denyoom", "fast"],
+        ONE_KEY,
+        parsers::rpush,
+    ),
+    CommandSpec::new(
+        "rpushcap",
+        -4,
+        &["write", "denyoom", "fast"],
+        ONE_KEY,
+        parsers::rpushcap,
+    ),
+    CommandSpec::new("lpop", -2, &["write", "fast"], ONE_KEY, parsers::lpop),
+    CommandSpec::new("rpop", -2, &["write", "fast"], ONE_KEY, parsers::rpop),
+    CommandSpec::new("lrange", 4, &["readonly"], ONE_KEY, parsers::lrange),
+    CommandSpec::new(
+        "rpoplpush",
+        3,
+        &["write", "denyoom"],
+        (1, 2, 1),
+        parsers::rpoplpush,
+    ),
+    CommandSpec::new(
+        "brpoplpush",
+        4,
+        &["write", "denyoom", "noscript", "blocking"],
+        (1, 2, 1),
+        parsers::brpoplpush,
+    ),
+    CommandSpec::new(
+        "subscribe",
+        -2,
+        &["pubsub", "loading", "stale"],
+        NO_KEYS,
+        parsers::subscribe,
+    ),
+    CommandSpec::new(
+        "unsubscribe",
+        -1,
+        &["pubsub", "loading", "stale"],
+        NO_KEYS,
+        parsers::unsubscribe,
+    ),
+    CommandSpec::new(
+        "psubscribe",
+        -2,
+        &["pubsub", "loading", "stale"],
+        NO_KEYS,
+        parsers::psubscribe,
+    ),
+    CommandSpec::new(
+        "punsubscribe",
+        -1,
+        &["pubsub", "loading", "stale"],
+        NO_KEYS,
+        parsers::punsubscribe,
+    ),
+    CommandSpec::new(
+        "publish",
+        3,
+        &["pubsub", "loading", "stale", "fast"],
+        NO_KEYS,
+        parsers::publish,
+    ),
+    CommandSpec::new(
+        "bitop",
+        -4,
+        &["write", "denyoom"],
+        (2, -1, 1),
+        parsers::bitop,
+    ),
+    CommandSpec::new("rename", 3, &["write"], (1, 2, 1), parsers::rename),
+    CommandSpec::new(
+        "renamenx",
+        3,
+        &["write", "fast"],
+        (1, 2, 1),
+        parsers::renamenx,
+    ),
+    CommandSpec::new("type", 2, &["readonly", "fast"], ONE_KEY, parsers::key_type),
+    CommandSpec::new("keys", 2, &["readonly", "sortable"], NO_KEYS, parsers::keys),
+    CommandSpec::new("dbsize", 1, &["readonly", "fast"], NO_KEYS, parsers::dbsize),
+    CommandSpec::new(
+        "time",
+        1,
+        &["random", "loading", "stale", "fast"],
+        NO_KEYS,
+        parsers::time,
+    ),
+    CommandSpec::new("info", -1, &["loading", "stale"], NO_KEYS, parsers::info),
+    CommandSpec::new(
+        "replicaof",
+        3,
+        &["admin", "noscript", "stale"],
+        NO_KEYS,
+        parsers::replicaof,
+    ),
+    CommandSpec::new(
+        "replconf",
+        -1,
+        &["admin", "noscript", "loading", "stale"],
+        NO_KEYS,
+        parsers::replconf,
+    ),
+    CommandSpec::new("psync", 3, &["admin", "noscript"], NO_KEYS, parsers::psync),
+    CommandSpec::new(
+        "debug",
+        -2,
+        &["admin", "loading", "stale"],
+        NO_KEYS,
+        parsers::debug,
+    ),
+    CommandSpec::new(
+        "client",
+        -2,
+        &["loading", "stale"],
+        NO_KEYS,
+        parsers::client,
+    ),
+    CommandSpec::new(
+        "config",
+        -2,
+        &["admin", "noscript", "loading", "stale"],
+        NO_KEYS,
+        parsers::config,
+    ),
+    CommandSpec::new(
+        "memory",
+        -2,
+        &["readonly", "random"],
+        NO_KEYS,
+        parsers::memory,
+    ),
+    CommandSpec::new(
+        "object",
+        -2,
+        &["readonly", "random"],
+        (2, 2, 1),
+        parsers::object,
+    ),
+    CommandSpec::new(
+        "cluster",
+        -2,
+        &["random", "loading", "stale"],
+        NO_KEYS,
+        parsers::cluster,
+    ),
+    CommandSpec::new(
+        "command",
+        -1,
+        &["random", "loading", "stale"],
+        NO_KEYS,
+        parsers::command,
+    ),
+];
+
+/// Maior nome da `COMMAND_TABLE`; nomes maiores nem são procurados.
+const MAX_NAME_LEN: usize = 16;
+
+/// Índice da `COMMAND_TABLE` por nome, montado no primeiro uso.
+static COMMAND_INDEX: LazyLock<HashMap<&'static str, &'static CommandSpec>> =
+    LazyLock::new(|| COMMAND_TABLE.iter().map(|spec| (spec.name, spec)).collect());
+
+/// Busca os metadados de um comando pelo nome (case-insensitive). O nome
+/// vai para minúsculas num buffer na pilha, sem alocar.
+pub fn lookup_command(name: &str) -> Option<&'static CommandSpec> {
+    let name = name.as_bytes();
+    let mut buf = [0u8; MAX_NAME_LEN];
+    let lower = buf.get_mut(..name.len())?;
+    lower.copy_from_slice(name);
+    lower.make_ascii_lowercase();
+    let lower = std::str::from_utf8(lower).ok()?;
+    COMMAND_INDEX.get(lower).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookup_is_case_insensitive() {
+        assert_eq!(lookup_command("GET").unwrap().arity, 2);
+        assert_eq!(lookup_command("set").unwrap().arity, -3);
+        assert!(lookup_command("nosuchcommand").is_none());
+    }
+
+    #[test]
+    fn every_name_is_indexed() {
+        for spec in COMMAND_TABLE {
+            assert!(spec.name.len() <= MAX_NAME_LEN, "{}", spec.name);
+            let found = lookup_command(&spec.name.to_uppercase()).unwrap();
+            assert_eq!(found.name, spec.name);
+        }
+        assert_eq!(COMMAND_INDEX.len(), COMMAND_TABLE.len(), "nome duplicado");
+        assert!(lookup_command("averyveryverylongcommandname").is_none());
+    }
+
+    #[test]
+    fn arity_and_write_flags() {
+        let get = lookup_command("get").unwrap();
+        assert!(get.accepts(2) && !get.accepts(3));
+        let del = lookup_command("del").unwrap();
+        assert!(!del.accepts(1) && del.accepts(2) && del.accepts(10));
+
+        assert!(del.is_write() && !get.is_write());
+        // Bloqueantes propagam o efeito, não o comando
+        assert!(!lookup_command("brpoplpush").unwrap().is_write());
+    }
+
+    #[test]
+    fn spec_to_frame() {
+        let frame = lookup_command("get").unwrap().to_frame();
+        assert_eq!(
+            frame,
+            Frame::Array(vec![
+                Frame::bulk("get"),
+                Frame::Integer(2),
+                Frame::Array(vec![
+                    Frame::Simple("readonly".into()),
+                    Frame::Simple("fast".into()),
+                ]),
+                Frame::Integer(1),
+                Frame::Integer(1),
+                Frame::Integer(1),
+            ])
+        );
+    }
+}
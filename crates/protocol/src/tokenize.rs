@@ -0,0 +1,99 @@
+/// Tokeniza uma linha de comando em texto (estilo redis-cli), com suporte a
+/// strings entre aspas simples ou duplas e escapes dentro delas.
+pub fn tokenize(input: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quote = false;
+    let mut quote_char = '"';
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quote {
+            if c == quote_char {
+                in_quote = false;
+            } else if c == '\\' {
+                if let Some(&next) = chars.peek() {
+                    match next {
+                        'n' => {
+                            current.push('\n');
+                            chars.next();
+                        }
+                        't' => {
+                            current.push('\t');
+                            chars.next();
+                        }
+                        '\\' => {
+                            current.push('\\');
+                            chars.next();
+                        }
+                        '"' => {
+                            current.push('"');
+                            chars.next();
+                        }
+                        '\'' => {
+                            current.push('\'');
+                            chars.next();
+                        }
+                        _ => current.push(c),
+                    }
+                }
+            } else {
+                current.push(c);
+            }
+        } else if c == '"' || c == '\'' {
+            in_quote = true;
+            quote_char = c;
+        } else if c.is_whitespace() {
+            if !current.is_empty() {
+                tokens.push(std::mem::take(&mut current));
+            }
+        } else {
+            current.push(c);
+        }
+    }
+
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenize_simple() {
+        assert_eq!(tokenize("SET key value"), vec!["SET", "key", "value"]);
+    }
+
+    #[test]
+    fn tokenize_quoted() {
+        assert_eq!(
+            tokenize(r#"SET key "hello world""#),
+            vec!["SET", "key", "hello world"]
+        );
+    }
+
+    #[test]
+    fn tokenize_single_quotes() {
+        assert_eq!(
+            tokenize("SET key 'hello world'"),
+            vec!["SET", "key", "hello world"]
+        );
+    }
+
+    #[test]
+    fn tokenize_escaped() {
+        assert_eq!(
+            tokenize(r#"SET key "hello\"world""#),
+            vec!["SET", "key", r#"hello"world"#]
+        );
+    }
+
+    #[test]
+    fn tokenize_empty() {
+        assert_eq!(tokenize(""), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,797 @@
+//! Execução de cada comando, registrada por nome em `HANDLERS`.
+//!
+//! A `COMMAND_TABLE` do protocolo guarda metadados e parser; aqui fica o
+//! que o comando faz no servidor. Os testes garantem que as duas tabelas
+//! cobrem os mesmos nomes.
+
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::LazyLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bytes::Bytes;
+use tracing::{debug, info};
+
+use stormdb_common::{CommandError, ErrorReply, StorageError};
+use stormdb_protocol::{
+    COMMAND_TABLE, ClientCommand, ClusterCommand, Command, CommandSpec, ConfigCommand,
+    DebugCommand, Frame, MemoryCommand, ObjectCommand, SetOptions, lookup_command,
+};
+use stormdb_storage::{AofSender, Db, WritePermit, replay_aof, unix_time_ms};
+
+use crate::ServerConfig;
+use crate::info;
+use crate::replication::{self, SharedReplState};
+
+/// Resposta de um comando e, opcionalmente, os efeitos a propagar para o
+/// AOF e as réplicas no lugar dele.
+pub(crate) type Reply = (Frame, Option<Vec<Command>>);
+
+type BoxFuture<'a> = Pin<Box<dyn Future<Output = Reply> + Send + 'a>>;
+
+/// O que um handler pode usar da conexão e do servidor.
+pub(crate) struct ExecContext<'a> {
+    pub db: &'a Db,
+    pub conn_id: u64,
+    pub aof_tx: Option<&'a AofSender>,
+    pub config: &'a ServerConfig,
+    pub repl: &'a SharedReplState,
+    /// Um comando que pega o próprio `WritePermit` (BRPOPLPUSH) o deixa
+    /// aqui, para ser segurado até a propagação.
+    pub permit: &'a mut Option<WritePermit>,
+}
+
+/// Execução de um comando. Só os que esperam algo (bloqueio, pub/sub,
+/// recarga) são assíncronos; o resto roda sem alocar um future.
+#[derive(Clone, Copy)]
+pub(crate) enum Handler {
+    Sync(fn(&Command, &ExecContext<'_>) -> Reply),
+    Async(for<'c> fn(&'c Command, &'c mut ExecContext<'_>) -> BoxFuture<'c>),
+    /// Tratado pelo loop da conexão antes de chegar à execução (PSYNC e os
+    /// de inscrição).
+    Connection,
+}
+
+/// Handler de cada nome da `COMMAND_TABLE`.
+const HANDLER_TABLE: &[(&str, Handler)] = &[
+    ("ping", Handler::Sync(ping)),
+    ("echo", Handler::Sync(echo)),
+    ("get", Handler::Sync(get)),
+    ("set", Handler::Sync(set)),
+    ("del", Handler::Sync(del)),
+    ("exists", Handler::Sync(exists)),
+    ("existsatleast", Handler::Sync(exists_at_least)),
+    ("expire", Handler::Sync(expire)),
+    ("pexpire", Handler::Sync(expire)),
+    ("expireat", Handler::Sync(expire_at)),
+    ("pexpireat", Handler::Sync(expire_at)),
+    ("ttl", Handler::Sync(ttl)),
+    ("pttl", Handler::Sync(pttl)),
+    ("incr", Handler::Sync(incr)),
+    ("decr", Handler::Sync(decr)),
+    ("getrange", Handler::Sync(getrange)),
+    ("setrange", Handler::Sync(setrange)),
+    ("append", Handler::Sync(append)),
+    ("strlen", Handler::Sync(strlen)),
+    ("mget", Handler::Sync(mget)),
+    ("mset", Handler::Sync(mset)),
+    ("msetnx", Handler::Sync(msetnx)),
+    ("lpush", Handler::Sync(lpush)),
+    ("rpush", Handler::Sync(rpush)),
+    ("rpushcap", Handler::Sync(rpushcap)),
+    ("lpop", Handler::Sync(lpop)),
+    ("rpop", Handler::Sync(rpop)),
+    ("lrange", Handler::Sync(lrange)),
+    ("rpoplpush", Handler::Sync(rpoplpush)),
+    ("brpoplpush", Handler::Async(brpoplpush)),
+    ("subscribe", Handler::Connection),
+    ("unsubscribe", Handler::Connection),
+    ("psubscribe", Handler::Connection),
+    ("punsubscribe", Handler::Connection),
+    ("publish", Handler::Async(publish)),
+    ("bitop", Handler::Sync(bitop)),
+    ("rename", Handler::Sync(rename)),
+    ("renamenx", Handler::Sync(renamenx)),
+    ("type", Handler::Sync(key_type)),
+    ("keys", Handler::Sync(keys)),
+    ("dbsize", Handler::Sync(dbsize)),
+    ("time", Handler::Sync(time)),
+    ("info", Handler::Sync(info)),
+    ("replicaof", Handler::Sync(replicaof)),
+    ("replconf", Handler::Sync(replconf)),
+    ("psync", Handler::Connection),
+    ("debug", Handler::Async(debug)),
+    ("client", Handler::Sync(client)),
+    ("memory", Handler::Sync(memory)),
+    ("object", Handler::Sync(object)),
+    ("config", Handler::Sync(config)),
+    ("cluster", Handler::Sync(cluster)),
+    ("command", Handler::Sync(command)),
+];
+
+/// Índice da `HANDLER_TABLE` por nome, montado no primeiro uso.
+static HANDLERS: LazyLock<HashMap<&'static str, Handler>> =
+    LazyLock::new(|| HANDLER_TABLE.iter().copied().collect());
+
+/// Handler registrado para o comando; `None` só para `Unknown`.
+pub(crate) fn handler_for(spec: &CommandSpec) -> Option<Handler> {
+    HANDLERS.get(spec.name).copied()
+}
+
+/// Resposta sem efeitos próprios: se for escrita, propaga o comando como veio.
+fn reply(frame: Frame) -> Reply {
+    (frame, None)
+}
+
+fn ping(cmd: &Command, _: &ExecContext<'_>) -> Reply {
+    let Command::Ping(msg) = cmd else {
+        unreachable!()
+    };
+    reply(match msg {
+        Some(m) => Frame::Bulk(m.clone()),
+        None => Frame::Simple("PONG".into()),
+    })
+}
+
+fn echo(cmd: &Command, _: &ExecContext<'_>) -> Reply {
+    let Command::Echo(msg) = cmd else {
+        unreachable!()
+    };
+    reply(Frame::Bulk(msg.clone()))
+}
+
+fn get(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::Get(key) = cmd else {
+        unreachable!()
+    };
+    reply(match ctx.db.get(key) {
+        Some(value) => Frame::Bulk(value),
+        None => Frame::Null,
+    })
+}
+
+fn set(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::Set {
+        key,
+        value,
+        options,
+    } = cmd
+    else {
+        unreachable!()
+    };
+    // TTL resolvido para um horário absoluto (PXAT) antes de executar: é o
+    // que vai para o AOF e as réplicas, então um replay depois de um
+    // restart expira a chave no mesmo horário.
+    let Some(options) = ctx.db.absolute_set_options(options) else {
+        let err = CommandError::InvalidExpireTime("SET".into());
+        return reply(Frame::Error(err.reply()));
+    };
+    let response = if options.get {
+        match ctx.db.set_get(key.clone(), value.clone(), &options) {
+            Ok(Some(old)) => Frame::Bulk(old),
+            Ok(None) => Frame::Null,
+            Err(e) => Frame::Error(e.reply()),
+        }
+    } else {
+        match ctx.db.set(key.clone(), value.clone(), &options) {
+            Ok(true) => Frame::Simple("OK".into()),
+            Ok(false) => Frame::Null, // NX/XX condition not met
+            Err(e) => Frame::Error(e.reply()),
+        }
+    };
+    let effect = Command::Set {
+        key: key.clone(),
+        value: value.clone(),
+        options,
+    };
+    (response, Some(vec![effect]))
+}
+
+fn del(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::Del(keys) = cmd else {
+        unreachable!()
+    };
+    reply(Frame::Integer(ctx.db.del(keys) as i64))
+}
+
+fn exists(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::Exists(keys) = cmd else {
+        unreachable!()
+    };
+    reply(Frame::Integer(ctx.db.exists(keys) as i64))
+}
+
+fn exists_at_least(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::ExistsAtLeast { keys, n } = cmd else {
+        unreachable!()
+    };
+    reply(Frame::Integer(ctx.db.exists_at_least(keys, *n) as i64))
+}
+
+fn expire(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::Expire { key, ms, condition } = cmd else {
+        unreachable!()
+    };
+    // Propagado como PEXPIREAT, pelo mesmo motivo do SET. Um deadline além
+    // de i64::MAX ms é erro, como no SET/INCR, e não um TTL cortado.
+    let Some(unix_ms) = (unix_time_ms() as i64).checked_add(*ms) else {
+        let err = CommandError::InvalidExpireTime("EXPIRE".into());
+        return reply(Frame::Error(err.reply()));
+    };
+    let response = Frame::Integer(ctx.db.expire_at(key, unix_ms, *condition) as i64);
+    let effect = Command::ExpireAt {
+        key: key.clone(),
+        unix_ms,
+        condition: *condition,
+    };
+    (response, Some(vec![effect]))
+}
+
+fn expire_at(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::ExpireAt {
+        key,
+        unix_ms,
+        condition,
+    } = cmd
+    else {
+        unreachable!()
+    };
+    reply(Frame::Integer(
+        ctx.db.expire_at(key, *unix_ms, *condition) as i64
+    ))
+}
+
+fn ttl(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::Ttl(key) = cmd else {
+        unreachable!()
+    };
+    reply(ttl_reply(ctx.db, key, 1000))
+}
+
+fn pttl(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::Pttl(key) = cmd else {
+        unreachable!()
+    };
+    reply(ttl_reply(ctx.db, key, 1))
+}
+
+fn incr(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::Incr { key, expire } = cmd else {
+        unreachable!()
+    };
+    let Some(expiry) = expire else {
+        return reply(integer_reply(ctx.db.incr(key)));
+    };
+    let Some(expiry) = expiry.absolute(unix_time_ms()) else {
+        let err = CommandError::InvalidExpireTime("INCR".into());
+        return reply(Frame::Error(err.reply()));
+    };
+    let response = integer_reply(ctx.db.incr_with_ttl(key, expiry));
+    let effect = Command::Incr {
+        key: key.clone(),
+        expire: Some(expiry),
+    };
+    (response, Some(vec![effect]))
+}
+
+fn decr(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::Decr(key) = cmd else {
+        unreachable!()
+    };
+    reply(integer_reply(ctx.db.decr(key)))
+}
+
+fn getrange(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::GetRange { key, start, end } = cmd else {
+        unreachable!()
+    };
+    reply(match ctx.db.getrange(key, *start, *end) {
+        Ok(data) => Frame::Bulk(data),
+        Err(e) => Frame::Error(e.reply()),
+    })
+}
+
+fn setrange(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::SetRange { key, offset, value } = cmd else {
+        unreachable!()
+    };
+    reply(len_reply(ctx.db.setrange(key, *offset, value)))
+}
+
+fn append(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::Append { key, value } = cmd else {
+        unreachable!()
+    };
+    reply(len_reply(ctx.db.append(key, value)))
+}
+
+fn strlen(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::Strlen(key) = cmd else {
+        unreachable!()
+    };
+    reply(len_reply(ctx.db.strlen(key)))
+}
+
+fn mget(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::MGet(keys) = cmd else {
+        unreachable!()
+    };
+    reply(Frame::Array(
+        ctx.db
+            .mget(keys)
+            .into_iter()
+            .map(|value| value.map_or(Frame::Null, Frame::Bulk))
+            .collect(),
+    ))
+}
+
+// MSET e MSETNX são propagados como um SET por chave, com o TTL que cada
+// uma recebeu (ver `Db::mset`)
+fn mset(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::MSet(pairs) = cmd else {
+        unreachable!()
+    };
+    match ctx.db.mset(pairs.clone()) {
+        Ok(applied) => (
+            Frame::Simple("OK".into()),
+            Some(set_effects(pairs, applied)),
+        ),
+        Err(e) => reply(Frame::Error(e.reply())),
+    }
+}
+
+fn msetnx(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::MSetNx(pairs) = cmd else {
+        unreachable!()
+    };
+    match ctx.db.msetnx(pairs.clone()) {
+        Ok(Some(applied)) => (Frame::Integer(1), Some(set_effects(pairs, applied))),
+        // Nada foi gravado: nada a propagar
+        Ok(None) => (Frame::Integer(0), Some(Vec::new())),
+        Err(e) => reply(Frame::Error(e.reply())),
+    }
+}
+
+fn lpush(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::LPush { key, values } = cmd else {
+        unreachable!()
+    };
+    reply(len_reply(ctx.db.lpush(key, values)))
+}
+
+fn rpush(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::RPush { key, values } = cmd else {
+        unreachable!()
+    };
+    reply(len_reply(ctx.db.rpush(key, values)))
+}
+
+fn rpushcap(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::RPushCap {
+        key,
+        maxlen,
+        values,
+    } = cmd
+    else {
+        unreachable!()
+    };
+    reply(len_reply(ctx.db.rpush_capped(key, values, Some(*maxlen))))
+}
+
+fn lpop(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::LPop { key, count } = cmd else {
+        unreachable!()
+    };
+    reply(pop_reply(ctx.db.lpop(key, *count), *count))
+}
+
+fn rpop(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::RPop { key, count } = cmd else {
+        unreachable!()
+    };
+    reply(pop_reply(ctx.db.rpop(key, *count), *count))
+}
+
+fn lrange(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::LRange { key, start, stop } = cmd else {
+        unreachable!()
+    };
+    reply(match ctx.db.lrange(key, *start, *stop) {
+        Ok(items) => Frame::Array(items.into_iter().map(Frame::Bulk).collect()),
+        Err(e) => Frame::Error(e.reply()),
+    })
+}
+
+fn rpoplpush(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::RPopLPush {
+        source,
+        destination,
+    } = cmd
+    else {
+        unreachable!()
+    };
+    reply(move_reply(ctx.db.rpoplpush(source, destination)))
+}
+
+fn brpoplpush<'c>(cmd: &'c Command, ctx: &'c mut ExecContext<'_>) -> BoxFuture<'c> {
+    Box::pin(async move {
+        let Command::BRPopLPush {
+            source,
+            destination,
+            timeout_ms,
+        } = cmd
+        else {
+            unreachable!()
+        };
+        let timeout = (*timeout_ms > 0).then(|| Duration::from_millis(*timeout_ms));
+        let moved = match ctx.db.brpoplpush(source, destination, timeout).await {
+            Ok(Some((item, moved_with))) => {
+                *ctx.permit = Some(moved_with);
+                Ok(Some(item))
+            }
+            other => other.map(|_| None),
+        };
+        let response = move_reply(moved);
+        // Propagado como o RPOPLPUSH que de fato executou: o replay no
+        // AOF/réplicas nunca pode bloquear
+        if !matches!(response, Frame::Bulk(_)) {
+            return reply(response);
+        }
+        let effect = Command::RPopLPush {
+            source: source.clone(),
+            destination: destination.clone(),
+        };
+        (response, Some(vec![effect]))
+    })
+}
+
+fn publish<'c>(cmd: &'c Command, ctx: &'c mut ExecContext<'_>) -> BoxFuture<'c> {
+    Box::pin(async move {
+        let Command::Publish { channel, message } = cmd else {
+            unreachable!()
+        };
+        let count = ctx.db.publish(channel, message.clone()).await;
+        reply(Frame::Integer(count as i64))
+    })
+}
+
+fn bitop(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::BitOp { op, dest, keys } = cmd else {
+        unreachable!()
+    };
+    reply(len_reply(ctx.db.bitop(*op, dest, keys)))
+}
+
+fn rename(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::Rename {
+        source,
+        destination,
+    } = cmd
+    else {
+        unreachable!()
+    };
+    reply(match ctx.db.rename(source, destination) {
+        Ok(()) => Frame::Simple("OK".into()),
+        Err(e) => Frame::Error(e.reply()),
+    })
+}
+
+fn renamenx(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::RenameNx {
+        source,
+        destination,
+    } = cmd
+    else {
+        unreachable!()
+    };
+    reply(match ctx.db.renamenx(source, destination) {
+        Ok(renamed) => Frame::Integer(renamed as i64),
+        Err(e) => Frame::Error(e.reply()),
+    })
+}
+
+fn key_type(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::Type(key) = cmd else {
+        unreachable!()
+    };
+    reply(Frame::Simple(ctx.db.key_type(key).unwrap_or("none").into()))
+}
+
+fn keys(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::Keys(pattern) = cmd else {
+        unreachable!()
+    };
+    let keys = ctx.db.keys(pattern);
+    reply(Frame::Array(keys.into_iter().map(Frame::Bulk).collect()))
+}
+
+fn dbsize(_: &Command, ctx: &ExecContext<'_>) -> Reply {
+    reply(Frame::Integer(ctx.db.len() as i64))
+}
+
+fn time(_: &Command, _: &ExecContext<'_>) -> Reply {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    reply(Frame::Array(vec![
+        Frame::bulk(&now.as_secs().to_string()),
+        Frame::bulk(&now.subsec_micros().to_string()),
+    ]))
+}
+
+fn info(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::Info(section) = cmd else {
+        unreachable!()
+    };
+    let text = info::render(
+        section.as_deref(),
+        ctx.db,
+        ctx.aof_tx,
+        &ctx.repl.read().unwrap(),
+        ctx.config,
+    );
+    reply(Frame::Bulk(Bytes::from(text)))
+}
+
+fn replicaof(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::ReplicaOf(master) = cmd else {
+        unreachable!()
+    };
+    match master {
+        Some((host, port)) => {
+            info!("REPLICAOF {host}:{port}");
+            replication::start_replica(ctx.repl, host.clone(), *port, ctx.db.clone());
+        }
+        None => {
+            info!("REPLICAOF NO ONE: promovido a master");
+            replication::stop_replica(ctx.repl);
+        }
+    }
+    reply(Frame::Simple("OK".into()))
+}
+
+fn replconf(cmd: &Command, _: &ExecContext<'_>) -> Reply {
+    let Command::ReplConf(options) = cmd else {
+        unreachable!()
+    };
+    debug!("REPLCONF {options:?}");
+    reply(Frame::Simple("OK".into()))
+}
+
+fn client(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::Client(client) = cmd else {
+        unreachable!()
+    };
+    reply(match client {
+        ClientCommand::Id => Frame::Integer(ctx.conn_id as i64),
+        ClientCommand::Pause { ms, mode } => {
+            ctx.config.pause.pause(Duration::from_millis(*ms), *mode);
+            Frame::Simple("OK".into())
+        }
+        ClientCommand::Unpause => {
+            ctx.config.pause.unpause();
+            Frame::Simple("OK".into())
+        }
+    })
+}
+
+fn memory(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::Memory(MemoryCommand::Stats) = cmd else {
+        unreachable!()
+    };
+    reply(memory_stats_reply(ctx.db))
+}
+
+fn object(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::Object(ObjectCommand::Encoding(key)) = cmd else {
+        unreachable!()
+    };
+    reply(match ctx.db.object_encoding(key) {
+        Some(encoding) => Frame::bulk(encoding),
+        None => Frame::Null,
+    })
+}
+
+fn config(cmd: &Command, ctx: &ExecContext<'_>) -> Reply {
+    let Command::Config(ConfigCommand::ResetStat) = cmd else {
+        unreachable!()
+    };
+    ctx.config.stats.reset();
+    ctx.db.reset_stats();
+    reply(Frame::Simple("OK".into()))
+}
+
+// Standalone: só o suficiente para drivers cluster-aware conectarem
+fn cluster(cmd: &Command, _: &ExecContext<'_>) -> Reply {
+    let Command::Cluster(cluster) = cmd else {
+        unreachable!()
+    };
+    reply(match cluster {
+        ClusterCommand::Info => Frame::bulk("cluster_enabled:0\r\n"),
+        ClusterCommand::Slots | ClusterCommand::Nodes => Frame::Array(vec![]),
+    })
+}
+
+fn command(cmd: &Command, _: &ExecContext<'_>) -> Reply {
+    let Command::CommandInfo(names) = cmd else {
+        unreachable!()
+    };
+    let entries = if names.is_empty() {
+        COMMAND_TABLE.iter().map(CommandSpec::to_frame).collect()
+    } else {
+        names
+            .iter()
+            .map(|name| lookup_command(name).map_or(Frame::Null, CommandSpec::to_frame))
+            .collect()
+    };
+    reply(Frame::Array(entries))
+}
+
+/// Executa um subcomando DEBUG (exige `--enable-debug`).
+fn debug<'c>(cmd: &'c Command, ctx: &'c mut ExecContext<'_>) -> BoxFuture<'c> {
+    Box::pin(async move {
+        let Command::Debug(debug) = cmd else {
+            unreachable!()
+        };
+        reply(execute_debug(debug, ctx).await)
+    })
+}
+
+async fn execute_debug(debug: &DebugCommand, ctx: &ExecContext<'_>) -> Frame {
+    let db = ctx.db;
+    if !ctx.config.enable_debug {
+        return Frame::Error("ERR DEBUG command not allowed. Enable it with --enable-debug".into());
+    }
+
+    match debug {
+        DebugCommand::Reload => {
+            let (Some(tx), Some(path)) = (ctx.aof_tx, ctx.config.aof_path.as_ref()) else {
+                return Frame::Error("ERR DEBUG RELOAD requires AOF persistence (--aof)".into());
+            };
+            // Nenhuma escrita pode cair entre o sync e o flush: ela estaria
+            // no keyspace, mas não no AOF relido, e se perderia no reload
+            let _barrier = db.write_barrier().await;
+            if let Err(e) = tx.sync().await {
+                return Frame::Error(format!("ERR error syncing AOF: {e}"));
+            }
+            db.set_loading(true);
+            db.flush().await;
+            let result = replay_aof(path, db).await;
+            db.set_loading(false);
+            match result {
+                Ok(count) => {
+                    info!("DEBUG RELOAD: {count} comandos recarregados do AOF");
+                    Frame::Simple("OK".into())
+                }
+                Err(e) => Frame::Error(format!("ERR error reloading AOF: {e}")),
+            }
+        }
+        DebugCommand::ChangeReplId => {
+            let mut state = ctx.repl.write().unwrap();
+            state.change_replid();
+            info!("DEBUG CHANGE-REPL-ID: novo replid {}", state.replid);
+            Frame::Simple("OK".into())
+        }
+        DebugCommand::SetActiveExpire(enabled) => {
+            db.set_active_expire(*enabled);
+            Frame::Simple("OK".into())
+        }
+        DebugCommand::Object(key) => match db.object_stats(key) {
+            Some(stats) => {
+                let mut reply = format!(
+                    "encoding:{} serializedlength:{}",
+                    stats.encoding, stats.serialized_length
+                );
+                if let Some(elements) = stats.elements {
+                    reply.push_str(&format!(" elements:{elements}"));
+                }
+                Frame::Bulk(reply.into())
+            }
+            None => Frame::Error("ERR no such key".into()),
+        },
+    }
+}
+
+/// Um `SET` por par de um MSET/MSETNX, com as opções com que cada chave
+/// foi gravada.
+fn set_effects(pairs: &[(Bytes, Bytes)], applied: Vec<SetOptions>) -> Vec<Command> {
+    pairs
+        .iter()
+        .zip(applied)
+        .map(|((key, value), options)| Command::Set {
+            key: key.clone(),
+            value: value.clone(),
+            options,
+        })
+        .collect()
+}
+
+/// Resposta do TTL/PTTL na unidade `unit_ms`: -2 se a chave não existe
+/// (ou já expirou), -1 se não tem TTL. Como no Redis, o TTL em segundos é
+/// arredondado para o inteiro mais próximo.
+fn ttl_reply(db: &Db, key: &[u8], unit_ms: u128) -> Frame {
+    match db.pttl(key) {
+        None => Frame::Integer(-2),
+        Some(None) => Frame::Integer(-1),
+        Some(Some(ttl)) => Frame::Integer(((ttl.as_millis() + unit_ms / 2) / unit_ms) as i64),
+    }
+}
+
+/// MEMORY STATS como array plano de pares nome/valor.
+fn memory_stats_reply(db: &Db) -> Frame {
+    let stats = db.memory_stats();
+    let (largest_key, largest_bytes) = match stats.largest_key {
+        Some((key, size)) => (Frame::Bulk(key), size as i64),
+        None => (Frame::Null, 0),
+    };
+    Frame::Array(vec![
+        Frame::bulk("keys.count"),
+        Frame::Integer(stats.keys as i64),
+        Frame::bulk("total.bytes"),
+        Frame::Integer(stats.total_bytes as i64),
+        Frame::bulk("dataset.bytes"),
+        Frame::Integer(stats.dataset_bytes as i64),
+        Frame::bulk("overhead.total"),
+        Frame::Integer(stats.overhead_bytes as i64),
+        Frame::bulk("largest.key"),
+        largest_key,
+        Frame::bulk("largest.key.bytes"),
+        Frame::Integer(largest_bytes),
+    ])
+}
+
+/// Resposta do RPOPLPUSH/BRPOPLPUSH: o elemento movido, ou Null.
+fn move_reply(result: Result<Option<Bytes>, StorageError>) -> Frame {
+    match result {
+        Ok(Some(item)) => Frame::Bulk(item),
+        Ok(None) => Frame::Null,
+        Err(e) => Frame::Error(e.reply()),
+    }
+}
+
+/// Resposta do LPOP/RPOP, seguindo o Redis: chave inexistente é sempre
+/// Null; sem `count` responde um bulk, com `count` um array (vazio se
+/// `count` for 0).
+fn pop_reply(result: Result<Option<Vec<Bytes>>, StorageError>, count: Option<usize>) -> Frame {
+    match result {
+        Ok(None) => Frame::Null,
+        Ok(Some(items)) if count.is_none() => match items.into_iter().next() {
+            Some(item) => Frame::Bulk(item),
+            None => Frame::Null,
+        },
+        Ok(Some(items)) => Frame::Array(items.into_iter().map(Frame::Bulk).collect()),
+        Err(e) => Frame::Error(e.reply()),
+    }
+}
+
+fn integer_reply(result: Result<i64, StorageError>) -> Frame {
+    match result {
+        Ok(n) => Frame::Integer(n),
+        Err(e) => Frame::Error(e.reply()),
+    }
+}
+
+fn len_reply(result: Result<usize, StorageError>) -> Frame {
+    match result {
+        Ok(len) => Frame::Integer(len as i64),
+        Err(e) => Frame::Error(e.reply()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_table_entry_has_a_handler() {
+        for spec in COMMAND_TABLE {
+            assert!(handler_for(spec).is_some(), "{} sem handler", spec.name);
+        }
+        // Nada registrado fora da tabela, nem registrado duas vezes
+        for (name, _) in HANDLER_TABLE {
+            assert!(lookup_command(name).is_some(), "{name} fora da COMMAND_TABLE");
+        }
+        assert_eq!(HANDLERS.len(), HANDLER_TABLE.len());
+        assert_eq!(HANDLERS.len(), COMMAND_TABLE.len());
+    }
+}
@@ -0,0 +1,54 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
+
+use stormdb_common::{DEFAULT_PORT, MAX_ARRAY_LEN, MAX_COMMAND_ARGS};
+
+use crate::replication::new_replid;
+use crate::{ClientPause, ServerStats};
+
+/// Configuração do servidor relevante para o tratamento de conexões.
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// Habilita os comandos DEBUG (`--enable-debug`).
+    pub enable_debug: bool,
+    /// Caminho do arquivo AOF, quando a persistência está ativa.
+    pub aof_path: Option<PathBuf>,
+    /// Limite de comandos por segundo por conexão (`--max-commands-per-sec`).
+    pub max_commands_per_sec: Option<u32>,
+    /// Responde com erro e ressincroniza em frames malformados, em vez de
+    /// fechar a conexão (`--resync-on-protocol-error`).
+    pub resync_on_protocol_error: bool,
+    /// Máximo de elementos por array recebido (`--proto-max-array-len`).
+    pub max_array_len: usize,
+    /// Máximo de argumentos por comando, sem contar o nome (`--max-args`).
+    pub max_args: usize,
+    /// Contadores do INFO `stats`, atualizados pelo accept loop e pelas conexões.
+    pub stats: Arc<ServerStats>,
+    /// Pausa do `CLIENT PAUSE`, compartilhada por todas as conexões.
+    pub pause: Arc<ClientPause>,
+    /// Porta TCP em que o servidor escuta (INFO `server`).
+    pub port: u16,
+    /// Id aleatório deste processo, gerado no startup (INFO `run_id`).
+    pub run_id: String,
+    /// Início do processo, base do `uptime_in_seconds`.
+    pub started_at: Instant,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            enable_debug: false,
+            aof_path: None,
+            max_commands_per_sec: None,
+            resync_on_protocol_error: false,
+            max_array_len: MAX_ARRAY_LEN,
+            max_args: MAX_COMMAND_ARGS,
+            stats: Arc::default(),
+            pause: Arc::default(),
+            port: DEFAULT_PORT,
+            run_id: new_replid(),
+            started_at: Instant::now(),
+        }
+    }
+}
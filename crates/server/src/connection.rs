@@ -1,25 +1,69 @@
-use bytes::BytesMut;
+use bytes::{Buf, BufMut, BytesMut};
+use std::fmt::Write;
 use std::io::Cursor;
+use std::sync::atomic::{AtomicU64, Ordering};
 use tokio::io::{AsyncReadExt, AsyncWriteExt, BufWriter};
 use tokio::net::TcpStream;
 
-use stormdb_common::{ConnectionError, INITIAL_BUFFER_CAPACITY};
+use stormdb_common::{ConnectionError, INITIAL_BUFFER_CAPACITY, MAX_ARRAY_LEN};
 use stormdb_protocol::Frame;
 
+/// Próximo id de conexão (monotônico, compartilhado pelo processo).
+static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Bulks a partir deste tamanho vão direto do `Bytes` para o socket; os
+/// menores são copiados para o buffer de saída junto com os headers.
+const DIRECT_WRITE_MIN: usize = 16 * 1024;
+
+/// Tamanho do buffer de saída a partir do qual ele é repassado ao socket.
+const WRITE_BATCH_SIZE: usize = 64 * 1024;
+
 /// Wrapper sobre TcpStream com buffer para leitura/escrita de frames RESP.
 pub struct Connection {
+    id: u64,
     stream: BufWriter<TcpStream>,
     buffer: BytesMut,
+    /// Headers e valores pequenos da resposta em construção, reaproveitado
+    /// entre respostas.
+    out: BytesMut,
+    resync_on_error: bool,
+    max_array_len: usize,
 }
 
 impl Connection {
     pub fn new(stream: TcpStream) -> Self {
         Self {
+            id: NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed),
             stream: BufWriter::new(stream),
             buffer: BytesMut::with_capacity(INITIAL_BUFFER_CAPACITY),
+            out: BytesMut::with_capacity(INITIAL_BUFFER_CAPACITY),
+            resync_on_error: false,
+            max_array_len: MAX_ARRAY_LEN,
         }
     }
 
+    /// Com `true`, um frame malformado não derruba a conexão: o buffer é
+    /// avançado até o próximo início plausível de frame e `read_frame`
+    /// retorna `ConnectionError::Protocol`, permitindo responder e seguir.
+    pub fn set_resync_on_error(&mut self, enabled: bool) {
+        self.resync_on_error = enabled;
+    }
+
+    /// Número máximo de elementos aceitos em um array recebido.
+    pub fn set_max_array_len(&mut self, max: usize) {
+        self.max_array_len = max;
+    }
+
+    /// Endereço do peer remoto.
+    pub fn peer_addr(&self) -> std::io::Result<std::net::SocketAddr> {
+        self.stream.get_ref().peer_addr()
+    }
+
+    /// Id único da conexão, atribuído no accept (exposto via `CLIENT ID`).
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
     /// Lê um frame completo do stream. Retorna None no EOF.
     pub async fn read_frame(&mut self) -> Result<Option<Frame>, ConnectionError> {
         loop {
@@ -38,18 +82,58 @@ impl Connection {
     }
 
     /// Escreve um frame no stream.
+    ///
+    /// Headers e valores pequenos são encodados num buffer reaproveitado e
+    /// vão ao socket em lotes, sem uma alocação e uma escrita por elemento.
+    /// Bulks grandes (soltos ou dentro de um array) têm o payload escrito
+    /// direto do `Bytes`, sem copiar: o `BufWriter` repassa ao socket
+    /// escritas maiores que sua capacidade.
     pub async fn write_frame(&mut self, frame: &Frame) -> Result<(), ConnectionError> {
-        let mut buf = BytesMut::new();
-        frame.encode(&mut buf);
-        self.stream.write_all(&buf).await?;
+        match frame {
+            Frame::Array(items) => {
+                put_header(&mut self.out, '*', items.len());
+                for item in items {
+                    self.write_value(item).await?;
+                }
+            }
+            _ => self.write_value(frame).await?,
+        }
+        self.write_out().await?;
         self.stream.flush().await?;
         Ok(())
     }
 
+    /// Escreve um frame que não é o array de topo. Arrays aninhados e tipos
+    /// pequenos são encodados normalmente no buffer de saída.
+    async fn write_value(&mut self, frame: &Frame) -> Result<(), ConnectionError> {
+        match frame {
+            Frame::Bulk(data) if data.len() >= DIRECT_WRITE_MIN => {
+                put_header(&mut self.out, '$', data.len());
+                self.write_out().await?;
+                self.stream.write_all(data).await?;
+                self.out.put_slice(b"\r\n");
+            }
+            _ => frame.encode(&mut self.out),
+        }
+        if self.out.len() >= WRITE_BATCH_SIZE {
+            self.write_out().await?;
+        }
+        Ok(())
+    }
+
+    /// Repassa o buffer de saída ao stream e o esvazia.
+    async fn write_out(&mut self) -> Result<(), ConnectionError> {
+        if !self.out.is_empty() {
+            self.stream.write_all(&self.out).await?;
+            self.out.clear();
+        }
+        Ok(())
+    }
+
     fn parse_frame(&mut self) -> Result<Option<Frame>, ConnectionError> {
         let mut cursor = Cursor::new(&self.buffer[..]);
 
-        match Frame::check(&mut cursor) {
+        match Frame::check_with_limit(&mut cursor, self.max_array_len) {
             Ok(()) => {
                 let len = cursor.position() as usize;
                 cursor.set_position(0);
@@ -63,10 +147,33 @@ impl Connection {
                 Ok(Some(frame))
             }
             Err(stormdb_common::ProtocolError::Incomplete) => Ok(None),
+            Err(e) if self.resync_on_error => {
+                self.resync();
+                Err(ConnectionError::Protocol(e))
+            }
             Err(e) => Err(ConnectionError::Io(std::io::Error::new(
                 std::io::ErrorKind::InvalidData,
                 e.to_string(),
             ))),
         }
     }
+
+    /// Descarta o frame inválido no início do buffer.
+    ///
+    /// Comandos de clientes são sempre arrays, então o próximo início
+    /// plausível é um `*` logo após um CRLF. Se não houver nenhum no buffer,
+    /// descarta tudo: o próximo read começa num frame novo ou em mais lixo,
+    /// que gera outro erro e outra ressincronização.
+    fn resync(&mut self) {
+        match self.buffer.windows(3).position(|w| w == b"\r\n*") {
+            Some(i) => self.buffer.advance(i + 2),
+            None => self.buffer.clear(),
+        }
+    }
+}
+
+/// `{prefix}{len}\r\n` sem alocar: `BytesMut` implementa `fmt::Write`.
+fn put_header(out: &mut BytesMut, prefix: char, len: usize) {
+    // Escrever num BytesMut não falha
+    let _ = write!(out, "{prefix}{len}\r\n");
 }
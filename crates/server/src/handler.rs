@@ -1,75 +1,153 @@
-use tokio::sync::{broadcast, mpsc};
+use std::sync::Arc;
+
+use bytes::Bytes;
+use tokio::sync::broadcast;
 use tokio_stream::wrappers::BroadcastStream;
 use tokio_stream::{StreamExt, StreamMap};
 use tracing::debug;
 
-use stormdb_common::{ConnectionError, StorageError};
+use stormdb_common::{ConnectionError, ErrorReply};
 use stormdb_protocol::{Command, Frame};
-use stormdb_storage::{Db, is_write_command};
+use stormdb_storage::{AofSender, Db, PatternMessage, WritePermit, is_write_command};
 
-use crate::Connection;
+use crate::commands::{ExecContext, Handler, Reply, handler_for};
+use crate::rate_limit::TokenBucket;
+use crate::{Connection, ServerConfig};
 
-use crate::replication::handle_replica_stream;
+use crate::replication::{SharedReplState, handle_replica_stream};
 
 /// Loop principal de tratamento de uma conexão.
 pub async fn handle_connection(
     mut conn: Connection,
     db: Db,
     shutdown: &mut broadcast::Receiver<()>,
-    aof_tx: Option<mpsc::Sender<Command>>,
-    replication_tx: broadcast::Sender<Command>,
+    aof_tx: Option<AofSender>,
+    replication_tx: broadcast::Sender<Arc<Command>>,
+    config: Arc<ServerConfig>,
+    repl: SharedReplState,
 ) -> Result<(), ConnectionError> {
+    let mut rate_limit = config.max_commands_per_sec.map(TokenBucket::new);
+    conn.set_resync_on_error(config.resync_on_protocol_error);
+    conn.set_max_array_len(config.max_array_len);
+
     loop {
         let frame = tokio::select! {
-            result = conn.read_frame() => result?,
+            result = conn.read_frame() => result,
             _ = shutdown.recv() => {
+                send_shutdown_error(&mut conn).await;
                 return Ok(());
             }
         };
 
         let frame = match frame {
-            Some(f) => f,
-            None => return Ok(()), // EOF
+            Ok(Some(f)) => f,
+            Ok(None) => return Ok(()), // EOF
+            Err(ConnectionError::Protocol(e)) => {
+                let response = Frame::Error(e.reply());
+                conn.write_frame(&response).await?;
+                continue;
+            }
+            Err(e) => return Err(e),
         };
 
-        let cmd = match Command::from_frame(frame) {
+        let cmd = match Command::from_frame_with_max_args(frame, config.max_args) {
             Ok(cmd) => cmd,
             Err(e) => {
-                let response = Frame::Error(format!("ERR {e}"));
+                debug!("comando inválido: {e}");
+                let response = Frame::Error(e.reply());
                 conn.write_frame(&response).await?;
                 continue;
             }
         };
 
         debug!("comando recebido: {cmd:?}");
+        config.stats.command_processed();
 
-        // Verificar Handshake de Réplica
-        if let Command::Ping(Some(ref msg)) = cmd
-            && msg.as_ref() == b"REPLICA_HANDSHAKE" {
-                // Upgrade para conexão de réplica
-                let rx = replication_tx.subscribe();
-                handle_replica_stream(conn, rx).await?;
+        if let Some(ref mut bucket) = rate_limit
+            && !bucket.try_acquire()
+        {
+            conn.write_frame(&Frame::Error("ERR rate limit exceeded".into()))
+                .await?;
+            continue;
+        }
+
+        // CLIENT PAUSE: segura o comando até a pausa acabar. Conexões em
+        // modo subscribe não passam por aqui e seguem recebendo mensagens.
+        tokio::select! {
+            _ = config.pause.wait(&cmd) => {}
+            _ = shutdown.recv() => {
+                send_shutdown_error(&mut conn).await;
                 return Ok(());
             }
+        }
+
+        // Verificar Handshake de Réplica
+        if let Command::Psync { .. } = cmd {
+            // Upgrade para conexão de réplica (sempre full resync)
+            handle_replica_stream(conn, &db, &replication_tx, &repl, shutdown).await?;
+            return Ok(());
+        }
 
         match cmd {
-            Command::Subscribe(channels) => {
-                handle_subscribe(&mut conn, &db, channels, shutdown).await?;
-                return Ok(());
+            Command::Subscribe(_) | Command::PSubscribe(_) => {
+                if !handle_subscribe(&mut conn, &db, cmd, config.max_args, shutdown).await? {
+                    return Ok(());
+                }
+            }
+            Command::Unsubscribe(channels) => {
+                for reply in unsubscribe_replies_when_not_subscribed("unsubscribe", &channels) {
+                    conn.write_frame(&reply).await?;
+                }
+            }
+            Command::PUnsubscribe(patterns) => {
+                for reply in unsubscribe_replies_when_not_subscribed("punsubscribe", &patterns) {
+                    conn.write_frame(&reply).await?;
+                }
             }
             _ => {
-                let response = execute_command(&cmd, &db).await;
-
-                // Se é comando de escrita e foi bem-sucedido:
-                // 1. Persistir no AOF
-                // 2. Enviar para Replicação
-                if is_write_command(&cmd) && !matches!(response, Frame::Error(_)) {
-                    if let Some(ref tx) = aof_tx {
-                        let _ = tx.send(cmd.clone()).await;
+                // Escritas seguram um permit da aplicação até a propagação
+                // (ver `Db::write_permit`). O BRPOPLPUSH pega o seu só ao
+                // mover, para não segurá-lo enquanto espera.
+                let mut permit = match cmd {
+                    Command::BRPopLPush { .. } => None,
+                    _ if is_write_command(&cmd) => Some(db.write_permit().await),
+                    _ => None,
+                };
+                let exec = execute_command(
+                    &cmd,
+                    &db,
+                    conn.id(),
+                    aof_tx.as_ref(),
+                    &config,
+                    &repl,
+                    &mut permit,
+                );
+                // Um cliente bloqueado não pode segurar o shutdown. Cancelar o
+                // BRPOPLPUSH é seguro: o elemento só sai da lista quando ele
+                // retorna.
+                let (response, effect) = if matches!(cmd, Command::BRPopLPush { .. }) {
+                    tokio::select! {
+                        result = exec => result,
+                        _ = shutdown.recv() => {
+                            send_shutdown_error(&mut conn).await;
+                            return Ok(());
+                        }
+                    }
+                } else {
+                    exec.await
+                };
+
+                // Se é comando de escrita e foi bem-sucedido, vai para o AOF
+                // e as réplicas. Quando a execução devolve efeitos, eles são
+                // propagados no lugar do comando original.
+                if !matches!(response, Frame::Error(_)) {
+                    for cmd in effect.unwrap_or_else(|| vec![cmd]) {
+                        if is_write_command(&cmd) {
+                            propagate_write(cmd, aof_tx.as_ref(), &replication_tx).await;
+                        }
                     }
-                    // Broadcast para réplicas (não bloqueante se buffer cheio)
-                    let _ = replication_tx.send(cmd.clone());
                 }
+                drop(permit);
 
                 conn.write_frame(&response).await?;
             }
@@ -77,132 +155,277 @@ pub async fn handle_connection(
     }
 }
 
-/// Executa um comando e retorna o Frame de resposta.
-async fn execute_command(cmd: &Command, db: &Db) -> Frame {
-    match cmd {
-        Command::Ping(msg) => match msg {
-            Some(m) => Frame::Bulk(m.clone()),
-            None => Frame::Simple("PONG".into()),
-        },
-        Command::Echo(msg) => Frame::Bulk(msg.clone()),
-        Command::Get(key) => match db.get(key) {
-            Some(value) => Frame::Bulk(value),
-            None => Frame::Null,
-        },
-        Command::Set {
-            key,
-            value,
-            options,
-        } => match db.set(key.clone(), value.clone(), options) {
-            Ok(true) => Frame::Simple("OK".into()),
-            Ok(false) => Frame::Null, // NX/XX condition not met
-            Err(e) => Frame::Error(format!("ERR {e}")),
-        },
-        Command::Del(keys) => {
-            let count = db.del(keys);
-            Frame::Integer(count as i64)
-        }
-        Command::Exists(keys) => {
-            let count = db.exists(keys);
-            Frame::Integer(count as i64)
+/// Persiste um comando de escrita no AOF e o envia às réplicas. Com os dois
+/// destinos, o comando é movido para um Arc compartilhado, sem copiar
+/// chave/valores; sem réplicas conectadas (o caso standalone), o broadcast e
+/// o clone do Arc são pulados. Retorna se o comando foi para o broadcast.
+///
+/// Chamado com o `WritePermit` da escrita: uma réplica que se conecte logo
+/// depois da checagem só tira o snapshot depois daqui, e o comando, já
+/// aplicado no Db, entra nele.
+async fn propagate_write(
+    cmd: Command,
+    aof_tx: Option<&AofSender>,
+    replication_tx: &broadcast::Sender<Arc<Command>>,
+) -> bool {
+    let replicas = replication_tx.receiver_count() > 0;
+    let cmd = Arc::new(cmd);
+    match aof_tx {
+        Some(tx) if replicas => {
+            let _ = tx.send(cmd.clone()).await;
+            // Broadcast para réplicas (não bloqueante se buffer cheio)
+            let _ = replication_tx.send(cmd);
+            true
         }
-        Command::Incr(key) => match db.incr(key) {
-            Ok(n) => Frame::Integer(n),
-            Err(StorageError::WrongType) => Frame::Error(
-                "WRONGTYPE Operation against a key holding the wrong kind of value".into(),
-            ),
-            Err(StorageError::NotAnInteger) => {
-                Frame::Error("ERR value is not an integer or out of range".into())
-            }
-            Err(e) => Frame::Error(format!("ERR {e}")),
-        },
-        Command::Decr(key) => match db.decr(key) {
-            Ok(n) => Frame::Integer(n),
-            Err(StorageError::WrongType) => Frame::Error(
-                "WRONGTYPE Operation against a key holding the wrong kind of value".into(),
-            ),
-            Err(StorageError::NotAnInteger) => {
-                Frame::Error("ERR value is not an integer or out of range".into())
-            }
-            Err(e) => Frame::Error(format!("ERR {e}")),
-        },
-        Command::LPush { key, values } => match db.lpush(key, values) {
-            Ok(len) => Frame::Integer(len as i64),
-            Err(StorageError::WrongType) => Frame::Error(
-                "WRONGTYPE Operation against a key holding the wrong kind of value".into(),
-            ),
-            Err(e) => Frame::Error(format!("ERR {e}")),
-        },
-        Command::RPush { key, values } => match db.rpush(key, values) {
-            Ok(len) => Frame::Integer(len as i64),
-            Err(StorageError::WrongType) => Frame::Error(
-                "WRONGTYPE Operation against a key holding the wrong kind of value".into(),
-            ),
-            Err(e) => Frame::Error(format!("ERR {e}")),
-        },
-        Command::LPop { key, count } => match db.lpop(key, *count) {
-            Ok(items) if items.is_empty() => Frame::Null,
-            Ok(items) if count.is_none() => Frame::Bulk(items.into_iter().next().unwrap()),
-            Ok(items) => Frame::Array(items.into_iter().map(Frame::Bulk).collect()),
-            Err(StorageError::WrongType) => Frame::Error(
-                "WRONGTYPE Operation against a key holding the wrong kind of value".into(),
-            ),
-            Err(e) => Frame::Error(format!("ERR {e}")),
-        },
-        Command::RPop { key, count } => match db.rpop(key, *count) {
-            Ok(items) if items.is_empty() => Frame::Null,
-            Ok(items) if count.is_none() => Frame::Bulk(items.into_iter().next().unwrap()),
-            Ok(items) => Frame::Array(items.into_iter().map(Frame::Bulk).collect()),
-            Err(StorageError::WrongType) => Frame::Error(
-                "WRONGTYPE Operation against a key holding the wrong kind of value".into(),
-            ),
-            Err(e) => Frame::Error(format!("ERR {e}")),
-        },
-        Command::LRange { key, start, stop } => match db.lrange(key, *start, *stop) {
-            Ok(items) => Frame::Array(items.into_iter().map(Frame::Bulk).collect()),
-            Err(StorageError::WrongType) => Frame::Error(
-                "WRONGTYPE Operation against a key holding the wrong kind of value".into(),
-            ),
-            Err(e) => Frame::Error(format!("ERR {e}")),
-        },
-        Command::Publish { channel, message } => {
-            let count = db.publish(channel, message.clone()).await;
-            Frame::Integer(count as i64)
+        Some(tx) => {
+            let _ = tx.send(cmd).await;
+            false
         }
-        Command::DbSize => {
-            let len = db.len();
-            Frame::Integer(len as i64)
+        None if replicas => {
+            let _ = replication_tx.send(cmd);
+            true
         }
-        Command::Subscribe(_) => unreachable!("handled above"),
-        Command::Unsubscribe(_) => Frame::Simple("OK".into()),
-        Command::Unknown(name) => Frame::Error(format!("ERR unknown command '{name}'")),
+        None => false,
     }
 }
 
-/// Handler dedicado para modo subscribe.
-async fn handle_subscribe(
+/// Executa um comando e retorna o Frame de resposta e, opcionalmente, os
+/// efeitos a propagar para o AOF e as réplicas no lugar do comando. Um
+/// comando cujo replay poderia divergir do que executou aqui (bloqueante ou
+/// não determinístico) devolve os comandos concretos que reproduzem o
+/// resultado. Um comando que pega o próprio `WritePermit` (BRPOPLPUSH) o
+/// deixa em `permit`, para ser segurado até a propagação.
+async fn execute_command(
+    cmd: &Command,
+    db: &Db,
+    conn_id: u64,
+    aof_tx: Option<&AofSender>,
+    config: &ServerConfig,
+    repl: &SharedReplState,
+    permit: &mut Option<WritePermit>,
+) -> Reply {
+    // Durante uma recarga o dataset está incompleto: só rodam os comandos
+    // marcados com `loading` na tabela (PING, INFO, CONFIG, ...)
+    if db.is_loading()
+        && cmd
+            .spec()
+            .is_some_and(|spec| !spec.flags.contains(&"loading"))
+    {
+        let err = Frame::Error("LOADING Redis is loading the dataset in memory".into());
+        return (err, None);
+    }
+
+    let Some(spec) = cmd.spec() else {
+        return (
+            Frame::Error(format!("ERR unknown command '{}'", cmd.name())),
+            None,
+        );
+    };
+    let mut ctx = ExecContext {
+        db,
+        conn_id,
+        aof_tx,
+        config,
+        repl,
+        permit,
+    };
+    match handler_for(spec) {
+        Some(Handler::Sync(handler)) => handler(cmd, &ctx),
+        Some(Handler::Async(handler)) => handler(cmd, &mut ctx).await,
+        Some(Handler::Connection) => unreachable!("handled above"),
+        None => unreachable!("{} sem handler registrado", spec.name),
+    }
+}
+
+/// Respostas de um UNSUBSCRIBE/PUNSUBSCRIBE (`kind`) fora do modo subscribe,
+/// como no Redis: uma confirmação por canal pedido (ou uma só, com canal nil,
+/// sem argumentos), todas com 0 inscrições restantes.
+fn unsubscribe_replies_when_not_subscribed(kind: &str, channels: &[String]) -> Vec<Frame> {
+    let confirm =
+        |channel: Frame| Frame::Array(vec![Frame::bulk(kind), channel, Frame::Integer(0)]);
+    if channels.is_empty() {
+        return vec![confirm(Frame::Null)];
+    }
+    channels.iter().map(|ch| confirm(Frame::bulk(ch))).collect()
+}
+
+/// Inscrições de uma conexão em modo subscribe. As confirmações de todos
+/// os (P)SUBSCRIBE/(P)UNSUBSCRIBE reportam o total de canais mais padrões,
+/// como no Redis.
+#[derive(Default)]
+struct Subscriptions {
+    channels: StreamMap<String, BroadcastStream<Bytes>>,
+    patterns: StreamMap<String, BroadcastStream<PatternMessage>>,
+}
+
+impl Subscriptions {
+    fn count(&self) -> usize {
+        self.channels.len() + self.patterns.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.count() == 0
+    }
+}
+
+/// Inscreve a conexão nos canais, enviando a confirmação de cada um.
+///
+/// A confirmação é escrita antes do receiver entrar no `StreamMap`: como o
+/// map só é consultado pelo loop de `handle_subscribe`, nenhuma mensagem do
+/// canal pode chegar ao cliente antes do seu `subscribe`.
+async fn subscribe_channels(
     conn: &mut Connection,
     db: &Db,
+    subs: &mut Subscriptions,
     channels: Vec<String>,
-    shutdown: &mut broadcast::Receiver<()>,
 ) -> Result<(), ConnectionError> {
-    let mut receivers = StreamMap::new();
-
-    for (i, channel) in channels.iter().enumerate() {
-        let rx = db.subscribe(channel).await;
-        receivers.insert(channel.clone(), BroadcastStream::new(rx));
+    for channel in channels {
+        // Re-subscribe num canal já inscrito mantém o receiver atual (e as
+        // mensagens já enfileiradas nele).
+        let rx = if subs.channels.contains_key(&channel) {
+            None
+        } else {
+            Some(db.subscribe(&channel).await)
+        };
+        let count = subs.count() + usize::from(rx.is_some());
 
         let confirm = Frame::Array(vec![
             Frame::bulk("subscribe"),
-            Frame::bulk(channel),
-            Frame::Integer((i + 1) as i64),
+            Frame::bulk(&channel),
+            Frame::Integer(count as i64),
         ]);
         conn.write_frame(&confirm).await?;
+
+        if let Some(rx) = rx {
+            subs.channels.insert(channel, BroadcastStream::new(rx));
+        }
+    }
+    Ok(())
+}
+
+/// Como `subscribe_channels`, para os padrões do PSUBSCRIBE.
+async fn subscribe_patterns(
+    conn: &mut Connection,
+    db: &Db,
+    subs: &mut Subscriptions,
+    patterns: Vec<String>,
+) -> Result<(), ConnectionError> {
+    for pattern in patterns {
+        let rx = if subs.patterns.contains_key(&pattern) {
+            None
+        } else {
+            Some(db.psubscribe(&pattern).await)
+        };
+        let count = subs.count() + usize::from(rx.is_some());
+
+        let confirm = Frame::Array(vec![
+            Frame::bulk("psubscribe"),
+            Frame::bulk(&pattern),
+            Frame::Integer(count as i64),
+        ]);
+        conn.write_frame(&confirm).await?;
+
+        if let Some(rx) = rx {
+            subs.patterns.insert(pattern, BroadcastStream::new(rx));
+        }
+    }
+    Ok(())
+}
+
+/// Cancela as inscrições nos canais (todos, sem argumentos), enviando uma
+/// confirmação por canal com o total que sobra depois dele, como no Redis.
+/// Sem argumentos e sem canais inscritos, a confirmação única leva canal nil.
+async fn unsubscribe_channels(
+    conn: &mut Connection,
+    db: &Db,
+    subs: &mut Subscriptions,
+    channels: Vec<String>,
+) -> Result<(), ConnectionError> {
+    let channels = if channels.is_empty() {
+        subs.channels.keys().cloned().collect()
+    } else {
+        channels
+    };
+    if channels.is_empty() {
+        return write_unsubscribe_confirm(conn, "unsubscribe", None, subs.count()).await;
+    }
+    for channel in channels {
+        if subs.channels.remove(&channel).is_some() {
+            db.unsubscribe(&channel).await;
+        }
+        write_unsubscribe_confirm(conn, "unsubscribe", Some(&channel), subs.count()).await?;
+    }
+    Ok(())
+}
+
+/// Como `unsubscribe_channels`, para os padrões do PUNSUBSCRIBE.
+async fn unsubscribe_patterns(
+    conn: &mut Connection,
+    db: &Db,
+    subs: &mut Subscriptions,
+    patterns: Vec<String>,
+) -> Result<(), ConnectionError> {
+    let patterns = if patterns.is_empty() {
+        subs.patterns.keys().cloned().collect()
+    } else {
+        patterns
+    };
+    if patterns.is_empty() {
+        return write_unsubscribe_confirm(conn, "punsubscribe", None, subs.count()).await;
+    }
+    for pattern in patterns {
+        if subs.patterns.remove(&pattern).is_some() {
+            db.punsubscribe(&pattern).await;
+        }
+        write_unsubscribe_confirm(conn, "punsubscribe", Some(&pattern), subs.count()).await?;
+    }
+    Ok(())
+}
+
+async fn write_unsubscribe_confirm(
+    conn: &mut Connection,
+    kind: &str,
+    name: Option<&str>,
+    count: usize,
+) -> Result<(), ConnectionError> {
+    let confirm = Frame::Array(vec![
+        Frame::bulk(kind),
+        name.map_or(Frame::Null, Frame::bulk),
+        Frame::Integer(count as i64),
+    ]);
+    conn.write_frame(&confirm).await
+}
+
+/// Avisa o cliente de que o servidor está desligando, antes de fechar a
+/// conexão. Falhas de escrita são ignoradas: o cliente pode já ter saído.
+async fn send_shutdown_error(conn: &mut Connection) {
+    let frame = Frame::Error("ERR server is shutting down".into());
+    let _ = conn.write_frame(&frame).await;
+}
+
+/// Handler dedicado para modo subscribe, iniciado por um SUBSCRIBE ou
+/// PSUBSCRIBE.
+///
+/// Retorna `true` quando o cliente sai do modo subscribe (todas as inscrições
+/// removidas) e a conexão volta ao loop principal, ou `false` se a conexão
+/// foi fechada / o servidor está desligando.
+async fn handle_subscribe(
+    conn: &mut Connection,
+    db: &Db,
+    first: Command,
+    max_args: usize,
+    shutdown: &mut broadcast::Receiver<()>,
+) -> Result<bool, ConnectionError> {
+    let mut subs = Subscriptions::default();
+    match first {
+        Command::Subscribe(channels) => subscribe_channels(conn, db, &mut subs, channels).await?,
+        Command::PSubscribe(patterns) => subscribe_patterns(conn, db, &mut subs, patterns).await?,
+        _ => unreachable!("handle_subscribe só é chamado com SUBSCRIBE/PSUBSCRIBE"),
     }
 
     loop {
         tokio::select! {
-            Some((channel, result)) = receivers.next() => {
+            Some((channel, result)) = subs.channels.next() => {
                 match result {
                     Ok(message) => {
                         let msg_frame = Frame::Array(vec![
@@ -214,9 +437,29 @@ async fn handle_subscribe(
                     }
                     Err(e) => {
                         debug!("erro no stream do canal {channel}: {e}");
-                        receivers.remove(&channel);
-                        if receivers.is_empty() {
-                            return Ok(());
+                        subs.channels.remove(&channel);
+                        if subs.is_empty() {
+                            return Ok(true);
+                        }
+                    }
+                }
+            }
+            Some((pattern, result)) = subs.patterns.next() => {
+                match result {
+                    Ok((channel, message)) => {
+                        let msg_frame = Frame::Array(vec![
+                            Frame::bulk("pmessage"),
+                            Frame::bulk(&pattern),
+                            Frame::bulk(&channel),
+                            Frame::Bulk(message),
+                        ]);
+                        conn.write_frame(&msg_frame).await?;
+                    }
+                    Err(e) => {
+                        debug!("erro no stream do padrão {pattern}: {e}");
+                        subs.patterns.remove(&pattern);
+                        if subs.is_empty() {
+                            return Ok(true);
                         }
                     }
                 }
@@ -224,58 +467,93 @@ async fn handle_subscribe(
             result = conn.read_frame() => {
                 match result? {
                     Some(frame) => {
-                        if let Ok(cmd) = Command::from_frame(frame) {
-                            match cmd {
-                                Command::Unsubscribe(unsub_channels) => {
-                                    let channels_to_unsub = if unsub_channels.is_empty() {
-                                        receivers.keys().cloned().collect::<Vec<_>>()
-                                    } else {
-                                        unsub_channels
-                                    };
-
-                                    for ch in &channels_to_unsub {
-                                        receivers.remove(ch);
-                                        db.unsubscribe(ch).await;
-                                    }
-
-                                    let confirm = Frame::Array(vec![
-                                        Frame::bulk("unsubscribe"),
-                                        Frame::bulk(channels_to_unsub.first().map(|s| s.as_str()).unwrap_or("")),
-                                        Frame::Integer(receivers.len() as i64),
-                                    ]);
-                                    conn.write_frame(&confirm).await?;
-
-                                    if receivers.is_empty() {
-                                        return Ok(());
-                                    }
-                                }
-                                Command::Subscribe(new_channels) => {
-                                    let current_count = receivers.len();
-                                    for (i, channel) in new_channels.iter().enumerate() {
-                                        let rx = db.subscribe(channel).await;
-                                        receivers.insert(channel.clone(), BroadcastStream::new(rx));
-
-                                        let confirm = Frame::Array(vec![
-                                            Frame::bulk("subscribe"),
-                                            Frame::bulk(channel),
-                                            Frame::Integer((current_count + i + 1) as i64),
-                                        ]);
-                                        conn.write_frame(&confirm).await?;
-                                    }
+                        // Como no loop principal: respeita o --max-args e
+                        // responde o erro de parse em vez de ignorar o comando
+                        let cmd = match Command::from_frame_with_max_args(frame, max_args) {
+                            Ok(cmd) => cmd,
+                            Err(e) => {
+                                debug!("comando inválido: {e}");
+                                conn.write_frame(&Frame::Error(e.reply())).await?;
+                                continue;
+                            }
+                        };
+                        match cmd {
+                            Command::Unsubscribe(channels) => {
+                                unsubscribe_channels(conn, db, &mut subs, channels).await?;
+                                if subs.is_empty() {
+                                    return Ok(true);
                                 }
-                                _ => {
-                                    let err = Frame::Error("ERR only SUBSCRIBE / UNSUBSCRIBE are allowed in subscribe mode".into());
-                                    conn.write_frame(&err).await?;
+                            }
+                            Command::PUnsubscribe(patterns) => {
+                                unsubscribe_patterns(conn, db, &mut subs, patterns).await?;
+                                if subs.is_empty() {
+                                    return Ok(true);
                                 }
                             }
+                            Command::Subscribe(new_channels) => {
+                                subscribe_channels(conn, db, &mut subs, new_channels).await?;
+                            }
+                            Command::PSubscribe(new_patterns) => {
+                                subscribe_patterns(conn, db, &mut subs, new_patterns).await?;
+                            }
+                            // Em modo subscribe o PING responde como push: ["pong", msg]
+                            Command::Ping(msg) => {
+                                let pong = Frame::Array(vec![
+                                    Frame::bulk("pong"),
+                                    Frame::Bulk(msg.unwrap_or_default()),
+                                ]);
+                                conn.write_frame(&pong).await?;
+                            }
+                            _ => {
+                                let err = Frame::Error("ERR only (P)SUBSCRIBE / (P)UNSUBSCRIBE / PING are allowed in subscribe mode".into());
+                                conn.write_frame(&err).await?;
+                            }
                         }
                     }
-                    None => return Ok(()),
+                    None => return Ok(false),
                 }
             }
             _ = shutdown.recv() => {
-                return Ok(());
+                send_shutdown_error(conn).await;
+                return Ok(false);
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stormdb_storage::{FsyncPolicy, create_aof, replay_aof};
+
+    fn del(key: &'static str) -> Command {
+        Command::Del(vec![Bytes::from_static(key.as_bytes())])
+    }
+
+    #[tokio::test]
+    async fn propagate_write_skips_broadcast_without_replicas() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("propagate.aof");
+        let (aof_tx, writer) = create_aof(path.clone(), FsyncPolicy::No, 10);
+        let writer_handle = tokio::spawn(writer.run());
+        let (replication_tx, _) = broadcast::channel(4);
+
+        // Sem réplicas, só o AOF recebe o comando: o broadcast nem é tentado
+        // (um `send` sem receivers não deixaria rastro no canal)
+        assert!(!propagate_write(del("a"), Some(&aof_tx), &replication_tx).await);
+        assert!(!propagate_write(del("x"), None, &replication_tx).await);
+
+        // Com uma réplica, os dois destinos recebem o mesmo Arc
+        let mut replica = replication_tx.subscribe();
+        assert!(propagate_write(del("b"), Some(&aof_tx), &replication_tx).await);
+        assert_eq!(*replica.try_recv().unwrap(), del("b"));
+        assert!(replica.try_recv().is_err());
+
+        aof_tx.sync().await.unwrap();
+        let db = Db::new();
+        assert_eq!(replay_aof(&path, &db).await.unwrap(), 2);
+
+        drop(aof_tx);
+        writer_handle.await.unwrap().unwrap();
+    }
+}
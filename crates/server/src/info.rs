@@ -0,0 +1,173 @@
+use stormdb_storage::{AofSender, Db};
+
+use crate::ServerConfig;
+use crate::memory::allocator_stats;
+use crate::replication::{ReplState, Role};
+
+/// Versão do Redis cujo protocolo e comandos o StormDB imita; clientes
+/// comparam `redis_version` com isso para ligar recursos.
+const REDIS_COMPAT_VERSION: &str = "7.2.0";
+
+/// Monta o texto da resposta do INFO.
+///
+/// `section` filtra uma única seção (case-insensitive); `None`, `all` e
+/// `default` retornam todas.
+pub(crate) fn render(
+    section: Option<&str>,
+    db: &Db,
+    aof_tx: Option<&AofSender>,
+    repl: &ReplState,
+    config: &ServerConfig,
+) -> String {
+    let stats = &config.stats;
+    let wanted = section.map(|s| s.to_lowercase());
+    let include = |name: &str| match wanted.as_deref() {
+        None | Some("all") | Some("default") | Some("everything") => true,
+        Some(s) => s == name,
+    };
+
+    let mut sections = Vec::new();
+
+    if include("server") {
+        let uptime = config.started_at.elapsed().as_secs();
+        let executable = std::env::current_exe()
+            .map(|path| path.display().to_string())
+            .unwrap_or_default();
+        let mut s = String::from("# Server\r\n");
+        // Clientes leem `redis_version` para decidir quais recursos usar; a
+        // versão do próprio StormDB vai num campo separado
+        s.push_str(&format!("redis_version:{REDIS_COMPAT_VERSION}\r\n"));
+        s.push_str(&format!(
+            "stormdb_version:{}\r\n",
+            env!("CARGO_PKG_VERSION")
+        ));
+        s.push_str(&format!("run_id:{}\r\n", config.run_id));
+        s.push_str(&format!("tcp_port:{}\r\n", config.port));
+        s.push_str(&format!("uptime_in_seconds:{uptime}\r\n"));
+        s.push_str(&format!("uptime_in_days:{}\r\n", uptime / 86_400));
+        s.push_str(&format!("process_id:{}\r\n", std::process::id()));
+        s.push_str(&format!("executable:{executable}\r\n"));
+        // Não há arquivo de configuração: só flags de linha de comando
+        s.push_str("config_file:\r\n");
+        sections.push(s);
+    }
+
+    if include("clients") {
+        let mut s = String::from("# Clients\r\n");
+        s.push_str(&format!(
+            "connected_clients:{}\r\n",
+            stats.connected_clients()
+        ));
+        sections.push(s);
+    }
+
+    if include("stats") {
+        let mut s = String::from("# Stats\r\n");
+        s.push_str(&format!(
+            "total_connections_received:{}\r\n",
+            stats.total_connections_received()
+        ));
+        s.push_str(&format!(
+            "rejected_connections:{}\r\n",
+            stats.rejected_connections()
+        ));
+        s.push_str(&format!(
+            "total_commands_processed:{}\r\n",
+            stats.total_commands_processed()
+        ));
+        s.push_str(&format!(
+            "instantaneous_ops_per_sec:{}\r\n",
+            stats.instantaneous_ops_per_sec()
+        ));
+        s.push_str(&format!("keyspace_hits:{}\r\n", db.keyspace_hits()));
+        s.push_str(&format!("keyspace_misses:{}\r\n", db.keyspace_misses()));
+        sections.push(s);
+    }
+
+    // Sem jemalloc o `used_memory` é a estimativa do keyspace, que é O(N):
+    // a seção só sai quando pedida explicitamente
+    let allocator = allocator_stats();
+    let explicit = |name: &str| match wanted.as_deref() {
+        Some("all") | Some("everything") => true,
+        Some(s) => s == name,
+        None => false,
+    };
+    if (allocator.is_some() && include("memory")) || explicit("memory") {
+        let mut s = String::from("# Memory\r\n");
+        match allocator {
+            Some(stats) => {
+                s.push_str(&format!("used_memory:{}\r\n", stats.allocated));
+                s.push_str(&format!("used_memory_rss:{}\r\n", stats.resident));
+                s.push_str(&format!(
+                    "mem_fragmentation_ratio:{:.2}\r\n",
+                    stats.fragmentation_ratio()
+                ));
+                s.push_str("mem_allocator:jemalloc\r\n");
+            }
+            None => {
+                s.push_str(&format!(
+                    "used_memory:{}\r\n",
+                    db.memory_stats().total_bytes
+                ));
+                s.push_str("mem_allocator:libc\r\n");
+            }
+        }
+        sections.push(s);
+    }
+
+    if include("persistence") {
+        let mut s = String::from("# Persistence\r\n");
+        s.push_str(&format!("loading:{}\r\n", db.is_loading() as u8));
+        s.push_str(&format!("aof_enabled:{}\r\n", aof_tx.is_some() as u8));
+        s.push_str(&format!(
+            "aof_pending_writes:{}\r\n",
+            aof_tx.map(|tx| tx.pending()).unwrap_or(0)
+        ));
+        s.push_str(&format!(
+            "aof_fsync_slow_count:{}\r\n",
+            aof_tx.map(|tx| tx.fsync_slow_count()).unwrap_or(0)
+        ));
+        s.push_str(&format!(
+            "aof_last_fsync_usec:{}\r\n",
+            aof_tx.map(|tx| tx.last_fsync_usec()).unwrap_or(0)
+        ));
+        sections.push(s);
+    }
+
+    if include("replication") {
+        let mut s = String::from("# Replication\r\n");
+        match repl.role {
+            Role::Master => s.push_str("role:master\r\n"),
+            Role::Replica => s.push_str("role:slave\r\n"),
+        }
+        s.push_str(&format!("master_replid:{}\r\n", repl.replid));
+        if let Some((host, port)) = &repl.master {
+            s.push_str(&format!("master_host:{host}\r\n"));
+            s.push_str(&format!("master_port:{port}\r\n"));
+        }
+        s.push_str(&format!("connected_slaves:{}\r\n", repl.replicas.len()));
+        for (i, addr) in repl.replicas.iter().enumerate() {
+            s.push_str(&format!(
+                "slave{i}:ip={},port={},state=online\r\n",
+                addr.ip(),
+                addr.port()
+            ));
+        }
+        sections.push(s);
+    }
+
+    if include("keyspace") {
+        let mut s = String::from("# Keyspace\r\n");
+        if !db.is_empty() {
+            let by_type: String = db
+                .count_by_type()
+                .iter()
+                .map(|(name, n)| format!(",{name}s={n}"))
+                .collect();
+            s.push_str(&format!("db0:keys={}{by_type}\r\n", db.len()));
+        }
+        sections.push(s);
+    }
+
+    sections.join("\r\n")
+}
@@ -0,0 +1,65 @@
+use std::io;
+use std::time::Duration;
+
+use socket2::{SockRef, TcpKeepalive};
+use tokio::net::TcpStream;
+
+/// Parâmetros do TCP keepalive das conexões aceitas.
+///
+/// Detecta peers que sumiram sem FIN (cabo puxado, NAT que esqueceu a
+/// conexão): o `read` de um socket half-open nunca acorda, e a task e o
+/// permit da conexão ficariam presos para sempre — inclusive de subscribers,
+/// que podem ficar ociosos legitimamente. Um peer morto é derrubado em no
+/// máximo `idle + interval * retries`.
+///
+/// Para verificar à mão: conecte um cliente, descarte os pacotes dele
+/// (`iptables -A INPUT -p tcp --sport <porta do cliente> -j DROP`) e
+/// observe o log `conexão encerrada` depois desse prazo.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeepaliveConfig {
+    /// Ociosidade antes do primeiro probe.
+    pub idle: Duration,
+    /// Intervalo entre probes sem resposta.
+    pub interval: Duration,
+    /// Probes sem resposta antes de o kernel fechar a conexão.
+    pub retries: u32,
+}
+
+impl KeepaliveConfig {
+    /// Liga o keepalive no socket com estes parâmetros.
+    pub fn apply(&self, socket: &TcpStream) -> io::Result<()> {
+        let keepalive = TcpKeepalive::new()
+            .with_time(self.idle)
+            .with_interval(self.interval)
+            .with_retries(self.retries);
+        SockRef::from(socket).set_tcp_keepalive(&keepalive)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn apply_sets_socket_options() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let _client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (socket, _) = listener.accept().await.unwrap();
+
+        let config = KeepaliveConfig {
+            idle: Duration::from_secs(60),
+            interval: Duration::from_secs(5),
+            retries: 4,
+        };
+        config.apply(&socket).unwrap();
+
+        let sock = SockRef::from(&socket);
+        assert!(sock.keepalive().unwrap());
+        assert_eq!(sock.tcp_keepalive_time().unwrap(), config.idle);
+        assert_eq!(sock.tcp_keepalive_interval().unwrap(), config.interval);
+        assert_eq!(sock.tcp_keepalive_retries().unwrap(), config.retries);
+    }
+}
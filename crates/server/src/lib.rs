@@ -1,8 +1,20 @@
 #![forbid(unsafe_code)]
 
+mod commands;
+mod config;
 mod connection;
 pub mod handler;
+mod info;
+mod keepalive;
+mod memory;
+mod pause;
+mod rate_limit;
 pub mod replication;
+mod stats;
 
+pub use config::ServerConfig;
 pub use connection::Connection;
 pub use handler::handle_connection;
+pub use keepalive::KeepaliveConfig;
+pub use pause::ClientPause;
+pub use stats::ServerStats;
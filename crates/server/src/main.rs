@@ -1,14 +1,30 @@
 use std::path::PathBuf;
+use std::time::Duration;
 
 use clap::Parser;
+use tokio::io::AsyncWriteExt;
 use tokio::net::TcpListener;
 use tokio::signal;
 use tokio::sync::broadcast;
-use tracing::{error, info};
+use tracing::{Instrument, error, info, info_span, warn};
 
-use stormdb_common::{DEFAULT_HOST, DEFAULT_PORT, MAX_CONNECTIONS};
-use stormdb_server::{Connection, handle_connection, replication};
-use stormdb_storage::{Db, FsyncPolicy, create_aof, replay_aof};
+use stormdb_common::{
+    DEFAULT_HOST, DEFAULT_PORT, MAX_ARRAY_LEN, MAX_COMMAND_ARGS, MAX_CONNECTIONS, MAX_FRAME_SIZE,
+};
+use stormdb_server::{Connection, KeepaliveConfig, ServerConfig, handle_connection, replication};
+use stormdb_storage::{
+    Db, DbConfig, FsyncPolicy, NotifyFlags, check_aof_writable, create_aof, load_seed_file,
+    replay_aof,
+};
+
+#[cfg(feature = "jemalloc")]
+#[global_allocator]
+static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
+
+/// Tempo máximo para as conexões fecharem depois do sinal de shutdown.
+const SHUTDOWN_DRAIN_TIMEOUT: Duration = Duration::from_secs(2);
+/// Tempo máximo para entregar o erro a uma conexão recusada por `--reject-when-full`.
+const REJECT_WRITE_TIMEOUT: Duration = Duration::from_secs(1);
 
 #[derive(Parser, Debug)]
 #[command(name = "stormdb-server", about = "StormDB — in-memory data store")]
@@ -19,12 +35,76 @@ struct Args {
     port: u16,
     #[arg(long, default_value_t = MAX_CONNECTIONS)]
     max_connections: usize,
+    /// Com todas as conexões ocupadas, recusa novas na hora (em vez de deixá-las na fila).
+    #[arg(long)]
+    reject_when_full: bool,
+    /// TCP keepalive: segundos ociosos antes do primeiro probe (0 = desligado).
+    #[arg(long, default_value_t = 300)]
+    tcp_keepalive: u64,
+    /// Segundos entre probes de keepalive sem resposta.
+    #[arg(long, default_value_t = 30, value_parser = clap::value_parser!(u64).range(1..))]
+    tcp_keepalive_interval: u64,
+    /// Probes sem resposta antes de o kernel fechar a conexão.
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u32).range(1..))]
+    tcp_keepalive_count: u32,
     #[arg(long, value_name = "FILE")]
     aof: Option<PathBuf>,
     #[arg(long, default_value = "everysec", value_parser = parse_fsync)]
     fsync: FsyncPolicy,
     #[arg(long, num_args = 2, value_names = ["HOST", "PORT"])]
     replicaof: Option<Vec<String>>,
+    /// Tamanho máximo (bytes) de um valor string; limitado a MAX_FRAME_SIZE.
+    #[arg(long, default_value_t = MAX_FRAME_SIZE, value_parser = parse_max_bulk_len)]
+    proto_max_bulk_len: usize,
+    /// Máximo de elementos por array recebido de clientes.
+    #[arg(long, default_value_t = MAX_ARRAY_LEN as u32, value_parser = clap::value_parser!(u32).range(1..))]
+    proto_max_array_len: u32,
+    /// Máximo de argumentos por comando (ex.: chaves de um DEL); por padrão
+    /// igual a `--proto-max-array-len`.
+    #[arg(long, default_value_t = MAX_COMMAND_ARGS as u32, value_parser = clap::value_parser!(u32).range(1..))]
+    max_args: u32,
+    /// Soma um offset aleatório de 0..N ms a cada TTL (estende levemente os TTLs).
+    #[arg(long, default_value_t = 0)]
+    expire_jitter_ms: u64,
+    /// TTL (segundos) de todo SET sem EX/PX nem PERSIST (0 = desligado). Não afeta chaves já existentes.
+    #[arg(long, default_value_t = 0)]
+    default_ttl: u64,
+    /// Máximo de elementos por LRANGE; acima disso o cliente deve paginar.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    max_list_range: Option<u32>,
+    /// Classes de notificações de keyspace via pub/sub (ex.: KEA, Elx). Vazio = desligado.
+    #[arg(long, default_value = "")]
+    notify_keyspace_events: NotifyFlags,
+    /// Publica neste canal o valor final de cada chave expirada, como `SET`/`RPUSH`.
+    #[arg(long, value_name = "CHANNEL")]
+    expired_dump: Option<String>,
+    /// Maior string reportada como `embstr` pelo OBJECT ENCODING; acima disso é `raw`.
+    #[arg(long, default_value_t = 44)]
+    embstr_size_limit: usize,
+    /// Padrões glob (KEYS, PSUBSCRIBE) ignoram maiúsculas/minúsculas ASCII. Buscas exatas não mudam.
+    #[arg(long)]
+    pattern_case_insensitive: bool,
+    /// Comandos pendentes no canal do AOF antes de aplicar backpressure nas escritas.
+    #[arg(long, default_value_t = 10_000, value_parser = clap::value_parser!(u32).range(1..))]
+    aof_buffer_size: u32,
+    /// Loga um warning quando um fsync do AOF demora mais que N ms.
+    #[arg(long, default_value_t = 100)]
+    aof_slow_fsync_ms: u64,
+    /// Arquivo de comandos (texto, um por linha, ou RESP) aplicado no startup, após o AOF.
+    #[arg(long, value_name = "FILE")]
+    seed_file: Option<PathBuf>,
+    /// Habilita comandos DEBUG (ex.: DEBUG RELOAD). Não use em produção.
+    #[arg(long)]
+    enable_debug: bool,
+    /// Limita cada conexão a N comandos por segundo (desligado por padrão).
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    max_commands_per_sec: Option<u32>,
+    /// Em frames malformados, responde com erro e segue lendo (em vez de fechar a conexão).
+    #[arg(long)]
+    resync_on_protocol_error: bool,
+    /// Threads de I/O do runtime (padrão: número de CPUs).
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    io_threads: Option<u32>,
 }
 
 fn parse_fsync(s: &str) -> Result<FsyncPolicy, String> {
@@ -36,8 +116,15 @@ fn parse_fsync(s: &str) -> Result<FsyncPolicy, String> {
     }
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
+fn parse_max_bulk_len(s: &str) -> Result<usize, String> {
+    let len: usize = s.parse().map_err(|_| format!("valor inválido: '{s}'"))?;
+    if len == 0 || len > MAX_FRAME_SIZE {
+        return Err(format!("deve estar entre 1 e {MAX_FRAME_SIZE}"));
+    }
+    Ok(len)
+}
+
+fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt()
         .with_env_filter(
             tracing_subscriber::EnvFilter::try_from_default_env()
@@ -46,21 +133,52 @@ async fn main() -> anyhow::Result<()> {
         .init();
 
     let args = Args::parse();
+
+    // Runtime multi-thread montado à mão para permitir fixar o número de
+    // workers; sem --io-threads o tokio usa um por CPU.
+    let mut builder = tokio::runtime::Builder::new_multi_thread();
+    builder.enable_all();
+    if let Some(n) = args.io_threads {
+        builder.worker_threads(n as usize);
+    }
+    builder.build()?.block_on(run(args))
+}
+
+async fn run(args: Args) -> anyhow::Result<()> {
     let addr = format!("{}:{}", args.host, args.port);
 
-    let db = Db::new();
+    let db = Db::with_config(DbConfig {
+        max_bulk_len: args.proto_max_bulk_len,
+        expire_jitter_ms: args.expire_jitter_ms,
+        default_ttl_ms: (args.default_ttl > 0).then(|| args.default_ttl.saturating_mul(1000)),
+        max_list_range: args.max_list_range.map(|n| n as usize),
+        notify_keyspace_events: args.notify_keyspace_events,
+        expired_dump: args.expired_dump,
+        embstr_max_len: args.embstr_size_limit,
+        pattern_case_insensitive: args.pattern_case_insensitive,
+    });
 
     // Replay AOF se configurado
     let aof_tx = if let Some(ref aof_path) = args.aof {
+        // Falha no startup em vez de seguir sem persistência
+        check_aof_writable(aof_path).await.map_err(|e| {
+            anyhow::anyhow!("AOF {aof_path:?} não pode ser aberto para escrita: {e}")
+        })?;
         let count = replay_aof(aof_path, &db).await?;
         if count > 0 {
             info!("{count} comandos restaurados do AOF");
         }
 
-        let (tx, writer) = create_aof(aof_path.clone(), args.fsync, 10_000);
+        let (tx, writer) = create_aof(aof_path.clone(), args.fsync, args.aof_buffer_size as usize);
+        // O writer é dono do receiver do canal, então não dá pra reiniciá-lo
+        // com segurança — mas um pânico precisa ao menos aparecer no log.
+        let writer = writer.slow_fsync_threshold(Duration::from_millis(args.aof_slow_fsync_ms));
+        let writer_handle = tokio::spawn(writer.run());
         tokio::spawn(async move {
-            if let Err(e) = writer.run().await {
-                error!("AOF writer erro: {e}");
+            match writer_handle.await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => error!("AOF writer erro: {e}"),
+                Err(e) => error!("AOF writer entrou em pânico, persistência parada: {e}"),
             }
         });
         Some(tx)
@@ -68,36 +186,68 @@ async fn main() -> anyhow::Result<()> {
         None
     };
 
+    // Fixtures de desenvolvimento: aplicadas depois do AOF, erros não são fatais
+    if let Some(ref seed_path) = args.seed_file
+        && let Err(e) = load_seed_file(seed_path, &db).await
+    {
+        warn!("falha ao carregar seed {seed_path:?}: {e}");
+    }
+
+    let config = std::sync::Arc::new(ServerConfig {
+        enable_debug: args.enable_debug,
+        aof_path: args.aof.clone(),
+        max_commands_per_sec: args.max_commands_per_sec,
+        resync_on_protocol_error: args.resync_on_protocol_error,
+        max_array_len: args.proto_max_array_len as usize,
+        max_args: args.max_args as usize,
+        stats: Default::default(),
+        pause: Default::default(),
+        port: args.port,
+        run_id: replication::new_replid(),
+        started_at: std::time::Instant::now(),
+    });
+    tokio::spawn(config.stats.clone().run_ops_sampler());
+
     let listener = TcpListener::bind(&addr).await?;
     info!("StormDB escutando em {addr}");
 
+    let keepalive = (args.tcp_keepalive > 0).then(|| KeepaliveConfig {
+        idle: Duration::from_secs(args.tcp_keepalive),
+        interval: Duration::from_secs(args.tcp_keepalive_interval),
+        retries: args.tcp_keepalive_count,
+    });
+
     let semaphore = std::sync::Arc::new(tokio::sync::Semaphore::new(args.max_connections));
     let (shutdown_tx, _) = broadcast::channel::<()>(1);
     // Canal de replicação: Master -> Réplicas (capacidade 10000 cmds em buffer)
-    let (replication_tx, _) = broadcast::channel::<stormdb_protocol::Command>(10000);
+    let (replication_tx, _) =
+        broadcast::channel::<std::sync::Arc<stormdb_protocol::Command>>(10000);
+
+    // Estado de replicação: alterado em runtime pelo REPLICAOF
+    let repl = replication::SharedReplState::default();
 
     // Iniciar Replicação se configurado
     if let Some(replica_args) = args.replicaof
-        && replica_args.len() == 2 {
-            let master_host = replica_args[0].clone();
-            let master_port = replica_args[1].parse::<u16>().unwrap_or(6379);
-
-            let db_replica = db.clone();
-            let shutdown_replica = shutdown_tx.subscribe();
-
-            tokio::spawn(async move {
-                replication::replica_task(master_host, master_port, db_replica, shutdown_replica)
-                    .await;
-            });
-        }
+        && replica_args.len() == 2
+    {
+        let master_host = replica_args[0].clone();
+        let master_port = replica_args[1].parse::<u16>().unwrap_or(6379);
+        replication::start_replica(&repl, master_host, master_port, db.clone());
+    }
 
     loop {
-        let permit = tokio::select! {
-            permit = semaphore.clone().acquire_owned() => permit.unwrap(),
-            _ = signal::ctrl_c() => {
-                info!("shutdown signal recebido");
-                drop(shutdown_tx);
-                break;
+        // Por padrão espera uma vaga antes de aceitar: as conexões novas
+        // ficam na fila do listener até alguma fechar.
+        let waited_permit = if args.reject_when_full {
+            None
+        } else {
+            tokio::select! {
+                permit = semaphore.clone().acquire_owned() => Some(permit.unwrap()),
+                _ = signal::ctrl_c() => {
+                    info!("shutdown signal recebido");
+                    drop(shutdown_tx);
+                    break;
+                }
             }
         };
 
@@ -118,22 +268,75 @@ async fn main() -> anyhow::Result<()> {
             }
         };
 
-        info!("nova conexão: {addr}");
+        let permit = match waited_permit {
+            Some(permit) => permit,
+            None => match semaphore.clone().try_acquire_owned() {
+                Ok(permit) => permit,
+                Err(_) => {
+                    config.stats.connection_rejected();
+                    warn!("conexão de {addr} recusada: limite de conexões atingido");
+                    // Melhor esforço, numa task para não bloquear o accept loop
+                    // (um try_write direto pode dar WouldBlock num socket recém-aceito)
+                    tokio::spawn(async move {
+                        let mut socket = socket;
+                        let reply = socket.write_all(b"-ERR max number of clients reached\r\n");
+                        let _ = tokio::time::timeout(REJECT_WRITE_TIMEOUT, reply).await;
+                    });
+                    continue;
+                }
+            },
+        };
+        config.stats.connection_received();
+        if let Some(keepalive) = keepalive
+            && let Err(e) = keepalive.apply(&socket)
+        {
+            warn!("falha ao ligar TCP keepalive para {addr}: {e}");
+        }
+
+        let conn = Connection::new(socket);
+        let span = info_span!("conn", conn_id = conn.id(), %addr);
+        span.in_scope(|| info!("nova conexão"));
         let db = db.clone();
         let aof_tx = aof_tx.clone();
         let replication_tx = replication_tx.clone();
+        let config = config.clone();
+        let stats = config.stats.clone();
+        let repl = repl.clone();
         let mut shutdown_rx = shutdown_tx.subscribe();
 
-        tokio::spawn(async move {
-            let conn = Connection::new(socket);
-            if let Err(e) =
-                handle_connection(conn, db, &mut shutdown_rx, aof_tx, replication_tx).await
-            {
-                error!("erro na conexão {addr}: {e}");
+        tokio::spawn(
+            async move {
+                if let Err(e) = handle_connection(
+                    conn,
+                    db,
+                    &mut shutdown_rx,
+                    aof_tx,
+                    replication_tx,
+                    config,
+                    repl,
+                )
+                .await
+                {
+                    error!("erro na conexão: {e}");
+                }
+                info!("conexão encerrada");
+                stats.connection_closed();
+                drop(permit);
             }
-            info!("conexão encerrada: {addr}");
-            drop(permit);
-        });
+            .instrument(span),
+        );
+    }
+
+    // Espera as conexões avisarem os clientes e fecharem (com limite, para
+    // não travar o shutdown numa conexão lenta)
+    let drain = semaphore.acquire_many(args.max_connections as u32);
+    if tokio::time::timeout(SHUTDOWN_DRAIN_TIMEOUT, drain)
+        .await
+        .is_err()
+    {
+        warn!(
+            "shutdown: conexões ainda abertas após {SHUTDOWN_DRAIN_TIMEOUT:?}, encerrando assim mesmo"
+        );
     }
 
     // Drop aof_tx para fechar o writer
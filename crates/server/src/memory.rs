@@ -0,0 +1,67 @@
+//! Números do alocador para o INFO `memory`. Quem instala o jemalloc como
+//! alocador global é o binário (`main.rs`); a biblioteca só lê as
+//! estatísticas, sem impor o alocador a quem a usa.
+
+/// Memória do processo segundo o alocador.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct AllocatorStats {
+    /// Bytes alocados pela aplicação (`used_memory`).
+    pub allocated: usize,
+    /// Bytes residentes em páginas físicas (`used_memory_rss`).
+    pub resident: usize,
+}
+
+impl AllocatorStats {
+    /// `used_memory_rss / used_memory`, como no Redis.
+    pub fn fragmentation_ratio(&self) -> f64 {
+        self.resident as f64 / self.allocated.max(1) as f64
+    }
+}
+
+/// Estatísticas do jemalloc, atualizadas a cada chamada. `None` quando o
+/// servidor foi compilado sem a feature `jemalloc`.
+#[cfg(feature = "jemalloc")]
+pub(crate) fn allocator_stats() -> Option<AllocatorStats> {
+    use tikv_jemalloc_ctl::{epoch, stats};
+
+    // Os contadores do jemalloc são cacheados até o epoch avançar
+    epoch::advance().ok()?;
+    Some(AllocatorStats {
+        allocated: stats::allocated::read().ok()?,
+        resident: stats::resident::read().ok()?,
+    })
+}
+
+#[cfg(not(feature = "jemalloc"))]
+pub(crate) fn allocator_stats() -> Option<AllocatorStats> {
+    None
+}
+
+#[cfg(all(test, feature = "jemalloc"))]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+
+    // O binário de testes da lib não passa pelo `main.rs`: instala o
+    // jemalloc aqui para as estatísticas refletirem as alocações
+    #[global_allocator]
+    static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
+
+    #[tokio::test]
+    async fn used_memory_grows_with_data() {
+        let before = allocator_stats().unwrap();
+        assert!(before.allocated > 0);
+
+        let db = stormdb_storage::Db::new();
+        for i in 0..10_000 {
+            db.rpush(
+                format!("key:{i}").as_bytes(),
+                &[Bytes::from(vec![b'x'; 256])],
+            )
+            .unwrap();
+        }
+        let after = allocator_stats().unwrap();
+        assert!(after.allocated > before.allocated + 10_000 * 256);
+        assert!(after.resident > 0);
+    }
+}
@@ -0,0 +1,70 @@
+use stormdb_protocol::{ClientCommand, Command, PauseMode};
+use stormdb_storage::is_write_command;
+use tokio::sync::watch;
+use tokio::time::{Duration, Instant};
+
+/// Pausa ativa do `CLIENT PAUSE`.
+#[derive(Debug, Clone, Copy)]
+struct Pause {
+    until: Instant,
+    mode: PauseMode,
+}
+
+/// Portão compartilhado do `CLIENT PAUSE`: cada conexão passa por ele
+/// antes de executar um comando e espera enquanto a pausa valer para ele.
+#[derive(Debug)]
+pub struct ClientPause {
+    state: watch::Sender<Option<Pause>>,
+}
+
+impl Default for ClientPause {
+    fn default() -> Self {
+        Self {
+            state: watch::Sender::new(None),
+        }
+    }
+}
+
+impl ClientPause {
+    /// Segura os comandos de `mode` por `duration`. Uma pausa nova
+    /// substitui a anterior.
+    pub fn pause(&self, duration: Duration, mode: PauseMode) {
+        self.state.send_replace(Some(Pause {
+            until: Instant::now() + duration,
+            mode,
+        }));
+    }
+
+    /// Libera na hora quem está esperando.
+    pub fn unpause(&self) {
+        self.state.send_replace(None);
+    }
+
+    /// Espera a pausa acabar (prazo ou `unpause`), se ela vale para `cmd`.
+    pub async fn wait(&self, cmd: &Command) {
+        let mut rx = self.state.subscribe();
+        loop {
+            let Some(pause) = *rx.borrow_and_update() else {
+                return;
+            };
+            if pause.until <= Instant::now() || !pauses(pause.mode, cmd) {
+                return;
+            }
+            tokio::select! {
+                _ = tokio::time::sleep_until(pause.until) => return,
+                _ = rx.changed() => {}
+            }
+        }
+    }
+}
+
+fn pauses(mode: PauseMode, cmd: &Command) -> bool {
+    match cmd {
+        // Senão ninguém conseguiria mandar o UNPAUSE durante uma pausa ALL
+        Command::Client(ClientCommand::Pause { .. } | ClientCommand::Unpause) => false,
+        _ => match mode {
+            PauseMode::All => true,
+            PauseMode::Write => is_write_command(cmd),
+        },
+    }
+}
@@ -0,0 +1,56 @@
+use tokio::time::Instant;
+
+/// Token bucket para limitar comandos por segundo de uma conexão.
+///
+/// Começa cheio (permite um burst de até `rate` comandos) e recarrega
+/// continuamente a `rate` tokens por segundo.
+#[derive(Debug)]
+pub(crate) struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub(crate) fn new(rate: u32) -> Self {
+        Self {
+            rate: rate as f64,
+            tokens: rate as f64,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Consome um token. Retorna `false` se o limite foi excedido.
+    pub(crate) fn try_acquire(&mut self) -> bool {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::Duration;
+
+    #[test]
+    fn bucket_refills_over_time() {
+        let mut bucket = TokenBucket::new(2);
+        assert!(bucket.try_acquire());
+        assert!(bucket.try_acquire());
+        assert!(!bucket.try_acquire());
+
+        // Simula meio segundo sem comandos: recarrega 1 token
+        bucket.last_refill -= Duration::from_millis(500);
+        assert!(bucket.try_acquire());
+        assert!(!bucket.try_acquire());
+    }
+}
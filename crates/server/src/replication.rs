@@ -1,19 +1,113 @@
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 use tokio::net::TcpStream;
-use tokio::sync::broadcast;
+use tokio::sync::{Notify, broadcast};
 use tracing::{error, info, warn};
 
 use stormdb_common::ConnectionError;
 use stormdb_protocol::{Command, Frame};
-use stormdb_storage::Db;
+use stormdb_storage::{Db, apply_command, supervise};
 
 use crate::Connection;
 
+/// Papel do servidor na replicação.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Role {
+    #[default]
+    Master,
+    Replica,
+}
+
+/// Estado de replicação em runtime, lido pelo INFO e alterado pelo
+/// `--replicaof`, pelo `REPLICAOF` e pelas conexões de réplicas.
+#[derive(Debug)]
+pub struct ReplState {
+    pub role: Role,
+    /// Id da história de replicação: enviado às réplicas no `FULLRESYNC`.
+    /// Uma réplica adota o replid do seu master.
+    pub replid: String,
+    /// Master atual (só quando `role` é `Replica`).
+    pub master: Option<(String, u16)>,
+    /// Réplicas conectadas a este servidor.
+    pub replicas: Vec<SocketAddr>,
+    /// Encerra a `replica_task` atual quando dropado.
+    stop_replica: Option<broadcast::Sender<()>>,
+    /// Avisa os streams de réplicas que o replid mudou.
+    replid_changed: Arc<Notify>,
+}
+
+impl Default for ReplState {
+    fn default() -> Self {
+        Self {
+            role: Role::Master,
+            replid: new_replid(),
+            master: None,
+            replicas: Vec::new(),
+            stop_replica: None,
+            replid_changed: Arc::new(Notify::new()),
+        }
+    }
+}
+
+impl ReplState {
+    /// Começa uma nova história de replicação. As réplicas conectadas são
+    /// desconectadas e, ao reconectar, recebem um FULLRESYNC com o novo id.
+    pub fn change_replid(&mut self) {
+        self.replid = new_replid();
+        self.replid_changed.notify_waiters();
+    }
+}
+
+/// Gera um replid aleatório de 40 caracteres hexadecimais.
+pub fn new_replid() -> String {
+    (0..40)
+        .map(|_| char::from_digit(fastrand::u32(0..16), 16).unwrap())
+        .collect()
+}
+
+pub type SharedReplState = Arc<RwLock<ReplState>>;
+
+/// Passa a replicar de `host:port`, encerrando a replicação anterior (se
+/// houver). A task de replicação roda supervisionada em background.
+pub fn start_replica(repl: &SharedReplState, host: String, port: u16, db: Db) {
+    let (stop_tx, stop_rx) = broadcast::channel::<()>(1);
+    {
+        let mut state = repl.write().unwrap();
+        state.role = Role::Replica;
+        state.master = Some((host.clone(), port));
+        // Dropar o sender anterior encerra a task antiga
+        state.stop_replica = Some(stop_tx);
+    }
+
+    let repl = repl.clone();
+    supervise("replica_task", move || {
+        replica_task(
+            host.clone(),
+            port,
+            db.clone(),
+            repl.clone(),
+            stop_rx.resubscribe(),
+        )
+    });
+}
+
+/// `REPLICAOF NO ONE`: para de replicar e volta a ser master. O dataset
+/// local é mantido, mas começa uma nova história de replicação (novo replid).
+pub fn stop_replica(repl: &SharedReplState) {
+    let mut state = repl.write().unwrap();
+    state.role = Role::Master;
+    state.master = None;
+    state.stop_replica = None;
+    state.change_replid();
+}
+
 /// Tarefa de fundo que mantém a conexão com o Master.
 pub async fn replica_task(
     master_host: String,
     master_port: u16,
     db: Db,
+    repl: SharedReplState,
     mut shutdown: broadcast::Receiver<()>,
 ) {
     let addr = format!("{}:{}", master_host, master_port);
@@ -37,17 +131,73 @@ pub async fn replica_task(
 
         info!("Conectado ao Master {}!", addr);
         let mut conn = Connection::new(stream);
+        // O snapshot do master manda cada lista num único RPUSH
+        conn.set_max_array_len(usize::MAX);
 
-        // Handshake simples (PSYNC ou similar - por enquanto enviamos um PING para testar)
-        // Num futuro, enviaríamos "PSYNC ? -1" para pedir sincronização total.
-        if let Err(e) = conn
-            .write_frame(&Frame::array_from_strs(&["PING", "REPLICA_HANDSHAKE"]))
-            .await
-        {
-            error!("Erro no handshake com Master: {}", e);
-            continue;
+        // Handshake: PSYNC com o replid conhecido. Ainda não há resync
+        // parcial, então o master sempre responde FULLRESYNC.
+        let known_replid = repl.read().unwrap().replid.clone();
+        let master_replid = tokio::select! {
+            result = psync(&mut conn, &known_replid) => result,
+            _ = shutdown.recv() => return,
+        };
+        match master_replid {
+            Ok(Some(replid)) => {
+                if replid != known_replid {
+                    info!("replid do Master: {replid} (anterior: {known_replid})");
+                }
+                repl.write().unwrap().replid = replid;
+            }
+            Ok(None) => {
+                error!("Resposta inesperada ao PSYNC do Master {}", addr);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+            Err(e) => {
+                error!("Erro no handshake com Master: {}", e);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
         }
 
+        // Toda (re)conexão é um full resync: o Master envia o dataset inteiro
+        // antes do stream. O snapshot é lido por completo antes de tocar no
+        // keyspace: se a conexão cair no meio dele, o estado local fica como
+        // estava, e nenhum comando do stream é aplicado antes do snapshot.
+        let snapshot = tokio::select! {
+            result = read_snapshot(&mut conn) => result,
+            _ = shutdown.recv() => return,
+        };
+        let snapshot = match snapshot {
+            Ok(Some(snapshot)) => snapshot,
+            Ok(None) => {
+                error!("Snapshot inesperado do Master {}", addr);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+            Err(e) => {
+                error!("Erro ao receber snapshot do Master: {}", e);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+        info!(
+            "Full resync: aplicando {} comandos do snapshot.",
+            snapshot.len()
+        );
+        // Descarta o estado local (que pode ter buracos se o Master nos
+        // desconectou por atraso) e aplica o snapshot. Aplicar no próprio Db,
+        // em vez de montar um novo e trocar, basta: o snapshot já foi lido
+        // inteiro, então do flush ao fim do replay não se espera a rede, e o
+        // LOADING recusa os comandos de dados nesse meio tempo, então nenhum
+        // cliente vê o dataset pela metade.
+        db.set_loading(true);
+        db.flush().await;
+        for cmd in snapshot {
+            apply_command(cmd, &db).await;
+        }
+        db.set_loading(false);
+
         // Loop de processamento de comandos vindos do Master
         // Reutilizamos o handle_connection mas sem responder nada (réplica é passiva na rede)
         // PORÉM, o handle_connection atual tenta escrever na socket.
@@ -62,7 +212,7 @@ pub async fn replica_task(
                                 Ok(cmd) => {
                                     // Executar comando localmente (blindly apply)
                                     // Réplicas aplicam tudo o que o master manda.
-                                    apply_replica_command(&cmd, &db).await;
+                                    apply_command(cmd, &db).await;
                                 }
                                 Err(e) => error!("Erro ao parsear comando do Master: {}", e),
                             }
@@ -84,63 +234,129 @@ pub async fn replica_task(
             }
         }
 
-        tokio::time::sleep(Duration::from_secs(1)).await;
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs(1)) => {}
+            _ = shutdown.recv() => return,
+        }
     }
 }
 
-async fn apply_replica_command(cmd: &Command, db: &Db) {
-    // Aqui executamos o comando direto no DB.
-    // Como é réplica, ignoramos comandos de leitura (GET) vindos do master (não devem vir, mas ok)
-    // E executamos os de escrita.
-
-    // NOTA: Precisamos expor o `execute_command` do handler ou duplicar a lógica mínima.
-    // Para simplificar, vou fazer um match manual nos comandos de escrita suportados.
-
-    match cmd {
-        Command::Set {
-            key,
-            value,
-            options,
-        } => {
-            let _ = db.set(key.clone(), value.clone(), options);
-        }
-        Command::Del(keys) => {
-            db.del(keys);
-        }
-        Command::Incr(key) => {
-            let _ = db.incr(key);
-        }
-        Command::Decr(key) => {
-            let _ = db.decr(key);
-        }
-        Command::LPush { key, values } => {
-            let _ = db.lpush(key, values);
-        }
-        Command::RPush { key, values } => {
-            let _ = db.rpush(key, values);
-        }
-        Command::LPop { key, count } => {
-            let _ = db.lpop(key, *count);
-        }
-        Command::RPop { key, count } => {
-            let _ = db.rpop(key, *count);
-        }
-        Command::Publish { channel, message } => {
-            db.publish(channel, message.clone()).await;
+/// Handshake da réplica: envia `REPLCONF capa psync2` e `PSYNC <replid> -1`
+/// e lê o `+FULLRESYNC <replid> <offset>` do master. Retorna o replid do
+/// master, ou `None` se a resposta não for um FULLRESYNC.
+async fn psync(conn: &mut Connection, replid: &str) -> Result<Option<String>, ConnectionError> {
+    conn.write_frame(&Frame::array_from_strs(&["REPLCONF", "capa", "psync2"]))
+        .await?;
+    match conn.read_frame().await? {
+        Some(Frame::Simple(ok)) if ok == "OK" => {}
+        other => warn!("master não aceitou REPLCONF: {other:?}"),
+    }
+
+    conn.write_frame(&Frame::array_from_strs(&["PSYNC", replid, "-1"]))
+        .await?;
+    match conn.read_frame().await? {
+        Some(Frame::Simple(line)) => Ok(line
+            .strip_prefix("FULLRESYNC ")
+            .and_then(|rest| rest.split(' ').next())
+            .map(str::to_string)),
+        _ => Ok(None),
+    }
+}
+
+/// Lê o snapshot do full resync: um inteiro com o número de comandos,
+/// seguido dos comandos. Retorna `None` se o Master não mandar o tamanho.
+async fn read_snapshot(conn: &mut Connection) -> Result<Option<Vec<Command>>, ConnectionError> {
+    let len = match conn.read_frame().await? {
+        Some(Frame::Integer(len)) if len >= 0 => len as usize,
+        Some(_) => return Ok(None),
+        None => return Err(ConnectionError::ConnectionReset),
+    };
+
+    // Sem pré-alocar pelo tamanho declarado pelo Master: o Vec só cresce com
+    // os comandos que realmente chegam.
+    let mut commands = Vec::new();
+    for _ in 0..len {
+        let Some(frame) = conn.read_frame().await? else {
+            return Err(ConnectionError::ConnectionReset);
+        };
+        match Command::from_frame(frame) {
+            Ok(cmd) => commands.push(cmd),
+            Err(e) => error!("Erro ao parsear comando do snapshot: {}", e),
         }
-        // Ping e outros comandos de controle podem ser ignorados na replicação passiva por enquanto
-        _ => {}
     }
+    Ok(Some(commands))
 }
 
 /// Handler para o lado do MASTER: envia comandos para a réplica conectada.
+///
+/// A réplica recebe primeiro um snapshot do dataset (full resync) e depois o
+/// stream de comandos de escrita. A assinatura do stream e o snapshot são
+/// feitos juntos, sem escrita em andamento (`Db::snapshot_with`): cada
+/// escrita concorrente chega à réplica exatamente uma vez.
 pub async fn handle_replica_stream(
     mut conn: Connection,
-    mut replication_rx: broadcast::Receiver<Command>,
+    db: &Db,
+    replication_tx: &broadcast::Sender<Arc<Command>>,
+    repl: &SharedReplState,
+    shutdown: &mut broadcast::Receiver<()>,
+) -> Result<(), ConnectionError> {
+    let addr = conn.peer_addr()?;
+    // Registra o aviso de troca de replid antes de lê-lo, para não perder
+    // uma troca que aconteça entre a leitura e o início do stream.
+    let replid_changed = repl.read().unwrap().replid_changed.clone();
+    let changed = replid_changed.notified();
+    tokio::pin!(changed);
+    changed.as_mut().enable();
+
+    let replid = {
+        let mut state = repl.write().unwrap();
+        state.replicas.push(addr);
+        state.replid.clone()
+    };
+    // Offsets de replicação ainda não são rastreados: sempre 0
+    let result = match conn
+        .write_frame(&Frame::Simple(format!("FULLRESYNC {replid} 0")))
+        .await
+    {
+        Ok(()) => tokio::select! {
+            result = stream_to_replica(conn, db, replication_tx) => result,
+            _ = changed => {
+                info!("replid mudou: desconectando réplica {addr} para full resync");
+                Ok(())
+            }
+            // O stream não termina sozinho: sem isso o shutdown esperaria o
+            // prazo de drenagem inteiro por cada réplica conectada
+            _ = shutdown.recv() => {
+                info!("shutdown: encerrando stream da réplica {addr}");
+                Ok(())
+            }
+        },
+        Err(e) => Err(e),
+    };
+    repl.write().unwrap().replicas.retain(|r| *r != addr);
+    result
+}
+
+async fn stream_to_replica(
+    mut conn: Connection,
+    db: &Db,
+    replication_tx: &broadcast::Sender<Arc<Command>>,
 ) -> Result<(), ConnectionError> {
     info!("Iniciando stream de replicação para cliente.");
     // conn.write_frame(&Frame::Simple("OK".into())).await?; // Removido: causava erro no parser da réplica
 
+    let (snapshot, mut replication_rx) = db.snapshot_with(|| replication_tx.subscribe()).await;
+    info!(
+        "Full resync: enviando {} chaves para a réplica.",
+        snapshot.len()
+    );
+    // O tamanho vai na frente para a réplica saber onde o snapshot termina
+    conn.write_frame(&Frame::Integer(snapshot.len() as i64))
+        .await?;
+    for cmd in &snapshot {
+        conn.write_frame(&cmd.to_frame()).await?;
+    }
+
     loop {
         match replication_rx.recv().await {
             Ok(cmd) => {
@@ -149,8 +365,13 @@ pub async fn handle_replica_stream(
                 conn.write_frame(&frame).await?;
             }
             Err(broadcast::error::RecvError::Lagged(n)) => {
-                warn!("Réplica atrasada: perdeu {} comandos.", n);
-                // Em um sistema real, aqui fecharíamos a conexão para forçar full-resync
+                // A réplica perdeu comandos e ficaria divergente para sempre.
+                // Fechamos a conexão: ela reconecta e faz um full resync.
+                warn!(
+                    "Réplica atrasada: perdeu {} comandos. Desconectando para forçar full resync.",
+                    n
+                );
+                return Ok(());
             }
             Err(broadcast::error::RecvError::Closed) => {
                 return Ok(());
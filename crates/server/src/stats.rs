@@ -0,0 +1,135 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use tokio::time::{Duration, Instant};
+
+/// Intervalo entre amostras do `instantaneous_ops_per_sec`.
+const OPS_SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+/// Número de amostras na média (janela de 1,6s).
+const OPS_SAMPLES: usize = 16;
+
+/// Contadores do servidor exibidos na seção `stats` do INFO, compartilhados
+/// entre o accept loop e as conexões.
+#[derive(Debug, Default)]
+pub struct ServerStats {
+    total_connections_received: AtomicU64,
+    connected_clients: AtomicU64,
+    rejected_connections: AtomicU64,
+    total_commands_processed: AtomicU64,
+    /// Última média calculada por `run_ops_sampler`.
+    instantaneous_ops_per_sec: AtomicU64,
+}
+
+impl ServerStats {
+    /// Conexão aceita pelo accept loop.
+    pub fn connection_received(&self) {
+        self.total_connections_received
+            .fetch_add(1, Ordering::Relaxed);
+        self.connected_clients.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Conexão aceita que terminou; par de `connection_received`.
+    pub fn connection_closed(&self) {
+        self.connected_clients.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Conexão recusada por falta de vaga (`--reject-when-full`).
+    pub fn connection_rejected(&self) {
+        self.rejected_connections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn command_processed(&self) {
+        self.total_commands_processed
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Zera os contadores acumulados (CONFIG RESETSTAT). `connected_clients`
+    /// é um valor corrente, não um acumulado, e fica como está.
+    pub fn reset(&self) {
+        self.total_connections_received.store(0, Ordering::Relaxed);
+        self.rejected_connections.store(0, Ordering::Relaxed);
+        self.total_commands_processed.store(0, Ordering::Relaxed);
+        self.instantaneous_ops_per_sec.store(0, Ordering::Relaxed);
+    }
+
+    pub fn total_connections_received(&self) -> u64 {
+        self.total_connections_received.load(Ordering::Relaxed)
+    }
+
+    pub fn connected_clients(&self) -> u64 {
+        self.connected_clients.load(Ordering::Relaxed)
+    }
+
+    pub fn rejected_connections(&self) -> u64 {
+        self.rejected_connections.load(Ordering::Relaxed)
+    }
+
+    pub fn total_commands_processed(&self) -> u64 {
+        self.total_commands_processed.load(Ordering::Relaxed)
+    }
+
+    pub fn instantaneous_ops_per_sec(&self) -> u64 {
+        self.instantaneous_ops_per_sec.load(Ordering::Relaxed)
+    }
+
+    /// Amostra o total de comandos a cada 100ms e publica a média das
+    /// últimas amostras em `instantaneous_ops_per_sec`. Roda para sempre;
+    /// sem ela o valor fica em 0.
+    pub async fn run_ops_sampler(self: Arc<Self>) {
+        let mut sampler = OpsSampler::default();
+        let mut interval = tokio::time::interval(OPS_SAMPLE_INTERVAL);
+        let mut last = (Instant::now(), self.total_commands_processed());
+        loop {
+            interval.tick().await;
+            let now = (Instant::now(), self.total_commands_processed());
+            // O total pode ter sido zerado por um RESETSTAT desde a última amostra
+            let rate = sampler.add(now.1.saturating_sub(last.1), now.0 - last.0);
+            self.instantaneous_ops_per_sec
+                .store(rate, Ordering::Relaxed);
+            last = now;
+        }
+    }
+}
+
+/// Média móvel de ops/s sobre as últimas `OPS_SAMPLES` amostras.
+#[derive(Debug, Default)]
+struct OpsSampler {
+    samples: [u64; OPS_SAMPLES],
+    next: usize,
+}
+
+impl OpsSampler {
+    /// Registra `ops` comandos em `elapsed` e retorna a nova média.
+    fn add(&mut self, ops: u64, elapsed: Duration) -> u64 {
+        let ms = (elapsed.as_millis() as u64).max(1);
+        self.samples[self.next] = ops * 1000 / ms;
+        self.next = (self.next + 1) % OPS_SAMPLES;
+        self.samples.iter().sum::<u64>() / OPS_SAMPLES as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ops_sampler_averages_window() {
+        let mut sampler = OpsSampler::default();
+        // 50 comandos em 100ms = 500 ops/s, diluído na janela ainda vazia
+        assert_eq!(
+            sampler.add(50, OPS_SAMPLE_INTERVAL),
+            500 / OPS_SAMPLES as u64
+        );
+
+        for _ in 1..OPS_SAMPLES {
+            sampler.add(50, OPS_SAMPLE_INTERVAL);
+        }
+        assert_eq!(sampler.add(50, OPS_SAMPLE_INTERVAL), 500);
+
+        // Amostras antigas saem da janela
+        for _ in 0..OPS_SAMPLES {
+            sampler.add(0, OPS_SAMPLE_INTERVAL);
+        }
+        assert_eq!(sampler.add(0, OPS_SAMPLE_INTERVAL), 0);
+    }
+}
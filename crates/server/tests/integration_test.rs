@@ -1,10 +1,14 @@
 use bytes::Bytes;
 use std::io::Cursor;
+use std::path::PathBuf;
+use std::sync::Arc;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
 use tokio::time::Duration;
 
 use stormdb_protocol::Frame;
+use stormdb_server::ServerConfig;
+use stormdb_storage::{AofSender, FsyncPolicy, create_aof};
 
 /// Helper: conecta ao servidor e executa um comando, retornando o frame de resposta.
 async fn send_command(stream: &mut TcpStream, args: &[&str]) -> Frame {
@@ -29,13 +33,57 @@ async fn send_command(stream: &mut TcpStream, args: &[&str]) -> Frame {
 }
 
 async fn start_server(port: u16) -> tokio::task::JoinHandle<()> {
+    spawn_server(port, 100, None, ServerConfig::default()).await
+}
+
+/// Igual a `start_server`, mas com capacidade configurável do canal de replicação.
+async fn start_server_with_replication(
+    port: u16,
+    replication_capacity: usize,
+) -> tokio::task::JoinHandle<()> {
+    spawn_server(port, replication_capacity, None, ServerConfig::default()).await
+}
+
+/// Sobe um servidor com AOF (writer rodando em background) e DEBUG habilitado.
+async fn start_server_with_aof(port: u16, aof_path: PathBuf) -> tokio::task::JoinHandle<()> {
+    let (aof_tx, writer) = create_aof(aof_path.clone(), FsyncPolicy::Always, 100);
+    tokio::spawn(writer.run());
+    let config = ServerConfig {
+        enable_debug: true,
+        aof_path: Some(aof_path),
+        ..Default::default()
+    };
+    spawn_server(port, 100, Some(aof_tx), config).await
+}
+
+async fn spawn_server(
+    port: u16,
+    replication_capacity: usize,
+    aof_tx: Option<AofSender>,
+    config: ServerConfig,
+) -> tokio::task::JoinHandle<()> {
+    let db = stormdb_storage::Db::new();
+    spawn_server_with_db(port, replication_capacity, aof_tx, config, db).await
+}
+
+/// Igual a `spawn_server`, servindo um `Db` criado pelo teste.
+async fn spawn_server_with_db(
+    port: u16,
+    replication_capacity: usize,
+    aof_tx: Option<AofSender>,
+    config: ServerConfig,
+    db: stormdb_storage::Db,
+) -> tokio::task::JoinHandle<()> {
+    // Como o `main`, o config carrega a porta em que o servidor escuta
+    let config = Arc::new(ServerConfig { port, ..config });
     let handle = tokio::spawn(async move {
         let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{port}"))
             .await
             .unwrap();
-        let db = stormdb_storage::Db::new();
         let (shutdown_tx, _) = tokio::sync::broadcast::channel::<()>(1);
-        let (replication_tx, _) = tokio::sync::broadcast::channel::<stormdb_protocol::Command>(100);
+        let (replication_tx, _) =
+            tokio::sync::broadcast::channel::<Arc<stormdb_protocol::Command>>(replication_capacity);
+        let repl = stormdb_server::replication::SharedReplState::default();
 
         loop {
             let (socket, _) = tokio::select! {
@@ -46,9 +94,21 @@ async fn start_server(port: u16) -> tokio::task::JoinHandle<()> {
             let db = db.clone();
             let mut shutdown_rx = shutdown_tx.subscribe();
             let replication_tx = replication_tx.clone();
+            let aof_tx = aof_tx.clone();
+            let config = config.clone();
+            let repl = repl.clone();
             tokio::spawn(async move {
                 let conn = stormdb_server::Connection::new(socket);
-                let _ = stormdb_server::handle_connection(conn, db, &mut shutdown_rx, None, replication_tx).await;
+                let _ = stormdb_server::handle_connection(
+                    conn,
+                    db,
+                    &mut shutdown_rx,
+                    aof_tx,
+                    replication_tx,
+                    config,
+                    repl,
+                )
+                .await;
             });
         }
     });
@@ -211,6 +271,38 @@ async fn test_list_operations() {
     assert_eq!(response, Frame::Bulk(Bytes::from("c")));
 }
 
+#[tokio::test]
+async fn test_lrange_reply_mixes_small_and_large_items() {
+    let port = 16458;
+    let _server = start_server(port).await;
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+
+    // Muitos itens pequenos (vão em lote) intercalados com alguns grandes
+    // (escritos direto do Bytes), para cruzar as duas fronteiras de escrita
+    let large = "x".repeat(40_000);
+    let mut expected = Vec::new();
+    for chunk in 0..10 {
+        let mut args = vec!["RPUSH".to_string(), "list".to_string()];
+        args.extend((0..1000).map(|i| format!("v{chunk}-{i}")));
+        args.push(large.clone());
+        let refs: Vec<&str> = args.iter().map(String::as_str).collect();
+        send_command(&mut stream, &refs).await;
+        expected.extend(
+            args.into_iter()
+                .skip(2)
+                .map(|v| Frame::Bulk(Bytes::from(v))),
+        );
+    }
+
+    let response = send_command(&mut stream, &["LRANGE", "list", "0", "-1"]).await;
+    assert_eq!(response, Frame::Array(expected));
+    let response = send_command(&mut stream, &["PING"]).await;
+    assert_eq!(response, Frame::Simple("PONG".into()));
+}
+
 #[tokio::test]
 async fn test_set_with_ex() {
     let port = 16407;
@@ -291,6 +383,538 @@ async fn test_unknown_command() {
     }
 }
 
+#[tokio::test]
+async fn test_time() {
+    let port = 16413;
+    let _server = start_server(port).await;
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+
+    let response = send_command(&mut stream, &["TIME"]).await;
+    let parts = match response {
+        Frame::Array(parts) => parts,
+        other => panic!("expected array, got {other:?}"),
+    };
+    assert_eq!(parts.len(), 2);
+
+    let as_u64 = |f: &Frame| match f {
+        Frame::Bulk(b) => std::str::from_utf8(b).unwrap().parse::<u64>().unwrap(),
+        other => panic!("expected bulk, got {other:?}"),
+    };
+    let secs = as_u64(&parts[0]);
+    let micros = as_u64(&parts[1]);
+
+    let local = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    assert!(secs.abs_diff(local) <= 5);
+    assert!(micros < 1_000_000);
+}
+
+#[tokio::test]
+async fn test_info_persistence() {
+    let port = 16415;
+    let _server = start_server(port).await;
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+
+    let response = send_command(&mut stream, &["INFO", "persistence"]).await;
+    let text = match response {
+        Frame::Bulk(b) => String::from_utf8(b.to_vec()).unwrap(),
+        other => panic!("expected bulk, got {other:?}"),
+    };
+    assert!(text.contains("aof_enabled:0"));
+    assert!(text.contains("aof_pending_writes:0"));
+    assert!(text.contains("aof_fsync_slow_count:0"));
+    assert!(!text.contains("# Keyspace"));
+}
+
+#[tokio::test]
+async fn test_info_server_uptime_increases() {
+    let port = 16445;
+    let _server = start_server(port).await;
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+    let server_info = |frame: Frame| match frame {
+        Frame::Bulk(b) => String::from_utf8(b.to_vec()).unwrap(),
+        other => panic!("expected bulk, got {other:?}"),
+    };
+    let field = |text: &str, name: &str| {
+        text.lines()
+            .find_map(|l| l.strip_prefix(&format!("{name}:")).map(str::to_string))
+            .unwrap_or_else(|| panic!("missing {name} in {text}"))
+    };
+
+    let first = server_info(send_command(&mut stream, &["INFO", "server"]).await);
+    assert!(first.starts_with("# Server\r\n"));
+    assert_eq!(field(&first, "redis_version"), "7.2.0");
+    assert_eq!(field(&first, "stormdb_version"), env!("CARGO_PKG_VERSION"));
+    assert_eq!(field(&first, "tcp_port"), port.to_string());
+    assert_eq!(field(&first, "run_id").len(), 40);
+    assert_eq!(field(&first, "process_id"), std::process::id().to_string());
+
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let second = server_info(send_command(&mut stream, &["INFO", "server"]).await);
+    let uptime = |text: &str| field(text, "uptime_in_seconds").parse::<u64>().unwrap();
+    assert!(uptime(&second) > uptime(&first));
+    assert_eq!(field(&first, "run_id"), field(&second, "run_id"));
+}
+
+#[tokio::test]
+async fn test_info_replication_follows_replicaof() {
+    let port = 16429;
+    let _server = start_server(port).await;
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+
+    let info = |frame: Frame| match frame {
+        Frame::Bulk(b) => String::from_utf8(b.to_vec()).unwrap(),
+        other => panic!("expected bulk, got {other:?}"),
+    };
+
+    let text = info(send_command(&mut stream, &["INFO", "replication"]).await);
+    assert!(text.contains("role:master"));
+
+    // Master inexistente: a réplica fica tentando conectar em background
+    let response = send_command(&mut stream, &["REPLICAOF", "127.0.0.1", "1"]).await;
+    assert_eq!(response, Frame::Simple("OK".into()));
+    let text = info(send_command(&mut stream, &["INFO", "replication"]).await);
+    assert!(text.contains("role:slave"));
+    assert!(text.contains("master_host:127.0.0.1"));
+    assert!(text.contains("master_port:1"));
+
+    let response = send_command(&mut stream, &["REPLICAOF", "NO", "ONE"]).await;
+    assert_eq!(response, Frame::Simple("OK".into()));
+    let text = info(send_command(&mut stream, &["INFO", "replication"]).await);
+    assert!(text.contains("role:master"));
+    assert!(!text.contains("master_host"));
+}
+
+#[tokio::test]
+async fn test_debug_reload() {
+    let port = 16416;
+    let dir = tempfile::tempdir().unwrap();
+    let _server = start_server_with_aof(port, dir.path().join("reload.aof")).await;
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+
+    send_command(&mut stream, &["SET", "persistent", "value"]).await;
+    send_command(&mut stream, &["SET", "temp", "value", "PX", "500"]).await;
+    send_command(&mut stream, &["RPUSH", "list", "a", "b"]).await;
+    send_command(&mut stream, &["INCR", "counter"]).await;
+
+    let response = send_command(&mut stream, &["DEBUG", "RELOAD"]).await;
+    assert_eq!(response, Frame::Simple("OK".into()));
+
+    let response = send_command(&mut stream, &["DBSIZE"]).await;
+    assert_eq!(response, Frame::Integer(4));
+    let response = send_command(&mut stream, &["GET", "persistent"]).await;
+    assert_eq!(response, Frame::Bulk(Bytes::from("value")));
+    let response = send_command(&mut stream, &["GET", "counter"]).await;
+    assert_eq!(response, Frame::Bulk(Bytes::from("1")));
+    let response = send_command(&mut stream, &["LRANGE", "list", "0", "-1"]).await;
+    assert_eq!(
+        response,
+        Frame::Array(vec![Frame::bulk("a"), Frame::bulk("b")])
+    );
+
+    // O TTL sobrevive ao reload: a chave ainda expira
+    let response = send_command(&mut stream, &["GET", "temp"]).await;
+    assert_eq!(response, Frame::Bulk(Bytes::from("value")));
+    tokio::time::sleep(Duration::from_millis(700)).await;
+    let response = send_command(&mut stream, &["GET", "temp"]).await;
+    assert_eq!(response, Frame::Null);
+}
+
+#[tokio::test]
+async fn test_debug_reload_keeps_wall_clock_deadlines() {
+    let port = 16449;
+    let dir = tempfile::tempdir().unwrap();
+    let _server = start_server_with_aof(port, dir.path().join("deadline.aof")).await;
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis();
+    let at = (now + 1500).to_string();
+    send_command(&mut stream, &["SET", "absolute", "v", "PXAT", &at]).await;
+    send_command(&mut stream, &["SET", "relative", "v", "PX", "1500"]).await;
+    send_command(&mut stream, &["SET", "expired", "v"]).await;
+    send_command(&mut stream, &["PEXPIRE", "expired", "1500"]).await;
+    // Um deadline além de i64::MAX ms não cabe no PXAT propagado: erro, sem
+    // gravar nada que o replay depois descartaria
+    let huge = i64::MAX.to_string();
+    let response = send_command(&mut stream, &["SET", "huge", "v", "PX", &huge]).await;
+    assert_eq!(
+        response,
+        Frame::Error("ERR invalid expire time in 'set' command".into())
+    );
+    let response = send_command(&mut stream, &["INCR", "huge", "PX", &huge]).await;
+    assert_eq!(
+        response,
+        Frame::Error("ERR invalid expire time in 'incr' command".into())
+    );
+    let response = send_command(&mut stream, &["PEXPIRE", "expired", &huge]).await;
+    assert_eq!(
+        response,
+        Frame::Error("ERR invalid expire time in 'expire' command".into())
+    );
+
+    // O reload acontece no meio do TTL: se o AOF guardasse o TTL relativo,
+    // o replay daria mais 1,5s às chaves
+    tokio::time::sleep(Duration::from_millis(800)).await;
+    let response = send_command(&mut stream, &["DEBUG", "RELOAD"]).await;
+    assert_eq!(response, Frame::Simple("OK".into()));
+    let response = send_command(&mut stream, &["DBSIZE"]).await;
+    assert_eq!(response, Frame::Integer(3));
+
+    tokio::time::sleep(Duration::from_millis(1000)).await;
+    for key in ["absolute", "relative", "expired"] {
+        let response = send_command(&mut stream, &["GET", key]).await;
+        assert_eq!(response, Frame::Null, "{key} sobreviveu ao deadline");
+    }
+}
+
+#[tokio::test]
+async fn test_mset_default_ttl_survives_reload_as_deadline() {
+    let port = 16455;
+    let dir = tempfile::tempdir().unwrap();
+    let aof_path = dir.path().join("mset.aof");
+    let (aof_tx, writer) = create_aof(aof_path.clone(), FsyncPolicy::Always, 100);
+    tokio::spawn(writer.run());
+    let config = ServerConfig {
+        enable_debug: true,
+        aof_path: Some(aof_path),
+        ..Default::default()
+    };
+    let db = stormdb_storage::Db::with_config(stormdb_storage::DbConfig {
+        default_ttl_ms: Some(1500),
+        ..Default::default()
+    });
+    let _server = spawn_server_with_db(port, 100, Some(aof_tx), config, db).await;
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+    send_command(&mut stream, &["MSET", "a", "1", "b", "2"]).await;
+    let response = send_command(&mut stream, &["MSETNX", "c", "3"]).await;
+    assert_eq!(response, Frame::Integer(1));
+
+    // Como no SET, o AOF guarda o deadline de cada chave: o replay não
+    // sorteia um `--default-ttl` novo
+    tokio::time::sleep(Duration::from_millis(800)).await;
+    let response = send_command(&mut stream, &["DEBUG", "RELOAD"]).await;
+    assert_eq!(response, Frame::Simple("OK".into()));
+    let response = send_command(&mut stream, &["DBSIZE"]).await;
+    assert_eq!(response, Frame::Integer(3));
+
+    tokio::time::sleep(Duration::from_millis(1000)).await;
+    for key in ["a", "b", "c"] {
+        let response = send_command(&mut stream, &["GET", key]).await;
+        assert_eq!(response, Frame::Null, "{key} sobreviveu ao deadline");
+    }
+}
+
+#[tokio::test]
+async fn test_loading_rejects_data_commands() {
+    let port = 16451;
+    let db = stormdb_storage::Db::new();
+    let _server = spawn_server_with_db(port, 100, None, ServerConfig::default(), db.clone()).await;
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+    send_command(&mut stream, &["SET", "key", "value"]).await;
+
+    // Simula uma recarga lenta em andamento
+    db.set_loading(true);
+    for args in [&["GET", "key"][..], &["SET", "key", "other"]] {
+        match send_command(&mut stream, args).await {
+            Frame::Error(msg) => assert!(msg.starts_with("LOADING "), "{msg}"),
+            other => panic!("esperava LOADING, veio {other:?}"),
+        }
+    }
+    let response = send_command(&mut stream, &["PING"]).await;
+    assert_eq!(response, Frame::Simple("PONG".into()));
+    let Frame::Bulk(info) = send_command(&mut stream, &["INFO", "persistence"]).await else {
+        panic!("esperava bulk");
+    };
+    assert!(String::from_utf8_lossy(&info).contains("loading:1"));
+
+    db.set_loading(false);
+    let response = send_command(&mut stream, &["GET", "key"]).await;
+    assert_eq!(response, Frame::Bulk(Bytes::from("value")));
+}
+
+#[tokio::test]
+async fn test_debug_requires_enable_flag() {
+    let port = 16417;
+    let _server = start_server(port).await;
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+
+    let response = send_command(&mut stream, &["DEBUG", "RELOAD"]).await;
+    match response {
+        Frame::Error(msg) => assert!(msg.contains("--enable-debug")),
+        other => panic!("expected error, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_command_info() {
+    let port = 16420;
+    let _server = start_server(port).await;
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+
+    let response = send_command(&mut stream, &["COMMAND", "INFO", "get", "set", "nosuch"]).await;
+    let Frame::Array(entries) = response else {
+        panic!("expected array, got {response:?}");
+    };
+    assert_eq!(entries.len(), 3);
+
+    let Frame::Array(get) = &entries[0] else {
+        panic!("expected array for get");
+    };
+    assert_eq!(get[0], Frame::bulk("get"));
+    assert_eq!(get[1], Frame::Integer(2));
+
+    let Frame::Array(set) = &entries[1] else {
+        panic!("expected array for set");
+    };
+    assert_eq!(set[0], Frame::bulk("set"));
+    assert_eq!(set[1], Frame::Integer(-3));
+    let Frame::Array(flags) = &set[2] else {
+        panic!("expected flags array");
+    };
+    assert!(flags.contains(&Frame::Simple("write".into())));
+
+    assert_eq!(entries[2], Frame::Null);
+}
+
+#[tokio::test]
+async fn test_rate_limit() {
+    let port = 16421;
+    let config = ServerConfig {
+        max_commands_per_sec: Some(5),
+        ..Default::default()
+    };
+    let _server = spawn_server(port, 100, None, config).await;
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+
+    let mut limited = 0;
+    for _ in 0..20 {
+        match send_command(&mut stream, &["PING"]).await {
+            Frame::Simple(s) if s == "PONG" => {}
+            Frame::Error(e) => {
+                assert_eq!(e, "ERR rate limit exceeded");
+                limited += 1;
+            }
+            other => panic!("unexpected response {other:?}"),
+        }
+    }
+    assert!(limited > 0, "nenhum comando foi limitado");
+
+    // Outras conexões têm o próprio bucket
+    let mut other = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+    let response = send_command(&mut other, &["PING"]).await;
+    assert_eq!(response, Frame::Simple("PONG".into()));
+}
+
+#[tokio::test]
+async fn test_brpoplpush_waits_for_element() {
+    let port = 16427;
+    let _server = start_server(port).await;
+
+    let mut worker = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+    let mut producer = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+
+    send_raw(&mut worker, &["BRPOPLPUSH", "jobs", "processing", "5"]).await;
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let response = send_command(&mut producer, &["LPUSH", "jobs", "job-1"]).await;
+    assert_eq!(response, Frame::Integer(1));
+
+    let moved = read_frame(&mut worker).await;
+    assert_eq!(moved, Frame::bulk("job-1"));
+
+    let response = send_command(&mut producer, &["LRANGE", "processing", "0", "-1"]).await;
+    assert_eq!(response, Frame::Array(vec![Frame::bulk("job-1")]));
+    let response = send_command(&mut producer, &["EXISTS", "jobs"]).await;
+    assert_eq!(response, Frame::Integer(0));
+}
+
+#[tokio::test]
+async fn test_brpoplpush_timeout() {
+    let port = 16428;
+    let _server = start_server(port).await;
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+
+    let start = std::time::Instant::now();
+    let response = send_command(&mut stream, &["BRPOPLPUSH", "jobs", "processing", "0.2"]).await;
+    assert_eq!(response, Frame::Null);
+    assert!(start.elapsed() >= Duration::from_millis(200));
+
+    // Nada foi movido
+    let response = send_command(&mut stream, &["EXISTS", "jobs", "processing"]).await;
+    assert_eq!(response, Frame::Integer(0));
+}
+
+#[tokio::test]
+async fn test_pop_reply_types() {
+    let port = 16422;
+    let _server = start_server(port).await;
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+
+    // Chave inexistente: Null com ou sem count
+    for cmd in ["LPOP", "RPOP"] {
+        assert_eq!(
+            send_command(&mut stream, &[cmd, "missing"]).await,
+            Frame::Null
+        );
+        assert_eq!(
+            send_command(&mut stream, &[cmd, "missing", "2"]).await,
+            Frame::Null
+        );
+        assert_eq!(
+            send_command(&mut stream, &[cmd, "missing", "0"]).await,
+            Frame::Null
+        );
+    }
+
+    send_command(&mut stream, &["RPUSH", "list", "a", "b", "c", "d"]).await;
+
+    // Sem count: bulk
+    let response = send_command(&mut stream, &["LPOP", "list"]).await;
+    assert_eq!(response, Frame::bulk("a"));
+    let response = send_command(&mut stream, &["RPOP", "list"]).await;
+    assert_eq!(response, Frame::bulk("d"));
+
+    // count = 0 numa chave existente: array vazio
+    let response = send_command(&mut stream, &["LPOP", "list", "0"]).await;
+    assert_eq!(response, Frame::Array(vec![]));
+    let response = send_command(&mut stream, &["RPOP", "list", "0"]).await;
+    assert_eq!(response, Frame::Array(vec![]));
+
+    // count = 1: array de um elemento, não bulk
+    let response = send_command(&mut stream, &["LPOP", "list", "1"]).await;
+    assert_eq!(response, Frame::Array(vec![Frame::bulk("b")]));
+
+    // count maior que a lista: array com o que sobrou
+    let response = send_command(&mut stream, &["RPOP", "list", "5"]).await;
+    assert_eq!(response, Frame::Array(vec![Frame::bulk("c")]));
+
+    // count negativo: erro
+    let response = send_command(&mut stream, &["LPOP", "list", "-1"]).await;
+    assert!(matches!(response, Frame::Error(_)));
+}
+
+#[tokio::test]
+async fn test_client_id() {
+    let port = 16423;
+    let _server = start_server(port).await;
+
+    let mut first = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+    let mut second = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+
+    let Frame::Integer(first_id) = send_command(&mut first, &["CLIENT", "ID"]).await else {
+        panic!("expected integer id");
+    };
+    let Frame::Integer(second_id) = send_command(&mut second, &["CLIENT", "ID"]).await else {
+        panic!("expected integer id");
+    };
+    assert_ne!(first_id, second_id);
+
+    // O id é estável durante a vida da conexão
+    let response = send_command(&mut first, &["CLIENT", "ID"]).await;
+    assert_eq!(response, Frame::Integer(first_id));
+}
+
+#[tokio::test]
+async fn test_resync_after_protocol_error() {
+    let port = 16424;
+    let config = ServerConfig {
+        resync_on_protocol_error: true,
+        ..Default::default()
+    };
+    let _server = spawn_server(port, 100, None, config).await;
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+
+    // Tamanho de bulk inválido seguido de um comando válido
+    stream
+        .write_all(b"*1\r\n$abc\r\nPING\r\n*2\r\n$4\r\nECHO\r\n$2\r\nok\r\n")
+        .await
+        .unwrap();
+
+    let frames = read_frames(&mut stream, 2).await;
+    assert!(
+        matches!(&frames[0], Frame::Error(e) if e.starts_with("ERR Protocol error")),
+        "got {:?}",
+        frames[0]
+    );
+    assert_eq!(frames[1], Frame::bulk("ok"));
+
+    // A conexão continua utilizável
+    let response = send_command(&mut stream, &["PING"]).await;
+    assert_eq!(response, Frame::Simple("PONG".into()));
+}
+
+#[tokio::test]
+async fn test_protocol_error_closes_connection_by_default() {
+    let port = 16425;
+    let _server = start_server(port).await;
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+
+    stream.write_all(b"!garbage\r\n").await.unwrap();
+    let mut buf = [0u8; 64];
+    let n = stream.read(&mut buf).await.unwrap();
+    assert_eq!(n, 0, "esperava a conexão fechada");
+}
+
 /// Helper: envia um comando raw (sem ler resposta).
 async fn send_raw(stream: &mut TcpStream, args: &[&str]) {
     let frame = Frame::array_from_strs(args);
@@ -315,6 +939,28 @@ async fn read_frame(stream: &mut TcpStream) -> Frame {
     }
 }
 
+/// Helper: lê `n` frames do stream, sem descartar frames que cheguem
+/// juntos no mesmo read.
+async fn read_frames(stream: &mut TcpStream, n: usize) -> Vec<Frame> {
+    let mut buf = bytes::BytesMut::new();
+    let mut frames = Vec::new();
+    while frames.len() < n {
+        let read = stream.read_buf(&mut buf).await.unwrap();
+        assert!(read > 0, "server closed connection unexpectedly");
+        loop {
+            let mut cursor = Cursor::new(&buf[..]);
+            if Frame::check(&mut cursor).is_err() {
+                break;
+            }
+            let len = cursor.position() as usize;
+            cursor.set_position(0);
+            frames.push(Frame::parse(&mut cursor).unwrap());
+            let _ = buf.split_to(len);
+        }
+    }
+    frames
+}
+
 #[tokio::test]
 async fn test_pubsub() {
     let port = 16411;
@@ -365,3 +1011,1135 @@ async fn test_pubsub() {
     let response = send_command(&mut pub_stream, &["PUBLISH", "empty", "hello"]).await;
     assert_eq!(response, Frame::Integer(0));
 }
+
+#[tokio::test]
+async fn test_subscribe_confirmation_before_messages() {
+    let port = 16419;
+    let _server = start_server(port).await;
+
+    let mut sub_stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+    let mut pub_stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+
+    // Primeiro SUBSCRIBE entra no modo subscribe; o segundo exercita o
+    // caminho de re-subscribe dentro do loop.
+    send_raw(&mut sub_stream, &["SUBSCRIBE", "first"]).await;
+    send_raw(&mut sub_stream, &["SUBSCRIBE", "second"]).await;
+
+    // Publica assim que o canal tiver um subscriber, sem ler nada antes
+    loop {
+        let response = send_command(&mut pub_stream, &["PUBLISH", "second", "hi"]).await;
+        if response == Frame::Integer(1) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+
+    let frames = read_frames(&mut sub_stream, 3).await;
+
+    let expected = |kind: &str, channel: &str, last: Frame| {
+        Frame::Array(vec![Frame::bulk(kind), Frame::bulk(channel), last])
+    };
+    assert_eq!(frames[0], expected("subscribe", "first", Frame::Integer(1)));
+    assert_eq!(
+        frames[1],
+        expected("subscribe", "second", Frame::Integer(2))
+    );
+    assert_eq!(frames[2], expected("message", "second", Frame::bulk("hi")));
+}
+
+#[tokio::test]
+async fn test_subscribe_count_spans_channels_and_patterns() {
+    let port = 16450;
+    let _server = start_server(port).await;
+
+    let mut sub_stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+    let mut pub_stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+
+    send_raw(&mut sub_stream, &["SUBSCRIBE", "news.tech", "sports"]).await;
+    send_raw(&mut sub_stream, &["PSUBSCRIBE", "news.*"]).await;
+    let frames = read_frames(&mut sub_stream, 3).await;
+
+    let expected = |kind: &str, channel: &str, count: i64| {
+        Frame::Array(vec![
+            Frame::bulk(kind),
+            Frame::bulk(channel),
+            Frame::Integer(count),
+        ])
+    };
+    assert_eq!(frames[0], expected("subscribe", "news.tech", 1));
+    assert_eq!(frames[1], expected("subscribe", "sports", 2));
+    assert_eq!(frames[2], expected("psubscribe", "news.*", 3));
+
+    // Canal e padrão recebem a mesma mensagem
+    let response = send_command(&mut pub_stream, &["PUBLISH", "news.tech", "hi"]).await;
+    assert_eq!(response, Frame::Integer(2));
+    let frames = read_frames(&mut sub_stream, 2).await;
+    assert!(frames.contains(&Frame::Array(vec![
+        Frame::bulk("message"),
+        Frame::bulk("news.tech"),
+        Frame::bulk("hi"),
+    ])));
+    assert!(frames.contains(&Frame::Array(vec![
+        Frame::bulk("pmessage"),
+        Frame::bulk("news.*"),
+        Frame::bulk("news.tech"),
+        Frame::bulk("hi"),
+    ])));
+
+    let confirm = send_command(&mut sub_stream, &["PUNSUBSCRIBE", "news.*"]).await;
+    assert_eq!(confirm, expected("punsubscribe", "news.*", 2));
+}
+
+#[tokio::test]
+async fn test_unsubscribe_confirms_each_channel_and_pattern() {
+    let port = 16457;
+    let _server = start_server(port).await;
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+
+    send_raw(&mut stream, &["SUBSCRIBE", "a", "b"]).await;
+    send_raw(&mut stream, &["PSUBSCRIBE", "p.*"]).await;
+    read_frames(&mut stream, 3).await;
+
+    let expected = |kind: &str, name: Frame, count: i64| {
+        Frame::Array(vec![Frame::bulk(kind), name, Frame::Integer(count)])
+    };
+
+    // Uma confirmação por canal, com o total decrescendo; o padrão segue
+    // inscrito, então a conexão continua em modo subscribe
+    send_raw(&mut stream, &["UNSUBSCRIBE"]).await;
+    let frames = read_frames(&mut stream, 2).await;
+    assert_eq!(frames[0], expected("unsubscribe", Frame::bulk("a"), 2));
+    assert_eq!(frames[1], expected("unsubscribe", Frame::bulk("b"), 1));
+
+    // Sem canais inscritos: uma confirmação só, com canal nil
+    send_raw(&mut stream, &["UNSUBSCRIBE"]).await;
+    let frames = read_frames(&mut stream, 1).await;
+    assert_eq!(frames[0], expected("unsubscribe", Frame::Null, 1));
+
+    // Padrão nunca inscrito ainda recebe a sua confirmação
+    send_raw(&mut stream, &["PUNSUBSCRIBE", "q.*", "p.*"]).await;
+    let frames = read_frames(&mut stream, 2).await;
+    assert_eq!(frames[0], expected("punsubscribe", Frame::bulk("q.*"), 1));
+    assert_eq!(frames[1], expected("punsubscribe", Frame::bulk("p.*"), 0));
+
+    // Fora do modo subscribe: comandos normais voltam a rodar
+    let response = send_command(&mut stream, &["PING"]).await;
+    assert_eq!(response, Frame::Simple("PONG".into()));
+}
+
+#[tokio::test]
+async fn test_unsubscribe_all_returns_to_normal_mode() {
+    let port = 16426;
+    let _server = start_server(port).await;
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+
+    send_command(&mut stream, &["SET", "key", "value"]).await;
+
+    send_raw(&mut stream, &["SUBSCRIBE", "a", "b"]).await;
+    read_frames(&mut stream, 2).await;
+
+    // UNSUBSCRIBE sem argumentos sai de todos os canais, um frame por canal
+    send_raw(&mut stream, &["UNSUBSCRIBE"]).await;
+    let frames = read_frames(&mut stream, 2).await;
+    let counts: Vec<_> = frames
+        .iter()
+        .map(|frame| match frame {
+            Frame::Array(parts) => parts[2].clone(),
+            _ => panic!("expected array for unsubscribe confirmation"),
+        })
+        .collect();
+    assert_eq!(counts, [Frame::Integer(1), Frame::Integer(0)]);
+
+    // A conexão continua aberta e aceita comandos normais
+    let response = send_command(&mut stream, &["GET", "key"]).await;
+    assert_eq!(response, Frame::Bulk(Bytes::from("value")));
+}
+
+#[tokio::test]
+async fn test_replica_resyncs_after_lag() {
+    let port = 16414;
+    // Canal minúsculo: um burst de escritas faz a réplica atrasar (Lagged)
+    let _server = start_server_with_replication(port, 4).await;
+
+    let replica_db = stormdb_storage::Db::new();
+    let (shutdown_tx, shutdown_rx) = tokio::sync::broadcast::channel::<()>(1);
+    tokio::spawn(stormdb_server::replication::replica_task(
+        "127.0.0.1".into(),
+        port,
+        replica_db.clone(),
+        Default::default(),
+        shutdown_rx,
+    ));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+
+    // Pipeline de 1000 SETs num único write
+    let total = 1000;
+    let mut buf = bytes::BytesMut::new();
+    for i in 0..total {
+        Frame::array_from_strs(&["SET", &format!("key:{i}"), &format!("value:{i}")])
+            .encode(&mut buf);
+    }
+    stream.write_all(&buf).await.unwrap();
+
+    // Consumir as 1000 respostas "+OK\r\n"
+    let expected = total * 5;
+    let mut received = 0;
+    let mut response_buf = vec![0u8; 8192];
+    while received < expected {
+        let n = stream.read(&mut response_buf).await.unwrap();
+        assert!(n > 0, "server closed connection unexpectedly");
+        received += n;
+    }
+
+    // A réplica deve convergir para o estado do Master (via full resync se atrasou)
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    loop {
+        let converged = replica_db.len() == total
+            && (0..total).all(|i| {
+                replica_db.get(format!("key:{i}").as_bytes())
+                    == Some(Bytes::from(format!("value:{i}")))
+            });
+        if converged {
+            break;
+        }
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "réplica divergiu: {} de {total} chaves",
+            replica_db.len()
+        );
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    drop(shutdown_tx);
+}
+
+#[tokio::test]
+async fn test_replica_consistent_when_master_writes_during_resync() {
+    let port = 16444;
+    let _server = start_server(port).await;
+
+    // O Master recebe escritas contínuas enquanto a réplica faz o full
+    // resync. INCR e RPUSH não são idempotentes: escrita perdida ou aplicada
+    // duas vezes muda o contador e a lista
+    let total = 2000;
+    let writer = tokio::spawn(async move {
+        let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+            .await
+            .unwrap();
+        for i in 0..total {
+            let value = format!("value:{i}");
+            send_command(&mut stream, &["INCR", "counter"]).await;
+            send_command(&mut stream, &["RPUSH", "list", &value]).await;
+            send_command(&mut stream, &["SET", &format!("key:{}", i % 100), &value]).await;
+        }
+    });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    let replica_db = stormdb_storage::Db::new();
+    let (shutdown_tx, shutdown_rx) = tokio::sync::broadcast::channel::<()>(1);
+    tokio::spawn(stormdb_server::replication::replica_task(
+        "127.0.0.1".into(),
+        port,
+        replica_db.clone(),
+        Default::default(),
+        shutdown_rx,
+    ));
+    writer.await.unwrap();
+
+    // O marcador chega à réplica depois de todas as escritas
+    let mut client = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+    send_command(&mut client, &["SET", "done", "1"]).await;
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    while replica_db.get(b"done").is_none() {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "réplica não recebeu o marcador"
+        );
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    assert_eq!(
+        replica_db.get(b"counter"),
+        Some(Bytes::from(total.to_string()))
+    );
+    let list = replica_db.lrange(b"list", 0, -1).unwrap();
+    assert_eq!(list.len(), total);
+    assert!(
+        list.iter()
+            .enumerate()
+            .all(|(i, value)| *value == format!("value:{i}")),
+        "lista da réplica fora de ordem"
+    );
+    for i in 0..100 {
+        let key = format!("key:{i}");
+        let value = match send_command(&mut client, &["GET", &key]).await {
+            Frame::Bulk(value) => value,
+            other => panic!("expected bulk for {key}, got {other:?}"),
+        };
+        assert_eq!(replica_db.get(key.as_bytes()), Some(value));
+    }
+    // counter, list, done e as 100 chaves de SET
+    assert_eq!(replica_db.len(), 103);
+
+    drop(shutdown_tx);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_resync_during_writes_applies_each_write_once() {
+    let port = 16456;
+    let dir = tempfile::tempdir().unwrap();
+    // Com AOF a propagação espera o writer: a janela entre aplicar a escrita
+    // e mandá-la às réplicas fica larga
+    let _server = start_server_with_aof(port, dir.path().join("resync.aof")).await;
+
+    // INCR e RPUSH não são idempotentes: uma escrita que chegasse à réplica
+    // pelo snapshot e de novo pelo stream deixaria o contador e a lista maiores
+    let (writers, total) = (8, 2000);
+    let mut handles = Vec::new();
+    for _ in 0..writers {
+        handles.push(tokio::spawn(async move {
+            let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+                .await
+                .unwrap();
+            for _ in 0..total {
+                send_command(&mut stream, &["INCR", "counter"]).await;
+                send_command(&mut stream, &["RPUSH", "list", "x"]).await;
+            }
+        }));
+    }
+
+    // Várias réplicas fazem full resync no meio das escritas
+    let (shutdown_tx, shutdown_rx) = tokio::sync::broadcast::channel::<()>(1);
+    let mut replicas = Vec::new();
+    for _ in 0..20 {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        let replica_db = stormdb_storage::Db::new();
+        tokio::spawn(stormdb_server::replication::replica_task(
+            "127.0.0.1".into(),
+            port,
+            replica_db.clone(),
+            Default::default(),
+            shutdown_rx.resubscribe(),
+        ));
+        replicas.push(replica_db);
+    }
+    for handle in handles {
+        handle.await.unwrap();
+    }
+
+    // O marcador chega depois de todas as escritas
+    let mut client = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+    send_command(&mut client, &["SET", "done", "1"]).await;
+
+    let expected = writers * total;
+    for replica_db in &replicas {
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+        while replica_db.get(b"done").is_none() {
+            assert!(
+                tokio::time::Instant::now() < deadline,
+                "réplica não recebeu o marcador"
+            );
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(
+            replica_db.get(b"counter"),
+            Some(Bytes::from(expected.to_string()))
+        );
+        assert_eq!(replica_db.lrange(b"list", 0, -1).unwrap().len(), expected);
+    }
+
+    drop(shutdown_tx);
+}
+
+#[tokio::test]
+async fn test_blocking_move_replicates_its_effect() {
+    let port = 16447;
+    let _server = start_server(port).await;
+
+    let replica_db = stormdb_storage::Db::new();
+    let (shutdown_tx, shutdown_rx) = tokio::sync::broadcast::channel::<()>(1);
+    tokio::spawn(stormdb_server::replication::replica_task(
+        "127.0.0.1".into(),
+        port,
+        replica_db.clone(),
+        Default::default(),
+        shutdown_rx,
+    ));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // BRPOPLPUSH bloqueado, desbloqueado por um RPUSH de outro cliente
+    let blocked = tokio::spawn(async move {
+        let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+            .await
+            .unwrap();
+        send_command(&mut stream, &["BRPOPLPUSH", "src", "dst", "2"]).await
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let mut client = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+    send_command(&mut client, &["RPUSH", "src", "a", "b"]).await;
+    assert_eq!(blocked.await.unwrap(), Frame::Bulk(Bytes::from("b")));
+    // Sem elementos: timeout, nada a propagar
+    let response = send_command(&mut client, &["BRPOPLPUSH", "empty", "dst", "0.05"]).await;
+    assert_eq!(response, Frame::Null);
+
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(2);
+    loop {
+        let src = replica_db.lrange(b"src", 0, -1).unwrap();
+        let dst = replica_db.lrange(b"dst", 0, -1).unwrap();
+        if src == vec![Bytes::from("a")] && dst == vec![Bytes::from("b")] {
+            break;
+        }
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "réplica divergiu: src={src:?} dst={dst:?}"
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    drop(shutdown_tx);
+}
+
+#[tokio::test]
+async fn test_psync_fullresync_with_replid() {
+    let port = 16430;
+    let config = ServerConfig {
+        enable_debug: true,
+        ..Default::default()
+    };
+    let _server = spawn_server(port, 100, None, config).await;
+
+    let mut client = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+    let replid = |frame: Frame| match frame {
+        Frame::Bulk(b) => String::from_utf8(b.to_vec())
+            .unwrap()
+            .lines()
+            .find_map(|l| l.strip_prefix("master_replid:").map(str::to_string))
+            .unwrap(),
+        other => panic!("expected bulk, got {other:?}"),
+    };
+    let master_replid = replid(send_command(&mut client, &["INFO", "replication"]).await);
+    assert_eq!(master_replid.len(), 40);
+    assert!(master_replid.chars().all(|c| c.is_ascii_hexdigit()));
+
+    let mut replica = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+    let response = send_command(&mut replica, &["REPLCONF", "capa", "psync2"]).await;
+    assert_eq!(response, Frame::Simple("OK".into()));
+    let response = send_command(&mut replica, &["PSYNC", "?", "-1"]).await;
+    assert_eq!(
+        response,
+        Frame::Simple(format!("FULLRESYNC {master_replid} 0"))
+    );
+
+    // Um novo replid derruba a réplica, que precisa refazer o full resync
+    let response = send_command(&mut client, &["DEBUG", "CHANGE-REPL-ID"]).await;
+    assert_eq!(response, Frame::Simple("OK".into()));
+    // Depois do FULLRESYNC pode ainda chegar o tamanho do snapshot (`:0`)
+    let mut buf = Vec::new();
+    tokio::time::timeout(Duration::from_secs(2), replica.read_to_end(&mut buf))
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(
+        buf.is_empty() || buf == b":0\r\n",
+        "unexpected data: {buf:?}"
+    );
+
+    let new_replid = replid(send_command(&mut client, &["INFO", "replication"]).await);
+    assert_ne!(new_replid, master_replid);
+    let mut replica = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+    let response = send_command(&mut replica, &["PSYNC", &master_replid, "-1"]).await;
+    assert_eq!(
+        response,
+        Frame::Simple(format!("FULLRESYNC {new_replid} 0"))
+    );
+}
+
+#[tokio::test]
+async fn test_large_value_roundtrip() {
+    let port = 16418;
+    let _server = start_server(port).await;
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+
+    // 4 MB: bem acima da capacidade do BufWriter, escrito direto do Bytes
+    let value: String = (0..4 * 1024 * 1024)
+        .map(|i| (b'a' + (i % 26) as u8) as char)
+        .collect();
+
+    let response = send_command(&mut stream, &["SET", "big", &value]).await;
+    assert_eq!(response, Frame::Simple("OK".into()));
+
+    let response = send_command(&mut stream, &["GET", "big"]).await;
+    assert_eq!(response, Frame::Bulk(Bytes::from(value.clone())));
+
+    // Bulk dentro de array (LRANGE)
+    send_command(&mut stream, &["RPUSH", "list", "small", &value]).await;
+    let response = send_command(&mut stream, &["LRANGE", "list", "0", "-1"]).await;
+    assert_eq!(
+        response,
+        Frame::Array(vec![
+            Frame::Bulk(Bytes::from("small")),
+            Frame::Bulk(Bytes::from(value)),
+        ])
+    );
+}
+
+#[tokio::test]
+async fn test_binary_starts_with_io_threads() {
+    let port = 16431;
+    let _server = tokio::process::Command::new(env!("CARGO_BIN_EXE_stormdb-server"))
+        .args(["--port", &port.to_string(), "--io-threads", "2"])
+        .kill_on_drop(true)
+        .spawn()
+        .unwrap();
+
+    // O processo leva um tempo pra subir e abrir o listener
+    let mut stream = None;
+    for _ in 0..50 {
+        if let Ok(s) = TcpStream::connect(format!("127.0.0.1:{port}")).await {
+            stream = Some(s);
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    let mut stream = stream.expect("server did not start");
+
+    let response = send_command(&mut stream, &["PING"]).await;
+    assert_eq!(response, Frame::Simple("PONG".into()));
+}
+
+#[tokio::test]
+async fn test_binary_key_roundtrip() {
+    let port = 16432;
+    let _server = start_server(port).await;
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+
+    // Chave com um byte 0xFF (UTF-8 inválido)
+    let key = Bytes::from_static(b"tenant:\xff:1");
+    let mut buf = bytes::BytesMut::new();
+    Frame::Array(vec![
+        Frame::bulk("SET"),
+        Frame::Bulk(key.clone()),
+        Frame::bulk("v"),
+    ])
+    .encode(&mut buf);
+    Frame::Array(vec![Frame::bulk("GET"), Frame::Bulk(key)]).encode(&mut buf);
+    stream.write_all(&buf).await.unwrap();
+
+    let responses = read_frames(&mut stream, 2).await;
+    assert_eq!(responses[0], Frame::Simple("OK".into()));
+    assert_eq!(responses[1], Frame::Bulk(Bytes::from("v")));
+
+    // Não colide com a mesma chave em outra codificação
+    let response = send_command(&mut stream, &["GET", "tenant:\u{ff}:1"]).await;
+    assert_eq!(response, Frame::Null);
+}
+
+#[tokio::test]
+async fn test_shutdown_sends_error_frame() {
+    let port = 16433;
+    let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+    let db = stormdb_storage::Db::new();
+    let (shutdown_tx, _) = tokio::sync::broadcast::channel::<()>(1);
+    let (replication_tx, _) = tokio::sync::broadcast::channel(100);
+    let config = Arc::new(ServerConfig::default());
+    let repl = stormdb_server::replication::SharedReplState::default();
+
+    let server = {
+        let shutdown_tx = shutdown_tx.clone();
+        tokio::spawn(async move {
+            loop {
+                let (socket, _) = listener.accept().await.unwrap();
+                let db = db.clone();
+                let mut shutdown_rx = shutdown_tx.subscribe();
+                let replication_tx = replication_tx.clone();
+                let config = config.clone();
+                let repl = repl.clone();
+                tokio::spawn(async move {
+                    let conn = stormdb_server::Connection::new(socket);
+                    let _ = stormdb_server::handle_connection(
+                        conn,
+                        db,
+                        &mut shutdown_rx,
+                        None,
+                        replication_tx,
+                        config,
+                        repl,
+                    )
+                    .await;
+                });
+            }
+        })
+    };
+
+    let connect = || TcpStream::connect(format!("127.0.0.1:{port}"));
+
+    // Um cliente ocioso, um em modo subscribe e um bloqueado
+    let mut idle = connect().await.unwrap();
+    send_command(&mut idle, &["PING"]).await;
+
+    let mut subscriber = connect().await.unwrap();
+    send_command(&mut subscriber, &["SUBSCRIBE", "news"]).await;
+
+    let mut blocked = connect().await.unwrap();
+    send_raw(&mut blocked, &["BRPOPLPUSH", "empty", "dst", "0"]).await;
+
+    // Uma réplica recebendo o stream (FULLRESYNC e snapshot vazio)
+    let mut replica = connect().await.unwrap();
+    send_raw(&mut replica, &["PSYNC", "?", "-1"]).await;
+    assert_eq!(read_frames(&mut replica, 2).await[1], Frame::Integer(0));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    shutdown_tx.send(()).unwrap();
+
+    for stream in [&mut idle, &mut subscriber, &mut blocked] {
+        let response = read_frame(stream).await;
+        assert_eq!(response, Frame::Error("ERR server is shutting down".into()));
+        let mut buf = [0u8; 16];
+        assert_eq!(stream.read(&mut buf).await.unwrap(), 0);
+    }
+
+    // O stream da réplica fecha na hora, sem esperar a drenagem
+    let mut buf = [0u8; 16];
+    let closed = tokio::time::timeout(Duration::from_millis(500), replica.read(&mut buf)).await;
+    assert_eq!(closed.expect("stream da réplica não fechou").unwrap(), 0);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_ping_contexts() {
+    let port = 16434;
+    let _server = start_server(port).await;
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+
+    let response = send_command(&mut stream, &["PING"]).await;
+    assert_eq!(response, Frame::Simple("PONG".into()));
+
+    // Argumento binário ecoado byte a byte
+    let mut buf = bytes::BytesMut::new();
+    Frame::Array(vec![
+        Frame::bulk("PING"),
+        Frame::Bulk(Bytes::from_static(b"\x00\xff\r\n")),
+    ])
+    .encode(&mut buf);
+    stream.write_all(&buf).await.unwrap();
+    assert_eq!(
+        read_frame(&mut stream).await,
+        Frame::Bulk(Bytes::from_static(b"\x00\xff\r\n"))
+    );
+
+    // O antigo payload de handshake de réplica é só um PING comum
+    let response = send_command(&mut stream, &["PING", "REPLICA_HANDSHAKE"]).await;
+    assert_eq!(response, Frame::Bulk(Bytes::from("REPLICA_HANDSHAKE")));
+    let response = send_command(&mut stream, &["SET", "still", "client"]).await;
+    assert_eq!(response, Frame::Simple("OK".into()));
+
+    // Em modo subscribe: ["pong", msg]
+    send_command(&mut stream, &["SUBSCRIBE", "ch"]).await;
+    let response = send_command(&mut stream, &["PING"]).await;
+    assert_eq!(
+        response,
+        Frame::Array(vec![Frame::bulk("pong"), Frame::bulk("")])
+    );
+    let response = send_command(&mut stream, &["PING", "hi"]).await;
+    assert_eq!(
+        response,
+        Frame::Array(vec![Frame::bulk("pong"), Frame::bulk("hi")])
+    );
+}
+
+#[tokio::test]
+async fn test_replconf_alone_does_not_upgrade_to_replica() {
+    let port = 16435;
+    let _server = start_server(port).await;
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+
+    // Só o PSYNC troca a conexão para o stream de réplica
+    let response = send_command(&mut stream, &["REPLCONF", "capa", "psync2"]).await;
+    assert_eq!(response, Frame::Simple("OK".into()));
+    let response = send_command(&mut stream, &["SET", "still", "client"]).await;
+    assert_eq!(response, Frame::Simple("OK".into()));
+
+    // A conexão segue como cliente comum e nenhuma réplica foi registrada
+    let response = send_command(&mut stream, &["INFO", "replication"]).await;
+    let Frame::Bulk(info) = response else {
+        panic!("expected bulk, got {response:?}");
+    };
+    assert!(String::from_utf8_lossy(&info).contains("connected_slaves:0"));
+}
+
+#[tokio::test]
+async fn test_error_prefixes() {
+    let port = 16436;
+    let _server = start_server(port).await;
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+
+    send_command(&mut stream, &["RPUSH", "list", "a"]).await;
+    let response = send_command(&mut stream, &["INCR", "list"]).await;
+    assert_eq!(
+        response,
+        Frame::Error("WRONGTYPE Operation against a key holding the wrong kind of value".into())
+    );
+
+    let response = send_command(&mut stream, &["GET"]).await;
+    assert_eq!(
+        response,
+        Frame::Error("ERR wrong number of arguments for 'get' command".into())
+    );
+    let response = send_command(&mut stream, &["GET", "a", "b"]).await;
+    assert_eq!(
+        response,
+        Frame::Error("ERR wrong number of arguments for 'get' command".into())
+    );
+
+    send_command(&mut stream, &["SET", "text", "abc"]).await;
+    let response = send_command(&mut stream, &["INCR", "text"]).await;
+    assert_eq!(
+        response,
+        Frame::Error("ERR value is not an integer or out of range".into())
+    );
+    let response = send_command(&mut stream, &["LRANGE", "list", "zero", "-1"]).await;
+    assert_eq!(
+        response,
+        Frame::Error("ERR value is not an integer or out of range".into())
+    );
+}
+
+#[tokio::test]
+async fn test_max_args_rejects_huge_del() {
+    let port = 16437;
+    let config = ServerConfig {
+        max_args: 4,
+        ..Default::default()
+    };
+    let _server = spawn_server(port, 100, None, config).await;
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+
+    send_command(&mut stream, &["SET", "k1", "v"]).await;
+    let keys: Vec<String> = (0..10).map(|i| format!("k{i}")).collect();
+    let mut args = vec!["DEL"];
+    args.extend(keys.iter().map(String::as_str));
+    let response = send_command(&mut stream, &args).await;
+    assert_eq!(
+        response,
+        Frame::Error("ERR too many arguments for 'del' command (max 4)".into())
+    );
+
+    // Nada foi apagado e a conexão continua utilizável
+    let response = send_command(&mut stream, &["DEL", "k1", "k2"]).await;
+    assert_eq!(response, Frame::Integer(1));
+
+    // O limite vale também em modo subscribe, e o erro chega ao cliente
+    send_command(&mut stream, &["SUBSCRIBE", "a"]).await;
+    let mut args = vec!["SUBSCRIBE"];
+    args.extend(keys.iter().map(String::as_str));
+    let response = send_command(&mut stream, &args).await;
+    assert_eq!(
+        response,
+        Frame::Error("ERR too many arguments for 'subscribe' command (max 4)".into())
+    );
+    let response = send_command(&mut stream, &["SUBSCRIBE"]).await;
+    assert_eq!(
+        response,
+        Frame::Error("ERR wrong number of arguments for 'subscribe' command".into())
+    );
+    let response = send_command(&mut stream, &["PING"]).await;
+    assert_eq!(
+        response,
+        Frame::Array(vec![Frame::bulk("pong"), Frame::bulk("")])
+    );
+}
+
+#[tokio::test]
+async fn test_reject_when_full_closes_extra_connection() {
+    let port = 16438;
+    let _server = tokio::process::Command::new(env!("CARGO_BIN_EXE_stormdb-server"))
+        .args([
+            "--port",
+            &port.to_string(),
+            "--max-connections",
+            "1",
+            "--reject-when-full",
+        ])
+        .kill_on_drop(true)
+        .spawn()
+        .unwrap();
+
+    let mut stream = None;
+    for _ in 0..50 {
+        if let Ok(s) = TcpStream::connect(format!("127.0.0.1:{port}")).await {
+            stream = Some(s);
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    let mut stream = stream.expect("server did not start");
+    // Garante que a primeira conexão já ocupa a única vaga
+    let response = send_command(&mut stream, &["PING"]).await;
+    assert_eq!(response, Frame::Simple("PONG".into()));
+
+    // A segunda recebe o erro e é fechada, sem ficar na fila
+    let mut extra = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+    let mut buf = Vec::new();
+    tokio::time::timeout(Duration::from_secs(2), extra.read_to_end(&mut buf))
+        .await
+        .expect("extra connection was not closed")
+        .unwrap();
+    assert_eq!(buf, b"-ERR max number of clients reached\r\n");
+
+    let Frame::Bulk(info) = send_command(&mut stream, &["INFO", "stats"]).await else {
+        panic!("INFO should return a bulk string");
+    };
+    let info = String::from_utf8_lossy(&info);
+    assert!(info.contains("total_connections_received:1\r\n"), "{info}");
+    assert!(info.contains("rejected_connections:1\r\n"), "{info}");
+}
+
+#[tokio::test]
+async fn test_cluster_stub() {
+    let port = 16439;
+    let _server = start_server(port).await;
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+
+    let Frame::Bulk(info) = send_command(&mut stream, &["CLUSTER", "INFO"]).await else {
+        panic!("CLUSTER INFO should return a bulk string");
+    };
+    assert!(String::from_utf8_lossy(&info).contains("cluster_enabled:0"));
+
+    let response = send_command(&mut stream, &["CLUSTER", "SLOTS"]).await;
+    assert_eq!(response, Frame::Array(vec![]));
+    let response = send_command(&mut stream, &["CLUSTER", "NODES"]).await;
+    assert_eq!(response, Frame::Array(vec![]));
+}
+
+#[tokio::test]
+async fn test_unsubscribe_outside_subscribe_mode() {
+    let port = 16440;
+    let _server = start_server(port).await;
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+
+    let response = send_command(&mut stream, &["UNSUBSCRIBE"]).await;
+    assert_eq!(
+        response,
+        Frame::Array(vec![
+            Frame::bulk("unsubscribe"),
+            Frame::Null,
+            Frame::Integer(0),
+        ])
+    );
+
+    let response = send_command(&mut stream, &["UNSUBSCRIBE", "news"]).await;
+    assert_eq!(
+        response,
+        Frame::Array(vec![
+            Frame::bulk("unsubscribe"),
+            Frame::bulk("news"),
+            Frame::Integer(0),
+        ])
+    );
+
+    // Segue em modo normal
+    let response = send_command(&mut stream, &["PING"]).await;
+    assert_eq!(response, Frame::Simple("PONG".into()));
+}
+
+#[tokio::test]
+async fn test_object_encoding_reports_int_and_embstr() {
+    let port = 16446;
+    let _server = start_server(port).await;
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+    send_command(&mut stream, &["SET", "k", "123"]).await;
+    let response = send_command(&mut stream, &["OBJECT", "ENCODING", "k"]).await;
+    assert_eq!(response, Frame::Bulk(Bytes::from("int")));
+
+    send_command(&mut stream, &["SET", "k", "hello"]).await;
+    let response = send_command(&mut stream, &["OBJECT", "ENCODING", "k"]).await;
+    assert_eq!(response, Frame::Bulk(Bytes::from("embstr")));
+
+    let response = send_command(&mut stream, &["OBJECT", "ENCODING", "missing"]).await;
+    assert_eq!(response, Frame::Null);
+}
+
+#[tokio::test]
+async fn test_debug_object_matches_object_encoding() {
+    let port = 16454;
+    let config = ServerConfig {
+        enable_debug: true,
+        ..Default::default()
+    };
+    let _server = spawn_server(port, 100, None, config).await;
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+    send_command(&mut stream, &["SET", "n", "123"]).await;
+    send_command(&mut stream, &["RPUSH", "l", "a", "bc"]).await;
+
+    let response = send_command(&mut stream, &["DEBUG", "OBJECT", "n"]).await;
+    assert_eq!(
+        response,
+        Frame::Bulk(Bytes::from("encoding:int serializedlength:3"))
+    );
+    let response = send_command(&mut stream, &["DEBUG", "OBJECT", "l"]).await;
+    assert_eq!(
+        response,
+        Frame::Bulk(Bytes::from(
+            "encoding:quicklist serializedlength:3 elements:2"
+        ))
+    );
+}
+
+#[tokio::test]
+async fn test_client_pause_delays_writes_until_unpause() {
+    let port = 16448;
+    let _server = start_server(port).await;
+
+    let mut admin = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+    let mut client = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+
+    let response = send_command(&mut admin, &["CLIENT", "PAUSE", "300", "WRITE"]).await;
+    assert_eq!(response, Frame::Simple("OK".into()));
+    // Leituras não são seguradas no modo WRITE
+    let start = tokio::time::Instant::now();
+    send_command(&mut client, &["GET", "k"]).await;
+    assert!(start.elapsed() < Duration::from_millis(200));
+    let response = send_command(&mut client, &["SET", "k", "v"]).await;
+    assert_eq!(response, Frame::Simple("OK".into()));
+    let elapsed = start.elapsed();
+    assert!(
+        elapsed >= Duration::from_millis(250),
+        "SET não esperou a pausa: {elapsed:?}"
+    );
+
+    // Pausa longa encerrada antes do prazo pelo UNPAUSE
+    send_command(&mut admin, &["CLIENT", "PAUSE", "10000"]).await;
+    let start = tokio::time::Instant::now();
+    let writer = tokio::spawn(async move { send_command(&mut client, &["SET", "k", "v2"]).await });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!writer.is_finished());
+    let response = send_command(&mut admin, &["CLIENT", "UNPAUSE"]).await;
+    assert_eq!(response, Frame::Simple("OK".into()));
+    assert_eq!(writer.await.unwrap(), Frame::Simple("OK".into()));
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
+
+#[tokio::test]
+async fn test_config_resetstat_zeroes_counters() {
+    let port = 16441;
+    let _server = start_server(port).await;
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+
+    send_command(&mut stream, &["SET", "k", "v"]).await;
+    send_command(&mut stream, &["GET", "k"]).await;
+    send_command(&mut stream, &["GET", "missing"]).await;
+
+    let response = send_command(&mut stream, &["CONFIG", "RESETSTAT"]).await;
+    assert_eq!(response, Frame::Simple("OK".into()));
+
+    let Frame::Bulk(info) = send_command(&mut stream, &["INFO", "stats"]).await else {
+        panic!("INFO should return a bulk string");
+    };
+    let info = String::from_utf8_lossy(&info);
+    // Só o próprio INFO conta depois do reset
+    assert!(info.contains("total_commands_processed:1\r\n"), "{info}");
+    assert!(info.contains("keyspace_hits:0\r\n"), "{info}");
+    assert!(info.contains("keyspace_misses:0\r\n"), "{info}");
+}
+
+#[tokio::test]
+async fn test_aof_path_directory_fails_fast() {
+    let dir = tempfile::tempdir().unwrap();
+    let child = tokio::process::Command::new(env!("CARGO_BIN_EXE_stormdb-server"))
+        .args(["--port", "16442", "--aof"])
+        .arg(dir.path())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .unwrap();
+
+    let output = tokio::time::timeout(Duration::from_secs(5), child.wait_with_output())
+        .await
+        .expect("server kept running with a directory as AOF")
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("diretório"), "{stderr}");
+}
+
+#[tokio::test]
+async fn test_string_range_commands_binary_roundtrip() {
+    let port = 16443;
+    let _server = start_server(port).await;
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+
+    async fn send_frame(stream: &mut TcpStream, parts: Vec<Frame>) -> Frame {
+        let mut buf = bytes::BytesMut::new();
+        Frame::Array(parts).encode(&mut buf);
+        stream.write_all(&buf).await.unwrap();
+        read_frame(stream).await
+    }
+
+    let raw = bytes::Bytes::from_static(&[0x00, 0xff, 0x0d, 0x0a, 0xfe]);
+    let response = send_frame(
+        &mut stream,
+        vec![
+            Frame::bulk("APPEND"),
+            Frame::bulk("bin"),
+            Frame::Bulk(raw.clone()),
+        ],
+    )
+    .await;
+    assert_eq!(response, Frame::Integer(5));
+
+    let response = send_frame(
+        &mut stream,
+        vec![
+            Frame::bulk("SETRANGE"),
+            Frame::bulk("bin"),
+            Frame::bulk("4"),
+            Frame::Bulk(bytes::Bytes::from_static(&[0x00, 0xff])),
+        ],
+    )
+    .await;
+    assert_eq!(response, Frame::Integer(6));
+
+    let response = send_command(&mut stream, &["GETRANGE", "bin", "-4", "-1"]).await;
+    assert_eq!(
+        response,
+        Frame::Bulk(bytes::Bytes::from_static(&[0x0d, 0x0a, 0x00, 0xff]))
+    );
+}
+
+#[tokio::test]
+async fn test_ttl_and_pttl() {
+    let port = 16452;
+    let _server = start_server(port).await;
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+
+    send_command(&mut stream, &["SET", "session", "v", "PX", "5000"]).await;
+    let Frame::Integer(pttl) = send_command(&mut stream, &["PTTL", "session"]).await else {
+        panic!("PTTL deve responder um inteiro");
+    };
+    assert!(
+        (4000..=5000).contains(&pttl),
+        "PTTL fora do intervalo: {pttl}"
+    );
+    let response = send_command(&mut stream, &["TTL", "session"]).await;
+    assert_eq!(response, Frame::Integer(5));
+
+    send_command(&mut stream, &["SET", "forever", "v"]).await;
+    let response = send_command(&mut stream, &["TTL", "forever"]).await;
+    assert_eq!(response, Frame::Integer(-1));
+    let response = send_command(&mut stream, &["PTTL", "missing"]).await;
+    assert_eq!(response, Frame::Integer(-2));
+
+    // Expirada mas ainda não purgada conta como ausente
+    send_command(&mut stream, &["SET", "short", "v", "PX", "50"]).await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let response = send_command(&mut stream, &["TTL", "short"]).await;
+    assert_eq!(response, Frame::Integer(-2));
+}
+
+#[tokio::test]
+async fn test_expire_and_pexpire_on_missing_key() {
+    let port = 16453;
+    let _server = start_server(port).await;
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+        .await
+        .unwrap();
+
+    // Sem a chave, nada é aplicado e nada é criado
+    let response = send_command(&mut stream, &["EXPIRE", "missing", "10"]).await;
+    assert_eq!(response, Frame::Integer(0));
+    let response = send_command(&mut stream, &["PEXPIRE", "missing", "10000"]).await;
+    assert_eq!(response, Frame::Integer(0));
+    let response = send_command(&mut stream, &["EXISTS", "missing"]).await;
+    assert_eq!(response, Frame::Integer(0));
+
+    send_command(&mut stream, &["SET", "k", "v"]).await;
+    let response = send_command(&mut stream, &["EXPIRE", "k", "10"]).await;
+    assert_eq!(response, Frame::Integer(1));
+    let response = send_command(&mut stream, &["TTL", "k"]).await;
+    assert_eq!(response, Frame::Integer(10));
+
+    let response = send_command(&mut stream, &["PEXPIRE", "k", "50"]).await;
+    assert_eq!(response, Frame::Integer(1));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let response = send_command(&mut stream, &["GET", "k"]).await;
+    assert_eq!(response, Frame::Null);
+}
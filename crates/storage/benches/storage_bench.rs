@@ -1,8 +1,11 @@
+use std::sync::Arc;
+
 use bytes::Bytes;
 use criterion::{Criterion, black_box, criterion_group, criterion_main};
+use tokio::sync::{broadcast, mpsc};
 
-use stormdb_protocol::SetOptions;
-use stormdb_storage::Db;
+use stormdb_protocol::{Command, Frame, SetOptions};
+use stormdb_storage::{Db, FsyncPolicy, Glob, create_aof, replay_aof};
 
 fn bench_set_get_sequential(c: &mut Criterion) {
     let rt = tokio::runtime::Runtime::new().unwrap();
@@ -12,11 +15,13 @@ fn bench_set_get_sequential(c: &mut Criterion) {
             rt.block_on(async {
                 let db = Db::new();
                 let opts = SetOptions {
-                    expire_ms: None,
+                    expire: None,
                     condition: None,
+                    get: false,
+                    persist: false,
                 };
                 for i in 0..10_000 {
-                    let key = format!("key:{i}");
+                    let key = Bytes::from(format!("key:{i}"));
                     let value = Bytes::from(format!("value:{i}"));
                     db.set(key.clone(), value, &opts).unwrap();
                     black_box(db.get(&key));
@@ -34,7 +39,7 @@ fn bench_incr_sequential(c: &mut Criterion) {
             rt.block_on(async {
                 let db = Db::new();
                 for _ in 0..10_000 {
-                    black_box(db.incr("counter").unwrap());
+                    black_box(db.incr(b"counter").unwrap());
                 }
             });
         })
@@ -54,7 +59,7 @@ fn bench_incr_concurrent(c: &mut Criterion) {
                     let db = db.clone();
                     handles.push(tokio::spawn(async move {
                         for _ in 0..2_500 {
-                            black_box(db.incr("counter").unwrap());
+                            black_box(db.incr(b"counter").unwrap());
                         }
                     }));
                 }
@@ -75,22 +80,159 @@ fn bench_list_operations(c: &mut Criterion) {
             rt.block_on(async {
                 let db = Db::new();
                 for i in 0..1_000 {
-                    db.rpush("list", &[Bytes::from(format!("item:{i}"))])
+                    db.rpush(b"list", &[Bytes::from(format!("item:{i}"))])
                         .unwrap();
                 }
                 for _ in 0..1_000 {
-                    black_box(db.lpop("list", None).unwrap());
+                    black_box(db.lpop(b"list", None).unwrap());
                 }
             });
         })
     });
 }
 
+/// Fan-out de um RPUSH grande para AOF + replicação: clonando o comando
+/// para cada destino vs. compartilhando um `Arc<Command>`.
+fn bench_write_fanout(c: &mut Criterion) {
+    let values: Vec<Bytes> = (0..10_000)
+        .map(|i| Bytes::from(format!("item:{i}")))
+        .collect();
+    let cmd = Command::RPush {
+        key: "list".into(),
+        values,
+    };
+
+    c.bench_function("fanout_rpush_10k_clone", |b| {
+        let (aof_tx, mut aof_rx) = mpsc::channel::<Command>(1);
+        let (repl_tx, mut repl_rx) = broadcast::channel::<Command>(1);
+        b.iter(|| {
+            aof_tx.try_send(cmd.clone()).unwrap();
+            repl_tx.send(cmd.clone()).unwrap();
+            black_box(aof_rx.try_recv().unwrap());
+            black_box(repl_rx.try_recv().unwrap());
+        })
+    });
+
+    c.bench_function("fanout_rpush_10k_arc", |b| {
+        let (aof_tx, mut aof_rx) = mpsc::channel::<Arc<Command>>(1);
+        let (repl_tx, mut repl_rx) = broadcast::channel::<Arc<Command>>(1);
+        b.iter(|| {
+            // O clone aqui faz o papel do comando recém-parseado que é
+            // movido para o Arc (o lado `clone` reaproveita `cmd` direto).
+            let cmd = Arc::new(cmd.clone());
+            aof_tx.try_send(cmd.clone()).unwrap();
+            repl_tx.send(cmd).unwrap();
+            black_box(aof_rx.try_recv().unwrap());
+            black_box(repl_rx.try_recv().unwrap());
+        })
+    });
+}
+
+/// Replay de um AOF com 100k SETs de 1KB: mede o caminho de aplicação que
+/// consome o comando parseado (chave e valor movidos para o Db).
+fn bench_aof_replay(c: &mut Criterion) {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("bench.aof");
+
+    let value = "x".repeat(1024);
+    let mut buf = bytes::BytesMut::new();
+    for i in 0..100_000 {
+        Frame::array_from_strs(&["SET", &format!("key:{i}"), &value]).encode(&mut buf);
+    }
+    std::fs::write(&path, &buf).unwrap();
+
+    let mut group = c.benchmark_group("aof_replay");
+    group.sample_size(10);
+    group.bench_function("set_100k_1kb", |b| {
+        b.iter(|| {
+            rt.block_on(async {
+                let db = Db::new();
+                black_box(replay_aof(&path, &db).await.unwrap());
+            });
+        })
+    });
+    group.finish();
+}
+
+/// 100k SETs passando pelo writer do AOF (EverySec): um `write_all` por
+/// comando vs. lotes drenados do canal com `try_recv`.
+fn bench_aof_write(c: &mut Criterion) {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("write.aof");
+    let opts = SetOptions {
+        expire: None,
+        condition: None,
+        get: false,
+        persist: false,
+    };
+    let cmds: Vec<Arc<Command>> = (0..100_000)
+        .map(|i| {
+            Arc::new(Command::Set {
+                key: Bytes::from(format!("key:{i}")),
+                value: Bytes::from(format!("value:{i}")),
+                options: opts.clone(),
+            })
+        })
+        .collect();
+
+    let mut group = c.benchmark_group("aof_write");
+    group.sample_size(10);
+    for (name, limit) in [("set_100k_unbatched", 1), ("set_100k_batched", 512)] {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                rt.block_on(async {
+                    let _ = std::fs::remove_file(&path);
+                    let (tx, writer) = create_aof(path.clone(), FsyncPolicy::EverySec, 10_000);
+                    let handle = tokio::spawn(writer.batch_limit(limit).run());
+                    for cmd in &cmds {
+                        tx.send(cmd.clone()).await.unwrap();
+                    }
+                    drop(tx);
+                    handle.await.unwrap().unwrap();
+                });
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Padrão contra 100k chaves: compilado uma vez por chamada (como num
+/// KEYS/SCAN) vs. recompilado a cada chave.
+fn bench_glob_match(c: &mut Criterion) {
+    let keys: Vec<Vec<u8>> = (0..100_000)
+        .map(|i| format!("user:{i}:session").into_bytes())
+        .collect();
+    let pattern = b"user:*[0-5]:sess*";
+
+    c.bench_function("glob_100k_keys_compiled_once", |b| {
+        b.iter(|| {
+            let glob = Glob::new(pattern);
+            black_box(keys.iter().filter(|k| glob.matches(k)).count())
+        })
+    });
+
+    c.bench_function("glob_100k_keys_compiled_per_key", |b| {
+        b.iter(|| {
+            black_box(
+                keys.iter()
+                    .filter(|k| Glob::new(pattern).matches(k))
+                    .count(),
+            )
+        })
+    });
+}
+
 criterion_group!(
     benches,
     bench_set_get_sequential,
     bench_incr_sequential,
     bench_incr_concurrent,
     bench_list_operations,
+    bench_write_fanout,
+    bench_aof_replay,
+    bench_aof_write,
+    bench_glob_match,
 );
 criterion_main!(benches);
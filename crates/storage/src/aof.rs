@@ -1,11 +1,14 @@
 use std::io::Cursor;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 
 use bytes::BytesMut;
 use tokio::fs::{File, OpenOptions};
 use tokio::io::{AsyncReadExt, AsyncWriteExt, BufWriter};
-use tokio::sync::mpsc;
-use tokio::time::{Duration, interval};
+use tokio::sync::mpsc::error::{SendError, TrySendError};
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::{Duration, Instant, interval};
 use tracing::{debug, info, warn};
 
 use stormdb_protocol::{Command, Frame};
@@ -24,16 +27,197 @@ pub enum FsyncPolicy {
     No,
 }
 
+/// Mensagem enviada ao AofWriter.
+#[derive(Debug)]
+pub enum AofMessage {
+    /// Comando de escrita a ser persistido (compartilhado com a replicação).
+    Command(Arc<Command>),
+    /// Pede flush + fsync de tudo que já foi recebido; responde ao terminar.
+    Sync(oneshot::Sender<std::io::Result<()>>),
+}
+
+/// Intervalo mínimo entre warnings de canal do AOF cheio.
+const FULL_WARNING_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Limite padrão acima do qual um fsync é considerado lento.
+pub const DEFAULT_SLOW_FSYNC_THRESHOLD: Duration = Duration::from_millis(100);
+
+/// Máximo padrão de mensagens drenadas do canal por acordada do writer.
+pub const DEFAULT_BATCH_LIMIT: usize = 512;
+
+/// Métricas de fsync compartilhadas entre o writer e o sender (para o INFO).
+#[derive(Debug, Default)]
+struct AofStats {
+    fsync_slow_count: AtomicU64,
+    last_fsync_usec: AtomicU64,
+}
+
+/// Lado produtor do canal do AOF.
+///
+/// Política para disco lento: o canal é limitado e, quando enche, `send`
+/// bloqueia até o writer liberar espaço — aplicando backpressure nos clientes
+/// em vez de descartar comandos. Enquanto o canal estiver cheio, um warning é
+/// logado no máximo uma vez por segundo.
+#[derive(Clone)]
+pub struct AofSender {
+    tx: mpsc::Sender<AofMessage>,
+    last_full_warning: Arc<Mutex<Option<Instant>>>,
+    stats: Arc<AofStats>,
+}
+
+impl AofSender {
+    /// Envia um comando para o writer, bloqueando se o canal estiver cheio.
+    pub async fn send(&self, cmd: impl Into<Arc<Command>>) -> Result<(), SendError<Arc<Command>>> {
+        let unwrap = |msg| match msg {
+            AofMessage::Command(cmd) => cmd,
+            AofMessage::Sync(_) => unreachable!(),
+        };
+        match self.tx.try_send(AofMessage::Command(cmd.into())) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(msg)) => {
+                self.warn_full();
+                self.tx
+                    .send(msg)
+                    .await
+                    .map_err(|SendError(msg)| SendError(unwrap(msg)))
+            }
+            Err(TrySendError::Closed(msg)) => Err(SendError(unwrap(msg))),
+        }
+    }
+
+    /// Espera o writer persistir (flush + fsync) tudo o que foi enviado até aqui.
+    pub async fn sync(&self) -> std::io::Result<()> {
+        let closed = || std::io::Error::other("AOF writer encerrado");
+        let (tx, rx) = oneshot::channel();
+        self.tx
+            .send(AofMessage::Sync(tx))
+            .await
+            .map_err(|_| closed())?;
+        rx.await.map_err(|_| closed())?
+    }
+
+    /// Número de comandos no canal aguardando o writer.
+    pub fn pending(&self) -> usize {
+        self.tx.max_capacity() - self.tx.capacity()
+    }
+
+    /// Quantos fsyncs passaram do limite de lentidão.
+    pub fn fsync_slow_count(&self) -> u64 {
+        self.stats.fsync_slow_count.load(Ordering::Relaxed)
+    }
+
+    /// Duração do último fsync, em microssegundos.
+    pub fn last_fsync_usec(&self) -> u64 {
+        self.stats.last_fsync_usec.load(Ordering::Relaxed)
+    }
+
+    fn warn_full(&self) {
+        let mut last = self.last_full_warning.lock().unwrap();
+        let now = Instant::now();
+        if last.is_none_or(|t| now.duration_since(t) >= FULL_WARNING_INTERVAL) {
+            *last = Some(now);
+            warn!(
+                "canal do AOF cheio ({} comandos pendentes): disco lento, aplicando backpressure nas escritas",
+                self.tx.max_capacity()
+            );
+        }
+    }
+}
+
 /// Writer que recebe comandos via channel e faz append no arquivo AOF.
 pub struct AofWriter {
-    rx: mpsc::Receiver<Command>,
+    rx: mpsc::Receiver<AofMessage>,
     path: PathBuf,
     policy: FsyncPolicy,
+    stats: Arc<AofStats>,
+    slow_fsync_threshold: Duration,
+    batch_limit: usize,
 }
 
 impl AofWriter {
-    pub fn new(rx: mpsc::Receiver<Command>, path: PathBuf, policy: FsyncPolicy) -> Self {
-        Self { rx, path, policy }
+    pub fn new(rx: mpsc::Receiver<AofMessage>, path: PathBuf, policy: FsyncPolicy) -> Self {
+        Self {
+            rx,
+            path,
+            policy,
+            stats: Arc::default(),
+            slow_fsync_threshold: DEFAULT_SLOW_FSYNC_THRESHOLD,
+            batch_limit: DEFAULT_BATCH_LIMIT,
+        }
+    }
+
+    /// Define quantas mensagens o writer drena do canal de uma vez antes de
+    /// escrever (mínimo 1, que desliga o agrupamento).
+    pub fn batch_limit(mut self, limit: usize) -> Self {
+        self.batch_limit = limit.max(1);
+        self
+    }
+
+    /// Define a partir de quanto tempo um fsync é logado como lento.
+    pub fn slow_fsync_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_fsync_threshold = threshold;
+        self
+    }
+
+    /// Flush do buffer + fsync, medindo a duração do fsync.
+    async fn fsync(&self, writer: &mut BufWriter<File>) -> std::io::Result<()> {
+        writer.flush().await?;
+
+        let start = Instant::now();
+        writer.get_ref().sync_data().await?;
+        let elapsed = start.elapsed();
+
+        self.stats
+            .last_fsync_usec
+            .store(elapsed.as_micros() as u64, Ordering::Relaxed);
+        if elapsed > self.slow_fsync_threshold {
+            self.stats.fsync_slow_count.fetch_add(1, Ordering::Relaxed);
+            warn!(
+                "fsync do AOF lento: {elapsed:?} (limite {:?})",
+                self.slow_fsync_threshold
+            );
+        }
+        Ok(())
+    }
+
+    /// Drena o canal sem esperar (até `batch_limit` mensagens, contando
+    /// `first`) e grava os comandos codificados num único `write_all`, em vez
+    /// de um por comando. Um `Sync` no meio do lote grava o que veio antes
+    /// dele e faz o fsync na hora, então a ordem e a garantia do SYNC valem
+    /// como sem lote. Com `Always`, o fsync é feito uma vez por lote.
+    async fn write_batch(
+        &mut self,
+        first: AofMessage,
+        writer: &mut BufWriter<File>,
+    ) -> std::io::Result<()> {
+        let mut buf = BytesMut::new();
+        let mut next = Some(first);
+        let mut taken = 0;
+        while let Some(msg) = next {
+            match msg {
+                AofMessage::Command(cmd) => cmd.to_frame().encode(&mut buf),
+                AofMessage::Sync(reply) => {
+                    writer.write_all(&buf).await?;
+                    buf.clear();
+                    let result = self.fsync(writer).await;
+                    let _ = reply.send(result);
+                }
+            }
+            taken += 1;
+            next = if taken < self.batch_limit {
+                self.rx.try_recv().ok()
+            } else {
+                None
+            };
+        }
+
+        if !buf.is_empty() {
+            writer.write_all(&buf).await?;
+            if self.policy == FsyncPolicy::Always {
+                self.fsync(writer).await?;
+            }
+        }
+        Ok(())
     }
 
     /// Loop principal: recebe comandos e escreve no arquivo.
@@ -51,37 +235,44 @@ impl AofWriter {
 
         loop {
             tokio::select! {
-                cmd = self.rx.recv() => {
-                    match cmd {
-                        Some(cmd) => {
-                            let frame = cmd.to_frame();
-                            let mut buf = BytesMut::new();
-                            frame.encode(&mut buf);
-                            writer.write_all(&buf).await?;
-
-                            if self.policy == FsyncPolicy::Always {
-                                writer.flush().await?;
-                                writer.get_ref().sync_data().await?;
-                            }
-                        }
+                msg = self.rx.recv() => {
+                    match msg {
+                        Some(first) => self.write_batch(first, &mut writer).await?,
                         None => {
                             // Channel fechado — flush final
-                            writer.flush().await?;
-                            writer.get_ref().sync_data().await?;
+                            self.fsync(&mut writer).await?;
                             info!("AOF writer encerrado");
                             return Ok(());
                         }
                     }
                 }
                 _ = tick.tick(), if self.policy == FsyncPolicy::EverySec => {
-                    writer.flush().await?;
-                    writer.get_ref().sync_data().await?;
+                    self.fsync(&mut writer).await?;
                 }
             }
         }
     }
 }
 
+/// Garante que o AOF pode ser aberto para escrita, criando o arquivo se
+/// preciso. O writer só abre o arquivo dentro da sua task: sem esta checagem
+/// no startup, um path inválido deixaria o servidor respondendo OK a escritas
+/// que nunca são persistidas.
+pub async fn check_aof_writable(path: &Path) -> std::io::Result<()> {
+    if path.is_dir() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "o caminho é um diretório",
+        ));
+    }
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?;
+    Ok(())
+}
+
 /// Lê o arquivo AOF e re-executa os comandos no Db para reconstruir estado.
 pub async fn replay_aof(path: &Path, db: &Db) -> std::io::Result<usize> {
     if !path.exists() {
@@ -93,111 +284,197 @@ pub async fn replay_aof(path: &Path, db: &Db) -> std::io::Result<usize> {
     let mut data = Vec::new();
     file.read_to_end(&mut data).await?;
 
-    let mut cursor = Cursor::new(&data[..]);
+    let count = replay_frames(&data, db).await;
+    info!("AOF replay completo: {count} comandos restaurados");
+    Ok(count)
+}
+
+/// Aplica no Db uma sequência de comandos em RESP, parando no primeiro
+/// frame corrompido ou incompleto. Retorna quantos comandos foram aplicados.
+///
+/// Linhas inline (`SET k v\r\n`, ou terminadas só em `\n`) também são
+/// aceitas, para que um operador possa acrescentar comandos ao AOF manualmente.
+pub(crate) async fn replay_frames(data: &[u8], db: &Db) -> usize {
+    let mut cursor = Cursor::new(data);
     let mut count = 0;
 
     loop {
-        if cursor.position() as usize >= data.len() {
+        let pos = cursor.position() as usize;
+        if pos >= data.len() {
             break;
         }
 
-        // Tentar fazer check do frame
-        let check_pos = cursor.position();
-        match Frame::check(&mut cursor) {
-            Ok(()) => {
-                cursor.set_position(check_pos);
-                match Frame::parse(&mut cursor) {
-                    Ok(frame) => match Command::from_frame(frame) {
-                        Ok(cmd) => {
-                            apply_command(&cmd, db).await;
-                            count += 1;
-                        }
+        let frame = if Frame::is_inline(data[pos]) {
+            match Frame::parse_inline(&mut cursor) {
+                Ok(frame) => frame,
+                Err(stormdb_common::ProtocolError::Incomplete) => {
+                    warn!(
+                        "AOF: linha inline sem quebra de linha no final do arquivo, parando replay"
+                    );
+                    break;
+                }
+                Err(e) => {
+                    warn!("AOF: dados corrompidos, parando replay: {e}");
+                    break;
+                }
+            }
+        } else {
+            // O AOF é gerado pelo próprio servidor: sem limite de elementos,
+            // que pode ter sido maior quando o arquivo foi escrito.
+            match Frame::check_with_limit(&mut cursor, usize::MAX) {
+                Ok(()) => {
+                    cursor.set_position(pos as u64);
+                    match Frame::parse(&mut cursor) {
+                        Ok(frame) => frame,
                         Err(e) => {
-                            warn!("AOF: comando inválido ignorado: {e}");
+                            warn!("AOF: frame corrompido, parando replay: {e}");
+                            break;
                         }
-                    },
-                    Err(e) => {
-                        warn!("AOF: frame corrompido, parando replay: {e}");
-                        break;
                     }
                 }
+                Err(stormdb_common::ProtocolError::Incomplete) => {
+                    warn!("AOF: frame incompleto no final do arquivo, parando replay");
+                    break;
+                }
+                Err(e) => {
+                    warn!("AOF: erro no frame, parando replay: {e}");
+                    break;
+                }
             }
-            Err(stormdb_common::ProtocolError::Incomplete) => {
-                warn!("AOF: frame incompleto no final do arquivo, parando replay");
-                break;
+        };
+
+        // Linha em branco
+        if matches!(&frame, Frame::Array(args) if args.is_empty()) {
+            continue;
+        }
+
+        match Command::from_frame(frame) {
+            Ok(cmd) => {
+                apply_command(cmd, db).await;
+                count += 1;
             }
             Err(e) => {
-                warn!("AOF: erro no frame, parando replay: {e}");
-                break;
+                warn!("AOF: comando inválido ignorado: {e}");
             }
         }
     }
 
-    info!("AOF replay completo: {count} comandos restaurados");
-    Ok(count)
+    count
 }
 
-/// Aplica um comando ao Db (replay do AOF).
-async fn apply_command(cmd: &Command, db: &Db) {
+/// Aplica um comando de escrita ao Db: o replay do AOF, do seed e o stream
+/// que a réplica recebe do master passam todos por aqui. Consome o comando
+/// para mover chave e valor para o Db em vez de cloná-los.
+pub async fn apply_command(cmd: Command, db: &Db) {
     match cmd {
         Command::Set {
             key,
             value,
             options,
         } => {
-            let _ = db.set(key.clone(), value.clone(), options);
+            let _ = db.set(key, value, &options);
         }
         Command::Del(keys) => {
-            db.del(keys);
+            db.del(&keys);
+        }
+        Command::Expire { key, ms, condition } => {
+            db.expire(&key, ms, condition);
+        }
+        Command::ExpireAt {
+            key,
+            unix_ms,
+            condition,
+        } => {
+            db.expire_at(&key, unix_ms, condition);
         }
-        Command::Incr(key) => {
-            let _ = db.incr(key);
+        Command::Incr { key, expire } => {
+            let _ = match expire {
+                Some(expiry) => db.incr_with_ttl(&key, expiry),
+                None => db.incr(&key),
+            };
         }
         Command::Decr(key) => {
-            let _ = db.decr(key);
+            let _ = db.decr(&key);
+        }
+        Command::SetRange { key, offset, value } => {
+            let _ = db.setrange(&key, offset, &value);
+        }
+        Command::Append { key, value } => {
+            let _ = db.append(&key, &value);
+        }
+        Command::MSet(pairs) => {
+            let _ = db.mset(pairs);
+        }
+        Command::MSetNx(pairs) => {
+            let _ = db.msetnx(pairs);
         }
         Command::LPush { key, values } => {
-            let _ = db.lpush(key, values);
+            let _ = db.lpush(&key, &values);
         }
         Command::RPush { key, values } => {
-            let _ = db.rpush(key, values);
+            let _ = db.rpush(&key, &values);
+        }
+        Command::RPushCap {
+            key,
+            maxlen,
+            values,
+        } => {
+            let _ = db.rpush_capped(&key, &values, Some(maxlen));
         }
         Command::LPop { key, count } => {
-            let _ = db.lpop(key, *count);
+            let _ = db.lpop(&key, count);
         }
         Command::RPop { key, count } => {
-            let _ = db.rpop(key, *count);
+            let _ = db.rpop(&key, count);
+        }
+        Command::RPopLPush {
+            source,
+            destination,
+        } => {
+            let _ = db.rpoplpush(&source, &destination);
+        }
+        Command::Rename {
+            source,
+            destination,
+        } => {
+            let _ = db.rename(&source, &destination);
         }
-        _ => {
-            debug!("AOF: comando {cmd:?} ignorado no replay (read-only/pubsub)");
+        Command::RenameNx {
+            source,
+            destination,
+        } => {
+            let _ = db.renamenx(&source, &destination);
+        }
+        Command::BitOp { op, dest, keys } => {
+            let _ = db.bitop(op, &dest, &keys);
+        }
+        other => {
+            debug!("comando {other:?} ignorado no replay (read-only/pubsub)");
         }
     }
 }
 
 /// Cria um par (sender, AofWriter) para uso no servidor.
+/// `buffer_size` é o número máximo de comandos pendentes antes do backpressure.
 pub fn create_aof(
     path: PathBuf,
     policy: FsyncPolicy,
     buffer_size: usize,
-) -> (mpsc::Sender<Command>, AofWriter) {
+) -> (AofSender, AofWriter) {
     let (tx, rx) = mpsc::channel(buffer_size);
     let writer = AofWriter::new(rx, path, policy);
-    (tx, writer)
+    let sender = AofSender {
+        tx,
+        last_full_warning: Arc::new(Mutex::new(None)),
+        stats: writer.stats.clone(),
+    };
+    (sender, writer)
 }
 
-/// Determina se um comando deve ser persistido no AOF.
+/// Determina se um comando deve ser persistido no AOF, pela flag `write`
+/// da `COMMAND_TABLE`.
 pub fn is_write_command(cmd: &Command) -> bool {
-    matches!(
-        cmd,
-        Command::Set { .. }
-            | Command::Del(_)
-            | Command::Incr(_)
-            | Command::Decr(_)
-            | Command::LPush { .. }
-            | Command::RPush { .. }
-            | Command::LPop { .. }
-            | Command::RPop { .. }
-    )
+    cmd.spec().is_some_and(|spec| spec.is_write())
 }
 
 #[cfg(test)]
@@ -207,6 +484,33 @@ mod tests {
     use stormdb_protocol::SetOptions;
     use tempfile::tempdir;
 
+    #[tokio::test]
+    async fn batched_writes_keep_order() {
+        let dir = tempdir().unwrap();
+        let aof_path = dir.path().join("batch.aof");
+        let (tx, writer) = create_aof(aof_path.clone(), FsyncPolicy::No, 100);
+
+        // Tudo já está no canal quando o writer acorda: sai em lotes de 4
+        for i in 0..10 {
+            tx.send(Command::RPush {
+                key: "list".into(),
+                values: vec![Bytes::from(i.to_string())],
+            })
+            .await
+            .unwrap();
+        }
+        let writer_handle = tokio::spawn(writer.batch_limit(4).run());
+        tx.sync().await.unwrap();
+
+        let db = Db::new();
+        assert_eq!(replay_aof(&aof_path, &db).await.unwrap(), 10);
+        let expected: Vec<Bytes> = (0..10).map(|i| Bytes::from(i.to_string())).collect();
+        assert_eq!(db.lrange(b"list", 0, -1).unwrap(), expected);
+
+        drop(tx);
+        writer_handle.await.unwrap().unwrap();
+    }
+
     #[tokio::test]
     async fn aof_write_and_replay() {
         let dir = tempdir().unwrap();
@@ -226,18 +530,23 @@ mod tests {
             key: "key1".into(),
             value: Bytes::from("value1"),
             options: SetOptions {
-                expire_ms: None,
+                expire: None,
                 condition: None,
+                get: false,
+                persist: false,
             },
         };
         tx.send(cmd.clone()).await.unwrap();
-        apply_command(&cmd, &db).await;
+        apply_command(cmd, &db).await;
 
         // INCR counter (3 vezes)
         for _ in 0..3 {
-            let cmd = Command::Incr("counter".into());
+            let cmd = Command::Incr {
+                key: "counter".into(),
+                expire: None,
+            };
             tx.send(cmd.clone()).await.unwrap();
-            apply_command(&cmd, &db).await;
+            apply_command(cmd, &db).await;
         }
 
         // RPUSH list a b
@@ -246,17 +555,17 @@ mod tests {
             values: vec![Bytes::from("a"), Bytes::from("b")],
         };
         tx.send(cmd.clone()).await.unwrap();
-        apply_command(&cmd, &db).await;
+        apply_command(cmd, &db).await;
 
         // Drop sender para fechar o writer
         drop(tx);
         writer_handle.await.unwrap();
 
         // Verificar estado original
-        assert_eq!(db.get("key1"), Some(Bytes::from("value1")));
-        assert_eq!(db.get("counter"), Some(Bytes::from("3")));
+        assert_eq!(db.get(b"key1"), Some(Bytes::from("value1")));
+        assert_eq!(db.get(b"counter"), Some(Bytes::from("3")));
         assert_eq!(
-            db.lrange("list", 0, -1).unwrap(),
+            db.lrange(b"list", 0, -1).unwrap(),
             vec![Bytes::from("a"), Bytes::from("b")]
         );
 
@@ -265,14 +574,26 @@ mod tests {
         let count = replay_aof(&aof_path, &db2).await.unwrap();
         assert_eq!(count, 5); // SET + 3x INCR + RPUSH
 
-        assert_eq!(db2.get("key1"), Some(Bytes::from("value1")));
-        assert_eq!(db2.get("counter"), Some(Bytes::from("3")));
+        assert_eq!(db2.get(b"key1"), Some(Bytes::from("value1")));
+        assert_eq!(db2.get(b"counter"), Some(Bytes::from("3")));
         assert_eq!(
-            db2.lrange("list", 0, -1).unwrap(),
+            db2.lrange(b"list", 0, -1).unwrap(),
             vec![Bytes::from("a"), Bytes::from("b")]
         );
     }
 
+    #[tokio::test]
+    async fn check_aof_writable_rejects_bad_paths() {
+        let dir = tempdir().unwrap();
+        assert!(check_aof_writable(dir.path()).await.is_err());
+        let missing_parent = dir.path().join("nope").join("x.aof");
+        assert!(check_aof_writable(&missing_parent).await.is_err());
+
+        let path = dir.path().join("ok.aof");
+        check_aof_writable(&path).await.unwrap();
+        assert!(path.is_file());
+    }
+
     #[tokio::test]
     async fn aof_replay_corrupted() {
         let dir = tempdir().unwrap();
@@ -283,8 +604,10 @@ mod tests {
             key: "key1".into(),
             value: Bytes::from("val"),
             options: SetOptions {
-                expire_ms: None,
+                expire: None,
                 condition: None,
+                get: false,
+                persist: false,
             },
         };
         let frame = cmd.to_frame();
@@ -297,7 +620,105 @@ mod tests {
         let db = Db::new();
         let count = replay_aof(&aof_path, &db).await.unwrap();
         assert_eq!(count, 1); // Apenas o primeiro comando válido
-        assert_eq!(db.get("key1"), Some(Bytes::from("val")));
+        assert_eq!(db.get(b"key1"), Some(Bytes::from("val")));
+    }
+
+    #[tokio::test]
+    async fn aof_replay_mixed_inline() {
+        let dir = tempdir().unwrap();
+        let aof_path = dir.path().join("mixed.aof");
+
+        let mut buf = BytesMut::new();
+        Frame::array_from_strs(&["SET", "key1", "val"]).encode(&mut buf);
+        // Linhas acrescentadas à mão por um operador (o editor pode ter
+        // salvo só com \n)
+        buf.extend_from_slice(b"INCR counter\r\n\nSET greeting \"hello world\"\n");
+        Frame::array_from_strs(&["INCR", "counter"]).encode(&mut buf);
+        // Lixo binário: o replay para aqui
+        buf.extend_from_slice(b"\x00\x01\x02\r\nSET after junk\r\n");
+
+        tokio::fs::write(&aof_path, &buf).await.unwrap();
+
+        let db = Db::new();
+        let count = replay_aof(&aof_path, &db).await.unwrap();
+        assert_eq!(count, 4);
+        assert_eq!(db.get(b"key1"), Some(Bytes::from("val")));
+        assert_eq!(db.get(b"counter"), Some(Bytes::from("2")));
+        assert_eq!(db.get(b"greeting"), Some(Bytes::from("hello world")));
+        assert_eq!(db.get(b"after"), None);
+    }
+
+    #[tokio::test]
+    async fn aof_replay_honors_wall_clock_deadlines() {
+        let now = crate::unix_time_ms();
+        let past = (now - 1000).to_string();
+        let future = (now + 60_000).to_string();
+
+        let mut buf = BytesMut::new();
+        for args in [
+            &["SET", "stale", "v", "PXAT", past.as_str()][..],
+            &["SET", "fresh", "v", "PXAT", future.as_str()],
+            &["SET", "expired-later", "v"],
+            &["PEXPIREAT", "expired-later", past.as_str()],
+            &["INCR", "counter", "PXAT", future.as_str()],
+        ] {
+            Frame::array_from_strs(args).encode(&mut buf);
+        }
+
+        let db = Db::new();
+        assert_eq!(replay_frames(&buf, &db).await, 5);
+        // Deadlines que passaram enquanto o servidor estava parado valem
+        assert_eq!(db.get(b"stale"), None);
+        assert_eq!(db.get(b"expired-later"), None);
+
+        let ttl = db.pttl(b"fresh").unwrap().unwrap();
+        assert!(ttl > Duration::from_secs(59) && ttl <= Duration::from_secs(60));
+        let ttl = db.pttl(b"counter").unwrap().unwrap();
+        assert!(ttl > Duration::from_secs(59) && ttl <= Duration::from_secs(60));
+    }
+
+    #[tokio::test]
+    async fn aof_full_channel_applies_backpressure() {
+        let dir = tempdir().unwrap();
+        let aof_path = dir.path().join("slow.aof");
+
+        // Writer ainda não está rodando: simula um disco parado
+        let (tx, writer) = create_aof(aof_path.clone(), FsyncPolicy::No, 2);
+
+        for i in 0..2 {
+            tx.send(Command::Incr {
+                key: format!("k{i}").into(),
+                expire: None,
+            })
+            .await
+            .unwrap();
+        }
+        assert_eq!(tx.pending(), 2);
+
+        // Canal cheio: o terceiro send bloqueia em vez de descartar
+        let blocked = tokio::time::timeout(
+            Duration::from_millis(100),
+            tx.send(Command::Incr {
+                key: "k2".into(),
+                expire: None,
+            }),
+        )
+        .await;
+        assert!(blocked.is_err());
+
+        // Quando o writer volta a consumir, o envio completa
+        let writer_handle = tokio::spawn(writer.run());
+        tx.send(Command::Incr {
+            key: "k2".into(),
+            expire: None,
+        })
+        .await
+        .unwrap();
+        drop(tx);
+        writer_handle.await.unwrap().unwrap();
+
+        let db = Db::new();
+        assert_eq!(replay_aof(&aof_path, &db).await.unwrap(), 3);
     }
 
     #[tokio::test]
@@ -309,18 +730,48 @@ mod tests {
         assert_eq!(count, 0);
     }
 
+    #[tokio::test]
+    async fn aof_counts_slow_fsyncs() {
+        let dir = tempdir().unwrap();
+        let aof_path = dir.path().join("fsync.aof");
+
+        // Limite zero: todo fsync conta como lento. Sem lote, o INCR e o
+        // SYNC fazem um fsync cada.
+        let (tx, writer) = create_aof(aof_path, FsyncPolicy::Always, 10);
+        let writer = writer.slow_fsync_threshold(Duration::ZERO).batch_limit(1);
+        let writer_handle = tokio::spawn(writer.run());
+
+        assert_eq!(tx.fsync_slow_count(), 0);
+        tx.send(Command::Incr {
+            key: "k".into(),
+            expire: None,
+        })
+        .await
+        .unwrap();
+        tx.sync().await.unwrap();
+        assert!(tx.fsync_slow_count() >= 2);
+
+        drop(tx);
+        writer_handle.await.unwrap().unwrap();
+    }
+
     #[test]
     fn is_write_command_check() {
         assert!(is_write_command(&Command::Set {
             key: "k".into(),
             value: Bytes::from("v"),
             options: SetOptions {
-                expire_ms: None,
+                expire: None,
                 condition: None,
+                get: false,
+                persist: false,
             },
         }));
         assert!(is_write_command(&Command::Del(vec!["k".into()])));
-        assert!(is_write_command(&Command::Incr("k".into())));
+        assert!(is_write_command(&Command::Incr {
+            key: "k".into(),
+            expire: None,
+        }));
         assert!(!is_write_command(&Command::Ping(None)));
         assert!(!is_write_command(&Command::Get("k".into())));
     }
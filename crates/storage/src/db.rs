@@ -1,29 +1,277 @@
-use std::collections::BTreeSet;
-use std::sync::Arc;
+use std::collections::{BTreeSet, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 use bytes::Bytes;
 use dashmap::DashMap;
-use tokio::sync::{Mutex, Notify, broadcast};
+use dashmap::mapref::one::{Ref, RefMut};
+use tokio::sync::{Notify, OwnedRwLockReadGuard, broadcast};
 use tokio::time::{Duration, Instant};
 use tracing::debug;
 
-use stormdb_common::StorageError;
-use stormdb_protocol::{SetCondition, SetOptions};
+use stormdb_common::{MAX_FRAME_SIZE, StorageError};
+use stormdb_protocol::{BitOp, Command, ExpireCondition, Expiry, SetCondition, SetOptions};
 
-use crate::entry::{Entry, Value};
-use crate::pubsub::PubSub;
+use crate::entry::{Entry, Value, instant_at_unix_ms, unix_ms_at, unix_time_ms};
+use crate::glob::Glob;
+use crate::notify::{KeyEvent, NotifyFlags, publish_event};
+use crate::pubsub::{PatternMessage, PubSub};
+use crate::supervisor::supervise;
 
 /// Item no BTreeSet de expiração: (instante, chave).
 /// Ordenado por instante para purga eficiente.
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
-struct ExpiryEntry(Instant, String);
+struct ExpiryEntry(Instant, Bytes);
+
+/// Configuração do engine de dados, fixada na criação do Db.
+#[derive(Debug, Clone)]
+pub struct DbConfig {
+    /// Tamanho máximo de um valor string (`proto-max-bulk-len`). Nunca deve
+    /// passar de `MAX_FRAME_SIZE`, senão o valor não cabe num bulk de resposta.
+    pub max_bulk_len: usize,
+    /// Jitter máximo (ms) somado a cada TTL no SET, para espalhar expirações
+    /// de chaves criadas com o mesmo TTL. Estende levemente os TTLs. 0 = desligado.
+    pub expire_jitter_ms: u64,
+    /// TTL (ms) aplicado a todo SET sem EX/PX nem PERSIST (`default-ttl`).
+    /// Só vale para escritas novas: chaves já existentes não ganham TTL.
+    /// `None` = desligado.
+    pub default_ttl_ms: Option<u64>,
+    /// Máximo de elementos que um único LRANGE pode retornar
+    /// (`max-list-range`); acima disso o cliente deve paginar. `None` = sem limite.
+    pub max_list_range: Option<usize>,
+    /// Classes de notificações de keyspace publicadas via pub/sub
+    /// (`notify-keyspace-events`). Vazio = desligado.
+    pub notify_keyspace_events: NotifyFlags,
+    /// Canal pub/sub que recebe o valor final de cada chave expirada
+    /// (`expired-dump`), para auditoria. `None` = desligado.
+    pub expired_dump: Option<String>,
+    /// Maior string reportada como `embstr` pelo `OBJECT ENCODING`
+    /// (`embstr-size-limit`); acima disso é `raw`.
+    pub embstr_max_len: usize,
+    /// Padrões glob (KEYS, PSUBSCRIBE) comparam letras ASCII sem diferenciar
+    /// maiúsculas (`pattern-case-insensitive`). Buscas exatas de chave e
+    /// canal nunca são afetadas.
+    pub pattern_case_insensitive: bool,
+}
+
+impl Default for DbConfig {
+    fn default() -> Self {
+        Self {
+            max_bulk_len: MAX_FRAME_SIZE,
+            expire_jitter_ms: 0,
+            default_ttl_ms: None,
+            max_list_range: None,
+            notify_keyspace_events: NotifyFlags::default(),
+            expired_dump: None,
+            embstr_max_len: 44,
+            pattern_case_insensitive: false,
+        }
+    }
+}
+
+/// Resultado do `MEMORY STATS`. Todos os tamanhos são aproximados (ver
+/// `Entry::estimated_size`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryStats {
+    pub keys: usize,
+    /// Memória total estimada do keyspace: `dataset_bytes + overhead_bytes`.
+    pub total_bytes: usize,
+    /// Bytes de chaves e valores.
+    pub dataset_bytes: usize,
+    /// Estruturas em volta dos dados (entradas, handles das chaves e dos
+    /// elementos de listas).
+    pub overhead_bytes: usize,
+    /// Maior chave e seu tamanho estimado (chave + entrada).
+    pub largest_key: Option<(Bytes, usize)>,
+}
+
+/// Resultado do `DEBUG OBJECT` para uma chave.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectStats {
+    /// Tipo do valor, como no TYPE (`string`, `list`).
+    pub value_type: &'static str,
+    /// Encoding, o mesmo do `OBJECT ENCODING` (ver `Entry::encoding`).
+    pub encoding: &'static str,
+    /// Bytes de dados do valor (ver `Entry::payload_size`).
+    pub serialized_length: usize,
+    /// Número de elementos, para listas.
+    pub elements: Option<usize>,
+}
+
+/// Permissão para aplicar uma escrita que ainda vai ser propagada ao AOF e
+/// às réplicas (ver `Db::write_permit`). Solta ao ser dropada.
+pub struct WritePermit {
+    _guard: OwnedRwLockReadGuard<()>,
+}
+
+/// Número de chaves de cada tipo, atualizado a cada inserção, remoção ou
+/// troca de tipo. Como o DBSIZE, inclui chaves expiradas ainda não removidas.
+#[derive(Debug, Default)]
+struct TypeCounts {
+    strings: AtomicU64,
+    lists: AtomicU64,
+}
+
+impl TypeCounts {
+    fn counter(&self, value: &Value) -> &AtomicU64 {
+        match value {
+            Value::String(_) => &self.strings,
+            Value::List(_) => &self.lists,
+        }
+    }
+
+    fn add(&self, value: &Value) {
+        self.counter(value).fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn sub(&self, value: &Value) {
+        self.counter(value).fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// O valor de uma chave existente foi trocado de `old` para `new`.
+    fn replace(&self, old: &Value, new: &Value) {
+        if std::mem::discriminant(old) != std::mem::discriminant(new) {
+            self.sub(old);
+            self.add(new);
+        }
+    }
+}
 
 /// Estado compartilhado entre todas as conexões.
 struct SharedState {
-    data: DashMap<String, Entry>,
+    config: DbConfig,
+    data: DashMap<Bytes, Entry>,
+    /// Serializa as escritas de várias chaves com as demais: escritas de uma
+    /// chave o seguram compartilhado (`write_one`) e as de várias chaves,
+    /// exclusivo (`write_many`). Só escritas participam; leituras não o
+    /// pegam. Ordem dos locks: este, depois o shard, depois `expiry`.
+    multi_key: RwLock<()>,
+    /// Barreira entre as escritas propagadas e o snapshot do full resync:
+    /// cada escrita segura um `WritePermit` (compartilhado) da aplicação até
+    /// a propagação, e `snapshot_with` pega o exclusivo. Async, porque o
+    /// permit atravessa o envio ao AOF.
+    propagation: Arc<tokio::sync::RwLock<()>>,
+    /// Índice de TTLs da task de purga: uma entrada por chave com TTL.
+    /// Locks síncronos (este e o do `pubsub`): são usados de dentro das
+    /// operações de escrita, que não são async.
     expiry: Mutex<BTreeSet<ExpiryEntry>>,
     pubsub: Mutex<PubSub>,
     notify_expiry: Notify,
+    /// Se a task de purga remove chaves vencidas (`DEBUG SET-ACTIVE-EXPIRE`).
+    /// Desligada, chaves vencidas só somem quando acessadas.
+    active_expire: AtomicBool,
+    /// Dataset sendo recarregado (DEBUG RELOAD, full resync da réplica):
+    /// o servidor recusa comandos de dados com `-LOADING` enquanto isso.
+    loading: AtomicBool,
+    keyspace_hits: AtomicU64,
+    keyspace_misses: AtomicU64,
+    type_counts: TypeCounts,
+    /// Clientes bloqueados (BRPOPLPUSH) por chave de lista, em ordem de
+    /// chegada. Cada um tem seu próprio `Notify`: um push acorda só tantos
+    /// clientes quantos elementos entraram, do mais antigo para o mais novo.
+    list_waiters: DashMap<Bytes, VecDeque<Arc<Notify>>>,
+    /// Força um pânico na próxima iteração da task de purga (testes do supervisor).
+    #[cfg(test)]
+    fail_purge: std::sync::atomic::AtomicBool,
+}
+
+impl SharedState {
+    /// Lock de uma escrita de uma chave só. Não pode ser pego de novo pela
+    /// mesma thread: métodos públicos o pegam e chamam os `_inner`.
+    fn write_one(&self) -> RwLockReadGuard<'_, ()> {
+        self.multi_key.read().unwrap()
+    }
+
+    /// Lock de uma escrita de várias chaves: nenhuma outra escrita roda até
+    /// ele ser solto, então a checagem e as escritas ficam atômicas.
+    fn write_many(&self) -> RwLockWriteGuard<'_, ()> {
+        self.multi_key.write().unwrap()
+    }
+
+    /// `remove_if` do DashMap que mantém as contagens por tipo. Toda remoção
+    /// de chave deve passar por aqui.
+    fn remove_if(&self, key: &[u8], f: impl FnOnce(&Entry) -> bool) -> bool {
+        self.take_if(key, f).is_some()
+    }
+
+    /// Como `remove_if`, devolvendo a entrada removida.
+    fn take_if(&self, key: &[u8], f: impl FnOnce(&Entry) -> bool) -> Option<Entry> {
+        let (key, entry) = self.data.remove_if(key, |_, e| f(e))?;
+        self.type_counts.sub(&entry.value);
+        self.reindex_expiry(&key, entry.expires_at, None);
+        Some(entry)
+    }
+
+    /// Atualiza o índice de expiração quando o TTL de `key` muda de `old`
+    /// para `new`. Quem define um TTL chama com o lock do shard da chave
+    /// retido, para a purga nunca ver o dado sem a entrada no índice (ordem
+    /// dos locks: shard, depois `expiry`).
+    fn reindex_expiry(&self, key: &Bytes, old: Option<Instant>, new: Option<Instant>) {
+        if old == new {
+            return;
+        }
+        let mut expiry = self.expiry.lock().unwrap();
+        if let Some(at) = old {
+            expiry.remove(&ExpiryEntry(at, key.clone()));
+        }
+        if let Some(at) = new {
+            expiry.insert(ExpiryEntry(at, key.clone()));
+        }
+        drop(expiry);
+        if new.is_some() {
+            self.notify_expiry.notify_one();
+        }
+    }
+
+    /// Remove a chave se o TTL já passou, emitindo o evento `expired` (e o
+    /// valor final no canal do `expired-dump`).
+    fn remove_expired(&self, key: &[u8]) -> bool {
+        let Some(entry) = self.take_if(key, Entry::is_expired) else {
+            return false;
+        };
+        if let Some(channel) = &self.config.expired_dump {
+            self.dump_expired(channel, key, entry.value);
+        }
+        self.notify(KeyEvent::Expired, key);
+        true
+    }
+
+    /// Publica em `channel` o comando RESP que recria a chave expirada (sem
+    /// TTL): `SET key valor PERSIST` ou `RPUSH key elementos...`. Pode ser
+    /// lido por um subscriber ou reaplicado direto num servidor — o PERSIST
+    /// impede que um `--default-ttl` de lá dê à chave um TTL que ela não tinha.
+    fn dump_expired(&self, channel: &str, key: &[u8], value: Value) {
+        let key = Bytes::copy_from_slice(key);
+        let cmd = match value {
+            Value::String(value) => Command::Set {
+                key,
+                value,
+                options: SetOptions {
+                    expire: None,
+                    condition: None,
+                    get: false,
+                    persist: true,
+                },
+            },
+            Value::List(list) => Command::RPush {
+                key,
+                values: list.into(),
+            },
+        };
+        let mut buf = bytes::BytesMut::new();
+        cmd.to_frame().encode(&mut buf);
+        self.pubsub.lock().unwrap().publish(channel, buf.freeze());
+    }
+
+    /// Publica uma notificação de keyspace, se a classe do evento estiver
+    /// habilitada. Não deve ser chamada segurando um lock do keyspace.
+    fn notify(&self, event: KeyEvent, key: &[u8]) {
+        let flags = self.config.notify_keyspace_events;
+        if event.enabled(flags) {
+            let mut pubsub = self.pubsub.lock().unwrap();
+            publish_event(&mut pubsub, flags, event, key);
+        }
+    }
 }
 
 /// Handle para o banco de dados in-memory.
@@ -34,19 +282,36 @@ pub struct Db {
 
 impl Db {
     pub fn new() -> Self {
+        Self::with_config(DbConfig::default())
+    }
+
+    pub fn with_config(config: DbConfig) -> Self {
         let db = Db {
             shared: Arc::new(SharedState {
+                pubsub: Mutex::new(PubSub::with_case_insensitive_patterns(
+                    config.pattern_case_insensitive,
+                )),
+                config,
                 data: DashMap::new(),
+                multi_key: RwLock::new(()),
+                propagation: Arc::new(tokio::sync::RwLock::new(())),
                 expiry: Mutex::new(BTreeSet::new()),
-                pubsub: Mutex::new(PubSub::new()),
                 notify_expiry: Notify::new(),
+                active_expire: AtomicBool::new(true),
+                loading: AtomicBool::new(false),
+                keyspace_hits: AtomicU64::new(0),
+                keyspace_misses: AtomicU64::new(0),
+                type_counts: TypeCounts::default(),
+                list_waiters: DashMap::new(),
+                #[cfg(test)]
+                fail_purge: std::sync::atomic::AtomicBool::new(false),
             }),
         };
 
-        // Spawn background task para purgar keys expiradas
+        // Spawn background task (supervisionada) para purgar keys expiradas
         let shared = db.shared.clone();
-        tokio::spawn(async move {
-            purge_expired_keys(shared).await;
+        supervise("purge_expired_keys", move || {
+            purge_expired_keys(shared.clone())
         });
 
         db
@@ -54,192 +319,890 @@ impl Db {
 
     // --- String operations ---
 
-    pub fn get(&self, key: &str) -> Option<Bytes> {
-        let entry = self.shared.data.get(key)?;
-        if entry.is_expired() {
-            drop(entry);
-            self.shared.data.remove(key);
-            return None;
-        }
+    /// Busca uma chave para um comando de leitura: remove-a se estiver
+    /// expirada e contabiliza `keyspace_hits`/`keyspace_misses`.
+    fn lookup_read(&self, key: &[u8]) -> Option<Ref<'_, Bytes, Entry>> {
+        let entry = match self.shared.data.get(key) {
+            Some(entry) if entry.is_expired() => {
+                drop(entry);
+                self.shared.remove_expired(key);
+                None
+            }
+            other => other,
+        };
+
+        let counter = if entry.is_some() {
+            &self.shared.keyspace_hits
+        } else {
+            &self.shared.keyspace_misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+
+        entry
+    }
+
+    pub fn get(&self, key: &[u8]) -> Option<Bytes> {
+        let entry = self.lookup_read(key)?;
         match &entry.value {
             Value::String(data) => Some(data.clone()),
             Value::List(_) => None,
         }
     }
 
+    /// MGET: o valor de cada chave, na mesma ordem. Chaves ausentes ou que
+    /// não são string dão `None`, como no Redis (MGET nunca dá WRONGTYPE).
+    pub fn mget(&self, keys: &[Bytes]) -> Vec<Option<Bytes>> {
+        keys.iter().map(|key| self.get(key)).collect()
+    }
+
+    /// SET. Retorna `false` se a condição NX/XX impediu a escrita.
     pub fn set(
         &self,
-        key: String,
+        key: Bytes,
         value: Bytes,
         options: &SetOptions,
     ) -> Result<bool, StorageError> {
-        let expires_at = options
-            .expire_ms
-            .map(|ms| Instant::now() + Duration::from_millis(ms));
+        let _write = self.shared.write_one();
+        self.set_inner(key, value, options, false)
+            .map(|(applied, _)| applied)
+    }
+
+    /// SET com a opção GET: retorna o valor anterior (`None` se não existia),
+    /// mesmo quando NX/XX impede a escrita. Falha com `WrongType` se a chave
+    /// guarda algo que não é string — nesse caso nada é escrito.
+    pub fn set_get(
+        &self,
+        key: Bytes,
+        value: Bytes,
+        options: &SetOptions,
+    ) -> Result<Option<Bytes>, StorageError> {
+        let _write = self.shared.write_one();
+        self.set_inner(key, value, options, true)
+            .map(|(_, old)| old)
+    }
+
+    fn set_inner(
+        &self,
+        key: Bytes,
+        value: Bytes,
+        options: &SetOptions,
+        read_old: bool,
+    ) -> Result<(bool, Option<Bytes>), StorageError> {
+        self.check_bulk_len(value.len())?;
+        let expires_at = self.set_deadline(options);
+
+        // O lock do shard fica retido entre a leitura do valor atual e a
+        // escrita, então NX/XX e GET enxergam o mesmo estado.
+        let slot = self.shared.data.entry(key);
+        let current = match &slot {
+            dashmap::Entry::Occupied(e) if !e.get().is_expired() => Some(&e.get().value),
+            _ => None,
+        };
+        let exists = current.is_some();
+        let old = match current {
+            Some(Value::String(s)) if read_old => Some(s.clone()),
+            Some(_) if read_old => return Err(StorageError::WrongType),
+            _ => None,
+        };
 
         // Verificar condição NX/XX
-        if let Some(ref cond) = options.condition {
-            let exists = self.shared.data.contains_key(&key);
-            match cond {
-                SetCondition::Nx if exists => return Ok(false),
-                SetCondition::Xx if !exists => return Ok(false),
-                _ => {}
+        match options.condition {
+            Some(SetCondition::Nx) if exists => return Ok((false, old)),
+            Some(SetCondition::Xx) if !exists => return Ok((false, old)),
+            _ => {}
+        }
+
+        let key = slot.key().clone();
+        let new = Entry::new(Value::String(value), expires_at);
+        match slot {
+            dashmap::Entry::Occupied(mut e) => {
+                let old = e.insert(new);
+                self.shared.type_counts.replace(&old.value, &e.get().value);
+                self.shared.reindex_expiry(&key, old.expires_at, expires_at);
+            }
+            dashmap::Entry::Vacant(e) => {
+                self.shared.type_counts.add(&new.value);
+                self.shared.reindex_expiry(&key, None, expires_at);
+                e.insert(new);
             }
         }
 
-        // Remover entrada expirada se existir
-        if let Some(entry) = self.shared.data.get(&key)
-            && entry.is_expired()
-        {
-            drop(entry);
-            self.shared.data.remove(&key);
+        self.shared.notify(KeyEvent::Set, &key);
+        if expires_at.is_some() {
+            self.shared.notify(KeyEvent::Expire, &key);
         }
 
-        let entry = Entry::new(Value::String(value), expires_at);
-        self.shared.data.insert(key.clone(), entry);
+        Ok((true, old))
+    }
+
+    /// MSET: grava os pares na ordem recebida, então uma chave repetida
+    /// fica com o último valor. Os tamanhos são validados antes, para um
+    /// valor grande demais não deixar metade dos pares gravada, e nenhuma
+    /// outra escrita roda no meio (`write_many`).
+    ///
+    /// Cada chave recebe o próprio TTL (o `--default-ttl`, com jitter), e o
+    /// retorno traz, na ordem dos pares, as opções absolutas com que cada uma
+    /// foi gravada (ver `absolute_set_options`): é o que vai para o AOF e as
+    /// réplicas, como um `SET ... PXAT`/`PERSIST` por chave.
+    pub fn mset(&self, pairs: Vec<(Bytes, Bytes)>) -> Result<Vec<SetOptions>, StorageError> {
+        let _write = self.shared.write_many();
+        self.mset_inner(pairs)
+    }
 
-        if expires_at.is_some() {
-            let shared = self.shared.clone();
-            let key_clone = key;
-            tokio::spawn(async move {
-                let mut expiry = shared.expiry.lock().await;
-                expiry.insert(ExpiryEntry(expires_at.unwrap(), key_clone));
-                drop(expiry);
-                shared.notify_expiry.notify_one();
-            });
+    /// MSETNX: só grava se nenhuma das chaves existe. Retorna as opções de
+    /// cada chave, como o `mset`, ou `None` se nada foi gravado.
+    ///
+    /// A checagem e as escritas acontecem sob o mesmo `write_many`: nenhuma
+    /// chave pode ser criada por outro cliente entre uma e outra.
+    pub fn msetnx(
+        &self,
+        pairs: Vec<(Bytes, Bytes)>,
+    ) -> Result<Option<Vec<SetOptions>>, StorageError> {
+        let _write = self.shared.write_many();
+        if self.count_existing(pairs.iter().map(|(key, _)| key), 1) > 0 {
+            return Ok(None);
         }
+        self.mset_inner(pairs).map(Some)
+    }
 
-        Ok(true)
+    fn mset_inner(&self, pairs: Vec<(Bytes, Bytes)>) -> Result<Vec<SetOptions>, StorageError> {
+        for (_, value) in &pairs {
+            self.check_bulk_len(value.len())?;
+        }
+        let plain = SetOptions {
+            expire: None,
+            condition: None,
+            get: false,
+            persist: false,
+        };
+        let mut applied = Vec::with_capacity(pairs.len());
+        for (key, value) in pairs {
+            let options = self
+                .absolute_set_options(&plain)
+                .ok_or(StorageError::InvalidExpireTime)?;
+            self.set_inner(key, value, &options, false)?;
+            applied.push(options);
+        }
+        Ok(applied)
+    }
+
+    /// Deadline de um SET: `EX`/`PX` (mais o jitter), `EXAT`/`PXAT`, ou o
+    /// `--default-ttl` quando não há TTL nem `PERSIST`.
+    fn set_deadline(&self, options: &SetOptions) -> Option<Instant> {
+        let ms = match options.expire {
+            Some(Expiry::PxAt(at)) => return Some(instant_at_unix_ms(at)),
+            Some(Expiry::Px(ms)) => ms,
+            None if options.persist => return None,
+            None => self.shared.config.default_ttl_ms?,
+        };
+        let jitter = self.shared.config.expire_jitter_ms;
+        let ms = if jitter > 0 {
+            ms.saturating_add(fastrand::u64(0..=jitter))
+        } else {
+            ms
+        };
+        Some(Instant::now() + Duration::from_millis(ms))
+    }
+
+    /// Opções de SET com o TTL resolvido para um instante absoluto (`PXAT`),
+    /// ou `PERSIST` se a chave fica sem TTL. É a forma gravada no AOF e
+    /// enviada às réplicas: o replay expira a chave no mesmo horário, mesmo
+    /// horas depois, e não depende do `--default-ttl` de quem lê.
+    ///
+    /// `None` se o deadline passa de i64::MAX ms: o `PXAT` não seria lido de
+    /// volta no replay, então o SET deve falhar antes de gravar.
+    pub fn absolute_set_options(&self, options: &SetOptions) -> Option<SetOptions> {
+        let expire = match self.set_deadline(options) {
+            Some(at) => Some(Expiry::PxAt(unix_ms_at(at)?)),
+            None => None,
+        };
+        Some(SetOptions {
+            expire,
+            persist: expire.is_none(),
+            ..options.clone()
+        })
     }
 
-    pub fn del(&self, keys: &[String]) -> usize {
+    pub fn del(&self, keys: &[Bytes]) -> usize {
+        let _write = self.shared.write_one();
         let mut count = 0;
         for key in keys {
-            if self.shared.data.remove(key).is_some() {
+            if self.shared.remove_if(key, |_| true) {
+                self.shared.notify(KeyEvent::Del, key);
                 count += 1;
             }
         }
         count
     }
 
-    pub fn exists(&self, keys: &[String]) -> usize {
-        let mut count = 0;
-        for key in keys {
-            if let Some(entry) = self.shared.data.get(key)
-                && !entry.is_expired()
-            {
-                count += 1;
+    pub fn exists(&self, keys: &[Bytes]) -> usize {
+        self.count_existing(keys, usize::MAX)
+    }
+
+    /// EXISTSATLEAST: se pelo menos `n` das chaves existem. Para de olhar
+    /// as chaves assim que o limiar é atingido.
+    pub fn exists_at_least(&self, keys: &[Bytes], n: usize) -> bool {
+        self.count_existing(keys, n) >= n
+    }
+
+    /// KEYS: todas as chaves não expiradas que casam com o glob, em ordem
+    /// arbitrária. O padrão é compilado uma vez e percorre o keyspace
+    /// inteiro (O(n)); chaves expiradas ficam para a purga.
+    pub fn keys(&self, pattern: &[u8]) -> Vec<Bytes> {
+        let glob = Glob::new(pattern).case_insensitive(self.shared.config.pattern_case_insensitive);
+        self.shared
+            .data
+            .iter()
+            .filter(|entry| !entry.is_expired() && glob.matches(entry.key()))
+            .map(|entry| entry.key().clone())
+            .collect()
+    }
+
+    /// RENAME: move a entrada inteira (valor e TTL) de `source` para
+    /// `destination`, sobrescrevendo o destino. `KeyNotFound` se a origem
+    /// não existe.
+    pub fn rename(&self, source: &[u8], destination: &[u8]) -> Result<(), StorageError> {
+        self.rename_inner(source, destination, false).map(|_| ())
+    }
+
+    /// RENAMENX: como `rename`, mas não mexe em nada se `destination` já
+    /// existe. Retorna se a chave foi renomeada.
+    pub fn renamenx(&self, source: &[u8], destination: &[u8]) -> Result<bool, StorageError> {
+        self.rename_inner(source, destination, true)
+    }
+
+    /// A entrada sai da origem e entra inteira no destino: um GET no destino
+    /// vê o valor antigo ou o novo, nunca um pela metade. O movimento todo
+    /// roda sob `write_many`, então nenhuma escrita cria o destino ou mexe na
+    /// origem no meio dele: a checagem do RENAMENX continua valendo na
+    /// inserção e não há rollback. Leituras não pegam esse lock, e entre a
+    /// remoção e a inserção podem não ver nenhuma das duas chaves.
+    fn rename_inner(
+        &self,
+        source: &[u8],
+        destination: &[u8],
+        nx: bool,
+    ) -> Result<bool, StorageError> {
+        let _write = self.shared.write_many();
+        if source == destination {
+            // Nada a mover; RENAMENX nunca sobrescreve, nem a si mesma
+            return match self.shared.data.get(source) {
+                Some(entry) if !entry.is_expired() => Ok(!nx),
+                _ => Err(StorageError::KeyNotFound),
+            };
+        }
+        if nx
+            && let Some(entry) = self.shared.data.get(destination)
+            && !entry.is_expired()
+        {
+            return if self.count_existing([&Bytes::copy_from_slice(source)], 1) > 0 {
+                Ok(false)
+            } else {
+                Err(StorageError::KeyNotFound)
+            };
+        }
+
+        let Some(entry) = self.shared.take_if(source, |e| !e.is_expired()) else {
+            self.shared.remove_expired(source);
+            return Err(StorageError::KeyNotFound);
+        };
+        let list_len = match &entry.value {
+            Value::List(list) => list.len(),
+            Value::String(_) => 0,
+        };
+        // O RENAMENX já saiu acima se o destino existia
+        self.put_entry(Bytes::copy_from_slice(destination), entry);
+
+        self.shared.notify(KeyEvent::RenameFrom, source);
+        self.shared.notify(KeyEvent::RenameTo, destination);
+        if list_len > 0 {
+            self.wake_list_waiters(destination, list_len);
+        }
+        Ok(true)
+    }
+
+    /// Grava uma entrada já pronta em `key`, sobrescrevendo o que houver e
+    /// mantendo as contagens por tipo e o índice de expiração (atualizado
+    /// com o lock do shard retido).
+    fn put_entry(&self, key: Bytes, entry: Entry) {
+        let expires_at = entry.expires_at;
+        self.shared.type_counts.add(&entry.value);
+        match self.shared.data.entry(key.clone()) {
+            dashmap::Entry::Occupied(mut slot) => {
+                let old = slot.insert(entry);
+                self.shared.type_counts.sub(&old.value);
+                self.shared.reindex_expiry(&key, old.expires_at, expires_at);
+            }
+            dashmap::Entry::Vacant(slot) => {
+                let _slot = slot.insert(entry);
+                self.shared.reindex_expiry(&key, None, expires_at);
             }
         }
-        count
     }
 
-    pub fn incr(&self, key: &str) -> Result<i64, StorageError> {
+    /// Conta as chaves existentes (não expiradas), parando ao chegar em `limit`.
+    fn count_existing<'k>(&self, keys: impl IntoIterator<Item = &'k Bytes>, limit: usize) -> usize {
+        keys.into_iter()
+            .filter(|key| match self.shared.data.get(*key) {
+                // Expiração lazy, como nas leituras
+                Some(entry) if entry.is_expired() => {
+                    drop(entry);
+                    self.shared.remove_expired(key);
+                    false
+                }
+                Some(_) => true,
+                None => false,
+            })
+            .take(limit)
+            .count()
+    }
+
+    /// EXPIRE/PEXPIRE: define o TTL da chave para `ms` a partir de agora, se
+    /// a condição for satisfeita. `ms <= 0` remove a chave. Retorna `false`
+    /// se a chave não existe ou a condição não foi atendida.
+    pub fn expire(&self, key: &[u8], ms: i64, condition: Option<ExpireCondition>) -> bool {
+        let _write = self.shared.write_one();
+        let Some(mut entry) = self.shared.data.get_mut(key) else {
+            return false;
+        };
+        if entry.is_expired() {
+            drop(entry);
+            self.shared.remove_expired(key);
+            return false;
+        }
+
+        let now = Instant::now();
+        let Some(at) = now.checked_add(Duration::from_millis(ms.max(0) as u64)) else {
+            return false;
+        };
+
+        // Para GT/LT, uma chave sem TTL conta como TTL infinito
+        let applies = match (condition, entry.expires_at) {
+            (None, _) => true,
+            (Some(ExpireCondition::Nx), current) => current.is_none(),
+            (Some(ExpireCondition::Xx), current) => current.is_some(),
+            (Some(ExpireCondition::Gt), Some(current)) => at > current,
+            (Some(ExpireCondition::Gt), None) => false,
+            (Some(ExpireCondition::Lt), Some(current)) => at < current,
+            (Some(ExpireCondition::Lt), None) => true,
+        };
+        if !applies {
+            return false;
+        }
+
+        let old = entry.expires_at.replace(at);
+        self.shared.reindex_expiry(entry.key(), old, Some(at));
+        drop(entry);
+
+        if ms <= 0 {
+            if self.shared.remove_if(key, Entry::is_expired) {
+                self.shared.notify(KeyEvent::Del, key);
+            }
+        } else {
+            self.shared.notify(KeyEvent::Expire, key);
+        }
+        true
+    }
+
+    /// EXPIREAT/PEXPIREAT: como `expire`, com o TTL até `unix_ms` no relógio
+    /// de parede. Um instante no passado remove a chave (e retorna `true`).
+    pub fn expire_at(&self, key: &[u8], unix_ms: i64, condition: Option<ExpireCondition>) -> bool {
+        let ms = unix_ms.saturating_sub(unix_time_ms() as i64);
+        self.expire(key, ms, condition)
+    }
+
+    /// TTL restante da chave: `None` se ela não existe, `Some(None)` se não
+    /// tem TTL. Lê o `expires_at` da entrada, gravado sob o lock do shard
+    /// junto com o valor — não consulta o índice de expiração.
+    pub fn pttl(&self, key: &[u8]) -> Option<Option<Duration>> {
+        let entry = self.lookup_read(key)?;
+        Some(
+            entry
+                .expires_at
+                .map(|at| at.saturating_duration_since(Instant::now())),
+        )
+    }
+
+    pub fn incr(&self, key: &[u8]) -> Result<i64, StorageError> {
         self.incr_by(key, 1)
     }
 
-    pub fn decr(&self, key: &str) -> Result<i64, StorageError> {
+    pub fn decr(&self, key: &[u8]) -> Result<i64, StorageError> {
         self.incr_by(key, -1)
     }
 
-    fn incr_by(&self, key: &str, delta: i64) -> Result<i64, StorageError> {
-        // Usar entry API do DashMap para atomicidade
-        let mut entry = self
-            .shared
-            .data
-            .entry(key.to_string())
-            .or_insert_with(|| Entry::new(Value::String(Bytes::from("0")), None));
+    /// GETRANGE com a semântica do Redis: índices negativos contam do fim,
+    /// o intervalo é cortado ao tamanho da string e chave ausente dá vazio.
+    /// Devolve uma fatia dos bytes originais, sem conversão.
+    pub fn getrange(&self, key: &[u8], start: i64, end: i64) -> Result<Bytes, StorageError> {
+        let Some(entry) = self.lookup_read(key) else {
+            return Ok(Bytes::new());
+        };
+        let data = match &entry.value {
+            Value::String(data) => data,
+            Value::List(_) => return Err(StorageError::WrongType),
+        };
 
-        if entry.is_expired() {
-            entry.value = Value::String(Bytes::from("0"));
-            entry.expires_at = None;
+        let len = data.len() as i64;
+        let start = if start < 0 {
+            (len + start).max(0)
+        } else {
+            start
+        };
+        let end = if end < 0 {
+            (len + end).max(0)
+        } else {
+            end.min(len - 1)
+        };
+        if len == 0 || start > end {
+            return Ok(Bytes::new());
+        }
+        Ok(data.slice(start as usize..=end as usize))
+    }
+
+    /// SETRANGE: sobrescreve a string a partir de `offset`, completando com
+    /// zeros se ela for menor. Retorna o tamanho final. Com `value` vazio
+    /// nada é escrito (e a chave não é criada).
+    pub fn setrange(&self, key: &[u8], offset: usize, value: &[u8]) -> Result<usize, StorageError> {
+        if value.is_empty() {
+            return self.string_len(key);
+        }
+        // Checado antes de criar a chave, para um erro não deixar string vazia
+        let end = offset
+            .checked_add(value.len())
+            .ok_or(StorageError::ValueTooLarge)?;
+        self.check_bulk_len(end)?;
+
+        let _write = self.shared.write_one();
+        let mut entry = self.entry_for_write(key, || Value::String(Bytes::new()));
+        let Value::String(data) = &mut entry.value else {
+            return Err(StorageError::WrongType);
+        };
+        let mut buf = data.to_vec();
+        if buf.len() < end {
+            buf.resize(end, 0);
         }
+        buf[offset..end].copy_from_slice(value);
+        let len = buf.len();
+        *data = Bytes::from(buf);
+        drop(entry);
+
+        self.shared.notify(KeyEvent::SetRange, key);
+        Ok(len)
+    }
+
+    /// APPEND: concatena `value` ao fim da string (criando-a se não existe)
+    /// e retorna o novo tamanho. O TTL é mantido.
+    pub fn append(&self, key: &[u8], value: &[u8]) -> Result<usize, StorageError> {
+        self.check_bulk_len(value.len())?;
+
+        let _write = self.shared.write_one();
+        let mut entry = self.entry_for_write(key, || Value::String(Bytes::new()));
+        let Value::String(data) = &mut entry.value else {
+            return Err(StorageError::WrongType);
+        };
+        let len = data.len() + value.len();
+        self.check_bulk_len(len)?;
+        let mut buf = Vec::with_capacity(len);
+        buf.extend_from_slice(data);
+        buf.extend_from_slice(value);
+        *data = Bytes::from(buf);
+        drop(entry);
+
+        self.shared.notify(KeyEvent::Append, key);
+        Ok(len)
+    }
 
+    /// STRLEN: tamanho em bytes da string (0 se a chave não existe).
+    /// Diferente de `string_len`, conta como leitura.
+    pub fn strlen(&self, key: &[u8]) -> Result<usize, StorageError> {
+        let Some(entry) = self.lookup_read(key) else {
+            return Ok(0);
+        };
         match &entry.value {
+            Value::String(data) => Ok(data.len()),
+            Value::List(_) => Err(StorageError::WrongType),
+        }
+    }
+
+    /// Tamanho da string em `key` (0 se não existe), sem criar a chave nem
+    /// contar como leitura.
+    fn string_len(&self, key: &[u8]) -> Result<usize, StorageError> {
+        match self.shared.data.get(key) {
+            Some(entry) if !entry.is_expired() => match &entry.value {
+                Value::String(data) => Ok(data.len()),
+                Value::List(_) => Err(StorageError::WrongType),
+            },
+            _ => Ok(0),
+        }
+    }
+
+    /// INCR que, só quando cria a chave (ausente ou expirada), define o TTL
+    /// de `expiry`. Incrementos seguintes mantêm o TTL original: é o contador
+    /// de janela fixa de rate limiting, num passo só.
+    pub fn incr_with_ttl(&self, key: &[u8], expiry: Expiry) -> Result<i64, StorageError> {
+        self.incr_by_with_ttl(key, 1, Some(expiry.remaining_ms(unix_time_ms())))
+    }
+
+    fn incr_by(&self, key: &[u8], delta: i64) -> Result<i64, StorageError> {
+        self.incr_by_with_ttl(key, delta, None)
+    }
+
+    fn incr_by_with_ttl(
+        &self,
+        key: &[u8],
+        delta: i64,
+        ttl_ms: Option<u64>,
+    ) -> Result<i64, StorageError> {
+        // Usar entry API do DashMap para atomicidade
+        let _write = self.shared.write_one();
+        let (mut entry, created) =
+            self.entry_for_write_created(key, || Value::String(Bytes::from("0")));
+
+        let new_val = match &entry.value {
             Value::String(data) => {
                 let s = std::str::from_utf8(data).map_err(|_| StorageError::NotAnInteger)?;
                 let n: i64 = s.parse().map_err(|_| StorageError::NotAnInteger)?;
-                let new_val = n.checked_add(delta).ok_or(StorageError::NotAnInteger)?;
-                entry.value = Value::String(Bytes::from(new_val.to_string()));
-                Ok(new_val)
+                n.checked_add(delta).ok_or(StorageError::NotAnInteger)?
             }
-            Value::List(_) => Err(StorageError::WrongType),
+            Value::List(_) => return Err(StorageError::WrongType),
+        };
+        entry.value = Value::String(Bytes::from(new_val.to_string()));
+        let ttl = ttl_ms.filter(|_| created);
+        if let Some(ms) = ttl {
+            let expires_at = Some(Instant::now() + Duration::from_millis(ms));
+            entry.expires_at = expires_at;
+            self.shared.reindex_expiry(entry.key(), None, expires_at);
+        }
+        drop(entry);
+        self.shared.notify(KeyEvent::IncrBy, key);
+        if ttl.is_some() {
+            self.shared.notify(KeyEvent::Expire, key);
+        }
+        Ok(new_val)
+    }
+
+    /// Busca uma chave para escrita, criando-a com `init` se não existir.
+    /// Uma chave expirada é reaproveitada como nova (valor `init`, sem TTL).
+    fn entry_for_write(&self, key: &[u8], init: impl Fn() -> Value) -> RefMut<'_, Bytes, Entry> {
+        self.entry_for_write_created(key, init).0
+    }
+
+    /// Como `entry_for_write`, dizendo também se a chave foi criada agora.
+    fn entry_for_write_created(
+        &self,
+        key: &[u8],
+        init: impl Fn() -> Value,
+    ) -> (RefMut<'_, Bytes, Entry>, bool) {
+        let mut entry = match self.shared.data.entry(Bytes::copy_from_slice(key)) {
+            dashmap::Entry::Occupied(e) => e.into_ref(),
+            dashmap::Entry::Vacant(e) => {
+                let value = init();
+                self.shared.type_counts.add(&value);
+                return (e.insert(Entry::new(value, None)), true);
+            }
+        };
+
+        let expired = entry.is_expired();
+        if expired {
+            let value = init();
+            self.shared.type_counts.replace(&entry.value, &value);
+            entry.value = value;
+            let old = entry.expires_at.take();
+            self.shared.reindex_expiry(entry.key(), old, None);
+        }
+        (entry, expired)
+    }
+
+    /// Garante que um valor string de `len` bytes respeita o `max_bulk_len`.
+    fn check_bulk_len(&self, len: usize) -> Result<(), StorageError> {
+        if len > self.shared.config.max_bulk_len {
+            return Err(StorageError::ValueTooLarge);
+        }
+        Ok(())
+    }
+
+    /// BITOP: aplica a operação byte a byte sobre as strings de origem
+    /// (chaves ausentes contam como string vazia; as mais curtas são
+    /// completadas com zeros) e grava o resultado em `dest`, sem TTL.
+    /// Retorna o tamanho do resultado; se for 0, `dest` é removida.
+    ///
+    /// As leituras das origens e a escrita em `dest` rodam sob o mesmo
+    /// `write_many`: nenhuma escrita numa origem aplica (e propaga) no meio,
+    /// então o replay do BITOP no AOF e nas réplicas chega ao mesmo `dest`.
+    pub fn bitop(&self, op: BitOp, dest: &[u8], keys: &[Bytes]) -> Result<usize, StorageError> {
+        let _write = self.shared.write_many();
+        let mut sources = Vec::with_capacity(keys.len());
+        for key in keys {
+            match self.shared.data.get(key) {
+                Some(entry) if entry.is_expired() => {
+                    drop(entry);
+                    self.shared.remove_expired(key);
+                    sources.push(Bytes::new());
+                }
+                Some(entry) => match &entry.value {
+                    Value::String(s) => sources.push(s.clone()),
+                    Value::List(_) => return Err(StorageError::WrongType),
+                },
+                None => sources.push(Bytes::new()),
+            }
+        }
+
+        let len = sources.iter().map(Bytes::len).max().unwrap_or(0);
+        self.check_bulk_len(len)?;
+
+        let byte_at = |s: &Bytes, i: usize| s.get(i).copied().unwrap_or(0);
+        let result: Vec<u8> = (0..len)
+            .map(|i| match op {
+                BitOp::And => sources.iter().fold(0xff, |acc, s| acc & byte_at(s, i)),
+                BitOp::Or => sources.iter().fold(0, |acc, s| acc | byte_at(s, i)),
+                BitOp::Xor => sources.iter().fold(0, |acc, s| acc ^ byte_at(s, i)),
+                BitOp::Not => !byte_at(&sources[0], i),
+            })
+            .collect();
+
+        if len == 0 {
+            if self.shared.remove_if(dest, |_| true) {
+                self.shared.notify(KeyEvent::Del, dest);
+            }
+        } else {
+            let new = Entry::new(Value::String(Bytes::from(result)), None);
+            let dest = Bytes::copy_from_slice(dest);
+            self.shared.type_counts.add(&new.value);
+            if let Some(old) = self.shared.data.insert(dest.clone(), new) {
+                self.shared.type_counts.sub(&old.value);
+                self.shared.reindex_expiry(&dest, old.expires_at, None);
+            }
+            self.shared.notify(KeyEvent::Set, &dest);
         }
+        Ok(len)
     }
 
     // --- List operations ---
 
-    pub fn lpush(&self, key: &str, values: &[Bytes]) -> Result<usize, StorageError> {
-        let mut entry =
-            self.shared.data.entry(key.to_string()).or_insert_with(|| {
-                Entry::new(Value::List(std::collections::VecDeque::new()), None)
-            });
+    /// Tamanho atual da lista, sem criar a chave. Usado por LPUSH/RPUSH sem
+    /// valores (só possível pela API da lib): nada é escrito, então não pode
+    /// sobrar uma lista vazia no keyspace.
+    fn list_len(&self, key: &[u8]) -> Result<usize, StorageError> {
+        match self.shared.data.get(key) {
+            Some(entry) if !entry.is_expired() => match &entry.value {
+                Value::List(list) => Ok(list.len()),
+                Value::String(_) => Err(StorageError::WrongType),
+            },
+            _ => Ok(0),
+        }
+    }
 
-        if entry.is_expired() {
-            entry.value = Value::List(std::collections::VecDeque::new());
-            entry.expires_at = None;
+    pub fn lpush(&self, key: &[u8], values: &[Bytes]) -> Result<usize, StorageError> {
+        if values.is_empty() {
+            return self.list_len(key);
         }
+        let _write = self.shared.write_one();
+        self.lpush_inner(key, values)
+    }
 
-        match &mut entry.value {
+    fn lpush_inner(&self, key: &[u8], values: &[Bytes]) -> Result<usize, StorageError> {
+        let mut entry = self.entry_for_write(key, || Value::List(VecDeque::new()));
+
+        let len = match &mut entry.value {
             Value::List(list) => {
                 for v in values {
                     list.push_front(v.clone());
                 }
-                Ok(list.len())
+                list.len()
             }
-            Value::String(_) => Err(StorageError::WrongType),
-        }
+            Value::String(_) => return Err(StorageError::WrongType),
+        };
+        drop(entry);
+        self.shared.notify(KeyEvent::LPush, key);
+        self.wake_list_waiters(key, values.len());
+        Ok(len)
     }
 
-    pub fn rpush(&self, key: &str, values: &[Bytes]) -> Result<usize, StorageError> {
-        let mut entry =
-            self.shared.data.entry(key.to_string()).or_insert_with(|| {
-                Entry::new(Value::List(std::collections::VecDeque::new()), None)
-            });
+    pub fn rpush(&self, key: &[u8], values: &[Bytes]) -> Result<usize, StorageError> {
+        self.rpush_capped(key, values, None)
+    }
 
-        if entry.is_expired() {
-            entry.value = Value::List(std::collections::VecDeque::new());
-            entry.expires_at = None;
+    /// RPUSH que, com `maxlen`, descarta os elementos mais antigos até a
+    /// lista ter no máximo `maxlen` (RPUSHCAP). Push e trim acontecem sob o
+    /// mesmo lock, então ninguém vê a lista acima do limite. Retorna o
+    /// tamanho final; com `maxlen` 0 a lista fica vazia e a chave é removida,
+    /// como no LTRIM.
+    pub fn rpush_capped(
+        &self,
+        key: &[u8],
+        values: &[Bytes],
+        maxlen: Option<usize>,
+    ) -> Result<usize, StorageError> {
+        if values.is_empty() {
+            return self.list_len(key);
         }
+        let _write = self.shared.write_one();
+        let mut entry = self.entry_for_write(key, || Value::List(VecDeque::new()));
 
-        match &mut entry.value {
+        let len = match &mut entry.value {
             Value::List(list) => {
                 for v in values {
                     list.push_back(v.clone());
                 }
-                Ok(list.len())
+                if let Some(max) = maxlen
+                    && list.len() > max
+                {
+                    list.drain(..list.len() - max);
+                }
+                list.len()
             }
-            Value::String(_) => Err(StorageError::WrongType),
+            Value::String(_) => return Err(StorageError::WrongType),
+        };
+        drop(entry);
+        self.shared.notify(KeyEvent::RPush, key);
+        if len == 0 {
+            if self
+                .shared
+                .remove_if(key, |e| matches!(&e.value, Value::List(l) if l.is_empty()))
+            {
+                self.shared.notify(KeyEvent::Del, key);
+            }
+            return Ok(0);
         }
+        self.wake_list_waiters(key, values.len());
+        Ok(len)
     }
 
-    pub fn lpop(&self, key: &str, count: Option<usize>) -> Result<Vec<Bytes>, StorageError> {
-        self.list_pop(key, count, true)
-    }
+    /// RPOPLPUSH: move o último elemento de `source` para o início de
+    /// `destination`. `None` se `source` não existe.
+    ///
+    /// Roda sob `write_many`: o tipo de `destination` é validado antes do
+    /// pop e nenhuma escrita pode mudá-lo até o push, então o elemento nunca
+    /// precisa voltar para a origem. Leituras não pegam esse lock e podem
+    /// ver o elemento fora das duas listas por um instante.
+    pub fn rpoplpush(
+        &self,
+        source: &[u8],
+        destination: &[u8],
+    ) -> Result<Option<Bytes>, StorageError> {
+        let _write = self.shared.write_many();
+        if let Some(entry) = self.shared.data.get(destination)
+            && !entry.is_expired()
+            && !matches!(entry.value, Value::List(_))
+        {
+            return Err(StorageError::WrongType);
+        }
 
-    pub fn rpop(&self, key: &str, count: Option<usize>) -> Result<Vec<Bytes>, StorageError> {
-        self.list_pop(key, count, false)
+        let Some(item) = self
+            .list_pop(source, None, false)?
+            .and_then(|mut v| v.pop())
+        else {
+            return Ok(None);
+        };
+        self.lpush_inner(destination, std::slice::from_ref(&item))?;
+        Ok(Some(item))
     }
 
-    fn list_pop(
+    /// BRPOPLPUSH: como `rpoplpush`, mas espera até `timeout` (`None` =
+    /// para sempre) por um elemento em `source`. No timeout nada é movido
+    /// e retorna `None`. Cada tentativa roda com um `WritePermit`, solto
+    /// durante a espera; o da tentativa que moveu volta junto com o
+    /// elemento, para ser segurado até a propagação.
+    pub async fn brpoplpush(
         &self,
-        key: &str,
-        count: Option<usize>,
-        from_left: bool,
-    ) -> Result<Vec<Bytes>, StorageError> {
-        let mut entry = match self.shared.data.get_mut(key) {
-            Some(e) => e,
-            None => return Ok(vec![]),
+        source: &[u8],
+        destination: &[u8],
+        timeout: Option<Duration>,
+    ) -> Result<Option<(Bytes, WritePermit)>, StorageError> {
+        let deadline = timeout.map(|t| Instant::now() + t);
+        let mut waiter = ListWaiter {
+            db: self,
+            key: source,
+            notify: Arc::new(Notify::new()),
+            served: false,
         };
 
-        if entry.is_expired() {
-            drop(entry);
-            self.shared.data.remove(key);
-            return Ok(vec![]);
-        }
+        let mut first = true;
+        loop {
+            // Entra na fila antes de tentar o pop, para não perder um push
+            // que aconteça entre a tentativa e a espera. Quem foi acordado
+            // e perdeu o elemento volta para a frente da fila.
+            waiter.enqueue(first);
+            first = false;
+
+            let permit = self.write_permit().await;
+            match self.rpoplpush(source, destination) {
+                Ok(None) => drop(permit),
+                other => {
+                    waiter.served = true;
+                    return other.map(|item| item.map(|item| (item, permit)));
+                }
+            }
 
-        match &mut entry.value {
-            Value::List(list) => {
+            let notified = waiter.notify.notified();
+            match deadline {
+                Some(deadline) => {
+                    if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                        return Ok(None);
+                    }
+                }
+                None => notified.await,
+            }
+        }
+    }
+
+    /// Acorda, em ordem de chegada, até `count` clientes bloqueados
+    /// esperando elementos em `key`. Os acordados saem da fila.
+    fn wake_list_waiters(&self, key: &[u8], count: usize) {
+        let Some(mut queue) = self.shared.list_waiters.get_mut(key) else {
+            return;
+        };
+        let count = count.min(queue.len());
+        for notify in queue.drain(..count) {
+            notify.notify_one();
+        }
+        drop(queue);
+        self.shared
+            .list_waiters
+            .remove_if(key, |_, queue| queue.is_empty());
+    }
+
+    /// LPOP. `None` se a chave não existe; senão os elementos removidos
+    /// (vazio quando `count` é 0).
+    pub fn lpop(
+        &self,
+        key: &[u8],
+        count: Option<usize>,
+    ) -> Result<Option<Vec<Bytes>>, StorageError> {
+        let _write = self.shared.write_one();
+        self.list_pop(key, count, true)
+    }
+
+    /// RPOP. Mesma semântica de retorno do `lpop`.
+    pub fn rpop(
+        &self,
+        key: &[u8],
+        count: Option<usize>,
+    ) -> Result<Option<Vec<Bytes>>, StorageError> {
+        let _write = self.shared.write_one();
+        self.list_pop(key, count, false)
+    }
+
+    fn list_pop(
+        &self,
+        key: &[u8],
+        count: Option<usize>,
+        from_left: bool,
+    ) -> Result<Option<Vec<Bytes>>, StorageError> {
+        let mut entry = match self.shared.data.get_mut(key) {
+            Some(e) => e,
+            None => return Ok(None),
+        };
+
+        if entry.is_expired() {
+            drop(entry);
+            self.shared.remove_expired(key);
+            return Ok(None);
+        }
+
+        match &mut entry.value {
+            Value::List(list) => {
                 let n = count.unwrap_or(1).min(list.len());
                 let mut result = Vec::with_capacity(n);
                 for _ in 0..n {
@@ -252,29 +1215,38 @@ impl Db {
                         result.push(v);
                     }
                 }
-                // Limpar chave se lista ficou vazia
-                if list.is_empty() {
-                    drop(entry);
-                    self.shared.data.remove(key);
+                let emptied = list.is_empty();
+                drop(entry);
+                if !result.is_empty() {
+                    let event = if from_left {
+                        KeyEvent::LPop
+                    } else {
+                        KeyEvent::RPop
+                    };
+                    self.shared.notify(event, key);
+                }
+                // Limpar chave se lista ficou vazia. A checagem é refeita sob
+                // o lock do shard: um push concorrente entre o drop e a
+                // remoção não pode ser apagado.
+                if emptied
+                    && self
+                        .shared
+                        .remove_if(key, |e| matches!(&e.value, Value::List(l) if l.is_empty()))
+                {
+                    self.shared.notify(KeyEvent::Del, key);
                 }
-                Ok(result)
+                Ok(Some(result))
             }
             Value::String(_) => Err(StorageError::WrongType),
         }
     }
 
-    pub fn lrange(&self, key: &str, start: i64, stop: i64) -> Result<Vec<Bytes>, StorageError> {
-        let entry = match self.shared.data.get(key) {
+    pub fn lrange(&self, key: &[u8], start: i64, stop: i64) -> Result<Vec<Bytes>, StorageError> {
+        let entry = match self.lookup_read(key) {
             Some(e) => e,
             None => return Ok(vec![]),
         };
 
-        if entry.is_expired() {
-            drop(entry);
-            self.shared.data.remove(key);
-            return Ok(vec![]);
-        }
-
         match &entry.value {
             Value::List(list) => {
                 let len = list.len() as i64;
@@ -293,6 +1265,12 @@ impl Db {
                 if s > e || s >= list.len() {
                     return Ok(vec![]);
                 }
+                // Checado antes de montar a resposta
+                if let Some(max) = self.shared.config.max_list_range
+                    && e - s + 1 > max
+                {
+                    return Err(StorageError::RangeTooLarge(max));
+                }
 
                 Ok(list.range(s..=e).cloned().collect())
             }
@@ -303,278 +1281,1787 @@ impl Db {
     // --- Pub/Sub ---
 
     pub async fn publish(&self, channel: &str, message: Bytes) -> usize {
-        let pubsub = self.shared.pubsub.lock().await;
+        let mut pubsub = self.shared.pubsub.lock().unwrap();
         pubsub.publish(channel, message)
     }
 
-    pub async fn subscribe(&self, channel: &str) -> broadcast::Receiver<Bytes> {
-        let mut pubsub = self.shared.pubsub.lock().await;
-        pubsub.subscribe(channel)
-    }
+    pub async fn subscribe(&self, channel: &str) -> broadcast::Receiver<Bytes> {
+        let mut pubsub = self.shared.pubsub.lock().unwrap();
+        pubsub.subscribe(channel)
+    }
+
+    pub async fn unsubscribe(&self, channel: &str) {
+        let mut pubsub = self.shared.pubsub.lock().unwrap();
+        pubsub.cleanup_channel(channel);
+    }
+
+    pub async fn psubscribe(&self, pattern: &str) -> broadcast::Receiver<PatternMessage> {
+        let mut pubsub = self.shared.pubsub.lock().unwrap();
+        pubsub.psubscribe(pattern)
+    }
+
+    pub async fn punsubscribe(&self, pattern: &str) {
+        let mut pubsub = self.shared.pubsub.lock().unwrap();
+        pubsub.cleanup_pattern(pattern);
+    }
+
+    // --- Keyspace ---
+
+    /// Remove todas as chaves (e o agendamento de expiração).
+    pub async fn flush(&self) {
+        let _write = self.shared.write_many();
+        // `retain` em vez de `clear` para descontar cada chave removida
+        let counts = &self.shared.type_counts;
+        self.shared.data.retain(|_, e| {
+            counts.sub(&e.value);
+            false
+        });
+        self.shared.expiry.lock().unwrap().clear();
+    }
+
+    /// Permissão para uma escrita que vai ser propagada: pega antes de
+    /// aplicar a escrita e solta só depois de propagá-la, para o full resync
+    /// não ver a escrita no snapshot e de novo no stream (ver `snapshot_with`).
+    pub async fn write_permit(&self) -> WritePermit {
+        WritePermit {
+            _guard: self.shared.propagation.clone().read_owned().await,
+        }
+    }
+
+    /// Barreira contra as escritas propagadas: espera as que têm
+    /// `WritePermit` serem propagadas e segura as próximas até ser solta.
+    /// Usada por quem precisa do dataset parado entre o AOF e o keyspace
+    /// (full resync, DEBUG RELOAD).
+    pub async fn write_barrier(&self) -> tokio::sync::RwLockWriteGuard<'_, ()> {
+        self.shared.propagation.write().await
+    }
+
+    /// Snapshot do full resync. Espera as escritas com `WritePermit` em
+    /// andamento serem propagadas e, antes de outra começar, chama
+    /// `subscribe` e gera o snapshot: cada escrita fica ou no snapshot ou no
+    /// que o assinante vai receber, nunca nos dois.
+    pub async fn snapshot_with<T>(&self, subscribe: impl FnOnce() -> T) -> (Vec<Command>, T) {
+        let _barrier = self.write_barrier().await;
+        let subscription = subscribe();
+        (self.snapshot(), subscription)
+    }
+
+    /// Gera a sequência de comandos de escrita que reconstrói o dataset atual
+    /// (usado no full resync de réplicas). Chaves já expiradas são omitidas.
+    pub fn snapshot(&self) -> Vec<Command> {
+        let now = Instant::now();
+        let mut commands = Vec::with_capacity(self.shared.data.len());
+
+        for entry in self.shared.data.iter() {
+            if entry.is_expired() {
+                continue;
+            }
+            let key = entry.key().clone();
+            match &entry.value {
+                Value::String(data) => {
+                    let expire_ms = entry
+                        .expires_at
+                        .map(|t| t.saturating_duration_since(now).as_millis().max(1) as u64);
+                    commands.push(Command::Set {
+                        key,
+                        value: data.clone(),
+                        options: SetOptions {
+                            expire: expire_ms.map(Expiry::Px),
+                            condition: None,
+                            get: false,
+                            // Sem TTL no snapshot = sem TTL no replay, mesmo com --default-ttl
+                            persist: expire_ms.is_none(),
+                        },
+                    });
+                }
+                Value::List(list) => {
+                    if list.is_empty() {
+                        continue;
+                    }
+                    let expire_ms = entry
+                        .expires_at
+                        .map(|t| t.saturating_duration_since(now).as_millis().max(1) as i64);
+                    commands.push(Command::RPush {
+                        key: key.clone(),
+                        values: list.iter().cloned().collect(),
+                    });
+                    if let Some(ms) = expire_ms {
+                        commands.push(Command::Expire {
+                            key,
+                            ms,
+                            condition: None,
+                        });
+                    }
+                }
+            }
+        }
+
+        commands
+    }
+
+    /// Liga/desliga a remoção ativa de chaves vencidas pela task de purga.
+    /// Desligada, a expiração é só lazy: a chave vencida continua no
+    /// keyspace até ser acessada, mas já não é visível a nenhum comando.
+    pub fn set_active_expire(&self, enabled: bool) {
+        self.shared.active_expire.store(enabled, Ordering::Relaxed);
+        // Acorda a purga para reavaliar o estado
+        self.shared.notify_expiry.notify_one();
+    }
+
+    /// Marca o início/fim de uma recarga do dataset. Quem recarrega liga a
+    /// flag antes de limpar o keyspace e desliga depois do último comando.
+    pub fn set_loading(&self, loading: bool) {
+        self.shared.loading.store(loading, Ordering::Relaxed);
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.shared.loading.load(Ordering::Relaxed)
+    }
+
+    // --- Stats ---
+
+    pub fn len(&self) -> usize {
+        self.shared.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shared.data.is_empty()
+    }
+
+    /// Zera `keyspace_hits`/`keyspace_misses` (CONFIG RESETSTAT).
+    pub fn reset_stats(&self) {
+        self.shared.keyspace_hits.store(0, Ordering::Relaxed);
+        self.shared.keyspace_misses.store(0, Ordering::Relaxed);
+    }
+
+    /// Leituras que encontraram a chave.
+    pub fn keyspace_hits(&self) -> u64 {
+        self.shared.keyspace_hits.load(Ordering::Relaxed)
+    }
+
+    /// Leituras que não encontraram a chave (ausente ou expirada).
+    pub fn keyspace_misses(&self) -> u64 {
+        self.shared.keyspace_misses.load(Ordering::Relaxed)
+    }
+
+    /// Agrega o tamanho estimado de todas as chaves não expiradas. O(N):
+    /// percorre o keyspace inteiro.
+    pub fn memory_stats(&self) -> MemoryStats {
+        let mut stats = MemoryStats {
+            keys: 0,
+            total_bytes: 0,
+            dataset_bytes: 0,
+            overhead_bytes: 0,
+            largest_key: None,
+        };
+
+        for entry in self.shared.data.iter() {
+            if entry.is_expired() {
+                continue;
+            }
+            let key = entry.key();
+            let size = std::mem::size_of::<Bytes>() + key.len() + entry.estimated_size();
+            let dataset = key.len() + entry.payload_size();
+
+            stats.keys += 1;
+            stats.total_bytes += size;
+            stats.dataset_bytes += dataset;
+            stats.overhead_bytes += size - dataset;
+            if stats
+                .largest_key
+                .as_ref()
+                .is_none_or(|(_, max)| size > *max)
+            {
+                stats.largest_key = Some((key.clone(), size));
+            }
+        }
+
+        stats
+    }
+
+    /// Estatísticas do `DEBUG OBJECT`, ou `None` se a chave não existe. Não
+    /// conta como leitura em `keyspace_hits`/`keyspace_misses`.
+    pub fn object_stats(&self, key: &[u8]) -> Option<ObjectStats> {
+        let entry = self.shared.data.get(key).filter(|e| !e.is_expired())?;
+        let elements = match &entry.value {
+            Value::String(_) => None,
+            Value::List(list) => Some(list.len()),
+        };
+        Some(ObjectStats {
+            value_type: entry.value.type_name(),
+            encoding: entry.encoding(self.shared.config.embstr_max_len),
+            serialized_length: entry.payload_size(),
+            elements,
+        })
+    }
+
+    /// TYPE: tipo do valor da chave, ou `None` se ela não existe (chaves
+    /// expiradas inclusive).
+    pub fn key_type(&self, key: &[u8]) -> Option<&'static str> {
+        let entry = self.lookup_read(key)?;
+        Some(entry.value.type_name())
+    }
+
+    /// Encoding de uma chave para o `OBJECT ENCODING` (ver `Entry::encoding`),
+    /// ou `None` se ela não existe. Não conta como leitura.
+    pub fn object_encoding(&self, key: &[u8]) -> Option<&'static str> {
+        let entry = self.shared.data.get(key).filter(|e| !e.is_expired())?;
+        Some(entry.encoding(self.shared.config.embstr_max_len))
+    }
+
+    /// Número de chaves por tipo, em O(1).
+    pub fn count_by_type(&self) -> Vec<(&'static str, u64)> {
+        let counts = &self.shared.type_counts;
+        vec![
+            ("string", counts.strings.load(Ordering::Relaxed)),
+            ("list", counts.lists.load(Ordering::Relaxed)),
+        ]
+    }
+}
+
+impl Default for Db {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Lugar de um cliente do BRPOPLPUSH na fila de espera da lista. Ao ser
+/// dropado (elemento obtido, timeout ou cancelamento) sai da fila; se tinha
+/// sido acordado e não levou o elemento, repassa o aviso ao próximo.
+struct ListWaiter<'a> {
+    db: &'a Db,
+    key: &'a [u8],
+    notify: Arc<Notify>,
+    served: bool,
+}
+
+impl ListWaiter<'_> {
+    /// Entra no fim da fila (`first`) ou volta para a frente dela, se já
+    /// não estiver lá.
+    fn enqueue(&self, first: bool) {
+        let mut queue = self
+            .db
+            .shared
+            .list_waiters
+            .entry(Bytes::copy_from_slice(self.key))
+            .or_default();
+        if first {
+            queue.push_back(self.notify.clone());
+        } else if !queue.iter().any(|n| Arc::ptr_eq(n, &self.notify)) {
+            queue.push_front(self.notify.clone());
+        }
+    }
+}
+
+impl Drop for ListWaiter<'_> {
+    fn drop(&mut self) {
+        let waiters = &self.db.shared.list_waiters;
+        let queued = match waiters.get_mut(self.key) {
+            Some(mut queue) => {
+                let before = queue.len();
+                queue.retain(|n| !Arc::ptr_eq(n, &self.notify));
+                queue.len() != before
+            }
+            None => false,
+        };
+        waiters.remove_if(self.key, |_, queue| queue.is_empty());
+        if !queued && !self.served {
+            self.db.wake_list_waiters(self.key, 1);
+        }
+    }
+}
+
+/// Background task que purga chaves expiradas.
+async fn purge_expired_keys(shared: Arc<SharedState>) {
+    loop {
+        #[cfg(test)]
+        if shared
+            .fail_purge
+            .swap(false, std::sync::atomic::Ordering::SeqCst)
+        {
+            panic!("pânico simulado na task de purga");
+        }
+
+        if !shared.active_expire.load(Ordering::Relaxed) {
+            shared.notify_expiry.notified().await;
+            continue;
+        }
+
+        let next_expiry = shared.expiry.lock().unwrap().first().map(|e| e.0);
+
+        match next_expiry {
+            Some(when) => {
+                tokio::select! {
+                    _ = tokio::time::sleep_until(when) => {}
+                    _ = shared.notify_expiry.notified() => { continue; }
+                }
+            }
+            None => {
+                shared.notify_expiry.notified().await;
+                continue;
+            }
+        }
+
+        // Tira as entradas vencidas do índice (BTreeSet é ordenado) e solta
+        // o lock antes de remover as chaves, que pega o lock dos shards
+        let now = Instant::now();
+        let mut due = Vec::new();
+        {
+            let mut expiry = shared.expiry.lock().unwrap();
+            while expiry.first().is_some_and(|e| e.0 <= now) {
+                due.extend(expiry.pop_first());
+            }
+        }
+
+        for ExpiryEntry(_, key) in due {
+            // Confere o TTL da própria entrada antes de remover
+            if shared.remove_expired(&key) {
+                debug!("key expirada removida: {key:?}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn get_set_basic() {
+        let db = Db::new();
+        let opts = SetOptions {
+            expire: None,
+            condition: None,
+            get: false,
+            persist: false,
+        };
+        db.set("key".into(), Bytes::from("value"), &opts).unwrap();
+        assert_eq!(db.get(b"key"), Some(Bytes::from("value")));
+    }
+
+    #[tokio::test]
+    async fn get_nonexistent() {
+        let db = Db::new();
+        assert_eq!(db.get(b"missing"), None);
+    }
+
+    #[tokio::test]
+    async fn keyspace_hits_and_misses() {
+        let db = Db::new();
+        let opts = SetOptions {
+            expire: None,
+            condition: None,
+            get: false,
+            persist: false,
+        };
+        db.set("key".into(), Bytes::from("value"), &opts).unwrap();
+
+        db.get(b"key");
+        assert_eq!((db.keyspace_hits(), db.keyspace_misses()), (1, 0));
+
+        db.get(b"missing");
+        assert_eq!((db.keyspace_hits(), db.keyspace_misses()), (1, 1));
+
+        // Escritas não contam como leitura
+        db.set("other".into(), Bytes::from("v"), &opts).unwrap();
+        db.lrange(b"missing-list", 0, -1).unwrap();
+        assert_eq!((db.keyspace_hits(), db.keyspace_misses()), (1, 2));
+    }
+
+    #[tokio::test]
+    async fn set_nx_key_exists() {
+        let db = Db::new();
+        let opts_none = SetOptions {
+            expire: None,
+            condition: None,
+            get: false,
+            persist: false,
+        };
+        db.set("key".into(), Bytes::from("v1"), &opts_none).unwrap();
+
+        let opts_nx = SetOptions {
+            expire: None,
+            condition: Some(SetCondition::Nx),
+            get: false,
+            persist: false,
+        };
+        let result = db.set("key".into(), Bytes::from("v2"), &opts_nx).unwrap();
+        assert!(!result); // não deve sobrescrever
+        assert_eq!(db.get(b"key"), Some(Bytes::from("v1")));
+    }
+
+    #[tokio::test]
+    async fn set_get_on_list_is_wrongtype() {
+        let db = Db::new();
+        db.rpush(b"list", &[Bytes::from("a")]).unwrap();
+
+        let opts = SetOptions {
+            expire: None,
+            condition: None,
+            get: true,
+            persist: false,
+        };
+        let result = db.set_get("list".into(), Bytes::from("v"), &opts);
+        assert!(matches!(result, Err(StorageError::WrongType)));
+        // A lista não foi sobrescrita
+        assert_eq!(db.lrange(b"list", 0, -1).unwrap(), vec![Bytes::from("a")]);
+    }
+
+    #[tokio::test]
+    async fn set_nx_get_returns_old_without_overwrite() {
+        let db = Db::new();
+        let opts = SetOptions {
+            expire: None,
+            condition: Some(SetCondition::Nx),
+            get: true,
+            persist: false,
+        };
+
+        let old = db.set_get("key".into(), Bytes::from("v1"), &opts).unwrap();
+        assert_eq!(old, None);
+        assert_eq!(db.get(b"key"), Some(Bytes::from("v1")));
+
+        let old = db.set_get("key".into(), Bytes::from("v2"), &opts).unwrap();
+        assert_eq!(old, Some(Bytes::from("v1")));
+        assert_eq!(db.get(b"key"), Some(Bytes::from("v1")));
+    }
+
+    #[tokio::test]
+    async fn mget_keeps_order_with_missing_keys() {
+        let db = Db::new();
+        db.mset(vec![("a".into(), "1".into()), ("c".into(), "3".into())])
+            .unwrap();
+        db.rpush(b"list", &[Bytes::from("x")]).unwrap();
+
+        let keys: Vec<Bytes> = ["a", "b", "c", "list", "a"].map(Bytes::from).to_vec();
+        assert_eq!(
+            db.mget(&keys),
+            [
+                Some(Bytes::from("1")),
+                None,
+                Some(Bytes::from("3")),
+                None,
+                Some(Bytes::from("1")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn mset_duplicate_key_last_wins() {
+        let db = Db::new();
+        db.mset(vec![
+            ("k".into(), Bytes::from("v1")),
+            ("other".into(), Bytes::from("x")),
+            ("k".into(), Bytes::from("v2")),
+        ])
+        .unwrap();
+        assert_eq!(db.get(b"k"), Some(Bytes::from("v2")));
+        assert_eq!(db.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn mset_reports_absolute_ttl_per_key() {
+        let db = Db::new();
+        let applied = db.mset(vec![("a".into(), "1".into())]).unwrap();
+        assert_eq!(applied[0].expire, None);
+        assert!(applied[0].persist);
+
+        let db = Db::with_config(DbConfig {
+            default_ttl_ms: Some(60_000),
+            expire_jitter_ms: 1_000,
+            ..Default::default()
+        });
+        let applied = db
+            .mset(vec![("a".into(), "1".into()), ("b".into(), "2".into())])
+            .unwrap();
+        for (key, options) in [b"a", b"b"].iter().zip(&applied) {
+            let Some(Expiry::PxAt(at)) = options.expire else {
+                panic!("sem PXAT: {options:?}");
+            };
+            assert!(!options.persist);
+            // O deadline propagado é o gravado, com o jitter já sorteado
+            let stored = unix_ms_at(expires_at(&db, *key).unwrap()).unwrap();
+            assert!(stored.abs_diff(at) <= 1, "{stored} != {at}");
+        }
+    }
+
+    #[tokio::test]
+    async fn msetnx_writes_nothing_if_any_key_exists() {
+        let db = Db::new();
+        db.rpush(b"b", &[Bytes::from("x")]).unwrap();
+
+        let applied = db
+            .msetnx(vec![
+                ("a".into(), Bytes::from("1")),
+                ("b".into(), Bytes::from("2")),
+                ("c".into(), Bytes::from("3")),
+            ])
+            .unwrap();
+        assert!(applied.is_none());
+        assert_eq!(db.get(b"a"), None);
+        assert_eq!(db.get(b"c"), None);
+        assert_eq!(db.lrange(b"b", 0, -1).unwrap(), vec![Bytes::from("x")]);
+
+        let applied = db
+            .msetnx(vec![
+                ("a".into(), Bytes::from("1")),
+                ("c".into(), Bytes::from("3")),
+            ])
+            .unwrap();
+        assert!(applied.is_some());
+        assert_eq!(db.get(b"a"), Some(Bytes::from("1")));
+    }
+
+    #[tokio::test]
+    async fn concurrent_msetnx_on_shared_key_applies_once() {
+        // Duas MSETNX que disputam "b": com a checagem e a escrita separadas,
+        // as duas podiam ver "b" livre e gravar
+        for _ in 0..500 {
+            let db = Db::new();
+            let start = Arc::new(std::sync::Barrier::new(2));
+            let racers: Vec<_> = [["a", "b"], ["b", "c"]]
+                .into_iter()
+                .map(|keys| {
+                    let db = db.clone();
+                    let start = start.clone();
+                    std::thread::spawn(move || {
+                        let pairs = keys
+                            .iter()
+                            .map(|k| (Bytes::from(*k), Bytes::from(keys.concat())))
+                            .collect();
+                        start.wait();
+                        db.msetnx(pairs).unwrap().is_some()
+                    })
+                })
+                .collect();
+            let applied: Vec<bool> = racers.into_iter().map(|t| t.join().unwrap()).collect();
+            assert_eq!(applied.iter().filter(|&&ok| ok).count(), 1, "{applied:?}");
+            // Só os pares da vencedora existem, nenhum da perdedora
+            let winner = if applied[0] { "ab" } else { "bc" };
+            assert_eq!(db.get(b"b"), Some(Bytes::from(winner)));
+            assert_eq!(db.len(), 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn bitop_and_not() {
+        let db = Db::new();
+        let opts = SetOptions {
+            expire: None,
+            condition: None,
+            get: false,
+            persist: false,
+        };
+        db.set("a".into(), Bytes::from_static(&[0b1111_0000, 0xff]), &opts)
+            .unwrap();
+        db.set("b".into(), Bytes::from_static(&[0b1010_1010]), &opts)
+            .unwrap();
+
+        // b é completada com zero
+        let len = db
+            .bitop(BitOp::And, b"dest", &["a".into(), "b".into()])
+            .unwrap();
+        assert_eq!(len, 2);
+        assert_eq!(db.get(b"dest"), Some(Bytes::from_static(&[0b1010_0000, 0])));
+
+        let len = db.bitop(BitOp::Not, b"dest", &["b".into()]).unwrap();
+        assert_eq!(len, 1);
+        assert_eq!(db.get(b"dest"), Some(Bytes::from_static(&[0b0101_0101])));
+
+        // Só chaves ausentes: resultado vazio remove o destino
+        assert_eq!(
+            db.bitop(BitOp::Or, b"dest", &["missing".into()]).unwrap(),
+            0
+        );
+        assert_eq!(db.get(b"dest"), None);
+
+        db.rpush(b"list", &[Bytes::from("x")]).unwrap();
+        assert!(matches!(
+            db.bitop(BitOp::Or, b"dest", &["list".into()]),
+            Err(StorageError::WrongType)
+        ));
+
+        // Origem vencida conta como vazia e sai do keyspace (expiração lazy)
+        let short = SetOptions {
+            expire: Some(Expiry::Px(10)),
+            ..opts
+        };
+        db.set("gone".into(), Bytes::from_static(&[0xff]), &short)
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        let len = db
+            .bitop(BitOp::Or, b"dest", &["gone".into(), "b".into()])
+            .unwrap();
+        assert_eq!(len, 1);
+        assert_eq!(db.get(b"dest"), Some(Bytes::from_static(&[0b1010_1010])));
+        assert!(!db.shared.data.contains_key(b"gone".as_slice()));
+    }
+
+    #[tokio::test]
+    async fn concurrent_pop_to_empty_never_loses_pushes() {
+        const PUSHERS: usize = 3;
+        const PUSHES: usize = 50_000;
+        let db = Db::new();
+        let done = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+        // Threads do SO para concorrência real sobre o mesmo shard
+        let pushers: Vec<_> = (0..PUSHERS)
+            .map(|_| {
+                let db = db.clone();
+                let done = done.clone();
+                std::thread::spawn(move || {
+                    for i in 0..PUSHES {
+                        db.rpush(b"list", &[Bytes::from(i.to_string())]).unwrap();
+                    }
+                    done.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                })
+            })
+            .collect();
+
+        // Esvazia a lista o tempo todo, forçando a remoção da chave
+        let popper = {
+            let db = db.clone();
+            std::thread::spawn(move || {
+                let mut popped = 0;
+                loop {
+                    let finished = done.load(std::sync::atomic::Ordering::SeqCst) == PUSHERS;
+                    match db.lpop(b"list", Some(16)).unwrap() {
+                        Some(items) => popped += items.len(),
+                        None if finished => return popped,
+                        None => std::hint::spin_loop(),
+                    }
+                }
+            })
+        };
+
+        for pusher in pushers {
+            pusher.join().unwrap();
+        }
+        assert_eq!(popper.join().unwrap(), PUSHERS * PUSHES);
+    }
+
+    #[tokio::test]
+    async fn set_nx_key_not_exists() {
+        let db = Db::new();
+        let opts_nx = SetOptions {
+            expire: None,
+            condition: Some(SetCondition::Nx),
+            get: false,
+            persist: false,
+        };
+        let result = db.set("key".into(), Bytes::from("v1"), &opts_nx).unwrap();
+        assert!(result);
+        assert_eq!(db.get(b"key"), Some(Bytes::from("v1")));
+    }
+
+    #[tokio::test]
+    async fn set_xx_key_exists() {
+        let db = Db::new();
+        let opts_none = SetOptions {
+            expire: None,
+            condition: None,
+            get: false,
+            persist: false,
+        };
+        db.set("key".into(), Bytes::from("v1"), &opts_none).unwrap();
+
+        let opts_xx = SetOptions {
+            expire: None,
+            condition: Some(SetCondition::Xx),
+            get: false,
+            persist: false,
+        };
+        let result = db.set("key".into(), Bytes::from("v2"), &opts_xx).unwrap();
+        assert!(result);
+        assert_eq!(db.get(b"key"), Some(Bytes::from("v2")));
+    }
+
+    #[tokio::test]
+    async fn set_xx_key_not_exists() {
+        let db = Db::new();
+        let opts_xx = SetOptions {
+            expire: None,
+            condition: Some(SetCondition::Xx),
+            get: false,
+            persist: false,
+        };
+        let result = db.set("key".into(), Bytes::from("v1"), &opts_xx).unwrap();
+        assert!(!result);
+        assert_eq!(db.get(b"key"), None);
+    }
+
+    #[tokio::test]
+    async fn set_with_expiry() {
+        let db = Db::new();
+        let opts = SetOptions {
+            expire: Some(Expiry::Px(50)), // 50ms
+            condition: None,
+            get: false,
+            persist: false,
+        };
+        db.set("key".into(), Bytes::from("value"), &opts).unwrap();
+        assert_eq!(db.get(b"key"), Some(Bytes::from("value")));
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(db.get(b"key"), None);
+    }
+
+    #[tokio::test]
+    async fn default_ttl_applies_unless_persist() {
+        let db = Db::with_config(DbConfig {
+            default_ttl_ms: Some(50),
+            ..Default::default()
+        });
+        let plain = SetOptions {
+            expire: None,
+            condition: None,
+            get: false,
+            persist: false,
+        };
+        let persist = SetOptions {
+            persist: true,
+            ..plain.clone()
+        };
+        let explicit = SetOptions {
+            expire: Some(Expiry::Px(60_000)),
+            ..plain.clone()
+        };
+        db.set("plain".into(), Bytes::from("v"), &plain).unwrap();
+        db.set("persist".into(), Bytes::from("v"), &persist)
+            .unwrap();
+        db.set("explicit".into(), Bytes::from("v"), &explicit)
+            .unwrap();
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(db.get(b"plain"), None);
+        assert_eq!(db.get(b"persist"), Some(Bytes::from("v")));
+        assert!(expires_at(&db, b"persist").is_none());
+        // EX/PX explícito vence o default
+        assert_eq!(db.get(b"explicit"), Some(Bytes::from("v")));
+    }
+
+    #[tokio::test]
+    async fn ttl_visible_right_after_set_ex() {
+        let db = Db::new();
+        let opts = SetOptions {
+            expire: Some(Expiry::Px(10_000)),
+            condition: None,
+            get: false,
+            persist: false,
+        };
+        db.set("key".into(), Bytes::from("v"), &opts).unwrap();
+        // Sem yield: o TTL já está na entrada quando o SET retorna
+        let ttl = db.pttl(b"key").unwrap().unwrap();
+        assert!(ttl > Duration::ZERO && ttl <= Duration::from_secs(10));
+
+        db.rpush(b"list", &[Bytes::from("a")]).unwrap();
+        assert_eq!(db.pttl(b"list"), Some(None));
+        assert_eq!(db.pttl(b"missing"), None);
+    }
+
+    #[tokio::test]
+    async fn lazy_expiry_with_active_expire_off() {
+        let db = Db::new();
+        db.set_active_expire(false);
+        let opts = SetOptions {
+            expire: Some(Expiry::Px(10)),
+            condition: None,
+            get: false,
+            persist: false,
+        };
+        for key in ["ttl", "get", "exists"] {
+            db.set(key.into(), Bytes::from("v"), &opts).unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        // A purga não rodou: as chaves vencidas continuam no keyspace
+        assert_eq!(db.len(), 3);
+
+        // Cada acesso trata a chave como ausente e a remove
+        assert_eq!(db.pttl(b"ttl"), None);
+        assert_eq!(db.len(), 2);
+        assert_eq!(db.get(b"get"), None);
+        assert_eq!(db.len(), 1);
+        assert_eq!(db.exists(&[Bytes::from("exists")]), 0);
+        assert!(db.is_empty());
+
+        // Religada, a purga volta a remover sem acesso
+        db.set("k".into(), Bytes::from("v"), &opts).unwrap();
+        db.set_active_expire(true);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(db.is_empty());
+    }
+
+    #[tokio::test]
+    async fn blocked_clients_are_served_in_arrival_order() {
+        let db = Db::new();
+        let mut waiters = Vec::new();
+        for i in 0..3 {
+            let db = db.clone();
+            waiters.push(tokio::spawn(async move {
+                let dest = format!("dest{i}");
+                let moved = db.brpoplpush(b"src", dest.as_bytes(), None).await;
+                moved.unwrap().map(|(item, _)| item)
+            }));
+            // Garante a ordem de chegada
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        // Dois elementos: só os dois primeiros a chegar são acordados, e o
+        // primeiro leva o elemento do fim da lista
+        db.rpush(b"src", &[Bytes::from("a"), Bytes::from("b")])
+            .unwrap();
+        let third = waiters.pop().unwrap();
+        let results: Vec<_> = join_all(waiters).await;
+        assert_eq!(
+            results,
+            vec![Some(Bytes::from("b")), Some(Bytes::from("a"))]
+        );
+
+        // O terceiro continua bloqueado, sem ter sido acordado à toa
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!third.is_finished());
+        assert_eq!(db.shared.list_waiters.get(&b"src"[..]).unwrap().len(), 1);
+
+        db.rpush(b"src", &[Bytes::from("c")]).unwrap();
+        assert_eq!(third.await.unwrap(), Some(Bytes::from("c")));
+        assert!(db.shared.list_waiters.is_empty());
+    }
+
+    async fn join_all<T>(handles: Vec<tokio::task::JoinHandle<T>>) -> Vec<T> {
+        let mut results = Vec::new();
+        for handle in handles {
+            results.push(handle.await.unwrap());
+        }
+        results
+    }
+
+    #[tokio::test]
+    async fn cancelled_waiter_passes_wakeup_on() {
+        let db = Db::new();
+        let first = {
+            let db = db.clone();
+            tokio::spawn(async move { db.brpoplpush(b"src", b"d1", None).await })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let second = {
+            let db = db.clone();
+            tokio::spawn(async move { db.brpoplpush(b"src", b"d2", None).await })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        // O primeiro é acordado pelo push, mas cancelado antes de pegar o
+        // elemento: o aviso tem que chegar ao segundo
+        db.rpush(b"src", &[Bytes::from("a")]).unwrap();
+        first.abort();
+        let (item, _) = tokio::time::timeout(Duration::from_secs(1), second)
+            .await
+            .expect("segundo cliente não foi acordado")
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(item, Bytes::from("a"));
+    }
+
+    #[tokio::test]
+    async fn incr_with_ttl_sets_ttl_only_on_creation() {
+        let db = Db::new();
+        assert_eq!(db.incr_with_ttl(b"hits", Expiry::Px(10_000)).unwrap(), 1);
+        let first = expires_at(&db, b"hits").unwrap();
+
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert_eq!(db.incr_with_ttl(b"hits", Expiry::Px(10_000)).unwrap(), 2);
+        assert_eq!(db.incr(b"hits").unwrap(), 3);
+        // A janela não é renovada pelos incrementos seguintes
+        assert_eq!(expires_at(&db, b"hits"), Some(first));
+
+        // Chave já existente sem TTL continua sem TTL
+        db.incr(b"plain").unwrap();
+        db.incr_with_ttl(b"plain", Expiry::Px(10_000)).unwrap();
+        assert_eq!(expires_at(&db, b"plain"), None);
+
+        // Depois de expirar, a próxima janela começa com um TTL novo
+        db.incr_with_ttl(b"short", Expiry::Px(10)).unwrap();
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(db.incr_with_ttl(b"short", Expiry::Px(10_000)).unwrap(), 1);
+        assert!(expires_at(&db, b"short").unwrap() > Instant::now() + Duration::from_secs(5));
+    }
+
+    fn expires_at(db: &Db, key: &[u8]) -> Option<Instant> {
+        db.shared.data.get(key).unwrap().expires_at
+    }
+
+    #[tokio::test]
+    async fn memory_stats_small_dataset() {
+        let db = Db::new();
+        let opts = SetOptions {
+            expire: None,
+            condition: None,
+            get: false,
+            persist: false,
+        };
+        db.set("a".into(), Bytes::from("12345"), &opts).unwrap();
+        db.set("b".into(), Bytes::from(vec![b'x'; 1000]), &opts)
+            .unwrap();
+        db.rpush(b"list", &[Bytes::from("one"), Bytes::from("two")])
+            .unwrap();
+
+        let stats = db.memory_stats();
+        assert_eq!(stats.keys, 3);
+        // Chaves (1 + 1 + 4) + valores (5 + 1000 + 3 + 3)
+        assert_eq!(stats.dataset_bytes, 1017);
+        assert_eq!(
+            stats.total_bytes,
+            stats.dataset_bytes + stats.overhead_bytes
+        );
+        assert!(stats.overhead_bytes > 0 && stats.overhead_bytes < 1024);
+        assert_eq!(stats.largest_key.unwrap().0, Bytes::from("b"));
+
+        db.flush().await;
+        let stats = db.memory_stats();
+        assert_eq!((stats.keys, stats.total_bytes), (0, 0));
+        assert!(stats.largest_key.is_none());
+    }
+
+    #[tokio::test]
+    async fn rpush_capped_keeps_newest() {
+        let db = Db::new();
+        let items = |range: std::ops::Range<u32>| -> Vec<Bytes> {
+            range.map(|i| Bytes::from(i.to_string())).collect()
+        };
+        assert_eq!(db.rpush_capped(b"log", &items(0..3), Some(5)).unwrap(), 3);
+        assert_eq!(db.rpush_capped(b"log", &items(3..7), Some(5)).unwrap(), 5);
+        assert_eq!(db.lrange(b"log", 0, -1).unwrap(), items(2..7));
+
+        // Um único push maior que o limite também é cortado
+        assert_eq!(db.rpush_capped(b"log", &items(7..20), Some(5)).unwrap(), 5);
+        assert_eq!(db.lrange(b"log", 0, -1).unwrap(), items(15..20));
+
+        // Com limite 0 nada sobra: a chave some em vez de virar lista vazia
+        assert_eq!(db.rpush_capped(b"log", &items(20..22), Some(0)).unwrap(), 0);
+        assert_eq!(db.exists(&[Bytes::from("log")]), 0);
+    }
+
+    #[tokio::test]
+    async fn append_grows_and_strlen_follows() {
+        let db = Db::new();
+        assert_eq!(db.strlen(b"greeting").unwrap(), 0);
+        assert_eq!(db.append(b"greeting", b"Hello").unwrap(), 5);
+        assert_eq!(db.append(b"greeting", b", ").unwrap(), 7);
+        assert_eq!(db.append(b"greeting", b"World").unwrap(), 12);
+        assert_eq!(db.get(b"greeting"), Some(Bytes::from("Hello, World")));
+        assert_eq!(db.strlen(b"greeting").unwrap(), 12);
+
+        db.rpush(b"list", &[Bytes::from("a")]).unwrap();
+        assert!(matches!(db.strlen(b"list"), Err(StorageError::WrongType)));
+    }
+
+    #[tokio::test]
+    async fn string_range_commands_are_binary_safe() {
+        let db = Db::new();
+        let raw: &'static [u8] = &[0x00, 0xff, 0xfe, 0x80, 0x00];
+
+        assert_eq!(db.append(b"bin", &raw[..2]).unwrap(), 2);
+        assert_eq!(db.append(b"bin", &raw[2..]).unwrap(), 5);
+        assert_eq!(db.getrange(b"bin", 0, -1).unwrap(), Bytes::from_static(raw));
+
+        // Intervalo negativo de vários bytes: os três últimos
+        assert_eq!(
+            db.getrange(b"bin", -3, -1).unwrap(),
+            Bytes::from_static(&[0xfe, 0x80, 0x00])
+        );
+        assert_eq!(
+            db.getrange(b"bin", -100, 1).unwrap(),
+            Bytes::from_static(&raw[..2])
+        );
+        assert_eq!(db.getrange(b"bin", 4, 2).unwrap(), Bytes::new());
+        assert_eq!(db.getrange(b"bin", 10, 20).unwrap(), Bytes::new());
+
+        // SETRANGE além do fim completa com zeros
+        assert_eq!(db.setrange(b"bin", 7, &[0xff, 0x00]).unwrap(), 9);
+        assert_eq!(
+            db.getrange(b"bin", 0, -1).unwrap(),
+            Bytes::from_static(&[0x00, 0xff, 0xfe, 0x80, 0x00, 0x00, 0x00, 0xff, 0x00])
+        );
+        assert_eq!(db.setrange(b"bin", 1, &[0x01]).unwrap(), 9);
+        assert_eq!(
+            db.getrange(b"bin", 1, 1).unwrap(),
+            Bytes::from_static(&[0x01])
+        );
+
+        // SETRANGE vazio numa chave ausente não cria nada
+        assert_eq!(db.setrange(b"missing", 3, &[]).unwrap(), 0);
+        assert_eq!(db.exists(&["missing".into()]), 0);
+
+        db.rpush(b"list", &[Bytes::from("a")]).unwrap();
+        assert!(matches!(
+            db.append(b"list", b"x"),
+            Err(StorageError::WrongType)
+        ));
+        assert!(matches!(
+            db.getrange(b"list", 0, -1),
+            Err(StorageError::WrongType)
+        ));
+    }
+
+    #[tokio::test]
+    async fn push_without_values_creates_no_key() {
+        let db = Db::new();
+        assert_eq!(db.lpush(b"k", &[]).unwrap(), 0);
+        assert_eq!(db.rpush(b"k", &[]).unwrap(), 0);
+        assert!(db.is_empty());
+        assert_eq!(db.count_by_type()[1], ("list", 0));
+
+        db.rpush(b"k", &[Bytes::from("a"), Bytes::from("b")])
+            .unwrap();
+        assert_eq!(db.lpush(b"k", &[]).unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn object_stats_sums_element_bytes() {
+        let db = Db::new();
+        db.rpush(
+            b"list",
+            &[
+                Bytes::from("a"),
+                Bytes::from("bcd"),
+                Bytes::from(vec![b'x'; 10]),
+            ],
+        )
+        .unwrap();
+        let stats = db.object_stats(b"list").unwrap();
+        assert_eq!(stats.value_type, "list");
+        assert_eq!(stats.encoding, "quicklist");
+        assert_eq!((stats.elements, stats.serialized_length), (Some(3), 14));
+        assert_eq!(db.object_stats(b"missing"), None);
+
+        let opts = SetOptions {
+            expire: None,
+            condition: None,
+            get: false,
+            persist: false,
+        };
+        db.set("s".into(), Bytes::from("v"), &opts).unwrap();
+        let stats = db.object_stats(b"s").unwrap();
+        assert_eq!(stats.value_type, "string");
+        assert_eq!(stats.encoding, "embstr");
+        assert_eq!((stats.elements, stats.serialized_length), (None, 1));
+        // Não conta como leitura
+        assert_eq!((db.keyspace_hits(), db.keyspace_misses()), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn rename_moves_value_and_ttl() {
+        let db = Db::new();
+        db.rpush(b"src", &[Bytes::from("a"), Bytes::from("b")])
+            .unwrap();
+        db.expire(b"src", 60_000, None);
+        db.incr(b"dst").unwrap();
+
+        // Sobrescreve o destino, levando o TTL junto
+        db.rename(b"src", b"dst").unwrap();
+        assert_eq!(db.pttl(b"src"), None);
+        assert_eq!(
+            db.lrange(b"dst", 0, -1).unwrap(),
+            vec![Bytes::from("a"), Bytes::from("b")]
+        );
+        assert!(db.pttl(b"dst").unwrap().unwrap() > Duration::from_secs(59));
+        assert_eq!(db.count_by_type(), [("string", 0), ("list", 1)]);
+
+        // O índice de expiração segue a chave: a purga remove `dst`
+        db.expire(b"dst", 1, None);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(db.len(), 0);
+        assert!(db.shared.expiry.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn rename_missing_source_and_renamenx() {
+        let db = Db::new();
+        assert!(matches!(
+            db.rename(b"missing", b"dst"),
+            Err(StorageError::KeyNotFound)
+        ));
+        assert!(matches!(
+            db.renamenx(b"missing", b"dst"),
+            Err(StorageError::KeyNotFound)
+        ));
+
+        db.incr(b"a").unwrap();
+        db.incr_by(b"b", 2).unwrap();
+        assert!(!db.renamenx(b"a", b"b").unwrap());
+        assert_eq!(db.get(b"a"), Some(Bytes::from("1")));
+        assert_eq!(db.get(b"b"), Some(Bytes::from("2")));
 
-    pub async fn unsubscribe(&self, channel: &str) {
-        let mut pubsub = self.shared.pubsub.lock().await;
-        pubsub.cleanup_channel(channel);
+        assert!(db.renamenx(b"a", b"c").unwrap());
+        assert_eq!(db.get(b"a"), None);
+        assert_eq!(db.get(b"c"), Some(Bytes::from("1")));
+
+        // Renomear para si mesma: RENAME é OK, RENAMENX não faz nada
+        db.rename(b"c", b"c").unwrap();
+        assert!(!db.renamenx(b"c", b"c").unwrap());
+        assert_eq!(db.get(b"c"), Some(Bytes::from("1")));
     }
 
-    // --- Stats ---
+    #[tokio::test]
+    async fn concurrent_renamenx_never_loses_writes() {
+        // RENAMENX "s" -> "d" disputando com um SET NX em "d" e um INCR em
+        // "s": em qualquer ordem, o INCR soma 1 aos 10 de "s", esteja o
+        // contador em "s" ou já em "d"
+        let opts = SetOptions {
+            expire: None,
+            condition: Some(SetCondition::Nx),
+            get: false,
+            persist: false,
+        };
+        for _ in 0..500 {
+            let db = Db::new();
+            db.incr_by(b"s", 10).unwrap();
+            let start = Arc::new(std::sync::Barrier::new(3));
+            let spawn = |f: Box<dyn FnOnce(&Db) + Send>| {
+                let db = db.clone();
+                let start = start.clone();
+                std::thread::spawn(move || {
+                    start.wait();
+                    f(&db);
+                })
+            };
+            let opts = opts.clone();
+            let threads = [
+                spawn(Box::new(|db| {
+                    let _ = db.renamenx(b"s", b"d");
+                })),
+                spawn(Box::new(move |db| {
+                    db.set("d".into(), Bytes::from("x"), &opts).unwrap();
+                })),
+                spawn(Box::new(|db| {
+                    db.incr(b"s").unwrap();
+                })),
+            ];
+            for thread in threads {
+                thread.join().unwrap();
+            }
 
-    pub fn len(&self) -> usize {
-        self.shared.data.len()
+            let counter = |key: &[u8]| {
+                db.get(key)
+                    .and_then(|v| std::str::from_utf8(&v).ok()?.parse::<i64>().ok())
+                    .unwrap_or(0)
+            };
+            assert_eq!(counter(b"s") + counter(b"d"), 11);
+        }
     }
 
-    pub fn is_empty(&self) -> bool {
-        self.shared.data.is_empty()
+    #[tokio::test]
+    async fn key_type_reports_value_kind() {
+        let db = Db::new();
+        db.rpush(b"list", &[Bytes::from("a")]).unwrap();
+        db.incr(b"counter").unwrap();
+        assert_eq!(db.key_type(b"list"), Some("list"));
+        assert_eq!(db.key_type(b"counter"), Some("string"));
+        assert_eq!(db.key_type(b"missing"), None);
+
+        // Expirada mas ainda não purgada: não tem mais tipo
+        db.set_active_expire(false);
+        db.expire(b"list", 1, None);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(db.key_type(b"list"), None);
     }
-}
 
-impl Default for Db {
-    fn default() -> Self {
-        Self::new()
+    #[tokio::test]
+    async fn object_encoding_classifies_strings() {
+        let db = Db::with_config(DbConfig {
+            embstr_max_len: 8,
+            ..Default::default()
+        });
+        let opts = SetOptions {
+            expire: None,
+            condition: None,
+            get: false,
+            persist: false,
+        };
+        let encoding = |value: &'static str| {
+            db.set("k".into(), Bytes::from(value), &opts).unwrap();
+            db.object_encoding(b"k").unwrap()
+        };
+        assert_eq!(encoding("123"), "int");
+        assert_eq!(encoding("-9223372036854775808"), "int");
+        // Fora do i64 ou não canônico: string comum
+        assert_eq!(encoding("9223372036854775808"), "raw");
+        assert_eq!(encoding("007"), "embstr");
+        assert_eq!(encoding("+1"), "embstr");
+        assert_eq!(encoding("hello"), "embstr");
+        assert_eq!(encoding("longer than 8"), "raw");
+
+        db.rpush(b"list", &[Bytes::from("a")]).unwrap();
+        assert_eq!(db.object_encoding(b"list"), Some("quicklist"));
+        assert_eq!(db.object_encoding(b"missing"), None);
     }
-}
 
-/// Background task que purga chaves expiradas.
-async fn purge_expired_keys(shared: Arc<SharedState>) {
-    loop {
-        let next_expiry = {
-            let expiry = shared.expiry.lock().await;
-            expiry.iter().next().map(|e| e.0)
+    #[tokio::test]
+    async fn count_by_type_tracks_mixed_writes() {
+        let db = Db::new();
+        let counts = |db: &Db| {
+            let c = db.count_by_type();
+            (c[0].1, c[1].1)
+        };
+        let opts = SetOptions {
+            expire: None,
+            condition: None,
+            get: false,
+            persist: false,
         };
 
-        match next_expiry {
-            Some(when) => {
-                tokio::select! {
-                    _ = tokio::time::sleep_until(when) => {}
-                    _ = shared.notify_expiry.notified() => { continue; }
-                }
-            }
-            None => {
-                shared.notify_expiry.notified().await;
-                continue;
-            }
-        }
+        db.set("s1".into(), Bytes::from("a"), &opts).unwrap();
+        db.set("s2".into(), Bytes::from("b"), &opts).unwrap();
+        db.incr(b"n").unwrap();
+        db.rpush(b"l1", &[Bytes::from("x")]).unwrap();
+        db.lpush(b"l2", &[Bytes::from("y"), Bytes::from("z")])
+            .unwrap();
+        assert_eq!(counts(&db), (3, 2));
 
-        // Purgar todas as chaves que expiraram
-        let now = Instant::now();
-        let mut expiry = shared.expiry.lock().await;
-        let mut to_remove = Vec::new();
+        // Mesmo nome reaproveitado com outro tipo
+        db.del(&["s1".into()]);
+        db.rpush(b"s1", &[Bytes::from("x")]).unwrap();
+        assert_eq!(counts(&db), (2, 3));
 
-        for entry in expiry.iter() {
-            if entry.0 <= now {
-                to_remove.push(entry.clone());
-            } else {
-                break; // BTreeSet é ordenado, os próximos são todos futuros
-            }
-        }
+        // SET sobre uma lista troca o tipo; BITOP sobrescreve o destino
+        db.set("l2".into(), Bytes::from("v"), &opts).unwrap();
+        db.bitop(BitOp::Or, b"l1", &["s2".into()]).unwrap();
+        assert_eq!(counts(&db), (4, 1));
 
-        for entry in &to_remove {
-            expiry.remove(entry);
-            // Só remove se realmente expirou (pode ter sido re-setado)
-            if let Some(e) = shared.data.get(&entry.1)
-                && e.is_expired()
-            {
-                drop(e);
-                shared.data.remove(&entry.1);
-                debug!("key expirada removida: {}", entry.1);
-            }
-        }
-    }
-}
+        // Pop até esvaziar remove a lista
+        db.lpop(b"s1", None).unwrap();
+        assert_eq!(counts(&db), (4, 0));
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        // Expiração: uma string expirada reaproveitada por RPUSH vira lista
+        db.expire(b"s2", 1, None);
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        db.rpush(b"s2", &[Bytes::from("x")]).unwrap();
+        assert_eq!(counts(&db), (3, 1));
+
+        db.expire(b"n", 0, None);
+        assert_eq!(counts(&db), (2, 1));
+
+        db.flush().await;
+        assert_eq!(counts(&db), (0, 0));
+    }
 
     #[tokio::test]
-    async fn get_set_basic() {
+    async fn expire_gt_extends() {
         let db = Db::new();
         let opts = SetOptions {
-            expire_ms: None,
+            expire: None,
             condition: None,
+            get: false,
+            persist: false,
         };
-        db.set("key".into(), Bytes::from("value"), &opts).unwrap();
-        assert_eq!(db.get("key"), Some(Bytes::from("value")));
+        db.set("lock".into(), Bytes::from("me"), &opts).unwrap();
+
+        // Sem TTL conta como infinito: GT não aplica
+        assert!(!db.expire(b"lock", 1_000, Some(ExpireCondition::Gt)));
+        assert!(db.expire(b"lock", 1_000, None));
+        let before = expires_at(&db, b"lock").unwrap();
+
+        assert!(db.expire(b"lock", 60_000, Some(ExpireCondition::Gt)));
+        assert!(expires_at(&db, b"lock").unwrap() > before);
     }
 
     #[tokio::test]
-    async fn get_nonexistent() {
+    async fn expire_gt_never_shortens() {
         let db = Db::new();
-        assert_eq!(db.get("missing"), None);
+        let opts = SetOptions {
+            expire: None,
+            condition: None,
+            get: false,
+            persist: false,
+        };
+        db.set("lock".into(), Bytes::from("me"), &opts).unwrap();
+        assert!(db.expire(b"lock", 60_000, None));
+        let before = expires_at(&db, b"lock").unwrap();
+
+        assert!(!db.expire(b"lock", 1_000, Some(ExpireCondition::Gt)));
+        assert_eq!(expires_at(&db, b"lock").unwrap(), before);
+
+        // LT encurta
+        assert!(db.expire(b"lock", 1_000, Some(ExpireCondition::Lt)));
+        assert!(expires_at(&db, b"lock").unwrap() < before);
     }
 
     #[tokio::test]
-    async fn set_nx_key_exists() {
+    async fn expire_nx_xx_and_missing_key() {
         let db = Db::new();
-        let opts_none = SetOptions {
-            expire_ms: None,
-            condition: None,
-        };
-        db.set("key".into(), Bytes::from("v1"), &opts_none).unwrap();
+        assert!(!db.expire(b"missing", 1_000, None));
 
-        let opts_nx = SetOptions {
-            expire_ms: None,
-            condition: Some(SetCondition::Nx),
-        };
-        let result = db.set("key".into(), Bytes::from("v2"), &opts_nx).unwrap();
-        assert!(!result); // não deve sobrescrever
-        assert_eq!(db.get("key"), Some(Bytes::from("v1")));
+        db.rpush(b"list", &[Bytes::from("a")]).unwrap();
+        assert!(!db.expire(b"list", 1_000, Some(ExpireCondition::Xx)));
+        assert!(db.expire(b"list", 1_000, Some(ExpireCondition::Nx)));
+        assert!(!db.expire(b"list", 2_000, Some(ExpireCondition::Nx)));
+        assert!(db.expire(b"list", 2_000, Some(ExpireCondition::Xx)));
+
+        // TTL não positivo remove a chave
+        assert!(db.expire(b"list", 0, None));
+        assert_eq!(db.exists(&["list".into()]), 0);
     }
 
     #[tokio::test]
-    async fn set_nx_key_not_exists() {
+    async fn keys_matches_glob_and_skips_expired() {
         let db = Db::new();
-        let opts_nx = SetOptions {
-            expire_ms: None,
-            condition: Some(SetCondition::Nx),
+        for key in ["user:1", "user:2", "users", "session:1", "user:*"] {
+            db.rpush(key.as_bytes(), &[Bytes::from("v")]).unwrap();
+        }
+        db.rpush(b"user:3", &[Bytes::from("v")]).unwrap();
+        db.expire(b"user:3", 0, None);
+        db.rpush(b"user:4", &[Bytes::from("v")]).unwrap();
+        db.set_active_expire(false);
+        db.expire(b"user:4", 1, None);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let sorted = |pattern: &[u8]| {
+            let mut keys = db.keys(pattern);
+            keys.sort();
+            keys
         };
-        let result = db.set("key".into(), Bytes::from("v1"), &opts_nx).unwrap();
-        assert!(result);
-        assert_eq!(db.get("key"), Some(Bytes::from("v1")));
+        assert_eq!(sorted(b"user:*"), ["user:*", "user:1", "user:2"]);
+        assert_eq!(sorted(br"user:\*"), ["user:*"]);
+        assert_eq!(sorted(b"*").len(), 5);
+        assert!(sorted(b"USER:*").is_empty());
+
+        let db = Db::with_config(DbConfig {
+            pattern_case_insensitive: true,
+            ..DbConfig::default()
+        });
+        db.rpush(b"user:1", &[Bytes::from("v")]).unwrap();
+        assert_eq!(db.keys(b"USER:*"), ["user:1"]);
+        // Buscas exatas continuam diferenciando maiúsculas
+        assert_eq!(db.exists(&["USER:1".into()]), 0);
     }
 
     #[tokio::test]
-    async fn set_xx_key_exists() {
+    async fn expire_at_future_and_past() {
         let db = Db::new();
-        let opts_none = SetOptions {
-            expire_ms: None,
-            condition: None,
-        };
-        db.set("key".into(), Bytes::from("v1"), &opts_none).unwrap();
+        let now = unix_time_ms() as i64;
+        assert!(!db.expire_at(b"missing", now + 1_000, None));
 
-        let opts_xx = SetOptions {
-            expire_ms: None,
-            condition: Some(SetCondition::Xx),
+        db.rpush(b"k", &[Bytes::from("v")]).unwrap();
+        assert!(db.expire_at(b"k", now + 1_000, None));
+        let ttl = db.pttl(b"k").unwrap().unwrap();
+        assert!(ttl > Duration::from_millis(900) && ttl <= Duration::from_secs(1));
+
+        // No passado: a chave sai na hora
+        assert!(db.expire_at(b"k", now - 1_000, None));
+        assert_eq!(db.pttl(b"k"), None);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_set_ttl_and_overwrite_keep_expiry_index_exact() {
+        let db = Db::new();
+        let writers: Vec<_> = (0..4)
+            .map(|t| {
+                let db = db.clone();
+                tokio::spawn(async move {
+                    for i in 0..2000 {
+                        let opts = SetOptions {
+                            expire: ((t + i) % 2 == 0).then_some(Expiry::Px(60_000)),
+                            condition: None,
+                            get: false,
+                            persist: false,
+                        };
+                        db.set("k".into(), Bytes::from("v"), &opts).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.await.unwrap();
+        }
+
+        // O índice tem exatamente o TTL atual da chave: nenhuma entrada
+        // velha de um SET sobrescrito, e nenhum TTL faltando
+        let expected: Vec<ExpiryEntry> = expires_at(&db, b"k")
+            .map(|at| ExpiryEntry(at, "k".into()))
+            .into_iter()
+            .collect();
+        let index: Vec<ExpiryEntry> = db.shared.expiry.lock().unwrap().iter().cloned().collect();
+        assert_eq!(index, expected);
+
+        // Sobrescrever sem TTL tira a chave do índice na hora
+        let opts = SetOptions {
+            expire: None,
+            condition: None,
+            get: false,
+            persist: false,
         };
-        let result = db.set("key".into(), Bytes::from("v2"), &opts_xx).unwrap();
-        assert!(result);
-        assert_eq!(db.get("key"), Some(Bytes::from("v2")));
+        db.set("k".into(), Bytes::from("v"), &opts).unwrap();
+        assert!(db.shared.expiry.lock().unwrap().is_empty());
     }
 
     #[tokio::test]
-    async fn set_xx_key_not_exists() {
+    async fn expired_key_reset_by_write_leaves_no_expiry_entry() {
         let db = Db::new();
-        let opts_xx = SetOptions {
-            expire_ms: None,
-            condition: Some(SetCondition::Xx),
-        };
-        let result = db.set("key".into(), Bytes::from("v1"), &opts_xx).unwrap();
-        assert!(!result);
-        assert_eq!(db.get("key"), None);
+        let index_len = |db: &Db| db.shared.expiry.lock().unwrap().len();
+
+        for _ in 0..50 {
+            db.incr(b"counter").unwrap();
+            db.rpush(b"list", &[Bytes::from("x")]).unwrap();
+            assert!(db.expire(b"counter", 1, None));
+            assert!(db.expire(b"list", 1, None));
+            assert_eq!(index_len(&db), 2);
+            // Sleep bloqueante: a purga não roda, então a escrita seguinte
+            // encontra a chave expirada e a recria sem TTL
+            std::thread::sleep(Duration::from_millis(2));
+        }
+        assert_eq!(db.incr(b"counter").unwrap(), 1);
+        assert_eq!(db.rpush(b"list", &[Bytes::from("x")]).unwrap(), 1);
+        assert_eq!(index_len(&db), 0);
     }
 
     #[tokio::test]
-    async fn set_with_expiry() {
+    async fn purge_resumes_after_panic() {
         let db = Db::new();
+        db.shared
+            .fail_purge
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+
         let opts = SetOptions {
-            expire_ms: Some(50), // 50ms
+            expire: Some(Expiry::Px(50)),
             condition: None,
+            get: false,
+            persist: false,
         };
         db.set("key".into(), Bytes::from("value"), &opts).unwrap();
-        assert_eq!(db.get("key"), Some(Bytes::from("value")));
+        assert_eq!(db.len(), 1);
 
-        tokio::time::sleep(Duration::from_millis(100)).await;
-        assert_eq!(db.get("key"), None);
+        // A task de purga entra em pânico, é reiniciada pelo supervisor
+        // e deve remover a chave sem nenhuma leitura lazy.
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert_eq!(db.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn set_rejects_value_over_max_bulk_len() {
+        let db = Db::with_config(DbConfig {
+            max_bulk_len: 4,
+            ..Default::default()
+        });
+        let opts = SetOptions {
+            expire: None,
+            condition: None,
+            get: false,
+            persist: false,
+        };
+        db.set("key".into(), Bytes::from("abcd"), &opts).unwrap();
+
+        assert!(matches!(
+            db.set("key".into(), Bytes::from("abcde"), &opts),
+            Err(StorageError::ValueTooLarge)
+        ));
+        // O valor anterior continua intacto
+        assert_eq!(db.get(b"key"), Some(Bytes::from("abcd")));
+    }
+
+    #[tokio::test]
+    async fn append_and_setrange_respect_max_bulk_len() {
+        let db = Db::with_config(DbConfig {
+            max_bulk_len: 4,
+            ..Default::default()
+        });
+        assert_eq!(db.append(b"key", b"abc").unwrap(), 3);
+
+        // APPEND que passaria do limite falha e não mexe no valor
+        assert!(matches!(
+            db.append(b"key", b"de"),
+            Err(StorageError::ValueTooLarge)
+        ));
+        assert_eq!(db.get(b"key"), Some(Bytes::from("abc")));
+        assert_eq!(db.append(b"key", b"d").unwrap(), 4);
+
+        // Nem APPEND nem SETRANGE grandes demais criam a chave
+        assert!(matches!(
+            db.append(b"new", b"abcde"),
+            Err(StorageError::ValueTooLarge)
+        ));
+        assert!(matches!(
+            db.setrange(b"new", 4, b"x"),
+            Err(StorageError::ValueTooLarge)
+        ));
+        assert!(matches!(
+            db.setrange(b"new", usize::MAX, b"x"),
+            Err(StorageError::ValueTooLarge)
+        ));
+        assert_eq!(db.exists(&["new".into()]), 0);
+        assert_eq!(db.setrange(b"new", 3, b"x").unwrap(), 4);
+    }
+
+    #[tokio::test]
+    async fn lrange_enforces_max_list_range() {
+        let db = Db::with_config(DbConfig {
+            max_list_range: Some(3),
+            ..Default::default()
+        });
+        let values: Vec<Bytes> = (0..10).map(|i| Bytes::from(i.to_string())).collect();
+        db.rpush(b"list", &values).unwrap();
+
+        assert!(matches!(
+            db.lrange(b"list", 0, -1),
+            Err(StorageError::RangeTooLarge(3))
+        ));
+        // Janelas dentro do limite funcionam, inclusive no fim da lista
+        assert_eq!(db.lrange(b"list", 0, 2).unwrap(), values[0..3].to_vec());
+        assert_eq!(db.lrange(b"list", -3, -1).unwrap(), values[7..].to_vec());
+        assert_eq!(db.lrange(b"list", 9, 100).unwrap(), values[9..].to_vec());
+    }
+
+    #[tokio::test]
+    async fn set_with_expire_jitter() {
+        let db = Db::with_config(DbConfig {
+            expire_jitter_ms: 1_000_000,
+            ..Default::default()
+        });
+        let opts = SetOptions {
+            expire: Some(Expiry::Px(10_000)),
+            condition: None,
+            get: false,
+            persist: false,
+        };
+        let ttl = Duration::from_millis(10_000);
+        let jitter = Duration::from_millis(1_000_000);
+        let before = Instant::now();
+        db.set("a".into(), Bytes::from("1"), &opts).unwrap();
+        db.set("b".into(), Bytes::from("2"), &opts).unwrap();
+        let after = Instant::now();
+
+        let a = expires_at(&db, b"a").unwrap();
+        let b = expires_at(&db, b"b").unwrap();
+        assert_ne!(a, b);
+        // O jitter só estende o TTL, e no máximo em `expire_jitter_ms`
+        for at in [a, b] {
+            assert!(at >= before + ttl, "deadline antes do TTL");
+            assert!(at <= after + ttl + jitter, "jitter acima do configurado");
+        }
     }
 
     #[tokio::test]
     async fn del_keys() {
         let db = Db::new();
         let opts = SetOptions {
-            expire_ms: None,
+            expire: None,
             condition: None,
+            get: false,
+            persist: false,
         };
         db.set("a".into(), Bytes::from("1"), &opts).unwrap();
         db.set("b".into(), Bytes::from("2"), &opts).unwrap();
 
         let deleted = db.del(&["a".into(), "b".into(), "c".into()]);
         assert_eq!(deleted, 2);
-        assert_eq!(db.get("a"), None);
+        assert_eq!(db.get(b"a"), None);
     }
 
     #[tokio::test]
     async fn exists_keys() {
         let db = Db::new();
         let opts = SetOptions {
-            expire_ms: None,
+            expire: None,
             condition: None,
+            get: false,
+            persist: false,
         };
         db.set("a".into(), Bytes::from("1"), &opts).unwrap();
 
         assert_eq!(db.exists(&["a".into(), "b".into()]), 1);
     }
 
+    #[tokio::test]
+    async fn exists_at_least_stops_at_threshold() {
+        let db = Db::new();
+        let opts = SetOptions {
+            expire: None,
+            condition: None,
+            get: false,
+            persist: false,
+        };
+        let keys: Vec<Bytes> = (0..100).map(|i| Bytes::from(format!("k{i}"))).collect();
+        for key in &keys[..10] {
+            db.set(key.clone(), Bytes::from("1"), &opts).unwrap();
+        }
+
+        assert!(db.exists_at_least(&keys, 10));
+        assert!(!db.exists_at_least(&keys, 11));
+        assert!(db.exists_at_least(&[], 0));
+
+        // Com 3 chaves existentes no começo, só as 3 primeiras são olhadas
+        let mut examined = 0;
+        let found = db.count_existing(keys.iter().inspect(|_| examined += 1), 3);
+        assert_eq!((found, examined), (3, 3));
+    }
+
     #[tokio::test]
     async fn incr_decr_basic() {
         let db = Db::new();
         // INCR em chave inexistente deve criar com 0+1=1
-        assert_eq!(db.incr("counter").unwrap(), 1);
-        assert_eq!(db.incr("counter").unwrap(), 2);
-        assert_eq!(db.decr("counter").unwrap(), 1);
-        assert_eq!(db.decr("counter").unwrap(), 0);
-        assert_eq!(db.decr("counter").unwrap(), -1);
+        assert_eq!(db.incr(b"counter").unwrap(), 1);
+        assert_eq!(db.incr(b"counter").unwrap(), 2);
+        assert_eq!(db.decr(b"counter").unwrap(), 1);
+        assert_eq!(db.decr(b"counter").unwrap(), 0);
+        assert_eq!(db.decr(b"counter").unwrap(), -1);
     }
 
     #[tokio::test]
     async fn incr_not_integer() {
         let db = Db::new();
         let opts = SetOptions {
-            expire_ms: None,
+            expire: None,
             condition: None,
+            get: false,
+            persist: false,
         };
         db.set("key".into(), Bytes::from("not_a_number"), &opts)
             .unwrap();
-        assert!(matches!(db.incr("key"), Err(StorageError::NotAnInteger)));
+        assert!(matches!(db.incr(b"key"), Err(StorageError::NotAnInteger)));
     }
 
     #[tokio::test]
     async fn incr_wrong_type() {
         let db = Db::new();
-        db.lpush("list", &[Bytes::from("a")]).unwrap();
-        assert!(matches!(db.incr("list"), Err(StorageError::WrongType)));
+        db.lpush(b"list", &[Bytes::from("a")]).unwrap();
+        assert!(matches!(db.incr(b"list"), Err(StorageError::WrongType)));
     }
 
     #[tokio::test]
     async fn lpush_rpush() {
         let db = Db::new();
         assert_eq!(
-            db.rpush("list", &[Bytes::from("a"), Bytes::from("b")])
+            db.rpush(b"list", &[Bytes::from("a"), Bytes::from("b")])
                 .unwrap(),
             2
         );
-        assert_eq!(db.lpush("list", &[Bytes::from("c")]).unwrap(), 3);
+        assert_eq!(db.lpush(b"list", &[Bytes::from("c")]).unwrap(), 3);
         // list = [c, a, b]
-        let range = db.lrange("list", 0, -1).unwrap();
+        let range = db.lrange(b"list", 0, -1).unwrap();
         assert_eq!(
             range,
             vec![Bytes::from("c"), Bytes::from("a"), Bytes::from("b")]
         );
     }
 
+    #[tokio::test]
+    async fn rpoplpush_moves_and_rotates() {
+        let db = Db::new();
+        db.rpush(b"src", &[Bytes::from("a"), Bytes::from("b")])
+            .unwrap();
+
+        assert_eq!(
+            db.rpoplpush(b"src", b"dst").unwrap(),
+            Some(Bytes::from("b"))
+        );
+        assert_eq!(db.lrange(b"dst", 0, -1).unwrap(), vec![Bytes::from("b")]);
+
+        // Mesma chave: rotaciona
+        db.rpush(b"src", &[Bytes::from("c")]).unwrap();
+        assert_eq!(
+            db.rpoplpush(b"src", b"src").unwrap(),
+            Some(Bytes::from("c"))
+        );
+        assert_eq!(
+            db.lrange(b"src", 0, -1).unwrap(),
+            vec![Bytes::from("c"), Bytes::from("a")]
+        );
+
+        assert_eq!(db.rpoplpush(b"missing", b"dst").unwrap(), None);
+
+        // Destino com tipo errado: nada sai da origem
+        db.incr(b"counter").unwrap();
+        assert!(matches!(
+            db.rpoplpush(b"src", b"counter"),
+            Err(StorageError::WrongType)
+        ));
+        assert_eq!(db.lrange(b"src", 0, -1).unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn concurrent_rpoplpush_keeps_source_order() {
+        // RPOPLPUSH disputando com um SET que troca o tipo do destino e um
+        // RPUSH na origem: se o move falha, a origem é exatamente a de antes
+        // mais o push, sem o elemento voltar fora de ordem
+        let opts = SetOptions {
+            expire: None,
+            condition: None,
+            get: false,
+            persist: false,
+        };
+        let items = |names: &[&'static str]| -> Vec<Bytes> {
+            names
+                .iter()
+                .map(|n| Bytes::from_static(n.as_bytes()))
+                .collect()
+        };
+        for _ in 0..500 {
+            let db = Db::new();
+            db.rpush(b"src", &items(&["a", "b", "c"])).unwrap();
+            let start = Arc::new(std::sync::Barrier::new(3));
+            let mover = {
+                let (db, start) = (db.clone(), start.clone());
+                std::thread::spawn(move || {
+                    start.wait();
+                    db.rpoplpush(b"src", b"dst")
+                })
+            };
+            let setter = {
+                let (db, start, opts) = (db.clone(), start.clone(), opts.clone());
+                std::thread::spawn(move || {
+                    start.wait();
+                    db.set("dst".into(), Bytes::from("x"), &opts).unwrap();
+                })
+            };
+            let pusher = {
+                let (db, start) = (db.clone(), start.clone());
+                std::thread::spawn(move || {
+                    start.wait();
+                    db.rpush(b"src", &[Bytes::from("d")]).unwrap();
+                })
+            };
+            let moved = mover.join().unwrap();
+            setter.join().unwrap();
+            pusher.join().unwrap();
+            let src = db.lrange(b"src", 0, -1).unwrap();
+            match moved {
+                Err(StorageError::WrongType) => assert_eq!(src, items(&["a", "b", "c", "d"])),
+                Ok(Some(moved)) => {
+                    assert_eq!(src.len(), 3);
+                    assert!(!src.contains(&moved));
+                }
+                other => panic!("resultado inesperado: {other:?}"),
+            }
+        }
+    }
+
     #[tokio::test]
     async fn lpop_rpop() {
         let db = Db::new();
         db.rpush(
-            "list",
+            b"list",
             &[Bytes::from("a"), Bytes::from("b"), Bytes::from("c")],
         )
         .unwrap();
 
-        let popped = db.lpop("list", None).unwrap();
-        assert_eq!(popped, vec![Bytes::from("a")]);
+        let popped = db.lpop(b"list", None).unwrap();
+        assert_eq!(popped, Some(vec![Bytes::from("a")]));
 
-        let popped = db.rpop("list", Some(2)).unwrap();
-        assert_eq!(popped, vec![Bytes::from("c"), Bytes::from("b")]);
+        let popped = db.lpop(b"list", Some(0)).unwrap();
+        assert_eq!(popped, Some(vec![]));
+
+        let popped = db.rpop(b"list", Some(2)).unwrap();
+        assert_eq!(popped, Some(vec![Bytes::from("c"), Bytes::from("b")]));
 
         // Lista deve estar vazia e a chave removida
-        assert_eq!(db.lrange("list", 0, -1).unwrap(), Vec::<Bytes>::new());
+        assert_eq!(db.lrange(b"list", 0, -1).unwrap(), Vec::<Bytes>::new());
+        assert_eq!(db.lpop(b"list", Some(1)).unwrap(), None);
     }
 
     #[tokio::test]
     async fn lrange_negative_indices() {
         let db = Db::new();
         db.rpush(
-            "list",
+            b"list",
             &[
                 Bytes::from("a"),
                 Bytes::from("b"),
@@ -585,11 +3072,11 @@ mod tests {
         .unwrap();
 
         // Últimos 2 elementos
-        let range = db.lrange("list", -2, -1).unwrap();
+        let range = db.lrange(b"list", -2, -1).unwrap();
         assert_eq!(range, vec![Bytes::from("c"), Bytes::from("d")]);
 
         // Primeiro ao penúltimo
-        let range = db.lrange("list", 0, -2).unwrap();
+        let range = db.lrange(b"list", 0, -2).unwrap();
         assert_eq!(
             range,
             vec![Bytes::from("a"), Bytes::from("b"), Bytes::from("c")]
@@ -599,12 +3086,12 @@ mod tests {
     #[tokio::test]
     async fn lrange_out_of_bounds() {
         let db = Db::new();
-        db.rpush("list", &[Bytes::from("a")]).unwrap();
+        db.rpush(b"list", &[Bytes::from("a")]).unwrap();
 
-        let range = db.lrange("list", 0, 100).unwrap();
+        let range = db.lrange(b"list", 0, 100).unwrap();
         assert_eq!(range, vec![Bytes::from("a")]);
 
-        let range = db.lrange("list", 5, 10).unwrap();
+        let range = db.lrange(b"list", 5, 10).unwrap();
         assert!(range.is_empty());
     }
 
@@ -612,12 +3099,14 @@ mod tests {
     async fn wrong_type_list_on_string() {
         let db = Db::new();
         let opts = SetOptions {
-            expire_ms: None,
+            expire: None,
             condition: None,
+            get: false,
+            persist: false,
         };
         db.set("key".into(), Bytes::from("value"), &opts).unwrap();
         assert!(matches!(
-            db.lpush("key", &[Bytes::from("a")]),
+            db.lpush(b"key", &[Bytes::from("a")]),
             Err(StorageError::WrongType)
         ));
     }
@@ -625,9 +3114,83 @@ mod tests {
     #[tokio::test]
     async fn wrong_type_string_on_list() {
         let db = Db::new();
-        db.rpush("list", &[Bytes::from("a")]).unwrap();
+        db.rpush(b"list", &[Bytes::from("a")]).unwrap();
         // GET em lista deve retornar None (não erro, similar ao Redis)
-        assert_eq!(db.get("list"), None);
+        assert_eq!(db.get(b"list"), None);
+    }
+
+    #[tokio::test]
+    async fn snapshot_rebuilds_dataset() {
+        let db = Db::new();
+        let opts = SetOptions {
+            expire: None,
+            condition: None,
+            get: false,
+            persist: false,
+        };
+        db.set("str".into(), Bytes::from("v"), &opts).unwrap();
+        db.set(
+            "ttl".into(),
+            Bytes::from("t"),
+            &SetOptions {
+                expire: Some(Expiry::Px(60_000)),
+                condition: None,
+                get: false,
+                persist: false,
+            },
+        )
+        .unwrap();
+        db.rpush(b"list", &[Bytes::from("a"), Bytes::from("b")])
+            .unwrap();
+
+        let copy = Db::new();
+        copy.set("stale".into(), Bytes::from("x"), &opts).unwrap();
+        copy.flush().await;
+        for cmd in db.snapshot() {
+            crate::aof::apply_command(cmd, &copy).await;
+        }
+
+        assert_eq!(copy.len(), 3);
+        assert_eq!(copy.get(b"str"), Some(Bytes::from("v")));
+        assert_eq!(copy.get(b"ttl"), Some(Bytes::from("t")));
+        assert!(expires_at(&copy, b"ttl").is_some());
+        assert_eq!(
+            copy.lrange(b"list", 0, -1).unwrap(),
+            vec![Bytes::from("a"), Bytes::from("b")]
+        );
+        assert_eq!(copy.get(b"stale"), None);
+    }
+
+    #[tokio::test]
+    async fn snapshot_waits_for_writes_being_propagated() {
+        let db = Db::new();
+        let permit = db.write_permit().await;
+        db.incr(b"counter").unwrap();
+
+        // Aplicada e ainda não propagada: o snapshot espera a propagação
+        let resync = {
+            let db = db.clone();
+            tokio::spawn(async move { db.snapshot_with(|| ()).await.0 })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!resync.is_finished());
+
+        // Uma escrita que chega depois espera o snapshot
+        let late = {
+            let db = db.clone();
+            tokio::spawn(async move {
+                let _permit = db.write_permit().await;
+                db.incr(b"counter").unwrap()
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!late.is_finished());
+
+        drop(permit);
+        let snapshot = resync.await.unwrap();
+        assert_eq!(late.await.unwrap(), 2);
+        assert_eq!(snapshot.len(), 1);
+        assert!(matches!(&snapshot[0], Command::Set { value, .. } if value == "1"));
     }
 
     #[tokio::test]
@@ -646,4 +3209,88 @@ mod tests {
         let count = db.publish("ch1", Bytes::from("hello")).await;
         assert_eq!(count, 0);
     }
+
+    #[tokio::test]
+    async fn keyevent_lpush_notification() {
+        let db = Db::with_config(DbConfig {
+            notify_keyspace_events: "El".parse().unwrap(),
+            ..Default::default()
+        });
+        let mut rx = db.subscribe("__keyevent@0__:lpush").await;
+
+        db.rpush(b"mylist", &[Bytes::from("a")]).unwrap();
+        db.lpush(b"mylist", &[Bytes::from("b")]).unwrap();
+
+        // Só o LPUSH chega, com a chave como payload
+        assert_eq!(rx.try_recv().unwrap(), Bytes::from("mylist"));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn expired_event_is_distinct_from_del() {
+        let db = Db::with_config(DbConfig {
+            notify_keyspace_events: "KEA".parse().unwrap(),
+            ..Default::default()
+        });
+        let mut expired = db.subscribe("__keyevent@0__:expired").await;
+        let mut keyspace = db.subscribe("__keyspace@0__:temp").await;
+
+        let opts = SetOptions {
+            expire: Some(Expiry::Px(20)),
+            condition: None,
+            get: false,
+            persist: false,
+        };
+        db.set("temp".into(), Bytes::from("v"), &opts).unwrap();
+        assert_eq!(keyspace.try_recv().unwrap(), Bytes::from("set"));
+        assert_eq!(keyspace.try_recv().unwrap(), Bytes::from("expire"));
+
+        // Removida pela task de purga, sem nenhum acesso
+        let key = tokio::time::timeout(Duration::from_secs(1), expired.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(key, Bytes::from("temp"));
+        assert_eq!(keyspace.try_recv().unwrap(), Bytes::from("expired"));
+        assert!(keyspace.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn expired_dump_publishes_final_value() {
+        let db = Db::with_config(DbConfig {
+            expired_dump: Some("expired".into()),
+            ..Default::default()
+        });
+        let mut rx = db.subscribe("expired").await;
+
+        let opts = SetOptions {
+            expire: Some(Expiry::Px(20)),
+            condition: None,
+            get: false,
+            persist: false,
+        };
+        db.set("session".into(), Bytes::from("user:42"), &opts)
+            .unwrap();
+
+        // Entregue pela task de purga, sem nenhum acesso à chave
+        let msg = tokio::time::timeout(Duration::from_secs(1), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        let frame = stormdb_protocol::Frame::parse(&mut std::io::Cursor::new(&msg[..])).unwrap();
+        assert_eq!(
+            Command::from_frame(frame).unwrap(),
+            Command::Set {
+                key: "session".into(),
+                value: Bytes::from("user:42"),
+                options: SetOptions {
+                    expire: None,
+                    condition: None,
+                    get: false,
+                    persist: true,
+                },
+            }
+        );
+        assert_eq!(db.get(b"session"), None);
+    }
 }
@@ -1,6 +1,7 @@
 use bytes::Bytes;
 use std::collections::VecDeque;
-use tokio::time::Instant;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::time::{Duration, Instant};
 
 /// Tipo do valor armazenado.
 #[derive(Debug, Clone)]
@@ -9,6 +10,16 @@ pub enum Value {
     List(VecDeque<Bytes>),
 }
 
+impl Value {
+    /// Nome do tipo, como no `TYPE` do Redis.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::List(_) => "list",
+        }
+    }
+}
+
 /// Entrada no store: valor + TTL opcional.
 #[derive(Debug, Clone)]
 pub struct Entry {
@@ -21,9 +32,74 @@ impl Entry {
         Self { value, expires_at }
     }
 
+    /// Bytes de dados do valor (o conteúdo da string ou a soma dos
+    /// elementos da lista).
+    pub fn payload_size(&self) -> usize {
+        match &self.value {
+            Value::String(data) => data.len(),
+            Value::List(list) => list.iter().map(Bytes::len).sum(),
+        }
+    }
+
+    /// Tamanho aproximado em memória da entrada (sem a chave): o payload mais
+    /// as estruturas que o guardam. Não conta o overhead do alocador.
+    pub fn estimated_size(&self) -> usize {
+        let elements = match &self.value {
+            Value::String(_) => 0,
+            Value::List(list) => list.len() * std::mem::size_of::<Bytes>(),
+        };
+        std::mem::size_of::<Entry>() + elements + self.payload_size()
+    }
+
+    /// Encoding reportado pelo `OBJECT ENCODING`, no vocabulário do Redis.
+    /// É só uma classificação: o armazenamento é sempre o mesmo `Bytes`.
+    /// Strings com um i64 canônico são `int`; as demais são `embstr` até
+    /// `embstr_max_len` bytes e `raw` acima disso.
+    pub fn encoding(&self, embstr_max_len: usize) -> &'static str {
+        match &self.value {
+            Value::String(data) if is_canonical_i64(data) => "int",
+            Value::String(data) if data.len() <= embstr_max_len => "embstr",
+            Value::String(_) => "raw",
+            Value::List(_) => "quicklist",
+        }
+    }
+
     pub fn is_expired(&self) -> bool {
         self.expires_at
             .map(|t| Instant::now() >= t)
             .unwrap_or(false)
     }
 }
+
+/// Horário atual do relógio de parede, em ms desde a época Unix.
+pub fn unix_time_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// Deadline monotônico (`Entry::expires_at`) equivalente a um instante do
+/// relógio de parede. Instantes já passados viram "agora", ou seja, vencidos.
+pub(crate) fn instant_at_unix_ms(unix_ms: u64) -> Instant {
+    Instant::now() + Duration::from_millis(unix_ms.saturating_sub(unix_time_ms()))
+}
+
+/// Instante do relógio de parede, em Unix ms, equivalente a um deadline
+/// monotônico. É o que vai para o AOF: o `Instant` não sobrevive a um restart.
+/// `None` se passa de i64::MAX ms, que um `PXAT` não consegue representar.
+pub(crate) fn unix_ms_at(deadline: Instant) -> Option<u64> {
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    u64::try_from(remaining.as_millis())
+        .ok()
+        .and_then(|ms| unix_time_ms().checked_add(ms))
+        .filter(|&at| at <= i64::MAX as u64)
+}
+
+/// Se `data` é a forma canônica de um i64 (sem `+`, zeros à esquerda ou
+/// espaços), que é o que o Redis guarda como `int`.
+fn is_canonical_i64(data: &[u8]) -> bool {
+    std::str::from_utf8(data)
+        .ok()
+        .and_then(|s| s.parse::<i64>().ok())
+        .is_some_and(|n| n.to_string().as_bytes() == data)
+}
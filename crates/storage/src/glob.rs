@@ -0,0 +1,221 @@
+/// Padrão glob no estilo do Redis (`*`, `?`, `[abc]`, `[^a-z]`, `\` para
+/// escapar), compilado uma vez e reutilizado contra várias chaves — o
+/// padrão não é re-interpretado a cada comparação.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Glob {
+    tokens: Vec<Token>,
+    /// Compara letras ASCII sem diferenciar maiúsculas (`pattern-case-insensitive`).
+    nocase: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Byte(u8),
+    /// `?`: um byte qualquer.
+    Any,
+    /// `*`: qualquer sequência, inclusive vazia.
+    Star,
+    /// `[...]`: um byte dentro (ou, com `^`, fora) dos intervalos.
+    Class {
+        negated: bool,
+        ranges: Vec<(u8, u8)>,
+    },
+}
+
+impl Token {
+    fn matches(&self, byte: u8, nocase: bool) -> bool {
+        match self {
+            Token::Byte(b) if nocase => b.eq_ignore_ascii_case(&byte),
+            Token::Byte(b) => *b == byte,
+            Token::Any => true,
+            Token::Star => unreachable!("`*` é tratado pelo matcher"),
+            Token::Class { negated, ranges } => {
+                let in_class = |c: u8| ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi);
+                let found = if nocase {
+                    in_class(byte.to_ascii_lowercase()) || in_class(byte.to_ascii_uppercase())
+                } else {
+                    in_class(byte)
+                };
+                found != *negated
+            }
+        }
+    }
+}
+
+impl Glob {
+    pub fn new(pattern: &[u8]) -> Self {
+        let mut tokens = Vec::with_capacity(pattern.len());
+        let mut i = 0;
+        while i < pattern.len() {
+            match pattern[i] {
+                b'*' => {
+                    // `**` equivale a `*`
+                    if tokens.last() != Some(&Token::Star) {
+                        tokens.push(Token::Star);
+                    }
+                }
+                b'?' => tokens.push(Token::Any),
+                b'\\' if i + 1 < pattern.len() => {
+                    i += 1;
+                    tokens.push(Token::Byte(pattern[i]));
+                }
+                b'[' => match parse_class(&pattern[i + 1..]) {
+                    Some((token, len)) => {
+                        tokens.push(token);
+                        i += len;
+                    }
+                    // `[` sem `]` vale como literal
+                    None => tokens.push(Token::Byte(b'[')),
+                },
+                b => tokens.push(Token::Byte(b)),
+            }
+            i += 1;
+        }
+        Self {
+            tokens,
+            nocase: false,
+        }
+    }
+
+    /// Liga a comparação ASCII case-insensitive. Só afeta o casamento de
+    /// padrões; buscas exatas de chave nunca passam por aqui.
+    pub fn case_insensitive(mut self, nocase: bool) -> Self {
+        self.nocase = nocase;
+        self
+    }
+
+    /// Se `text` casa com o padrão inteiro. Guarda só o último `*` visto
+    /// para backtracking, então não há recursão nem explosão exponencial.
+    pub fn matches(&self, text: &[u8]) -> bool {
+        let (mut p, mut t) = (0, 0);
+        // (token depois do último `*`, posição do texto onde ele começou)
+        let mut star: Option<(usize, usize)> = None;
+
+        while t < text.len() {
+            match self.tokens.get(p) {
+                Some(Token::Star) => {
+                    star = Some((p + 1, t));
+                    p += 1;
+                    continue;
+                }
+                Some(token) if token.matches(text[t], self.nocase) => {
+                    p += 1;
+                    t += 1;
+                    continue;
+                }
+                _ => {}
+            }
+            // Falhou: o último `*` absorve mais um byte
+            match star {
+                Some((after, start)) => {
+                    star = Some((after, start + 1));
+                    p = after;
+                    t = start + 1;
+                }
+                None => return false,
+            }
+        }
+        self.tokens[p..].iter().all(|token| *token == Token::Star)
+    }
+}
+
+/// Interpreta o conteúdo de uma classe logo após o `[`. Retorna o token e
+/// quantos bytes foram consumidos (incluindo o `]`), ou `None` se a classe
+/// não fecha.
+fn parse_class(pattern: &[u8]) -> Option<(Token, usize)> {
+    let mut i = 0;
+    let negated = pattern.first() == Some(&b'^');
+    if negated {
+        i += 1;
+    }
+
+    let mut ranges = Vec::new();
+    loop {
+        let mut lo = *pattern.get(i)?;
+        match lo {
+            b']' => return Some((Token::Class { negated, ranges }, i + 1)),
+            b'\\' => {
+                i += 1;
+                lo = *pattern.get(i)?;
+            }
+            _ => {}
+        }
+
+        // `a-z`; um `-` no fim (`[a-]`) é literal
+        if pattern.get(i + 1) == Some(&b'-') && pattern.get(i + 2).is_some_and(|&c| c != b']') {
+            let mut hi = pattern[i + 2];
+            i += 2;
+            if hi == b'\\' {
+                i += 1;
+                hi = *pattern.get(i)?;
+            }
+            ranges.push((lo.min(hi), lo.max(hi)));
+        } else {
+            ranges.push((lo, lo));
+        }
+        i += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, text: &str) -> bool {
+        Glob::new(pattern.as_bytes()).matches(text.as_bytes())
+    }
+
+    #[test]
+    fn wildcards_and_classes() {
+        assert!(matches("*", ""));
+        assert!(matches("user:*", "user:42"));
+        assert!(matches("*:42", "user:42"));
+        assert!(matches("u*r:*2", "user:42"));
+        assert!(!matches("user:*", "users"));
+        assert!(matches("h?llo", "hello"));
+        assert!(!matches("h?llo", "hllo"));
+        assert!(matches("h[ae]llo", "hallo"));
+        assert!(!matches("h[ae]llo", "hillo"));
+        assert!(matches("h[^e]llo", "hallo"));
+        assert!(!matches("h[^e]llo", "hello"));
+        assert!(matches("key[0-9]", "key7"));
+        assert!(matches("key[9-0]", "key7"));
+        assert!(!matches("key[0-9]", "keyx"));
+        assert!(matches("a*b*c", "aXXbYYbZZc"));
+        assert!(!matches("a*b*c", "aXXbYYbZZ"));
+    }
+
+    #[test]
+    fn escaped_metacharacters_match_literally() {
+        assert!(matches(r"star\*", "star*"));
+        assert!(!matches(r"star\*", "starry"));
+        assert!(matches(r"what\?", "what?"));
+        assert!(!matches(r"what\?", "whatX"));
+        assert!(matches(r"\[tag\]", "[tag]"));
+        assert!(matches(r"[\]]", "]"));
+        assert!(matches(r"back\\slash", r"back\slash"));
+        // `[` sem fechamento é literal
+        assert!(matches("a[b", "a[b"));
+    }
+
+    #[test]
+    fn case_sensitivity_is_opt_in() {
+        // Padrão: byte a byte, como o Redis
+        assert!(!matches("USER:*", "user:1"));
+        let glob = Glob::new(b"USER:*").case_insensitive(true);
+        assert!(glob.matches(b"user:1"));
+        assert!(glob.matches(b"User:1"));
+        assert!(!glob.matches(b"users:1"));
+
+        let class = Glob::new(b"[A-C]?[^x]").case_insensitive(true);
+        assert!(class.matches(b"b1y"));
+        assert!(!class.matches(b"b1X"));
+    }
+
+    #[test]
+    fn binary_keys() {
+        let glob = Glob::new(b"\x00*\xff");
+        assert!(glob.matches(b"\x00abc\xff"));
+        assert!(!glob.matches(b"\x01abc\xff"));
+    }
+}
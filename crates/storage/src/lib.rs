@@ -3,9 +3,20 @@
 pub mod aof;
 mod db;
 mod entry;
+mod glob;
+mod notify;
 mod pubsub;
+mod seed;
+mod supervisor;
 
-pub use aof::{AofWriter, FsyncPolicy, create_aof, is_write_command, replay_aof};
-pub use db::Db;
-pub use entry::Value;
-pub use pubsub::PubSub;
+pub use aof::{
+    AofMessage, AofSender, AofWriter, FsyncPolicy, apply_command, check_aof_writable, create_aof,
+    is_write_command, replay_aof,
+};
+pub use db::{Db, DbConfig, MemoryStats, ObjectStats, WritePermit};
+pub use entry::{Value, unix_time_ms};
+pub use glob::Glob;
+pub use notify::NotifyFlags;
+pub use pubsub::{PatternMessage, PubSub};
+pub use seed::load_seed_file;
+pub use supervisor::supervise;
@@ -0,0 +1,159 @@
+use std::str::FromStr;
+
+use bytes::Bytes;
+
+use crate::pubsub::PubSub;
+
+/// Classes de eventos habilitadas em `notify-keyspace-events`, no formato
+/// do Redis (ex.: `"KEA"`, `"Elx"`). Vazio = notificações desligadas.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NotifyFlags(u8);
+
+impl NotifyFlags {
+    /// `K`: publica em `__keyspace@0__:<chave>` com o evento como mensagem.
+    pub const KEYSPACE: Self = Self(1 << 0);
+    /// `E`: publica em `__keyevent@0__:<evento>` com a chave como mensagem.
+    pub const KEYEVENT: Self = Self(1 << 1);
+    /// `g`: comandos genéricos (DEL, EXPIRE, RENAME).
+    pub const GENERIC: Self = Self(1 << 2);
+    /// `$`: comandos de string.
+    pub const STRING: Self = Self(1 << 3);
+    /// `l`: comandos de lista.
+    pub const LIST: Self = Self(1 << 4);
+    /// `x`: chaves removidas por expiração.
+    pub const EXPIRED: Self = Self(1 << 5);
+    /// `A`: alias para todas as classes de evento (`g$lx`).
+    pub const ALL: Self = Self(Self::GENERIC.0 | Self::STRING.0 | Self::LIST.0 | Self::EXPIRED.0);
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Se algum evento pode ser publicado: exige K ou E e ao menos uma classe.
+    pub fn is_enabled(self) -> bool {
+        self.0 & (Self::KEYSPACE.0 | Self::KEYEVENT.0) != 0 && self.0 & Self::ALL.0 != 0
+    }
+}
+
+impl FromStr for NotifyFlags {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut flags = 0;
+        for c in s.chars() {
+            flags |= match c {
+                'K' => Self::KEYSPACE.0,
+                'E' => Self::KEYEVENT.0,
+                'g' => Self::GENERIC.0,
+                '$' => Self::STRING.0,
+                'l' => Self::LIST.0,
+                'x' => Self::EXPIRED.0,
+                'A' => Self::ALL.0,
+                _ => return Err(format!("classe inválida: '{c}'. Use: K, E, g, $, l, x, A")),
+            };
+        }
+        Ok(Self(flags))
+    }
+}
+
+/// Evento de keyspace. Tabela central com o nome (o mesmo do Redis) e a
+/// classe de cada evento emitido pelos comandos de escrita.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum KeyEvent {
+    /// SET, e BITOP com resultado não vazio.
+    Set,
+    /// DEL, EXPIRE com TTL <= 0, BITOP com resultado vazio e pops que
+    /// esvaziam a lista.
+    Del,
+    /// EXPIRE/PEXPIRE e SET com EX/PX.
+    Expire,
+    /// RENAME/RENAMENX: um evento na chave de origem e outro no destino.
+    RenameFrom,
+    RenameTo,
+    /// Chave removida pela purga ou ao ser acessada depois do TTL.
+    Expired,
+    /// INCR/DECR.
+    IncrBy,
+    SetRange,
+    Append,
+    LPush,
+    RPush,
+    LPop,
+    RPop,
+}
+
+impl KeyEvent {
+    fn name(self) -> &'static str {
+        match self {
+            KeyEvent::Set => "set",
+            KeyEvent::Del => "del",
+            KeyEvent::Expire => "expire",
+            KeyEvent::RenameFrom => "rename_from",
+            KeyEvent::RenameTo => "rename_to",
+            KeyEvent::Expired => "expired",
+            KeyEvent::IncrBy => "incrby",
+            KeyEvent::SetRange => "setrange",
+            KeyEvent::Append => "append",
+            KeyEvent::LPush => "lpush",
+            KeyEvent::RPush => "rpush",
+            KeyEvent::LPop => "lpop",
+            KeyEvent::RPop => "rpop",
+        }
+    }
+
+    fn class(self) -> NotifyFlags {
+        match self {
+            KeyEvent::Del | KeyEvent::Expire | KeyEvent::RenameFrom | KeyEvent::RenameTo => {
+                NotifyFlags::GENERIC
+            }
+            KeyEvent::Set | KeyEvent::IncrBy | KeyEvent::SetRange | KeyEvent::Append => {
+                NotifyFlags::STRING
+            }
+            KeyEvent::LPush | KeyEvent::RPush | KeyEvent::LPop | KeyEvent::RPop => {
+                NotifyFlags::LIST
+            }
+            KeyEvent::Expired => NotifyFlags::EXPIRED,
+        }
+    }
+
+    /// Se o evento deve ser publicado com as classes habilitadas.
+    pub(crate) fn enabled(self, flags: NotifyFlags) -> bool {
+        flags.is_enabled() && flags.contains(self.class())
+    }
+}
+
+/// Publica `event` sobre `key` nos canais de keyspace e/ou keyevent,
+/// conforme `flags`. Quem chama já checou `KeyEvent::enabled`.
+pub(crate) fn publish_event(pubsub: &mut PubSub, flags: NotifyFlags, event: KeyEvent, key: &[u8]) {
+    let name = event.name();
+    if flags.contains(NotifyFlags::KEYSPACE) {
+        // Canais são strings: chaves binárias entram no nome de forma lossy
+        let channel = format!("__keyspace@0__:{}", String::from_utf8_lossy(key));
+        pubsub.publish(&channel, Bytes::from_static(name.as_bytes()));
+    }
+    if flags.contains(NotifyFlags::KEYEVENT) {
+        let channel = format!("__keyevent@0__:{name}");
+        pubsub.publish(&channel, Bytes::copy_from_slice(key));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_flags() {
+        let flags: NotifyFlags = "KEA".parse().unwrap();
+        assert!(flags.contains(NotifyFlags::KEYSPACE));
+        assert!(KeyEvent::Expired.enabled(flags));
+
+        let flags: NotifyFlags = "El".parse().unwrap();
+        assert!(KeyEvent::LPush.enabled(flags));
+        assert!(!KeyEvent::Set.enabled(flags));
+
+        // Classes sem K/E não publicam nada
+        assert!(!KeyEvent::Set.enabled("A".parse().unwrap()));
+        assert!(!NotifyFlags::default().is_enabled());
+        assert!("KEz".parse::<NotifyFlags>().is_err());
+    }
+}
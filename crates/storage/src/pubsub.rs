@@ -2,26 +2,72 @@ use bytes::Bytes;
 use std::collections::HashMap;
 use tokio::sync::broadcast;
 
+use crate::glob::Glob;
+
 const CHANNEL_CAPACITY: usize = 128;
 
+/// Mensagem entregue a quem se inscreveu por padrão: o canal em que foi
+/// publicada e o conteúdo.
+pub type PatternMessage = (String, Bytes);
+
 /// Gerenciador de canais pub/sub.
 #[derive(Debug)]
 pub struct PubSub {
     channels: HashMap<String, broadcast::Sender<Bytes>>,
+    /// Inscrições de PSUBSCRIBE, pelo padrão original.
+    patterns: HashMap<String, (Glob, broadcast::Sender<PatternMessage>)>,
+    /// Padrões casam sem diferenciar maiúsculas (`pattern-case-insensitive`).
+    /// Canais de SUBSCRIBE continuam exatos.
+    nocase_patterns: bool,
 }
 
 impl PubSub {
     pub fn new() -> Self {
+        Self::with_case_insensitive_patterns(false)
+    }
+
+    pub fn with_case_insensitive_patterns(nocase: bool) -> Self {
         Self {
             channels: HashMap::new(),
+            patterns: HashMap::new(),
+            nocase_patterns: nocase,
         }
     }
 
     /// Publica uma mensagem no canal. Retorna o número de subscribers que receberam.
-    pub fn publish(&self, channel: &str, message: Bytes) -> usize {
-        match self.channels.get(channel) {
-            Some(tx) => tx.send(message).unwrap_or(0),
-            None => 0,
+    ///
+    /// Se todos os receivers já foram dropados (ex: conexão fechada sem
+    /// UNSUBSCRIBE), o canal é removido do mapa. Não há corrida com
+    /// `subscribe`: ambos rodam sob o mesmo lock do `Db`, e um subscribe
+    /// concluído já tem seu receiver contado.
+    ///
+    /// A contagem inclui os subscribers de padrões que casam com o canal.
+    pub fn publish(&mut self, channel: &str, message: Bytes) -> usize {
+        let mut count = 0;
+        if !self.patterns.is_empty() {
+            self.patterns.retain(|_, (glob, tx)| {
+                if !glob.matches(channel.as_bytes()) {
+                    return true;
+                }
+                match tx.send((channel.to_string(), message.clone())) {
+                    Ok(n) => {
+                        count += n;
+                        true
+                    }
+                    Err(_) => false,
+                }
+            });
+        }
+
+        let Some(tx) = self.channels.get(channel) else {
+            return count;
+        };
+        match tx.send(message) {
+            Ok(n) => count + n,
+            Err(_) => {
+                self.channels.remove(channel);
+                count
+            }
         }
     }
 
@@ -34,6 +80,27 @@ impl PubSub {
         tx.subscribe()
     }
 
+    /// Inscreve-se em todos os canais que casam com o padrão glob.
+    pub fn psubscribe(&mut self, pattern: &str) -> broadcast::Receiver<PatternMessage> {
+        let nocase = self.nocase_patterns;
+        let (_, tx) = self.patterns.entry(pattern.to_string()).or_insert_with(|| {
+            (
+                Glob::new(pattern.as_bytes()).case_insensitive(nocase),
+                broadcast::channel(CHANNEL_CAPACITY).0,
+            )
+        });
+        tx.subscribe()
+    }
+
+    /// Remove um padrão se não tem mais subscribers.
+    pub fn cleanup_pattern(&mut self, pattern: &str) {
+        if let Some((_, tx)) = self.patterns.get(pattern)
+            && tx.receiver_count() == 0
+        {
+            self.patterns.remove(pattern);
+        }
+    }
+
     /// Remove um canal se não tem mais subscribers.
     pub fn cleanup_channel(&mut self, channel: &str) {
         if let Some(tx) = self.channels.get(channel)
@@ -49,3 +116,52 @@ impl Default for PubSub {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn publish_reclaims_channel_without_subscribers() {
+        let mut pubsub = PubSub::new();
+        let rx = pubsub.subscribe("ch");
+        assert!(pubsub.channels.contains_key("ch"));
+
+        // Subscriber some sem UNSUBSCRIBE (ex: conexão caiu)
+        drop(rx);
+
+        assert_eq!(pubsub.publish("ch", Bytes::from("msg")), 0);
+        assert!(!pubsub.channels.contains_key("ch"));
+    }
+
+    #[test]
+    fn pattern_subscribers_receive_matching_channels() {
+        let mut pubsub = PubSub::new();
+        let mut news = pubsub.psubscribe("news.*");
+        let _exact = pubsub.subscribe("news.tech");
+
+        // O subscriber exato e o do padrão
+        assert_eq!(pubsub.publish("news.tech", Bytes::from("a")), 2);
+        assert_eq!(pubsub.publish("sports", Bytes::from("b")), 0);
+        assert_eq!(
+            news.try_recv().unwrap(),
+            ("news.tech".to_string(), Bytes::from("a"))
+        );
+        assert!(news.try_recv().is_err());
+
+        drop(news);
+        pubsub.cleanup_pattern("news.*");
+        assert!(pubsub.patterns.is_empty());
+    }
+
+    #[test]
+    fn case_insensitive_patterns_leave_channels_exact() {
+        let mut pubsub = PubSub::with_case_insensitive_patterns(true);
+        let _pattern = pubsub.psubscribe("USER:*");
+        let _exact = pubsub.subscribe("USER:1");
+
+        // Só o padrão casa; o canal exato continua diferenciando maiúsculas
+        assert_eq!(pubsub.publish("user:1", Bytes::from("a")), 1);
+        assert_eq!(pubsub.publish("USER:1", Bytes::from("b")), 2);
+    }
+}
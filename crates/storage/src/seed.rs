@@ -0,0 +1,101 @@
+use std::path::Path;
+
+use tracing::{info, warn};
+
+use stormdb_protocol::{Command, Frame, tokenize};
+
+use crate::Db;
+use crate::aof::{apply_command, replay_frames};
+
+/// Carrega um arquivo de fixtures e aplica seus comandos de escrita no Db.
+///
+/// O arquivo pode estar em RESP (se começar com `*`) ou em texto, um comando
+/// por linha no formato do stormdb-cli; linhas vazias e iniciadas por `#`
+/// são ignoradas. Linhas inválidas são logadas e puladas. Os comandos não
+/// passam pelo AOF nem pela replicação.
+pub async fn load_seed_file(path: &Path, db: &Db) -> std::io::Result<usize> {
+    let data = tokio::fs::read(path).await?;
+
+    if data.trim_ascii_start().starts_with(b"*") {
+        let count = replay_frames(data.trim_ascii_start(), db).await;
+        info!("seed {path:?}: {count} comandos aplicados");
+        return Ok(count);
+    }
+
+    let text = String::from_utf8(data)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+    let mut count = 0;
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let tokens = tokenize(line);
+        let args: Vec<&str> = tokens.iter().map(String::as_str).collect();
+        match Command::from_frame(Frame::array_from_strs(&args)) {
+            Ok(cmd) => {
+                apply_command(cmd, db).await;
+                count += 1;
+            }
+            Err(e) => warn!(
+                "seed {path:?}, linha {}: comando inválido ignorado: {e}",
+                i + 1
+            ),
+        }
+    }
+
+    info!("seed {path:?}: {count} comandos aplicados");
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn seed_text_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("seed.txt");
+        tokio::fs::write(
+            &path,
+            "# fixtures\n\
+             SET greeting \"hello world\"\n\
+             \n\
+             RPUSH queue a b c\n\
+             LRANGE\n\
+             SET counter 10\n",
+        )
+        .await
+        .unwrap();
+
+        let db = Db::new();
+        // LRANGE sem argumentos é inválido e só é logado
+        assert_eq!(load_seed_file(&path, &db).await.unwrap(), 3);
+
+        assert_eq!(db.get(b"greeting"), Some(Bytes::from("hello world")));
+        assert_eq!(db.get(b"counter"), Some(Bytes::from("10")));
+        assert_eq!(
+            db.lrange(b"queue", 0, -1).unwrap(),
+            vec![Bytes::from("a"), Bytes::from("b"), Bytes::from("c")]
+        );
+    }
+
+    #[tokio::test]
+    async fn seed_resp_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("seed.resp");
+        let mut buf = bytes::BytesMut::new();
+        Frame::array_from_strs(&["SET", "k", "v"]).encode(&mut buf);
+        Frame::array_from_strs(&["RPUSH", "l", "x"]).encode(&mut buf);
+        tokio::fs::write(&path, &buf).await.unwrap();
+
+        let db = Db::new();
+        assert_eq!(load_seed_file(&path, &db).await.unwrap(), 2);
+        assert_eq!(db.get(b"k"), Some(Bytes::from("v")));
+        assert_eq!(db.lrange(b"l", 0, -1).unwrap(), vec![Bytes::from("x")]);
+    }
+}
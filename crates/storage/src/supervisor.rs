@@ -0,0 +1,103 @@
+use std::future::Future;
+
+use tokio::task::JoinHandle;
+use tokio::time::{Duration, Instant, sleep};
+use tracing::{error, warn};
+
+/// Backoff inicial entre restarts de uma task que entrou em pânico.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+/// Teto do backoff exponencial. Uma task que rodou por mais que isso antes
+/// de entrar em pânico volta ao `INITIAL_BACKOFF`.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Executa uma task de fundo de longa duração sob supervisão.
+///
+/// `factory` cria uma nova instância da task a cada (re)início. Se a task
+/// entrar em pânico, o erro é logado e ela é re-spawnada com backoff
+/// exponencial. Se terminar normalmente (ou for cancelada), o supervisor
+/// também termina.
+pub fn supervise<F, Fut>(name: &'static str, mut factory: F) -> JoinHandle<()>
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut backoff = INITIAL_BACKOFF;
+        loop {
+            let started = Instant::now();
+            match tokio::spawn(factory()).await {
+                Ok(()) => return,
+                Err(e) if e.is_panic() => {
+                    // Falha isolada depois de um bom tempo rodando não herda
+                    // o backoff acumulado por pânicos antigos
+                    if started.elapsed() > MAX_BACKOFF {
+                        backoff = INITIAL_BACKOFF;
+                    }
+                    error!("task '{name}' entrou em pânico, reiniciando em {backoff:?}: {e}");
+                    sleep(backoff).await;
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                }
+                Err(e) => {
+                    warn!("task '{name}' cancelada: {e}");
+                    return;
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    #[tokio::test]
+    async fn restarts_after_panic() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counter = runs.clone();
+
+        let handle = supervise("test", move || {
+            let counter = counter.clone();
+            async move {
+                if counter.fetch_add(1, Ordering::SeqCst) < 2 {
+                    panic!("falha simulada");
+                }
+            }
+        });
+
+        handle.await.unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn backoff_resets_after_a_long_run() {
+        let starts = Arc::new(Mutex::new(Vec::new()));
+        let recorded = starts.clone();
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        let handle = supervise("test", move || {
+            let recorded = recorded.clone();
+            let runs = runs.clone();
+            async move {
+                recorded.lock().unwrap().push(Instant::now());
+                match runs.fetch_add(1, Ordering::SeqCst) {
+                    0 | 1 => panic!("falha simulada"),
+                    2 => {
+                        sleep(MAX_BACKOFF * 2).await;
+                        panic!("falha depois de rodar bem");
+                    }
+                    _ => {}
+                }
+            }
+        });
+
+        handle.await.unwrap();
+        let starts = starts.lock().unwrap();
+        assert_eq!(starts.len(), 4);
+        assert_eq!(starts[1] - starts[0], INITIAL_BACKOFF);
+        assert_eq!(starts[2] - starts[1], INITIAL_BACKOFF * 2);
+        // Sem o reset, o terceiro restart esperaria 4x o backoff inicial
+        assert_eq!(starts[3] - starts[2], MAX_BACKOFF * 2 + INITIAL_BACKOFF);
+    }
+}